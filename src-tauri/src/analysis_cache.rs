@@ -0,0 +1,200 @@
+//! Finished whole-book analyses, saved so they can be revisited and combined
+//!
+//! Each book's latest analysis is stored as `<book_id>.json` in a directory per
+//! library (named after a hash of the library's path, since book ids are only
+//! unique within a library), together with the options it ran with. Chapter-range
+//! analyses are not saved since they don't describe the book.
+
+use crate::nlp::readability::Readability;
+use crate::nlp::{pos::Pos, AnalysisOptions, HardWord, DEFAULT_FREQUENCY_THRESHOLD};
+use crate::resources;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CachedAnalysis {
+    pub book_id: i64,
+    pub word_count: usize,
+    pub hard_words: Vec<HardWord>,
+    /// Missing from analyses saved before readability was cached
+    #[serde(default)]
+    pub readability: Option<Readability>,
+    pub options: CachedOptions,
+}
+
+/// What an analysis ran with. A saved analysis only stands in for a new one with
+/// the same options: a lower threshold or fast mode finds other words.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct CachedOptions {
+    pub frequency_threshold: f32,
+    pub skip_ner: bool,
+    pub min_count: usize,
+    pub min_frequency: f32,
+    pub include_unlisted: bool,
+    pub collapse_derived: bool,
+    pub group_families: bool,
+    pub allowed_pos: Vec<Pos>,
+    pub skip_verse: bool,
+    pub include_footnotes: bool,
+    pub strip_page_artifacts: bool,
+    pub include_front_sections: bool,
+    /// Language asked for, used when the book's own can't be detected
+    pub language: Option<String>,
+}
+
+impl CachedOptions {
+    /// The text options of an analysis at `frequency_threshold`; extraction options are off
+    pub fn new(frequency_threshold: f32, options: &AnalysisOptions) -> Self {
+        let mut allowed_pos = options.allowed_pos.clone();
+        allowed_pos.sort_by_key(|pos| *pos as u8);
+        allowed_pos.dedup();
+        Self {
+            frequency_threshold,
+            skip_ner: options.skip_ner,
+            min_count: options.min_count,
+            min_frequency: options.min_frequency,
+            include_unlisted: options.include_unlisted,
+            collapse_derived: options.collapse_derived,
+            group_families: options.group_families,
+            allowed_pos,
+            skip_verse: options.skip_verse,
+            include_footnotes: false,
+            strip_page_artifacts: false,
+            include_front_sections: false,
+            language: None,
+        }
+    }
+}
+
+impl Default for CachedOptions {
+    /// The options of an analysis run with nothing changed
+    fn default() -> Self {
+        Self::new(DEFAULT_FREQUENCY_THRESHOLD, &AnalysisOptions::default())
+    }
+}
+
+/// Directory of `library`'s saved analyses
+fn library_dir(dir: &Path, library: &str) -> PathBuf {
+    let hash = format!("{:x}", Sha256::digest(library.as_bytes()));
+    dir.join(&hash[..16])
+}
+
+fn cache_path(dir: &Path, library: &str, book_id: i64) -> PathBuf {
+    library_dir(dir, library).join(format!("{}.json", book_id))
+}
+
+/// Save `analysis` of a book in `library`, replacing any earlier one for the same book
+pub fn save(library: &str, analysis: &CachedAnalysis) -> Result<(), String> {
+    save_in(&resources::get_analysis_cache_dir(), library, analysis)
+}
+
+/// The latest saved analysis of `book_id` in `library`, whatever it ran with, or
+/// None if it was never analyzed (or the file is unreadable)
+pub fn load(library: &str, book_id: i64) -> Option<CachedAnalysis> {
+    load_in(&resources::get_analysis_cache_dir(), library, book_id)
+}
+
+/// The saved analysis of `book_id` in `library` if it ran with `options`
+pub fn load_matching(library: &str, book_id: i64, options: &CachedOptions) -> Option<CachedAnalysis> {
+    load(library, book_id).filter(|saved| saved.options == *options)
+}
+
+fn save_in(dir: &Path, library: &str, analysis: &CachedAnalysis) -> Result<(), String> {
+    let path = cache_path(dir, library, analysis.book_id);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create analysis cache: {}", e))?;
+    }
+    let json = serde_json::to_string(analysis).map_err(|e| e.to_string())?;
+    fs::write(path, json).map_err(|e| format!("Failed to save analysis: {}", e))
+}
+
+fn load_in(dir: &Path, library: &str, book_id: i64) -> Option<CachedAnalysis> {
+    let json = fs::read_to_string(cache_path(dir, library, book_id)).ok()?;
+    serde_json::from_str(&json).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nlp::{frequency_band, HardWord};
+
+    const LIBRARY: &str = "/home/reader/Calibre Library";
+
+    #[test]
+    fn test_save_and_load_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let analysis = CachedAnalysis {
+            book_id: -42,
+            word_count: 1200,
+            hard_words: vec![HardWord {
+                word: "lugubrious".to_string(),
+                frequency_score: 2e-7,
+                contexts: Vec::new(),
+                count: 3,
+                variants: vec!["lugubriously".to_string()],
+                band: frequency_band(2e-7),
+                definition: None,
+                first_seen: 8,
+                pos: None,
+                first_occurrence: None,
+                first_chapter: None,
+                first_chapter_title: None,
+            }],
+            readability: None,
+            options: CachedOptions::default(),
+        };
+        save_in(dir.path(), LIBRARY, &analysis).unwrap();
+
+        let loaded = load_in(dir.path(), LIBRARY, -42).unwrap();
+        assert_eq!(loaded.word_count, 1200);
+        assert_eq!(loaded.hard_words.len(), 1);
+        assert_eq!(loaded.hard_words[0].word, "lugubrious");
+        assert_eq!(loaded.hard_words[0].band, analysis.hard_words[0].band);
+        assert!(load_in(dir.path(), LIBRARY, 7).is_none());
+        assert_eq!(loaded.options, CachedOptions::default());
+    }
+
+    #[test]
+    fn test_same_book_id_in_another_library() {
+        let dir = tempfile::tempdir().unwrap();
+        let analysis = CachedAnalysis {
+            book_id: 5,
+            word_count: 10,
+            hard_words: Vec::new(),
+            readability: None,
+            options: CachedOptions::default(),
+        };
+        save_in(dir.path(), LIBRARY, &analysis).unwrap();
+
+        assert!(load_in(dir.path(), LIBRARY, 5).is_some());
+        assert!(load_in(dir.path(), "/home/reader/Other Library", 5).is_none());
+    }
+
+    #[test]
+    fn test_options_are_part_of_the_match() {
+        let fast = AnalysisOptions { skip_ner: true, ..Default::default() };
+        assert_eq!(CachedOptions::new(DEFAULT_FREQUENCY_THRESHOLD, &AnalysisOptions::default()), CachedOptions::default());
+        assert_ne!(CachedOptions::new(DEFAULT_FREQUENCY_THRESHOLD, &fast), CachedOptions::default());
+        assert_ne!(CachedOptions::new(0.0001, &AnalysisOptions::default()), CachedOptions::default());
+        let front = CachedOptions { include_front_sections: true, ..CachedOptions::default() };
+        assert_ne!(front, CachedOptions::default());
+
+        // Order of the requested parts of speech doesn't matter
+        let nouns_verbs = AnalysisOptions { allowed_pos: vec![Pos::Noun, Pos::Verb], ..Default::default() };
+        let verbs_nouns = AnalysisOptions { allowed_pos: vec![Pos::Verb, Pos::Noun], ..Default::default() };
+        assert_eq!(CachedOptions::new(0.0001, &nouns_verbs), CachedOptions::new(0.0001, &verbs_nouns));
+    }
+
+    #[test]
+    fn test_loads_analysis_saved_without_readability() {
+        let dir = tempfile::tempdir().unwrap();
+        let options = serde_json::to_string(&CachedOptions::default()).unwrap();
+        let json = format!(r#"{{"book_id": 3, "word_count": 10, "hard_words": [], "options": {}}}"#, options);
+        fs::create_dir_all(library_dir(dir.path(), LIBRARY)).unwrap();
+        fs::write(cache_path(dir.path(), LIBRARY, 3), json).unwrap();
+        let loaded = load_in(dir.path(), LIBRARY, 3).unwrap();
+        assert!(loaded.readability.is_none());
+    }
+}
@@ -0,0 +1,126 @@
+//! Reading a book one chapter at a time
+//!
+//! The reader asks for the outline and then for chapters one by one, so the book
+//! read last is kept extracted. It is keyed by file, modification time and clean
+//! options, so a book Calibre rewrites in place (a format conversion, edited
+//! metadata) is extracted again.
+
+use crate::epub::{CleanOptions, ExtractedText};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+/// Which extraction of which file a cached book text is
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheKey {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    options: CleanOptions,
+}
+
+impl CacheKey {
+    /// Key for `path` as it is on disk now
+    pub fn new(path: &Path, options: CleanOptions) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            modified: std::fs::metadata(path).and_then(|m| m.modified()).ok(),
+            options,
+        }
+    }
+}
+
+/// The book read last, extracted
+#[derive(Default)]
+pub struct BookTextCache(Mutex<Option<(CacheKey, Arc<ExtractedText>)>>);
+
+impl BookTextCache {
+    pub fn get(&self, key: &CacheKey) -> Option<Arc<ExtractedText>> {
+        match self.0.lock().unwrap().as_ref() {
+            Some((cached, extracted)) if cached == key => Some(Arc::clone(extracted)),
+            _ => None,
+        }
+    }
+
+    /// Keep `extracted` in place of the book cached before
+    pub fn insert(&self, key: CacheKey, extracted: Arc<ExtractedText>) {
+        *self.0.lock().unwrap() = Some((key, extracted));
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct BookChapter {
+    pub index: usize,
+    pub title: String,
+    pub text: String,
+    pub footnotes: Vec<String>,
+    /// Where the chapter starts in `get_book_text`'s text, in characters
+    /// (None for a chapter holding only footnotes)
+    pub char_start: Option<usize>,
+}
+
+impl BookChapter {
+    /// Chapter `index` of `extracted`, None past the last chapter
+    pub fn from_extracted(extracted: &ExtractedText, index: usize) -> Option<Self> {
+        let chapter = extracted.chapters.get(index)?;
+        Some(Self {
+            index,
+            title: chapter.title.clone(),
+            text: chapter.text.clone(),
+            footnotes: chapter.footnotes.clone(),
+            char_start: extracted
+                .segments
+                .iter()
+                .find(|segment| segment.chapter_index == index)
+                .map(|segment| segment.char_start),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::epub::Chapter;
+    use crate::formats::{self, BookFormat};
+    use std::fs::File;
+    use std::time::Duration;
+
+    #[test]
+    fn test_cache_is_keyed_by_file_time_and_options() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("book.txt");
+        std::fs::write(&path, "First edition.").unwrap();
+        let options = CleanOptions::default();
+        let cache = BookTextCache::default();
+
+        let key = CacheKey::new(&path, options);
+        assert!(cache.get(&key).is_none());
+        let extracted = Arc::new(formats::extract_text_with_options(&path, BookFormat::Txt, options).unwrap());
+        cache.insert(key, Arc::clone(&extracted));
+        let hit = cache.get(&CacheKey::new(&path, options)).unwrap();
+        assert!(Arc::ptr_eq(&hit, &extracted));
+
+        let with_figures = CleanOptions {
+            strip_figures_and_tables: !options.strip_figures_and_tables,
+        };
+        assert!(cache.get(&CacheKey::new(&path, with_figures)).is_none());
+
+        // The file is rewritten in place
+        std::fs::write(&path, "Second edition.").unwrap();
+        let modified = SystemTime::now() + Duration::from_secs(60);
+        File::options().write(true).open(&path).unwrap().set_modified(modified).unwrap();
+        assert!(cache.get(&CacheKey::new(&path, options)).is_none());
+    }
+
+    #[test]
+    fn test_book_chapter() {
+        let extracted =
+            ExtractedText::from_chapters(vec![Chapter::fixture(0, "One", "It begins."), Chapter::fixture(1, "Two", "It ends.")]);
+
+        let second = BookChapter::from_extracted(&extracted, 1).unwrap();
+        assert_eq!(second.title, "Two");
+        assert_eq!(second.text, "It ends.");
+        assert_eq!(second.char_start, Some("It begins.\n\n".chars().count()));
+        assert!(BookChapter::from_extracted(&extracted, 2).is_none());
+    }
+}
@@ -1,25 +1,200 @@
-use rusqlite::{Connection, OpenFlags};
+pub mod folder;
+pub mod remote;
+
+use crate::formats::BookFormat;
+use rusqlite::{named_params, Connection, OpenFlags, OptionalExtension};
 use serde::Serialize;
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 #[derive(Debug, Serialize)]
 pub struct Book {
     pub id: i64,
     pub title: String,
+    /// All authors joined with " & " (kept for older exports and the UI)
     pub author: String,
+    /// Authors in Calibre's link order
+    pub authors: Vec<String>,
+    /// Calibre's author_sort, e.g. "Austen, Jane"
+    pub author_sort: String,
     pub path: String,
+    /// Where the cover is read from: Calibre's cover.jpg, a content server URL, or
+    /// for a folder EPUB the EPUB itself. None when the book has no cover.
     pub cover_path: Option<String>,
-    pub has_epub: bool,
+    pub has_readable_format: bool,
+    /// Best format Lexis can extract text from (EPUB preferred)
+    pub format: Option<BookFormat>,
+    /// Every format Calibre has for the book (e.g. "EPUB", "PDF"), sorted
+    pub formats: Vec<String>,
+    /// Calibre rating on its 0-10 half-star scale (None when unrated)
+    pub rating: Option<u8>,
+    /// Publication date as stored by Calibre (None when unset)
+    pub pubdate: Option<String>,
+    /// Date added to the library as stored by Calibre
+    pub timestamp: Option<String>,
+    pub tags: Vec<String>,
+    pub series: Option<String>,
+    /// Position within `series` (None when the book has no series)
+    pub series_index: Option<f64>,
+    /// Primary language as an ISO 639 code, e.g. "eng"
+    pub language: Option<String>,
+    /// User-defined Calibre columns: label -> value
+    pub custom: HashMap<String, serde_json::Value>,
+    /// Identifiers such as ISBN or Goodreads id: lowercased type -> value
+    pub identifiers: HashMap<String, String>,
+    /// Size of the EPUB as recorded by Calibre (None without an EPUB format)
+    pub epub_size_bytes: Option<i64>,
+    /// Rough word count guessed from `epub_size_bytes`, for analysis time estimates
+    pub approx_word_count: Option<i64>,
+}
+
+/// Bytes of EPUB per word of text, for `approx_word_count`
+const BYTES_PER_WORD: i64 = 6;
+
+/// Estimate a book's word count from its EPUB size, without opening the file
+pub(crate) fn approx_word_count(epub_size_bytes: Option<i64>) -> Option<i64> {
+    epub_size_bytes.filter(|size| *size > 0).map(|size| size / BYTES_PER_WORD)
+}
+
+/// Everything `get_book_details` knows about one book
+#[derive(Debug, Serialize)]
+pub struct BookDetails {
+    #[serde(flatten)]
+    pub book: Book,
+    pub publisher: Option<String>,
+    pub isbn: Option<String>,
+    /// Calibre's comments (the book description) as plain text
+    pub description: Option<String>,
+}
+
+/// Optional server-side filters for `scan_library` (case-insensitive substring matches)
+#[derive(Debug, Default, Clone)]
+pub struct BookFilter {
+    pub author: Option<String>,
+    pub tag: Option<String>,
+    /// Matches the title or any author name
+    pub query: Option<String>,
+}
+
+/// One page of `scan_library_page` results
+#[derive(Debug, Serialize)]
+pub struct BookPage {
+    pub books: Vec<Book>,
+    /// Number of books matching the filter across all pages
+    pub total: usize,
+}
+
+/// Sort order for `scan_library`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SortKey {
+    #[default]
+    Title,
+    Author,
+    DateAdded,
+    LastModified,
+    SeriesThenIndex,
+}
+
+impl SortKey {
+    /// Parse a sort name from the frontend; unknown names fall back to `Title`
+    pub fn from_name(name: &str) -> Self {
+        match name.to_ascii_lowercase().replace(['_', '-', ' '], "").as_str() {
+            "author" => SortKey::Author,
+            "dateadded" => SortKey::DateAdded,
+            "lastmodified" => SortKey::LastModified,
+            "seriesthenindex" | "series" => SortKey::SeriesThenIndex,
+            _ => SortKey::Title,
+        }
+    }
+
+    /// Whitelisted ORDER BY clause (never built from user input)
+    fn order_by(&self, descending: bool) -> String {
+        let dir = if descending { "DESC" } else { "ASC" };
+        match self {
+            SortKey::Title => format!("b.title {dir}"),
+            SortKey::Author => format!("b.author_sort {dir}, b.title ASC"),
+            SortKey::DateAdded => format!("b.timestamp {dir}, b.title ASC"),
+            SortKey::LastModified => format!("b.last_modified {dir}, b.title ASC"),
+            // Books without a series go last either way
+            SortKey::SeriesThenIndex => format!(
+                "series_name IS NULL, series_name {dir}, b.series_index {dir}, b.title ASC"
+            ),
+        }
+    }
+}
+
+/// Books that look like copies of each other (same normalized title and first author)
+#[derive(Debug, Serialize)]
+pub struct DuplicateGroup {
+    pub title: String,
+    pub author: String,
+    /// Oldest addition first
+    pub books: Vec<Book>,
+}
+
+/// An author with the number of books linked to them, for browsing by author
+#[derive(Debug, Serialize)]
+pub struct AuthorSummary {
+    pub id: i64,
+    pub name: String,
+    /// Calibre's author sort, e.g. "Eliot, George"
+    pub sort: String,
+    pub book_count: usize,
+}
+
+/// Definition of a Calibre custom column (from the `custom_columns` table)
+#[derive(Debug, Clone, Serialize)]
+pub struct CustomColumn {
+    pub id: i64,
+    pub label: String,
+    pub name: String,
+    pub datatype: String,
+    pub is_multiple: bool,
+    pub normalized: bool,
+}
+
+impl CustomColumn {
+    /// Only simple value types are read; composite, series, ratings etc. are skipped
+    fn is_supported(&self) -> bool {
+        matches!(
+            self.datatype.as_str(),
+            "text" | "comments" | "bool" | "int" | "float" | "enumeration"
+        )
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
 pub enum CalibreError {
     #[error("Database error: {0}")]
-    Database(#[from] rusqlite::Error),
+    Database(rusqlite::Error),
+    #[error("The Calibre library is busy (metadata.db is locked). Close Calibre or try again.")]
+    Busy,
     #[error("Library not found at path: {0}")]
     LibraryNotFound(String),
     #[error("Invalid library path: {0}")]
     InvalidPath(String),
+    #[error("Content server error: {0}")]
+    Remote(String),
+    #[error("Calibre lists this file but it is missing on disk: {0}")]
+    MissingFile(String),
+}
+
+impl From<rusqlite::Error> for CalibreError {
+    fn from(e: rusqlite::Error) -> Self {
+        if is_busy(&e) {
+            CalibreError::Busy
+        } else {
+            CalibreError::Database(e)
+        }
+    }
+}
+
+fn is_busy(e: &rusqlite::Error) -> bool {
+    matches!(
+        e.sqlite_error_code(),
+        Some(rusqlite::ErrorCode::DatabaseBusy | rusqlite::ErrorCode::DatabaseLocked)
+    )
 }
 
 impl Serialize for CalibreError {
@@ -31,47 +206,400 @@ impl Serialize for CalibreError {
     }
 }
 
-pub fn scan_library(library_path: &str) -> Result<Vec<Book>, CalibreError> {
-    let lib_path = Path::new(library_path);
-    let db_path = lib_path.join("metadata.db");
+/// How long SQLite waits on a lock before each attempt gives up
+const BUSY_TIMEOUT: Duration = Duration::from_millis(250);
+/// Extra attempts (with doubling backoff) while Calibre holds the lock
+const BUSY_RETRIES: u32 = 3;
 
-    if !db_path.exists() {
-        return Err(CalibreError::LibraryNotFound(library_path.to_string()));
+/// Open Calibre's metadata.db read-only (Calibre may hold a lock on it)
+///
+/// If Calibre keeps the database locked through a few retries, fall back to
+/// `immutable=1`, which skips locking entirely. That is safe for our read-only
+/// use, at worst missing a write Calibre is in the middle of.
+fn open_db(library_path: impl AsRef<Path>) -> Result<Connection, CalibreError> {
+    let db_uri = file_uri(&library_path.as_ref().join("metadata.db"))?;
+
+    match open_with_retry(&format!("{}?mode=ro", db_uri), BUSY_RETRIES) {
+        Err(CalibreError::Busy) => {
+            eprintln!("metadata.db is locked, opening it as immutable");
+            open_with_retry(&format!("{}?mode=ro&immutable=1", db_uri), 0)
+        }
+        result => result,
     }
+}
 
-    let db_uri = format!(
-        "file:{}?mode=ro",
-        db_path.to_str().ok_or_else(|| CalibreError::InvalidPath(library_path.to_string()))?
-    );
+/// SQLite `file:` URI for a path. Everything except unreserved characters, `/` and `:`
+/// is percent-encoded, so `#`, `?`, `%`, spaces and non-UTF-8 bytes survive the URI parser.
+fn file_uri(path: &Path) -> Result<String, CalibreError> {
+    #[cfg(unix)]
+    let bytes = std::os::unix::ffi::OsStrExt::as_bytes(path.as_os_str()).to_vec();
+    #[cfg(not(unix))]
+    let bytes = path
+        .to_str()
+        .ok_or_else(|| CalibreError::InvalidPath(path.to_string_lossy().to_string()))?
+        .replace('\\', "/")
+        .into_bytes();
 
-    let conn = Connection::open_with_flags(
-        &db_uri,
-        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_URI,
-    )?;
+    let mut uri = String::from("file://");
+    // Windows drive paths become file:///C:/...
+    if bytes.first() != Some(&b'/') {
+        uri.push('/');
+    }
+    for byte in bytes {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' | b':' => uri.push(byte as char),
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    Ok(uri)
+}
 
-    let mut stmt = conn.prepare(
-        r#"
+/// Open a read-only connection and check the schema is readable, retrying while busy
+fn open_with_retry(db_uri: &str, retries: u32) -> Result<Connection, CalibreError> {
+    let mut backoff = Duration::from_millis(100);
+    let mut attempt = 0;
+
+    loop {
+        let result = Connection::open_with_flags(
+            db_uri,
+            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_URI,
+        )
+        .and_then(|conn| {
+            conn.busy_timeout(BUSY_TIMEOUT)?;
+            // Opening is lazy; reading the schema is what actually takes the lock
+            conn.query_row("SELECT count(*) FROM sqlite_master", [], |_| Ok(()))?;
+            Ok(conn)
+        });
+
+        match result {
+            Err(e) if is_busy(&e) && attempt < retries => {
+                attempt += 1;
+                std::thread::sleep(backoff);
+                backoff *= 2;
+            }
+            result => return result.map_err(CalibreError::from),
+        }
+    }
+}
+
+/// Columns and joins shared by every query returning `Book` rows
+const BOOK_SELECT: &str = r#"
         SELECT
             b.id,
             b.title,
             b.path,
-            COALESCE(GROUP_CONCAT(a.name, ' & '), 'Unknown') as author,
-            b.has_cover
+            COALESCE(b.author_sort, '') as author_sort,
+            b.has_cover,
+            (SELECT r.rating FROM books_ratings_link brl
+             JOIN ratings r ON brl.rating = r.id
+             WHERE brl.book = b.id) as rating,
+            (SELECT s.name FROM books_series_link bsl
+             JOIN series s ON bsl.series = s.id
+             WHERE bsl.book = b.id) as series_name,
+            (SELECT GROUP_CONCAT(d.format, ',') FROM data d
+             WHERE d.book = b.id) as formats,
+            b.pubdate,
+            b.timestamp,
+            b.series_index,
+            (SELECT l.lang_code FROM books_languages_link bll
+             JOIN languages l ON bll.lang_code = l.id
+             WHERE bll.book = b.id
+             ORDER BY bll.item_order LIMIT 1) as language,
+            (SELECT d.uncompressed_size FROM data d
+             WHERE d.book = b.id AND UPPER(d.format) = 'EPUB' LIMIT 1) as epub_size
         FROM books b
-        LEFT JOIN books_authors_link bal ON b.id = bal.book
-        LEFT JOIN authors a ON bal.author = a.id
+"#;
+
+/// Maximum number of results returned by `search_books`
+const SEARCH_LIMIT: usize = 200;
+
+pub fn scan_library(
+    library_path: &str,
+    filter: &BookFilter,
+    sort: SortKey,
+    descending: bool,
+) -> Result<Vec<Book>, CalibreError> {
+    if remote::is_remote(library_path) {
+        return remote::scan_library(library_path, filter, sort, descending);
+    }
+
+    let lib_path = Path::new(library_path);
+    let db_path = lib_path.join("metadata.db");
+
+    if !db_path.exists() {
+        return Err(CalibreError::LibraryNotFound(library_path.to_string()));
+    }
+
+    let conn = open_db(library_path)?;
+
+    let (author, tag, query) = filter.patterns();
+
+    query_books(
+        &conn,
+        lib_path,
+        &format!("{} GROUP BY b.id ORDER BY {}", FILTER_WHERE, sort.order_by(descending)),
+        named_params! { ":author": author, ":tag": tag, ":query": query },
+    )
+}
+
+/// Like `scan_library`, but returns only `limit` books starting at `offset`,
+/// together with the total number of matches
+pub fn scan_library_page(
+    library_path: &str,
+    filter: &BookFilter,
+    sort: SortKey,
+    descending: bool,
+    offset: usize,
+    limit: usize,
+) -> Result<BookPage, CalibreError> {
+    if remote::is_remote(library_path) {
+        let books = remote::scan_library(library_path, filter, sort, descending)?;
+        let total = books.len();
+        return Ok(BookPage {
+            books: books.into_iter().skip(offset).take(limit).collect(),
+            total,
+        });
+    }
+
+    let lib_path = Path::new(library_path);
+    if !lib_path.join("metadata.db").exists() {
+        return Err(CalibreError::LibraryNotFound(library_path.to_string()));
+    }
+
+    let conn = open_db(library_path)?;
+    let (author, tag, query) = filter.patterns();
+
+    let total: i64 = conn.query_row(
+        &format!("SELECT COUNT(*) FROM books b {}", FILTER_WHERE),
+        named_params! { ":author": author, ":tag": tag, ":query": query },
+        |row| row.get(0),
+    )?;
+
+    let books = query_books(
+        &conn,
+        lib_path,
+        &format!(
+            "{} GROUP BY b.id ORDER BY {} LIMIT :limit OFFSET :offset",
+            FILTER_WHERE,
+            sort.order_by(descending)
+        ),
+        named_params! {
+            ":author": author,
+            ":tag": tag,
+            ":query": query,
+            ":limit": limit as i64,
+            ":offset": offset as i64,
+        },
+    )?;
+
+    Ok(BookPage {
+        books,
+        total: total as usize,
+    })
+}
+
+/// WHERE clause applying a `BookFilter` (parameters from `BookFilter::patterns`)
+const FILTER_WHERE: &str = r#"
+        WHERE (:author IS NULL OR EXISTS (
+                SELECT 1 FROM books_authors_link fal
+                JOIN authors fa ON fal.author = fa.id
+                WHERE fal.book = b.id AND lower(fa.name) LIKE :author ESCAPE '\'))
+          AND (:tag IS NULL OR EXISTS (
+                SELECT 1 FROM books_tags_link ftl
+                JOIN tags ft ON ftl.tag = ft.id
+                WHERE ftl.book = b.id AND lower(ft.name) LIKE :tag ESCAPE '\'))
+          AND (:query IS NULL OR lower(b.title) LIKE :query ESCAPE '\' OR EXISTS (
+                SELECT 1 FROM books_authors_link qal
+                JOIN authors qa ON qal.author = qa.id
+                WHERE qal.book = b.id AND lower(qa.name) LIKE :query ESCAPE '\'))
+"#;
+
+impl BookFilter {
+    /// `LIKE` patterns for the author, tag and query filters (blank filters are ignored)
+    fn patterns(&self) -> (Option<String>, Option<String>, Option<String>) {
+        let pattern = |value: &Option<String>| {
+            value
+                .as_deref()
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+                .map(like_pattern)
+        };
+        (pattern(&self.author), pattern(&self.tag), pattern(&self.query))
+    }
+}
+
+/// Search titles, author names and series names (case-insensitive substring match)
+pub fn search_books(library_path: &str, query: &str) -> Result<Vec<Book>, CalibreError> {
+    let query = query.trim();
+    if query.is_empty() {
+        return Ok(Vec::new());
+    }
+    if remote::is_remote(library_path) {
+        return remote::search_books(library_path, query);
+    }
+
+    let lib_path = Path::new(library_path);
+    let conn = open_db(library_path)?;
+    let pattern = like_pattern(query);
+
+    query_books(
+        &conn,
+        lib_path,
+        &format!(
+            r#"
+        WHERE lower(b.title) LIKE :q ESCAPE '\'
+           OR EXISTS (
+                SELECT 1 FROM books_authors_link sal
+                JOIN authors sa ON sal.author = sa.id
+                WHERE sal.book = b.id AND lower(sa.name) LIKE :q ESCAPE '\')
+           OR EXISTS (
+                SELECT 1 FROM books_series_link ssl
+                JOIN series ss ON ssl.series = ss.id
+                WHERE ssl.book = b.id AND lower(ss.name) LIKE :q ESCAPE '\')
         GROUP BY b.id
         ORDER BY b.title
+        LIMIT {}
+        "#,
+            SEARCH_LIMIT
+        ),
+        named_params! { ":q": pattern },
+    )
+}
+
+/// Group books by normalized title and primary author, keeping groups with
+/// more than one member. Read-only: nothing is merged or deleted.
+pub fn find_duplicates(library_path: &str) -> Result<Vec<DuplicateGroup>, CalibreError> {
+    let books = scan_library(library_path, &BookFilter::default(), SortKey::Title, false)?;
+
+    let mut groups: HashMap<(String, String), Vec<Book>> = HashMap::new();
+    for book in books {
+        let author = book.authors.first().map(|a| normalize_for_match(a)).unwrap_or_default();
+        let title = normalize_for_match(&book.title);
+        if title.is_empty() {
+            continue;
+        }
+        groups.entry((title, author)).or_default().push(book);
+    }
+
+    let mut duplicates: Vec<DuplicateGroup> = groups
+        .into_values()
+        .filter(|books| books.len() > 1)
+        .map(|mut books| {
+            books.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+            DuplicateGroup {
+                title: books[0].title.clone(),
+                author: books[0].authors.first().cloned().unwrap_or_else(|| books[0].author.clone()),
+                books,
+            }
+        })
+        .collect();
+    duplicates.sort_by_cached_key(|group| group.title.to_lowercase());
+
+    Ok(duplicates)
+}
+
+/// Authors that have at least one book, in author-sort order
+pub fn list_authors(library_path: &str) -> Result<Vec<AuthorSummary>, CalibreError> {
+    if remote::is_remote(library_path) {
+        return Err(CalibreError::Remote("Browsing by author is not supported for content servers".to_string()));
+    }
+
+    let conn = open_db(library_path)?;
+    let mut stmt = conn.prepare(
+        r#"
+        SELECT a.id, a.name, COALESCE(NULLIF(a.sort, ''), a.name) AS author_sort, COUNT(DISTINCT bal.book)
+        FROM authors a
+        JOIN books_authors_link bal ON bal.author = a.id
+        GROUP BY a.id
+        ORDER BY author_sort COLLATE NOCASE, a.name COLLATE NOCASE
         "#,
     )?;
 
-    let books = stmt
+    let authors = stmt
         .query_map([], |row| {
+            Ok(AuthorSummary {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                sort: row.get(2)?,
+                book_count: row.get::<_, i64>(3)? as usize,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(authors)
+}
+
+/// Books linked to one author (including co-authored ones)
+pub fn get_books_by_author(
+    library_path: &str,
+    author_id: i64,
+    sort: SortKey,
+    descending: bool,
+) -> Result<Vec<Book>, CalibreError> {
+    if remote::is_remote(library_path) {
+        return Err(CalibreError::Remote("Browsing by author is not supported for content servers".to_string()));
+    }
+
+    let conn = open_db(library_path)?;
+    query_books(
+        &conn,
+        Path::new(library_path),
+        &format!(
+            " WHERE b.id IN (SELECT book FROM books_authors_link WHERE author = ?) ORDER BY {}",
+            sort.order_by(descending)
+        ),
+        [author_id],
+    )
+}
+
+/// Books of a series (matched case-insensitively) in series order
+pub fn series_books(library_path: &str, series: &str) -> Result<Vec<Book>, CalibreError> {
+    if remote::is_remote(library_path) {
+        return remote::series_books(library_path, series);
+    }
+
+    let conn = open_db(library_path)?;
+    query_books(
+        &conn,
+        Path::new(library_path),
+        " WHERE b.id IN (SELECT bsl.book FROM books_series_link bsl \
+           JOIN series s ON bsl.series = s.id WHERE s.name = ? COLLATE NOCASE) \
+         ORDER BY b.series_index ASC, b.title ASC",
+        [series],
+    )
+}
+
+/// Lowercase and drop punctuation so "Pride & Prejudice." matches "pride  prejudice"
+fn normalize_for_match(value: &str) -> String {
+    value
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Run `BOOK_SELECT` with the given WHERE/GROUP BY/ORDER BY tail and build `Book`s.
+/// Authors, tags, identifiers and custom columns are then read for the returned books only.
+fn query_books<P: rusqlite::Params>(
+    conn: &Connection,
+    lib_path: &Path,
+    tail: &str,
+    params: P,
+) -> Result<Vec<Book>, CalibreError> {
+    let mut stmt = conn.prepare(&format!("{}{}", BOOK_SELECT, tail))?;
+
+    let mut books = stmt
+        .query_map(params, |row| {
             let id: i64 = row.get(0)?;
             let title: String = row.get(1)?;
             let book_path: String = row.get(2)?;
-            let author: String = row.get(3)?;
+            let author_sort: String = row.get(3)?;
             let has_cover: bool = row.get(4)?;
+            // Calibre uses 0 for "no rating"
+            let rating: Option<u8> = row.get::<_, Option<i64>>(5)?
+                .filter(|r| *r > 0)
+                .map(|r| r.clamp(0, 10) as u8);
 
             let full_book_path = lib_path.join(&book_path);
             let cover_path = if has_cover {
@@ -85,48 +613,189 @@ pub fn scan_library(library_path: &str) -> Result<Vec<Book>, CalibreError> {
                 None
             };
 
-            // Check if EPUB exists
-            let has_epub = find_epub(&full_book_path).is_some();
+            // The data table lists every format, so no need to read the book directory
+            let mut formats: Vec<String> = row
+                .get::<_, Option<String>>(7)?
+                .map(|f| f.split(',').map(|f| f.to_ascii_uppercase()).collect())
+                .unwrap_or_default();
+            formats.sort();
+            let format = BookFormat::best(&formats);
+
+            let series: Option<String> = row.get(6)?;
+            let series_index: Option<f64> = row.get(10)?;
+            let epub_size_bytes: Option<i64> = row.get(12)?;
 
             Ok(Book {
                 id,
                 title,
-                author,
+                author: String::new(),
+                authors: Vec::new(),
+                author_sort,
                 path: full_book_path.to_string_lossy().to_string(),
                 cover_path,
-                has_epub,
+                has_readable_format: format.is_some(),
+                format,
+                formats,
+                rating,
+                pubdate: row.get::<_, Option<String>>(8)?.filter(|d| !is_undefined_date(d)),
+                timestamp: row.get(9)?,
+                tags: Vec::new(),
+                series_index: series.as_ref().and(series_index),
+                series,
+                language: row.get(11)?,
+                custom: HashMap::new(),
+                identifiers: HashMap::new(),
+                approx_word_count: approx_word_count(epub_size_bytes),
+                epub_size_bytes,
             })
         })?
         .collect::<Result<Vec<_>, _>>()?;
 
+    if books.is_empty() {
+        return Ok(books);
+    }
+
+    // Ids are integers, so listing them in the SQL is safe
+    let ids = books.iter().map(|b| b.id.to_string()).collect::<Vec<_>>().join(",");
+    let mut custom_values = read_custom_values(conn, &ids)?;
+    let mut authors_by_book = read_names(
+        conn,
+        &format!(
+            "SELECT bal.book, a.name FROM books_authors_link bal \
+             JOIN authors a ON bal.author = a.id WHERE bal.book IN ({}) ORDER BY bal.id",
+            ids
+        ),
+    )?;
+    let mut tags_by_book = read_names(
+        conn,
+        &format!(
+            "SELECT btl.book, t.name FROM books_tags_link btl \
+             JOIN tags t ON btl.tag = t.id WHERE btl.book IN ({}) ORDER BY t.name COLLATE NOCASE",
+            ids
+        ),
+    )?;
+    let mut identifiers = read_identifiers(conn, &ids)?;
+
+    for book in &mut books {
+        book.authors = authors_by_book.remove(&book.id).unwrap_or_default();
+        book.author = if book.authors.is_empty() {
+            "Unknown".to_string()
+        } else {
+            book.authors.join(" & ")
+        };
+        book.tags = tags_by_book.remove(&book.id).unwrap_or_default();
+        book.custom = custom_values.remove(&book.id).unwrap_or_default();
+        book.identifiers = identifiers.remove(&book.id).unwrap_or_default();
+    }
+
     Ok(books)
 }
 
-pub fn find_epub(book_dir: &Path) -> Option<PathBuf> {
-    if let Ok(entries) = std::fs::read_dir(book_dir) {
-        for entry in entries.flatten() {
-            let path = entry.path();
-            if path.extension().map(|e| e == "epub").unwrap_or(false) {
-                return Some(path);
-            }
-        }
+/// Names linked to each book (authors, tags), in the order `sql` returns them.
+/// `sql` selects `(book, name)` rows; reading links separately avoids the row
+/// duplication a join plus GROUP_CONCAT would need to undo.
+fn read_names(conn: &Connection, sql: &str) -> Result<HashMap<i64, Vec<String>>, CalibreError> {
+    let mut stmt = conn.prepare(sql)?;
+
+    let mut names: HashMap<i64, Vec<String>> = HashMap::new();
+    let rows = stmt.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))?;
+    for row in rows {
+        let (book, name) = row?;
+        names.entry(book).or_default().push(name);
     }
-    None
+
+    Ok(names)
 }
 
-pub fn get_epub_path(library_path: &str, book_id: i64) -> Result<Option<PathBuf>, CalibreError> {
-    let lib_path = Path::new(library_path);
-    let db_path = lib_path.join("metadata.db");
+/// Identifiers of the books in `ids`, a comma-separated id list (`identifiers` table: type -> val)
+fn read_identifiers(conn: &Connection, ids: &str) -> Result<HashMap<i64, HashMap<String, String>>, CalibreError> {
+    let mut stmt = conn.prepare(&format!("SELECT book, type, val FROM identifiers WHERE book IN ({})", ids))?;
+
+    let mut identifiers: HashMap<i64, HashMap<String, String>> = HashMap::new();
+    let rows = stmt.query_map([], |row| {
+        Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?))
+    })?;
+    for row in rows {
+        let (book, kind, value) = row?;
+        identifiers.entry(book).or_default().insert(kind.to_lowercase(), value);
+    }
+
+    Ok(identifiers)
+}
+
+/// Calibre stores "no date" as a placeholder in the year 101
+pub(crate) fn is_undefined_date(date: &str) -> bool {
+    date.trim().is_empty() || date.starts_with("0101-01-01")
+}
+
+/// Build a case-insensitive `LIKE` substring pattern, escaping SQL wildcards
+fn like_pattern(value: &str) -> String {
+    let escaped = value
+        .to_lowercase()
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("%{}%", escaped)
+}
+
+/// Find the best readable book file in a directory, preferring EPUB
+pub fn find_book_file(book_dir: &Path) -> Option<(PathBuf, BookFormat)> {
+    let entries = std::fs::read_dir(book_dir).ok()?;
 
-    let db_uri = format!(
-        "file:{}?mode=ro",
-        db_path.to_str().ok_or_else(|| CalibreError::InvalidPath(library_path.to_string()))?
-    );
+    entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter_map(|path| {
+            let format = path
+                .extension()
+                .and_then(|e| e.to_str())
+                .and_then(BookFormat::from_extension)?;
+            Some((path, format))
+        })
+        .min_by_key(|(_, format)| BookFormat::PREFERENCE.iter().position(|f| f == format))
+}
 
-    let conn = Connection::open_with_flags(
-        &db_uri,
-        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_URI,
+/// Path of a book's EPUB as recorded in the `data` table (None if it has no EPUB format).
+/// Only if that file is gone is the book directory searched for another `.epub`.
+pub fn get_epub_path(library_path: &str, book_id: i64) -> Result<Option<PathBuf>, CalibreError> {
+    if remote::is_remote(library_path) {
+        return Ok(get_book_file(library_path, book_id)?
+            .filter(|(_, format)| *format == BookFormat::Epub)
+            .map(|(path, _)| path));
+    }
+
+    let conn = open_db(library_path)?;
+    let (book_path, name): (String, Option<String>) = conn.query_row(
+        "SELECT b.path, d.name FROM books b \
+         LEFT JOIN data d ON d.book = b.id AND UPPER(d.format) = 'EPUB' \
+         WHERE b.id = ?",
+        [book_id],
+        |row| Ok((row.get(0)?, row.get(1)?)),
     )?;
+    let Some(name) = name else {
+        return Ok(None);
+    };
+
+    let book_dir = Path::new(library_path).join(&book_path);
+    let expected = book_dir.join(format!("{}.epub", name));
+    if expected.is_file() {
+        return Ok(Some(expected));
+    }
+
+    match find_book_file(&book_dir) {
+        Some((path, BookFormat::Epub)) => Ok(Some(path)),
+        _ => Err(CalibreError::MissingFile(expected.to_string_lossy().to_string())),
+    }
+}
+
+/// Resolve the best readable file of a book (EPUB, then MOBI/AZW3/FB2/TXT)
+pub fn get_book_file(library_path: &str, book_id: i64) -> Result<Option<(PathBuf, BookFormat)>, CalibreError> {
+    if remote::is_remote(library_path) {
+        return remote::get_book_file(library_path, book_id);
+    }
+
+    let lib_path = Path::new(library_path);
+    let conn = open_db(library_path)?;
 
     let book_path: String = conn.query_row(
         "SELECT path FROM books WHERE id = ?",
@@ -135,5 +804,711 @@ pub fn get_epub_path(library_path: &str, book_id: i64) -> Result<Option<PathBuf>
     )?;
 
     let full_path = lib_path.join(&book_path);
-    Ok(find_epub(&full_path))
+    Ok(find_book_file(&full_path))
+}
+
+/// Get the cover image of a book, if it has one
+pub fn get_cover_path(library_path: &str, book_id: i64) -> Result<Option<PathBuf>, CalibreError> {
+    if remote::is_remote(library_path) {
+        return remote::get_cover_path(library_path, book_id);
+    }
+
+    let conn = open_db(library_path)?;
+
+    let (book_path, has_cover): (String, bool) = conn.query_row(
+        "SELECT path, has_cover FROM books WHERE id = ?",
+        [book_id],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+
+    let cover = Path::new(library_path).join(book_path).join("cover.jpg");
+    Ok((has_cover && cover.exists()).then_some(cover))
+}
+
+/// Full metadata of one book, including publisher and description
+pub fn get_book_details(library_path: &str, book_id: i64) -> Result<BookDetails, CalibreError> {
+    if remote::is_remote(library_path) {
+        return remote::get_book_details(library_path, book_id);
+    }
+
+    let conn = open_db(library_path)?;
+    let book = query_books(&conn, Path::new(library_path), " WHERE b.id = ?", [book_id])?
+        .pop()
+        .ok_or(rusqlite::Error::QueryReturnedNoRows)?;
+
+    let publisher: Option<String> = conn
+        .query_row(
+            "SELECT p.name FROM books_publishers_link bpl \
+             JOIN publishers p ON bpl.publisher = p.id WHERE bpl.book = ?",
+            [book_id],
+            |row| row.get(0),
+        )
+        .optional()?;
+    let comments: Option<String> = conn
+        .query_row("SELECT text FROM comments WHERE book = ?", [book_id], |row| row.get(0))
+        .optional()?
+        .flatten();
+
+    Ok(BookDetails::new(book, publisher, comments.as_deref()))
+}
+
+impl BookDetails {
+    fn new(book: Book, publisher: Option<String>, comments: Option<&str>) -> Self {
+        Self {
+            isbn: book.identifiers.get("isbn").cloned(),
+            publisher: publisher.filter(|p| !p.trim().is_empty()),
+            description: comments.map(description_text).filter(|d| !d.is_empty()),
+            book,
+        }
+    }
+}
+
+/// Comments are HTML; strip them the same way book chapters are cleaned
+fn description_text(html: &str) -> String {
+    crate::epub::clean_html(&crate::epub::text_cleaner(), html)
+}
+
+/// List the custom column definitions of a library
+pub fn get_custom_columns(library_path: &str) -> Result<Vec<CustomColumn>, CalibreError> {
+    // Custom columns are not read from content servers
+    if remote::is_remote(library_path) {
+        return Ok(Vec::new());
+    }
+
+    let conn = open_db(library_path)?;
+    query_custom_columns(&conn)
+}
+
+fn query_custom_columns(conn: &Connection) -> Result<Vec<CustomColumn>, CalibreError> {
+    let mut stmt = conn.prepare(
+        r#"
+        SELECT id, label, name, datatype, is_multiple, normalized
+        FROM custom_columns
+        WHERE mark_for_delete = 0
+        ORDER BY id
+        "#,
+    )?;
+
+    let columns = stmt
+        .query_map([], |row| {
+            Ok(CustomColumn {
+                id: row.get(0)?,
+                label: row.get(1)?,
+                name: row.get(2)?,
+                datatype: row.get(3)?,
+                is_multiple: row.get(4)?,
+                normalized: row.get(5)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(columns)
+}
+
+/// Read the values of all supported custom columns for the books in `ids`, a comma-separated
+/// id list, keyed by book id then column label
+fn read_custom_values(
+    conn: &Connection,
+    ids: &str,
+) -> Result<HashMap<i64, HashMap<String, serde_json::Value>>, CalibreError> {
+    let mut values: HashMap<i64, HashMap<String, serde_json::Value>> = HashMap::new();
+
+    for column in query_custom_columns(conn)? {
+        if !column.is_supported() {
+            continue;
+        }
+
+        // Table names are derived from the integer column id, so formatting them is safe
+        let sql = if column.normalized {
+            format!(
+                "SELECT l.book, v.value FROM books_custom_column_{id}_link l \
+                 JOIN custom_column_{id} v ON l.value = v.id WHERE l.book IN ({ids}) ORDER BY l.id",
+                id = column.id,
+                ids = ids
+            )
+        } else {
+            format!("SELECT book, value FROM custom_column_{} WHERE book IN ({}) ORDER BY id", column.id, ids)
+        };
+
+        let rows = conn.prepare(&sql).and_then(|mut stmt| {
+            stmt.query_map([], |row| {
+                let book: i64 = row.get(0)?;
+                let value = match column.datatype.as_str() {
+                    "bool" => row.get::<_, Option<bool>>(1)?.map(serde_json::Value::from),
+                    "int" => row.get::<_, Option<i64>>(1)?.map(serde_json::Value::from),
+                    "float" => row.get::<_, Option<f64>>(1)?.map(serde_json::Value::from),
+                    _ => row.get::<_, Option<String>>(1)?.map(serde_json::Value::from),
+                };
+                Ok((book, value))
+            })?
+            .collect::<Result<Vec<_>, _>>()
+        });
+
+        let rows = match rows {
+            Ok(rows) => rows,
+            Err(e) => {
+                eprintln!("Skipping custom column '{}': {}", column.label, e);
+                continue;
+            }
+        };
+
+        for (book, value) in rows {
+            let Some(value) = value else { continue };
+            let book_values = values.entry(book).or_default();
+            if column.is_multiple {
+                let list = book_values
+                    .entry(column.label.clone())
+                    .or_insert_with(|| serde_json::Value::Array(Vec::new()));
+                if let serde_json::Value::Array(items) = list {
+                    items.push(value);
+                }
+            } else {
+                book_values.insert(column.label.clone(), value);
+            }
+        }
+    }
+
+    Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Create a minimal metadata.db and hold an exclusive lock on it, as Calibre does mid-write
+    fn locked_library(dir: &Path) -> Connection {
+        let writer = Connection::open(dir.join("metadata.db")).unwrap();
+        writer
+            .execute_batch("CREATE TABLE books (id INTEGER PRIMARY KEY, title TEXT); BEGIN EXCLUSIVE;")
+            .unwrap();
+        writer
+    }
+
+    /// Minimal metadata.db with the tables `BOOK_SELECT` and the filters touch
+    fn test_library(dir: &Path) -> Connection {
+        let conn = Connection::open(dir.join("metadata.db")).unwrap();
+        conn.execute_batch(
+            r#"
+            CREATE TABLE books (id INTEGER PRIMARY KEY, title TEXT, sort TEXT, author_sort TEXT,
+                timestamp TIMESTAMP, pubdate TIMESTAMP, last_modified TIMESTAMP, series_index REAL DEFAULT 1.0,
+                path TEXT, has_cover BOOL DEFAULT 0);
+            CREATE TABLE authors (id INTEGER PRIMARY KEY, name TEXT, sort TEXT);
+            CREATE TABLE books_authors_link (id INTEGER PRIMARY KEY, book INTEGER, author INTEGER);
+            CREATE TABLE ratings (id INTEGER PRIMARY KEY, rating INTEGER);
+            CREATE TABLE books_ratings_link (id INTEGER PRIMARY KEY, book INTEGER, rating INTEGER);
+            CREATE TABLE tags (id INTEGER PRIMARY KEY, name TEXT);
+            CREATE TABLE books_tags_link (id INTEGER PRIMARY KEY, book INTEGER, tag INTEGER);
+            CREATE TABLE series (id INTEGER PRIMARY KEY, name TEXT);
+            CREATE TABLE books_series_link (id INTEGER PRIMARY KEY, book INTEGER, series INTEGER);
+            CREATE TABLE languages (id INTEGER PRIMARY KEY, lang_code TEXT);
+            CREATE TABLE books_languages_link (id INTEGER PRIMARY KEY, book INTEGER, lang_code INTEGER,
+                item_order INTEGER DEFAULT 0);
+            CREATE TABLE identifiers (id INTEGER PRIMARY KEY, book INTEGER, type TEXT, val TEXT);
+            CREATE TABLE data (id INTEGER PRIMARY KEY, book INTEGER, format TEXT,
+                uncompressed_size INTEGER, name TEXT);
+            CREATE TABLE custom_columns (id INTEGER PRIMARY KEY, label TEXT, name TEXT, datatype TEXT,
+                is_multiple BOOL, normalized BOOL, mark_for_delete BOOL DEFAULT 0);
+            CREATE TABLE publishers (id INTEGER PRIMARY KEY, name TEXT);
+            CREATE TABLE books_publishers_link (id INTEGER PRIMARY KEY, book INTEGER, publisher INTEGER);
+            CREATE TABLE comments (id INTEGER PRIMARY KEY, book INTEGER, text TEXT);
+            "#,
+        )
+        .unwrap();
+        conn
+    }
+
+    fn add_book(conn: &Connection, id: i64, title: &str, author: &str, added: &str) {
+        conn.execute(
+            "INSERT INTO books (id, title, author_sort, timestamp, last_modified, path) VALUES (?1, ?2, ?3, ?4, ?4, ?2)",
+            rusqlite::params![id, title, author, added],
+        )
+        .unwrap();
+        conn.execute("INSERT INTO authors (id, name) VALUES (?1, ?2)", rusqlite::params![id, author])
+            .unwrap();
+        conn.execute("INSERT INTO books_authors_link (book, author) VALUES (?1, ?1)", [id])
+            .unwrap();
+    }
+
+    fn link_author(conn: &Connection, book: i64, author: i64, name: &str) {
+        conn.execute("INSERT OR IGNORE INTO authors (id, name) VALUES (?1, ?2)", rusqlite::params![author, name])
+            .unwrap();
+        conn.execute("INSERT INTO books_authors_link (book, author) VALUES (?1, ?2)", [book, author])
+            .unwrap();
+    }
+
+    fn titles(books: &[Book]) -> Vec<&str> {
+        books.iter().map(|b| b.title.as_str()).collect()
+    }
+
+    #[test]
+    fn test_sort_by_date_added() {
+        let dir = tempfile::tempdir().unwrap();
+        let conn = test_library(dir.path());
+        add_book(&conn, 1, "Middlemarch", "Eliot, George", "2021-05-01 10:00:00+00:00");
+        add_book(&conn, 2, "Anna Karenina", "Tolstoy, Leo", "2023-01-15 09:30:00+00:00");
+        add_book(&conn, 3, "Walden", "Thoreau, Henry David", "2019-11-20 18:00:00+00:00");
+        let lib = dir.path().to_str().unwrap();
+
+        let oldest_first = scan_library(lib, &BookFilter::default(), SortKey::DateAdded, false).unwrap();
+        assert_eq!(titles(&oldest_first), vec!["Walden", "Middlemarch", "Anna Karenina"]);
+
+        let newest_first = scan_library(lib, &BookFilter::default(), SortKey::DateAdded, true).unwrap();
+        assert_eq!(titles(&newest_first), vec!["Anna Karenina", "Middlemarch", "Walden"]);
+
+        let by_title = scan_library(lib, &BookFilter::default(), SortKey::Title, false).unwrap();
+        assert_eq!(titles(&by_title), vec!["Anna Karenina", "Middlemarch", "Walden"]);
+
+        let by_author = scan_library(lib, &BookFilter::default(), SortKey::Author, false).unwrap();
+        assert_eq!(titles(&by_author), vec!["Middlemarch", "Walden", "Anna Karenina"]);
+    }
+
+    #[test]
+    fn test_formats_from_data_table() {
+        let dir = tempfile::tempdir().unwrap();
+        let conn = test_library(dir.path());
+        add_book(&conn, 1, "Middlemarch", "Eliot, George", "2021-05-01 10:00:00+00:00");
+        add_book(&conn, 2, "Scanned Atlas", "Unknown", "2021-05-02 10:00:00+00:00");
+        add_book(&conn, 3, "Walden", "Thoreau, Henry David", "2021-05-03 10:00:00+00:00");
+        conn.execute_batch(
+            "INSERT INTO data (book, format, name) VALUES
+                (1, 'PDF', 'Middlemarch'), (1, 'EPUB', 'Middlemarch'), (1, 'AZW3', 'Middlemarch'),
+                (2, 'PDF', 'Scanned Atlas');",
+        )
+        .unwrap();
+
+        // No book directories exist on disk; formats come from metadata.db alone
+        let books = scan_library(dir.path().to_str().unwrap(), &BookFilter::default(), SortKey::DateAdded, false).unwrap();
+        assert_eq!(books[0].formats, vec!["AZW3", "EPUB", "PDF"]);
+        assert_eq!(books[0].format, Some(BookFormat::Epub));
+        assert!(books[0].has_readable_format);
+
+        // PDFs are only readable with the `pdf` feature
+        assert_eq!(books[1].formats, vec!["PDF"]);
+        assert_eq!(books[1].format, cfg!(feature = "pdf").then_some(BookFormat::Pdf));
+        assert_eq!(books[1].has_readable_format, cfg!(feature = "pdf"));
+
+        assert!(books[2].formats.is_empty());
+        assert!(!books[2].has_readable_format);
+    }
+
+    #[test]
+    fn test_multiple_authors_in_link_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let conn = test_library(dir.path());
+        conn.execute(
+            "INSERT INTO books (id, title, author_sort, path) VALUES (1, 'Good Omens', 'Pratchett, Terry & Gaiman, Neil', 'x')",
+            [],
+        )
+        .unwrap();
+        conn.execute("INSERT INTO books (id, title, author_sort, path) VALUES (2, 'Anonymous', NULL, 'y')", [])
+            .unwrap();
+        // Author ids deliberately out of alphabetical and id order
+        link_author(&conn, 1, 20, "Terry Pratchett");
+        link_author(&conn, 1, 10, "Neil Gaiman");
+
+        let books = scan_library(dir.path().to_str().unwrap(), &BookFilter::default(), SortKey::Title, false).unwrap();
+        let anonymous = &books[0];
+        assert!(anonymous.authors.is_empty());
+        assert_eq!(anonymous.author, "Unknown");
+        assert_eq!(anonymous.author_sort, "");
+
+        let good_omens = &books[1];
+        assert_eq!(good_omens.authors, vec!["Terry Pratchett", "Neil Gaiman"]);
+        assert_eq!(good_omens.author, "Terry Pratchett & Neil Gaiman");
+        assert_eq!(good_omens.author_sort, "Pratchett, Terry & Gaiman, Neil");
+
+        let filtered = BookFilter { author: Some("gaiman".to_string()), ..Default::default() };
+        let books = scan_library(dir.path().to_str().unwrap(), &filtered, SortKey::Title, false).unwrap();
+        assert_eq!(titles(&books), vec!["Good Omens"]);
+    }
+
+    #[test]
+    fn test_identifiers() {
+        let dir = tempfile::tempdir().unwrap();
+        let conn = test_library(dir.path());
+        add_book(&conn, 1, "Middlemarch", "Eliot, George", "2021-05-01 10:00:00+00:00");
+        add_book(&conn, 2, "Walden", "Thoreau, Henry David", "2021-05-02 10:00:00+00:00");
+        conn.execute_batch(
+            "INSERT INTO identifiers (book, type, val) VALUES
+                (1, 'ISBN', '9780141439549'), (1, 'goodreads', '19089');",
+        )
+        .unwrap();
+
+        let books = scan_library(dir.path().to_str().unwrap(), &BookFilter::default(), SortKey::DateAdded, false).unwrap();
+        assert_eq!(books[0].identifiers.get("isbn").map(String::as_str), Some("9780141439549"));
+        assert_eq!(books[0].identifiers.get("goodreads").map(String::as_str), Some("19089"));
+        assert_eq!(books[0].identifiers.len(), 2);
+        assert!(books[1].identifiers.is_empty());
+
+        // Side tables are only read for the matching books, which still get theirs
+        let filtered = BookFilter { query: Some("middle".to_string()), ..Default::default() };
+        let books = scan_library(dir.path().to_str().unwrap(), &filtered, SortKey::DateAdded, false).unwrap();
+        assert_eq!(titles(&books), vec!["Middlemarch"]);
+        assert_eq!(books[0].identifiers.len(), 2);
+        assert_eq!(books[0].authors.len(), 1);
+    }
+
+    #[test]
+    fn test_book_details() {
+        let dir = tempfile::tempdir().unwrap();
+        let conn = test_library(dir.path());
+        add_book(&conn, 1, "Middlemarch", "Eliot, George", "2021-05-01 10:00:00+00:00");
+        add_book(&conn, 2, "Walden", "Thoreau, Henry David", "2021-05-02 10:00:00+00:00");
+        conn.execute_batch(
+            "UPDATE books SET pubdate = '1871-12-01 00:00:00+00:00' WHERE id = 1;
+             INSERT INTO identifiers (book, type, val) VALUES (1, 'isbn', '9780141439549');
+             INSERT INTO ratings (id, rating) VALUES (1, 8);
+             INSERT INTO books_ratings_link (book, rating) VALUES (1, 1);
+             INSERT INTO publishers (id, name) VALUES (1, 'Penguin Classics');
+             INSERT INTO books_publishers_link (book, publisher) VALUES (1, 1);
+             INSERT INTO comments (book, text) VALUES
+                (1, '<div><p>A study of <i>provincial</i> life.</p>\n<p>Set in 1829&ndash;32.</p><script>x()</script></div>');",
+        )
+        .unwrap();
+        let lib = dir.path().to_str().unwrap();
+
+        let details = get_book_details(lib, 1).unwrap();
+        assert_eq!(details.book.title, "Middlemarch");
+        assert_eq!(details.book.rating, Some(8));
+        assert_eq!(details.book.pubdate.as_deref(), Some("1871-12-01 00:00:00+00:00"));
+        assert_eq!(details.publisher.as_deref(), Some("Penguin Classics"));
+        assert_eq!(details.isbn.as_deref(), Some("9780141439549"));
+        assert_eq!(details.description.as_deref(), Some("A study of provincial life.\n\nSet in 1829\u{2013}32."));
+
+        // Missing metadata is None, not an error
+        let bare = get_book_details(lib, 2).unwrap();
+        assert_eq!(bare.book.title, "Walden");
+        assert!(bare.publisher.is_none() && bare.isbn.is_none() && bare.description.is_none());
+        assert!(bare.book.rating.is_none());
+
+        assert!(get_book_details(lib, 99).is_err());
+    }
+
+    #[test]
+    fn test_epub_path_from_data_table() {
+        let dir = tempfile::tempdir().unwrap();
+        let conn = test_library(dir.path());
+        add_book(&conn, 1, "Middlemarch", "Eliot, George", "2021-05-01 10:00:00+00:00");
+        add_book(&conn, 2, "Walden", "Thoreau, Henry David", "2021-05-02 10:00:00+00:00");
+        add_book(&conn, 3, "Emma", "Austen, Jane", "2021-05-03 10:00:00+00:00");
+        conn.execute_batch(
+            "INSERT INTO data (book, format, name) VALUES
+                (1, 'EPUB', 'Middlemarch - George Eliot'), (2, 'PDF', 'Walden'), (3, 'EPUB', 'Emma - Jane Austen');",
+        )
+        .unwrap();
+        let lib = dir.path().to_str().unwrap();
+
+        // An unrelated EPUB that sorts first must not be picked
+        let middlemarch = dir.path().join("Middlemarch");
+        std::fs::create_dir_all(&middlemarch).unwrap();
+        std::fs::write(middlemarch.join("A sample chapter.epub"), b"").unwrap();
+        std::fs::write(middlemarch.join("Middlemarch - George Eliot.epub"), b"").unwrap();
+        assert_eq!(get_epub_path(lib, 1).unwrap(), Some(middlemarch.join("Middlemarch - George Eliot.epub")));
+
+        // No EPUB format at all
+        assert_eq!(get_epub_path(lib, 2).unwrap(), None);
+
+        // Listed but gone: fall back to any EPUB in the directory, else report the missing file
+        let emma = dir.path().join("Emma");
+        std::fs::create_dir_all(&emma).unwrap();
+        assert!(matches!(get_epub_path(lib, 3), Err(CalibreError::MissingFile(_))));
+        std::fs::write(emma.join("emma.epub"), b"").unwrap();
+        assert_eq!(get_epub_path(lib, 3).unwrap(), Some(emma.join("emma.epub")));
+    }
+
+    #[test]
+    fn test_file_uri_escapes_special_characters() {
+        assert_eq!(
+            file_uri(Path::new("/home/me/Books #1/50% off?/metadata.db")).unwrap(),
+            "file:///home/me/Books%20%231/50%25%20off%3F/metadata.db"
+        );
+        assert_eq!(
+            file_uri(Path::new("/Bücher/metadata.db")).unwrap(),
+            "file:///B%C3%BCcher/metadata.db"
+        );
+    }
+
+    #[test]
+    fn test_exotic_library_and_book_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("Calibre Library #2 – Bücher");
+        std::fs::create_dir_all(&root).unwrap();
+        let conn = test_library(&root);
+        add_book(&conn, 1, "Rohstoff", "Fauser, Jörg", "2021-05-01 10:00:00+00:00");
+        let book_dir = "Jörg Fauser/Rohstoff #1 (1) ";
+        conn.execute_batch(&format!(
+            "UPDATE books SET path = '{}', has_cover = 1 WHERE id = 1;
+             INSERT INTO data (book, format, name) VALUES (1, 'EPUB', 'Rohstoff #1 - Jörg Fauser');",
+            book_dir
+        ))
+        .unwrap();
+        let full_dir = root.join(book_dir);
+        std::fs::create_dir_all(&full_dir).unwrap();
+        std::fs::write(full_dir.join("Rohstoff #1 - Jörg Fauser.epub"), b"").unwrap();
+        std::fs::write(full_dir.join("cover.jpg"), b"").unwrap();
+        let lib = root.to_str().unwrap();
+
+        let books = scan_library(lib, &BookFilter::default(), SortKey::Title, false).unwrap();
+        assert_eq!(titles(&books), vec!["Rohstoff"]);
+        assert_eq!(books[0].cover_path.as_deref(), Some(full_dir.join("cover.jpg").to_str().unwrap()));
+        assert_eq!(get_epub_path(lib, 1).unwrap(), Some(full_dir.join("Rohstoff #1 - Jörg Fauser.epub")));
+        assert_eq!(get_cover_path(lib, 1).unwrap(), Some(full_dir.join("cover.jpg")));
+        assert_eq!(get_book_file(lib, 1).unwrap().map(|(_, format)| format), Some(BookFormat::Epub));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_non_utf8_library_path() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join(std::ffi::OsStr::from_bytes(b"Library \xff"));
+        std::fs::create_dir_all(&root).unwrap();
+        let conn = test_library(&root);
+        add_book(&conn, 1, "Walden", "Thoreau, Henry David", "2021-05-01 10:00:00+00:00");
+
+        let conn = open_db(&root).unwrap();
+        let count: i64 = conn.query_row("SELECT count(*) FROM books", [], |row| row.get(0)).unwrap();
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn test_epub_size_and_word_estimate() {
+        let dir = tempfile::tempdir().unwrap();
+        let conn = test_library(dir.path());
+        add_book(&conn, 1, "Middlemarch", "Eliot, George", "2021-05-01 10:00:00+00:00");
+        add_book(&conn, 2, "Walden", "Thoreau, Henry David", "2021-05-02 10:00:00+00:00");
+        conn.execute_batch(
+            "INSERT INTO data (book, format, uncompressed_size, name) VALUES
+                (1, 'PDF', 9000000, 'Middlemarch'), (1, 'EPUB', 1800000, 'Middlemarch'),
+                (2, 'PDF', 600000, 'Walden');",
+        )
+        .unwrap();
+
+        let books = scan_library(dir.path().to_str().unwrap(), &BookFilter::default(), SortKey::DateAdded, false).unwrap();
+        assert_eq!(books[0].epub_size_bytes, Some(1_800_000));
+        assert_eq!(books[0].approx_word_count, Some(300_000));
+        // No EPUB: no estimate, even though another format has a size
+        assert_eq!(books[1].epub_size_bytes, None);
+        assert_eq!(books[1].approx_word_count, None);
+    }
+
+    #[test]
+    fn test_dates() {
+        let dir = tempfile::tempdir().unwrap();
+        let conn = test_library(dir.path());
+        add_book(&conn, 1, "Middlemarch", "Eliot, George", "2021-05-01 10:00:00+00:00");
+        add_book(&conn, 2, "Notes", "Unknown", "2021-05-02 10:00:00+00:00");
+        conn.execute_batch(
+            "UPDATE books SET pubdate = '1871-12-01 00:00:00+00:00' WHERE id = 1;
+             UPDATE books SET pubdate = '0101-01-01 00:00:00+00:00' WHERE id = 2;",
+        )
+        .unwrap();
+
+        let books = scan_library(dir.path().to_str().unwrap(), &BookFilter::default(), SortKey::DateAdded, false).unwrap();
+        assert_eq!(books[0].pubdate.as_deref(), Some("1871-12-01 00:00:00+00:00"));
+        assert_eq!(books[0].timestamp.as_deref(), Some("2021-05-01 10:00:00+00:00"));
+        assert_eq!(books[1].pubdate, None);
+        assert_eq!(books[1].timestamp.as_deref(), Some("2021-05-02 10:00:00+00:00"));
+    }
+
+    #[test]
+    fn test_scan_library_page() {
+        let dir = tempfile::tempdir().unwrap();
+        let conn = test_library(dir.path());
+        add_book(&conn, 1, "Middlemarch", "Eliot, George", "2021-05-01 10:00:00+00:00");
+        add_book(&conn, 2, "Anna Karenina", "Tolstoy, Leo", "2021-05-02 10:00:00+00:00");
+        add_book(&conn, 3, "Walden", "Thoreau, Henry David", "2021-05-03 10:00:00+00:00");
+        add_book(&conn, 4, "War and Peace", "Tolstoy, Leo", "2021-05-04 10:00:00+00:00");
+        let lib = dir.path().to_str().unwrap();
+
+        let page = scan_library_page(lib, &BookFilter::default(), SortKey::Title, false, 1, 2).unwrap();
+        assert_eq!(page.total, 4);
+        assert_eq!(titles(&page.books), vec!["Middlemarch", "Walden"]);
+
+        let past_end = scan_library_page(lib, &BookFilter::default(), SortKey::Title, false, 10, 2).unwrap();
+        assert_eq!(past_end.total, 4);
+        assert!(past_end.books.is_empty());
+
+        // Query matches titles and author names; blank queries match everything
+        let tolstoy = BookFilter { query: Some("TOLSTOY".to_string()), ..Default::default() };
+        let page = scan_library_page(lib, &tolstoy, SortKey::DateAdded, true, 0, 10).unwrap();
+        assert_eq!(page.total, 2);
+        assert_eq!(titles(&page.books), vec!["War and Peace", "Anna Karenina"]);
+
+        let title = BookFilter { query: Some("walden".to_string()), ..Default::default() };
+        assert_eq!(scan_library_page(lib, &title, SortKey::Title, false, 0, 10).unwrap().total, 1);
+
+        let blank = BookFilter { query: Some("  ".to_string()), ..Default::default() };
+        assert_eq!(scan_library_page(lib, &blank, SortKey::Title, false, 0, 10).unwrap().total, 4);
+    }
+
+    #[test]
+    fn test_find_duplicates() {
+        let dir = tempfile::tempdir().unwrap();
+        let conn = test_library(dir.path());
+        add_book(&conn, 1, "Middlemarch", "George Eliot", "2021-05-03 10:00:00+00:00");
+        add_book(&conn, 2, "MIDDLEMARCH.", "George Eliot", "2021-05-01 10:00:00+00:00");
+        add_book(&conn, 3, "Middlemarch", "Someone Else", "2021-05-02 10:00:00+00:00");
+        add_book(&conn, 4, "Walden", "Henry David Thoreau", "2021-05-04 10:00:00+00:00");
+        conn.execute("INSERT INTO data (book, format, name) VALUES (1, 'EPUB', 'Middlemarch')", [])
+            .unwrap();
+
+        let groups = find_duplicates(dir.path().to_str().unwrap()).unwrap();
+        assert_eq!(groups.len(), 1);
+        let ids: Vec<i64> = groups[0].books.iter().map(|b| b.id).collect();
+        // Oldest first, different author kept apart
+        assert_eq!(ids, vec![2, 1]);
+        assert_eq!(groups[0].author, "George Eliot");
+        assert_eq!(groups[0].books[1].formats, vec!["EPUB"]);
+    }
+
+    #[test]
+    fn test_list_authors_and_books_by_author() {
+        let dir = tempfile::tempdir().unwrap();
+        let conn = test_library(dir.path());
+        add_book(&conn, 1, "Middlemarch", "George Eliot", "2021-05-01 10:00:00+00:00");
+        add_book(&conn, 2, "Good Omens", "Terry Pratchett", "2021-05-02 10:00:00+00:00");
+        link_author(&conn, 2, 3, "Neil Gaiman");
+        add_book(&conn, 4, "Silas Marner", "George Eliot", "2021-05-03 10:00:00+00:00");
+        conn.execute_batch(
+            "DELETE FROM authors WHERE id = 4;
+             UPDATE books_authors_link SET author = 1 WHERE book = 4;
+             INSERT INTO authors (id, name, sort) VALUES (9, 'Nobody Atall', 'Atall, Nobody');
+             UPDATE authors SET sort = 'Eliot, George' WHERE id = 1;
+             UPDATE authors SET sort = 'Pratchett, Terry' WHERE id = 2;
+             UPDATE authors SET sort = 'Gaiman, Neil' WHERE id = 3;",
+        )
+        .unwrap();
+        let lib = dir.path().to_str().unwrap();
+
+        // The orphan author (no books) is left out
+        let authors = list_authors(lib).unwrap();
+        let summary: Vec<(&str, usize)> = authors.iter().map(|a| (a.name.as_str(), a.book_count)).collect();
+        assert_eq!(summary, vec![("George Eliot", 2), ("Neil Gaiman", 1), ("Terry Pratchett", 1)]);
+
+        let eliot = get_books_by_author(lib, 1, SortKey::Title, false).unwrap();
+        assert_eq!(titles(&eliot), vec!["Middlemarch", "Silas Marner"]);
+        let gaiman = get_books_by_author(lib, 3, SortKey::Title, false).unwrap();
+        assert_eq!(titles(&gaiman), vec!["Good Omens"]);
+        assert_eq!(gaiman[0].authors, vec!["Terry Pratchett", "Neil Gaiman"]);
+        assert!(get_books_by_author(lib, 9, SortKey::Title, false).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_series_books_in_series_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let conn = test_library(dir.path());
+        add_book(&conn, 1, "The Two Towers", "Tolkien, J.R.R.", "2021-05-01 10:00:00+00:00");
+        add_book(&conn, 2, "The Fellowship of the Ring", "Tolkien, J.R.R.", "2021-05-02 10:00:00+00:00");
+        add_book(&conn, 3, "The Return of the King", "Tolkien, J.R.R.", "2021-05-03 10:00:00+00:00");
+        add_book(&conn, 4, "The Hobbit", "Tolkien, J.R.R.", "2021-05-04 10:00:00+00:00");
+        conn.execute_batch(
+            "INSERT INTO series (id, name) VALUES (1, 'The Lord of the Rings');
+             INSERT INTO books_series_link (book, series) VALUES (1, 1), (2, 1), (3, 1);
+             UPDATE books SET series_index = 2 WHERE id = 1;
+             UPDATE books SET series_index = 1 WHERE id = 2;
+             UPDATE books SET series_index = 3 WHERE id = 3;",
+        )
+        .unwrap();
+        let lib = dir.path().to_str().unwrap();
+
+        let books = series_books(lib, "the lord of the rings").unwrap();
+        assert_eq!(
+            titles(&books),
+            vec!["The Fellowship of the Ring", "The Two Towers", "The Return of the King"]
+        );
+        assert!(series_books(lib, "Discworld").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_normalize_for_match() {
+        assert_eq!(normalize_for_match("  Pride & Prejudice. "), "pride prejudice");
+        assert_eq!(normalize_for_match("Brontë, Charlotte"), "brontë charlotte");
+        assert_eq!(normalize_for_match("?!"), "");
+    }
+
+    #[test]
+    fn test_tags_series_and_language() {
+        let dir = tempfile::tempdir().unwrap();
+        let conn = test_library(dir.path());
+        add_book(&conn, 1, "The Two Towers", "Tolkien, J. R. R.", "2021-05-01 10:00:00+00:00");
+        add_book(&conn, 2, "Walden", "Thoreau, Henry David", "2021-05-02 10:00:00+00:00");
+        // Two co-authors and several tags would multiply rows in a single join
+        link_author(&conn, 1, 50, "Christopher Tolkien");
+        conn.execute_batch(
+            "INSERT INTO tags (id, name) VALUES (1, 'Fantasy'), (2, 'classic'), (3, 'Adventure');
+             INSERT INTO books_tags_link (book, tag) VALUES (1, 1), (1, 2), (1, 3);
+             INSERT INTO series (id, name) VALUES (1, 'The Lord of the Rings');
+             INSERT INTO books_series_link (book, series) VALUES (1, 1);
+             UPDATE books SET series_index = 2.0 WHERE id = 1;
+             INSERT INTO languages (id, lang_code) VALUES (1, 'eng'), (2, 'fra');
+             INSERT INTO books_languages_link (book, lang_code, item_order) VALUES (1, 2, 1), (1, 1, 0);",
+        )
+        .unwrap();
+
+        let books = scan_library(dir.path().to_str().unwrap(), &BookFilter::default(), SortKey::DateAdded, false).unwrap();
+        let towers = &books[0];
+        assert_eq!(towers.tags, vec!["Adventure", "classic", "Fantasy"]);
+        assert_eq!(towers.authors.len(), 2);
+        assert_eq!(towers.series.as_deref(), Some("The Lord of the Rings"));
+        assert_eq!(towers.series_index, Some(2.0));
+        assert_eq!(towers.language.as_deref(), Some("eng"));
+
+        let walden = &books[1];
+        assert!(walden.tags.is_empty());
+        assert_eq!(walden.series, None);
+        // Calibre's default index of 1.0 means nothing without a series
+        assert_eq!(walden.series_index, None);
+        assert_eq!(walden.language, None);
+    }
+
+    #[test]
+    fn test_sort_key_from_name() {
+        assert_eq!(SortKey::from_name("DateAdded"), SortKey::DateAdded);
+        assert_eq!(SortKey::from_name("last_modified"), SortKey::LastModified);
+        assert_eq!(SortKey::from_name("SeriesThenIndex"), SortKey::SeriesThenIndex);
+        assert_eq!(SortKey::from_name("author"), SortKey::Author);
+        // Unknown values (including SQL) fall back to Title
+        assert_eq!(SortKey::from_name("title; DROP TABLE books"), SortKey::Title);
+        assert_eq!(SortKey::from_name(""), SortKey::Title);
+    }
+
+    #[test]
+    fn test_locked_database_reports_busy() {
+        let dir = tempfile::tempdir().unwrap();
+        let _writer = locked_library(dir.path());
+
+        let uri = format!("file:{}?mode=ro", dir.path().join("metadata.db").display());
+        let result = open_with_retry(&uri, 1);
+        assert!(matches!(result, Err(CalibreError::Busy)), "{:?}", result.err());
+    }
+
+    #[test]
+    fn test_retry_succeeds_once_lock_is_released() {
+        let dir = tempfile::tempdir().unwrap();
+        let writer = locked_library(dir.path());
+        let uri = format!("file:{}?mode=ro", dir.path().join("metadata.db").display());
+
+        // Held longer than one busy timeout, so only the backoff retry can succeed
+        let release = std::thread::spawn(move || {
+            std::thread::sleep(BUSY_TIMEOUT + Duration::from_millis(150));
+            writer.execute_batch("COMMIT").unwrap();
+        });
+
+        let conn = open_with_retry(&uri, BUSY_RETRIES).unwrap();
+        let count: i64 = conn.query_row("SELECT count(*) FROM books", [], |row| row.get(0)).unwrap();
+        assert_eq!(count, 0);
+        release.join().unwrap();
+    }
+
+    #[test]
+    fn test_locked_database_falls_back_to_immutable() {
+        let dir = tempfile::tempdir().unwrap();
+        let _writer = locked_library(dir.path());
+
+        let conn = open_db(dir.path().to_str().unwrap()).unwrap();
+        let count: i64 = conn.query_row("SELECT count(*) FROM books", [], |row| row.get(0)).unwrap();
+        assert_eq!(count, 0);
+    }
 }
@@ -0,0 +1,195 @@
+//! Folder mode: a plain directory of EPUBs (or text and Markdown files) used as
+//! a library without Calibre
+//!
+//! Books get synthetic negative ids derived from their path relative to the
+//! folder, so they stay stable across rescans and never collide with Calibre ids.
+
+use super::{Book, CalibreError};
+use crate::epub;
+use crate::formats::{self, BookFormat};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// How many directory levels below the chosen folder are searched
+pub const MAX_DEPTH: usize = 8;
+
+/// Formats a folder library picks up
+const FOLDER_FORMATS: [BookFormat; 3] = [BookFormat::Epub, BookFormat::Txt, BookFormat::Markdown];
+
+/// Walk `root` for book files and read each one's metadata.
+/// Returns the books (sorted by title) and each book's file by id.
+pub fn scan_folder(root: &Path, max_depth: usize) -> Result<(Vec<Book>, HashMap<i64, PathBuf>), CalibreError> {
+    if !root.is_dir() {
+        return Err(CalibreError::LibraryNotFound(root.to_string_lossy().to_string()));
+    }
+
+    let mut files = Vec::new();
+    collect_books(root, max_depth, &mut files);
+
+    let mut paths = HashMap::new();
+    let mut books: Vec<Book> = files
+        .into_iter()
+        .map(|path| {
+            let book = folder_book(root, &path);
+            paths.insert(book.id, path);
+            book
+        })
+        .collect();
+    books.sort_by_cached_key(|book| book.title.to_lowercase());
+
+    Ok((books, paths))
+}
+
+/// Recursively collect book files (see `FOLDER_FORMATS`). Symlinked directories are skipped to avoid cycles.
+fn collect_books(dir: &Path, depth_left: usize, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        let Ok(file_type) = entry.file_type() else { continue };
+        let path = entry.path();
+        if file_type.is_dir() {
+            if depth_left > 0 {
+                collect_books(&path, depth_left - 1, files);
+            }
+        } else if file_format(&path).is_some() {
+            files.push(path);
+        }
+    }
+}
+
+/// Stable negative id from the file's path relative to the library folder
+fn book_id(root: &Path, path: &Path) -> i64 {
+    path_id(path.strip_prefix(root).unwrap_or(path))
+}
+
+/// Stable negative id for a path, so it never clashes with Calibre's positive ids
+pub fn path_id(path: &Path) -> i64 {
+    let digest = Sha256::digest(path.to_string_lossy().as_bytes());
+    let mut bytes = [0u8; 8];
+    // 48 bits keeps ids exactly representable as JavaScript numbers
+    bytes[2..].copy_from_slice(&digest[..6]);
+    -(i64::from_be_bytes(bytes) + 1)
+}
+
+/// Format of a folder library file, from its extension
+pub fn file_format(path: &Path) -> Option<BookFormat> {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .and_then(BookFormat::from_extension)
+        .filter(|format| FOLDER_FORMATS.contains(format))
+}
+
+fn folder_book(root: &Path, path: &Path) -> Book {
+    let format = file_format(path).unwrap_or(BookFormat::Epub);
+    // Unreadable metadata still lists the book, titled by its file name
+    let metadata = match format {
+        BookFormat::Epub => epub::read_metadata(path).unwrap_or_else(|_| epub::EpubMetadata::untitled(path)),
+        _ => formats::read_text_metadata(path),
+    };
+    let author = if metadata.authors.is_empty() {
+        "Unknown".to_string()
+    } else {
+        metadata.authors.join(" & ")
+    };
+    let epub_size_bytes = std::fs::metadata(path).ok().map(|m| m.len() as i64);
+
+    Book {
+        id: book_id(root, path),
+        title: metadata.title,
+        author,
+        authors: metadata.authors,
+        author_sort: String::new(),
+        path: path.to_string_lossy().to_string(),
+        // get_cover reads the image out of the EPUB; other formats have none
+        cover_path: (format == BookFormat::Epub).then(|| path.to_string_lossy().to_string()),
+        has_readable_format: true,
+        format: Some(format),
+        formats: vec![format.label().to_string()],
+        rating: None,
+        pubdate: metadata.date,
+        timestamp: None,
+        tags: Vec::new(),
+        series: None,
+        series_index: None,
+        language: metadata.language,
+        custom: HashMap::new(),
+        identifiers: HashMap::new(),
+        approx_word_count: super::approx_word_count(epub_size_bytes),
+        epub_size_bytes,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scan_folder() {
+        let dir = tempfile::tempdir().unwrap();
+        let nested = dir.path().join("fiction").join("classics");
+        std::fs::create_dir_all(&nested).unwrap();
+        // Not valid EPUBs, so titles fall back to the file names
+        std::fs::write(dir.path().join("walden.epub"), b"not a zip").unwrap();
+        std::fs::write(nested.join("Middlemarch.EPUB"), b"not a zip").unwrap();
+        std::fs::write(nested.join("notes.md"), b"# Notes").unwrap();
+        std::fs::write(dir.path().join("cover.jpg"), b"ignored").unwrap();
+
+        let (books, paths) = scan_folder(dir.path(), MAX_DEPTH).unwrap();
+        let titles: Vec<&str> = books.iter().map(|b| b.title.as_str()).collect();
+        assert_eq!(titles, vec!["Middlemarch", "notes", "walden"]);
+        assert!(books.iter().all(|b| b.id < 0));
+        assert_eq!(books[0].format, Some(BookFormat::Epub));
+        assert_eq!(books[1].format, Some(BookFormat::Markdown));
+        assert_eq!(books[1].formats, ["MD"]);
+        assert_eq!(paths[&books[0].id], nested.join("Middlemarch.EPUB"));
+
+        // Ids are stable across scans
+        let (again, _) = scan_folder(dir.path(), MAX_DEPTH).unwrap();
+        assert_eq!(again[0].id, books[0].id);
+
+        // Depth limit: only the top level
+        let (shallow, _) = scan_folder(dir.path(), 0).unwrap();
+        assert_eq!(shallow.len(), 1);
+        assert_eq!(shallow[0].title, "walden");
+    }
+
+    #[test]
+    fn test_folder_epubs_have_covers() {
+        let dir = tempfile::tempdir().unwrap();
+        let fixture = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/minimal.epub");
+        std::fs::copy(fixture, dir.path().join("minimal.epub")).unwrap();
+        std::fs::write(dir.path().join("notes.txt"), "Plain text.").unwrap();
+
+        let (books, paths) = scan_folder(dir.path(), MAX_DEPTH).unwrap();
+        let text = books.iter().find(|b| b.format == Some(BookFormat::Txt)).unwrap();
+        assert_eq!(text.cover_path, None);
+
+        // The UI asks for the covers of books with a cover_path, and get_cover reads
+        // a folder book's cover out of its file
+        let book = books.iter().find(|b| b.format == Some(BookFormat::Epub)).unwrap();
+        let path = &paths[&book.id];
+        assert_eq!(book.cover_path.as_deref(), path.to_str());
+        let cover = epub::extract_cover(path).unwrap().unwrap();
+        assert!(cover.mime.starts_with("image/"));
+    }
+
+    #[test]
+    fn test_scan_missing_folder() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(matches!(
+            scan_folder(&dir.path().join("missing"), MAX_DEPTH),
+            Err(CalibreError::LibraryNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_book_id_fits_javascript_numbers() {
+        let root = Path::new("/books");
+        let id = book_id(root, Path::new("/books/a/b.epub"));
+        assert!(id < 0 && id.unsigned_abs() <= 1 << 53);
+        assert_ne!(id, book_id(root, Path::new("/books/a/c.epub")));
+    }
+}
@@ -0,0 +1,143 @@
+//! Cover thumbnails for the library grid
+//!
+//! Full-size Calibre covers are often 1-2 MB; the frontend gets a downsampled
+//! JPEG as a base64 data URL instead. Thumbnails are cached on disk, keyed by
+//! the cover path and size, and regenerated when the cover is newer. Folder
+//! libraries have no cover files, so their covers are read from the EPUB.
+
+use base64::Engine;
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+use crate::{epub, resources};
+
+const JPEG_QUALITY: u8 = 85;
+
+#[derive(Error, Debug)]
+pub enum CoverError {
+    #[error("Failed to read cover: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Failed to decode cover: {0}")]
+    Image(#[from] image::ImageError),
+    #[error(transparent)]
+    Epub(#[from] epub::EpubError),
+}
+
+impl serde::Serialize for CoverError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(self.to_string().as_ref())
+    }
+}
+
+/// Return the cover at `cover_path` resized to `max_dim` on its long edge, as a data URL
+pub fn thumbnail_data_url(cover_path: &Path, max_dim: u32) -> Result<String, CoverError> {
+    let bytes = thumbnail_bytes(cover_path, max_dim, || Ok(Some(std::fs::read(cover_path)?)))?;
+    Ok(data_url(&bytes.unwrap_or_default()))
+}
+
+/// Same as `thumbnail_data_url` for the cover image stored inside an EPUB (see
+/// `epub::extract_cover`), cached against the EPUB file itself. None if it has no image.
+pub fn epub_thumbnail_data_url(epub_path: &Path, max_dim: u32) -> Result<Option<String>, CoverError> {
+    let read = || {
+        // SVG covers can't be rasterized here; the grid shows its placeholder instead
+        let cover = epub::extract_cover(epub_path)?.filter(|cover| cover.mime != "image/svg+xml");
+        Ok(cover.map(|cover| cover.bytes))
+    };
+    let bytes = thumbnail_bytes(epub_path, max_dim, read)?;
+    Ok(bytes.map(|bytes| data_url(&bytes)))
+}
+
+fn data_url(jpeg: &[u8]) -> String {
+    format!("data:image/jpeg;base64,{}", base64::engine::general_purpose::STANDARD.encode(jpeg))
+}
+
+/// Cached thumbnail of the image `read` returns, keyed by (and as fresh as) `source`
+fn thumbnail_bytes(
+    source: &Path,
+    max_dim: u32,
+    read: impl FnOnce() -> Result<Option<Vec<u8>>, CoverError>,
+) -> Result<Option<Vec<u8>>, CoverError> {
+    let cache_path = cache_path(source, max_dim);
+
+    if is_fresh(&cache_path, source) {
+        if let Ok(bytes) = std::fs::read(&cache_path) {
+            return Ok(Some(bytes));
+        }
+    }
+
+    let Some(image) = read()? else {
+        return Ok(None);
+    };
+    let bytes = resize(&image, max_dim)?;
+
+    // A failed cache write only costs a resize next time
+    if let Some(parent) = cache_path.parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+    let _ = std::fs::write(&cache_path, &bytes);
+
+    Ok(Some(bytes))
+}
+
+/// Downsample an encoded image so its long edge is at most `max_dim`, re-encoded as JPEG
+fn resize(bytes: &[u8], max_dim: u32) -> Result<Vec<u8>, CoverError> {
+    let img = image::load_from_memory(bytes)?;
+    let img = if img.width() > max_dim || img.height() > max_dim {
+        img.resize(max_dim, max_dim, FilterType::Triangle)
+    } else {
+        img
+    };
+
+    let mut out = Vec::new();
+    JpegEncoder::new_with_quality(&mut out, JPEG_QUALITY).encode_image(&img.to_rgb8())?;
+    Ok(out)
+}
+
+fn cache_path(cover_path: &Path, max_dim: u32) -> PathBuf {
+    let mut hasher = DefaultHasher::new();
+    cover_path.hash(&mut hasher);
+    resources::get_cover_cache_dir().join(format!("{:016x}_{}.jpg", hasher.finish(), max_dim))
+}
+
+/// Whether the cached thumbnail exists and is at least as new as the cover
+fn is_fresh(cache_path: &Path, cover_path: &Path) -> bool {
+    let modified = |p: &Path| std::fs::metadata(p).and_then(|m| m.modified()).ok();
+    match (modified(cache_path), modified(cover_path)) {
+        (Some(cached), Some(cover)) => cached >= cover,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode_png(width: u32, height: u32) -> Vec<u8> {
+        let img = image::RgbImage::from_pixel(width, height, image::Rgb([200, 40, 40]));
+        let mut out = std::io::Cursor::new(Vec::new());
+        img.write_to(&mut out, image::ImageFormat::Png).unwrap();
+        out.into_inner()
+    }
+
+    #[test]
+    fn test_resize_limits_long_edge() {
+        let resized = resize(&encode_png(400, 600), 150).unwrap();
+        let img = image::load_from_memory(&resized).unwrap();
+        assert_eq!(img.height(), 150);
+        assert_eq!(img.width(), 100);
+    }
+
+    #[test]
+    fn test_resize_does_not_upscale() {
+        let resized = resize(&encode_png(40, 60), 150).unwrap();
+        let img = image::load_from_memory(&resized).unwrap();
+        assert_eq!((img.width(), img.height()), (40, 60));
+    }
+}
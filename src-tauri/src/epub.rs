@@ -1,14 +1,36 @@
+use crate::nlp;
 use ammonia::Builder;
 use epub::doc::EpubDoc;
-use std::collections::HashSet;
-use std::path::Path;
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufReader, Read, Seek};
+use std::ops::Range;
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use unicode_normalization::UnicodeNormalization;
 
 #[derive(Debug, thiserror::Error)]
 pub enum EpubError {
     #[error("Failed to open EPUB: {0}")]
     Open(String),
+    #[error("This file is not a readable EPUB: its zip archive is damaged ({0})")]
+    NotAZip(String),
+    #[error("This EPUB has no META-INF/container.xml, so its contents can't be found")]
+    MissingContainer,
+    #[error("This EPUB's package file {0} is missing")]
+    MissingOpf(String),
+    #[error("Spine item {id} could not be read")]
+    SpineItemUnreadable { id: String },
+    #[error("Only {readable} of this book's {total} sections could be read")]
+    MostlyUnreadable { readable: usize, total: usize },
     #[error("Failed to read chapter: {0}")]
     ReadChapter(String),
+    #[error("Unsupported book file: {0}")]
+    Unsupported(String),
+    #[error("This book is DRM-protected and its text cannot be read")]
+    DrmProtected,
+    #[error("Extraction cancelled")]
+    Cancelled,
 }
 
 impl serde::Serialize for EpubError {
@@ -20,66 +42,2174 @@ impl serde::Serialize for EpubError {
     }
 }
 
+/// A single spine item with its cleaned text
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct Chapter {
+    /// Position of this item in the EPUB spine (reading order)
+    pub spine_index: usize,
+    /// Resource path of the chapter inside the EPUB archive
+    pub path: String,
+    /// Closest table-of-contents title, or "Chapter N" when the TOC has none
+    pub title: String,
+    /// Main text, without footnote markers or note bodies
+    pub text: String,
+    /// Cleaned footnote/endnote bodies found in this item, in document order
+    pub footnotes: Vec<String>,
+    /// Character ranges of `text` that are verse (see `clean_html_tagged`)
+    pub verse: Vec<Range<usize>>,
+}
+
+#[cfg(test)]
+impl Chapter {
+    /// A chapter with only a title and text, for test books
+    pub(crate) fn fixture(spine_index: usize, title: &str, text: &str) -> Self {
+        Self {
+            spine_index,
+            path: String::new(),
+            title: title.to_string(),
+            text: text.to_string(),
+            footnotes: Vec::new(),
+            verse: Vec::new(),
+        }
+    }
+}
+
+/// A table-of-contents entry, flattened in reading order
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct TocEntry {
+    pub title: String,
+    /// Spine position the entry points to (None if it targets a non-spine resource)
+    pub spine_index: Option<usize>,
+    /// Nesting level, 0 for top-level entries
+    pub depth: usize,
+}
+
+/// A range of joined text (see `join_chapters`) and the spine item it came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub struct Segment {
+    pub spine_index: usize,
+    /// Position of the chapter in the list that was joined
+    pub chapter_index: usize,
+    /// Character (not byte) range within the joined text, end exclusive
+    pub char_start: usize,
+    pub char_end: usize,
+}
+
+/// A position in the book: a spine item and a character offset into its extracted text
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Location {
+    pub spine_index: usize,
+    pub char_offset: usize,
+}
+
+/// The segment holding a character offset into joined text, None if it falls between segments
+pub fn segment_at(segments: &[Segment], char_offset: usize) -> Option<&Segment> {
+    let segment = segments.get(segments.partition_point(|s| s.char_end <= char_offset))?;
+    (segment.char_start <= char_offset).then_some(segment)
+}
+
+/// The location of a character offset into joined text, None if it falls between segments
+pub fn locate(segments: &[Segment], char_offset: usize) -> Option<Location> {
+    segment_at(segments, char_offset).map(|segment| Location {
+        spine_index: segment.spine_index,
+        char_offset: char_offset - segment.char_start,
+    })
+}
+
 pub struct ExtractedText {
+    /// All chapters joined with blank lines (convenience for whole-book analysis).
+    /// Footnotes are not included; see `join_chapters`.
     pub full_text: String,
+    /// Where each chapter's text lies in `full_text`
+    pub segments: Vec<Segment>,
+    /// Character ranges of `full_text` that are verse, for `AnalysisOptions::skip_verse`
+    pub verse: Vec<Range<usize>>,
     pub chapter_count: usize,
+    pub chapters: Vec<Chapter>,
+    /// Every chapter's footnotes, in reading order
+    pub footnotes: Vec<String>,
+    /// Base language code from the book's own metadata (`dc:language`), e.g. "en"
+    pub language: Option<String>,
+    /// Spine idrefs whose content couldn't be loaded, so their text is missing
+    pub skipped_items: Vec<String>,
+    /// Number of spine items, readable or not (the chapter count for other formats)
+    pub spine_items: usize,
+    /// What went wrong with which spine item, for items that were unreadable or empty
+    pub warnings: Vec<String>,
+    /// Share of words that are real words (see `formats::text_quality`). Only
+    /// analyses measure it, once the book's language is known; None until then.
+    pub text_quality: Option<f32>,
+    /// Front sections left out by `exclude_front_sections`, in reading order
+    pub excluded_sections: Vec<ExcludedSection>,
+}
+
+/// A preface, dedication or similar section whose text was left out of the analysis
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct ExcludedSection {
+    /// Position in `ExtractedText::chapters`
+    pub index: usize,
+    pub spine_index: usize,
+    pub title: String,
+    pub word_count: usize,
+}
+
+/// A chapter's place in the book without its text, so readers can load chapters lazily
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct ChapterOutline {
+    /// Position in `ExtractedText::chapters`
+    pub index: usize,
+    pub spine_index: usize,
+    pub title: String,
+    pub word_count: usize,
 }
 
+impl ExtractedText {
+    /// Title and size of every chapter, in reading order
+    pub fn outline(&self) -> Vec<ChapterOutline> {
+        self.chapters
+            .iter()
+            .enumerate()
+            .map(|(index, chapter)| ChapterOutline {
+                index,
+                spine_index: chapter.spine_index,
+                title: chapter.title.clone(),
+                word_count: chapter.text.split_whitespace().count(),
+            })
+            .collect()
+    }
+
+    /// Number of footnote/endnote bodies moved out of the main text, so users can
+    /// check that note stripping isn't eating real content
+    pub fn removed_notes(&self) -> usize {
+        self.footnotes.len()
+    }
+
+    /// Fail with `EpubError::MostlyUnreadable` when less than `MIN_READABLE_SHARE` of
+    /// the spine could be loaded; a few lost items only cost the analysis some words
+    pub fn check_readable(&self) -> Result<(), EpubError> {
+        let total = self.spine_items;
+        let readable = total.saturating_sub(self.skipped_items.len());
+        if total > 0 && (readable as f64) < total as f64 * MIN_READABLE_SHARE {
+            return Err(EpubError::MostlyUnreadable { readable, total });
+        }
+        Ok(())
+    }
+
+    /// Leave out dedications, epigraphs, prefaces and the like (see `is_front_section`),
+    /// whose vocabulary isn't the book's own. Their chapters stay in `chapters` with
+    /// empty text, so chapter indices don't shift; returns how many were left out.
+    pub fn exclude_front_sections(&mut self) -> usize {
+        let total = self.chapters.len();
+        let mut excluded = 0;
+        for (index, chapter) in self.chapters.iter_mut().enumerate() {
+            if !is_front_section(&chapter.title, index, total) || chapter.text.is_empty() {
+                continue;
+            }
+            self.excluded_sections.push(ExcludedSection {
+                index,
+                spine_index: chapter.spine_index,
+                title: chapter.title.clone(),
+                word_count: chapter.text.split_whitespace().count(),
+            });
+            chapter.text.clear();
+            chapter.verse.clear();
+            chapter.footnotes.clear();
+            excluded += 1;
+        }
+
+        if excluded > 0 {
+            let (full_text, segments) = join_chapters(&self.chapters, false);
+            self.full_text = full_text;
+            self.verse = verse_ranges(&self.chapters, &segments);
+            self.segments = segments;
+            self.footnotes = self.chapters.iter().flat_map(|c| c.footnotes.iter().cloned()).collect();
+        }
+        excluded
+    }
+
+    /// Build the joined `full_text` from already-cleaned chapters
+    pub fn from_chapters(chapters: Vec<Chapter>) -> Self {
+        let (full_text, segments) = join_chapters(&chapters, false);
+        let chapters_len = chapters.len();
+        Self {
+            full_text,
+            verse: verse_ranges(&chapters, &segments),
+            segments,
+            chapter_count: chapters.len(),
+            footnotes: chapters.iter().flat_map(|c| c.footnotes.iter().cloned()).collect(),
+            chapters,
+            language: None,
+            skipped_items: Vec::new(),
+            spine_items: chapters_len,
+            warnings: Vec::new(),
+            text_quality: None,
+            excluded_sections: Vec::new(),
+        }
+    }
+}
+
+/// Share of spine items that must load for a book to be analyzed (see `ExtractedText::check_readable`)
+pub const MIN_READABLE_SHARE: f64 = 0.8;
+
+/// Titles of sections that come before the book proper (see `is_front_section`)
+const FRONT_SECTION_TITLES: [&str; 6] = [
+    "preface",
+    "introduction",
+    "note on the translation",
+    "translator's note",
+    "dedication",
+    "epigraph",
+];
+
+/// Words a front section title may start with ("The Preface", "Author's Introduction")
+const FRONT_SECTION_QUALIFIERS: [&str; 6] = ["a", "an", "the", "author's", "translator's", "editor's"];
+
+/// Words that may follow a front section title ("Preface to the Second Edition",
+/// "Introduction by the Editor"); anything else makes it a chapter title
+/// ("The Introduction of Mr. Darcy")
+const FRONT_SECTION_CONTINUATIONS: [&str; 3] = ["to", "by", "for"];
+
+/// Whether the chapter at `index` of `total` looks like front matter by its title.
+/// Only the first half of the book is considered, so an "Introduction" chapter
+/// late in a novel is kept.
+fn is_front_section(title: &str, index: usize, total: usize) -> bool {
+    let title = normalize_plain_text(&title.to_lowercase());
+    index * 2 < total && is_front_title(title.trim_end_matches(|c: char| !c.is_alphanumeric()))
+}
+
+/// Whether a lowercased title is one of `FRONT_SECTION_TITLES`, possibly qualified
+/// and continued (see `FRONT_SECTION_QUALIFIERS` and `FRONT_SECTION_CONTINUATIONS`)
+fn is_front_title(title: &str) -> bool {
+    let mut rest = title;
+    loop {
+        if FRONT_SECTION_TITLES.iter().any(|t| rest.strip_prefix(t).is_some_and(ends_front_title)) {
+            return true;
+        }
+        match FRONT_SECTION_QUALIFIERS.iter().find_map(|q| rest.strip_prefix(q)?.strip_prefix(' ')) {
+            Some(next) => rest = next,
+            None => return false,
+        }
+    }
+}
+
+/// Whether what follows a front section title leaves it one: nothing, a subtitle
+/// after punctuation ("Preface: On Method"), or a continuation word
+fn ends_front_title(after: &str) -> bool {
+    if after.starts_with(char::is_alphanumeric) {
+        return false;
+    }
+    let after = after.trim_start();
+    match after.chars().next() {
+        None => true,
+        Some(c) if !c.is_alphanumeric() => true,
+        Some(_) => after.split_whitespace().next().is_some_and(|word| FRONT_SECTION_CONTINUATIONS.contains(&word)),
+    }
+}
+
+/// Join chapter texts with blank lines, optionally following each chapter with its footnotes.
+/// Also returns the range each chapter (with its footnotes) occupies; offsets are counted
+/// as the text is built, so they stay exact for any content.
+pub fn join_chapters(chapters: &[Chapter], include_footnotes: bool) -> (String, Vec<Segment>) {
+    let mut text = String::new();
+    let mut segments = Vec::new();
+    let mut chars = 0;
+
+    for (chapter_index, chapter) in chapters.iter().enumerate() {
+        let notes = if include_footnotes { chapter.footnotes.as_slice() } else { &[] };
+        let mut segment: Option<Segment> = None;
+        for part in std::iter::once(&chapter.text).chain(notes).filter(|t| !t.is_empty()) {
+            if !text.is_empty() {
+                text.push_str("\n\n");
+                chars += 2;
+            }
+            let start = chars;
+            text.push_str(part);
+            chars += part.chars().count();
+            let segment = segment.get_or_insert(Segment {
+                spine_index: chapter.spine_index,
+                chapter_index,
+                char_start: start,
+                char_end: start,
+            });
+            segment.char_end = chars;
+        }
+        segments.extend(segment);
+    }
+
+    (text, segments)
+}
+
+/// The chapters' verse ranges in text they were joined into (see `join_chapters`)
+pub fn verse_ranges(chapters: &[Chapter], segments: &[Segment]) -> Vec<Range<usize>> {
+    segments
+        .iter()
+        .flat_map(|segment| {
+            let start = segment.char_start;
+            chapters[segment.chapter_index].verse.iter().map(move |r| start + r.start..start + r.end)
+        })
+        .collect()
+}
+
+/// `extract_text_with_options` with the default options (the app goes through `formats`)
+#[cfg(test)]
 pub fn extract_text(epub_path: &Path) -> Result<ExtractedText, EpubError> {
-    let mut doc = EpubDoc::new(epub_path).map_err(|e| EpubError::Open(e.to_string()))?;
+    extract_text_with_options(epub_path, CleanOptions::default())
+}
+
+/// Extract the book's text, keeping or dropping tables and figures as `options` say
+pub fn extract_text_with_options(epub_path: &Path, options: CleanOptions) -> Result<ExtractedText, EpubError> {
+    extract_text_with(epub_path, options, &AtomicBool::new(false), |_, _| {})
+}
+
+/// `extract_text` for big books: reports (spine items processed, total) after each
+/// item and stops with `EpubError::Cancelled` between items once `cancel` is set
+pub fn extract_text_with_progress(
+    epub_path: &Path,
+    cancel: &AtomicBool,
+    on_progress: impl FnMut(usize, usize),
+) -> Result<ExtractedText, EpubError> {
+    extract_text_with(epub_path, CleanOptions::default(), cancel, on_progress)
+}
+
+fn extract_text_with(
+    epub_path: &Path,
+    options: CleanOptions,
+    cancel: &AtomicBool,
+    on_progress: impl FnMut(usize, usize),
+) -> Result<ExtractedText, EpubError> {
+    let (chapters, report) = extract_chapters(epub_path, options, cancel, on_progress)?;
+    let mut extracted = ExtractedText::from_chapters(chapters);
+    extracted.skipped_items = report.skipped_items;
+    extracted.spine_items = report.spine_items;
+    extracted.warnings = report.warnings;
+    extracted.language = read_metadata(epub_path)
+        .ok()
+        .and_then(|metadata| metadata.language)
+        .and_then(|language| normalize_language(&language));
+    Ok(extracted)
+}
+
+/// Spine items that didn't yield text the way they should have
+#[derive(Debug, Default)]
+pub struct SpineReport {
+    /// Idrefs of items whose content couldn't be loaded
+    pub skipped_items: Vec<String>,
+    /// One message per unloadable or empty item
+    pub warnings: Vec<String>,
+    /// Number of items in the spine
+    pub spine_items: usize,
+}
+
+/// Extract the cleaned text of each non-empty spine item, in reading order,
+/// along with a report of items that couldn't be loaded or were empty
+pub fn extract_chapters(
+    epub_path: &Path,
+    options: CleanOptions,
+    cancel: &AtomicBool,
+    mut on_progress: impl FnMut(usize, usize),
+) -> Result<(Vec<Chapter>, SpineReport), EpubError> {
+    if is_drm_protected(epub_path) {
+        return Err(EpubError::DrmProtected);
+    }
+
+    let mut doc = open_doc(epub_path)?;
+
+    let toc = read_toc(&mut doc);
+    let notes_sections = notes_spine_items(&mut doc);
+    let mut chapters = Vec::new();
+
+    let cleaner = text_cleaner_with(options);
+
+    // Walk the spine by index and load each item by idref, so no item is skipped
+    // because the document's notion of "current" failed to advance
+    let spine: Vec<String> = doc.spine.iter().map(|item| item.idref.clone()).collect();
+    let mut report = SpineReport {
+        spine_items: spine.len(),
+        ..Default::default()
+    };
+    for (spine_index, idref) in spine.iter().enumerate() {
+        if cancel.load(Ordering::SeqCst) {
+            return Err(EpubError::Cancelled);
+        }
+        if spine_index > 0 {
+            on_progress(spine_index, spine.len());
+        }
+
+        let path = doc.resources.get(idref).map(|r| r.path.to_string_lossy().to_string());
+        let Ok(content) = read_spine_item(&mut doc, idref) else {
+            let warning = format!("Spine item {} ({}) could not be loaded", spine_index, idref);
+            eprintln!("Skipping: {}", warning);
+            report.skipped_items.push(idref.clone());
+            report.warnings.push(warning);
+            continue;
+        };
+        // An empty file is a broken item; a file with markup but no text is just an image page
+        if content.trim().is_empty() {
+            report.warnings.push(format!("Spine item {} ({}) is empty", spine_index, idref));
+            continue;
+        }
+
+        let (mut body, mut notes) = split_footnotes(&content);
+        // A notes section (per the landmarks) is all notes, whether or not they're marked up
+        if notes_sections.contains(&spine_index) {
+            if notes.is_empty() {
+                notes.push(body);
+            }
+            body = String::new();
+        }
+        let (normalized, verse) = clean_html_tagged(&cleaner, &body);
+        let footnotes: Vec<String> = notes
+            .iter()
+            .map(|note| clean_html(&cleaner, note))
+            .filter(|note| !note.is_empty())
+            .collect();
+
+        if !normalized.is_empty() || !footnotes.is_empty() {
+            chapters.push(Chapter {
+                spine_index,
+                path: path.unwrap_or_default(),
+                title: toc_title_for(&toc, spine_index)
+                    .unwrap_or_else(|| format!("Chapter {}", chapters.len() + 1)),
+                text: normalized,
+                footnotes,
+                verse,
+            });
+        }
+    }
+
+    on_progress(spine.len(), spine.len());
+    Ok((chapters, report))
+}
+
+/// Open an EPUB, telling a damaged archive, a missing container.xml and a missing
+/// package file apart instead of reporting the parser's error
+fn open_doc(epub_path: &Path) -> Result<EpubDoc<BufReader<File>>, EpubError> {
+    EpubDoc::new(epub_path).map_err(|e| diagnose_open_error(epub_path, e))
+}
+
+fn diagnose_open_error(epub_path: &Path, error: epub::doc::DocError) -> EpubError {
+    let mut archive = match epub::archive::EpubArchive::new(epub_path) {
+        Ok(archive) => archive,
+        Err(epub::archive::ArchiveError::Zip(e)) => return EpubError::NotAZip(e.to_string()),
+        Err(e) => return EpubError::Open(e.to_string()),
+    };
+    let Ok(container) = archive.get_container_file() else {
+        return EpubError::MissingContainer;
+    };
+    let container = String::from_utf8_lossy(&container);
+    let root_file = container
+        .split('<')
+        .find(|tag| tag_name(tag) == "rootfile")
+        .and_then(|tag| attr_value(tag, "full-path"));
+    match root_file {
+        Some(path) if archive.get_entry(&path).is_err() => EpubError::MissingOpf(path),
+        None => EpubError::MissingOpf("named in container.xml".to_string()),
+        Some(_) => EpubError::Open(error.to_string()),
+    }
+}
+
+/// The markup of a spine item. Text that isn't valid UTF-8 is read lossily rather
+/// than losing the whole item over a few bad bytes.
+fn read_spine_item<R: Read + Seek>(doc: &mut EpubDoc<R>, idref: &str) -> Result<String, EpubError> {
+    let (bytes, _mime) = doc
+        .get_resource(idref)
+        .ok_or_else(|| EpubError::SpineItemUnreadable { id: idref.to_string() })?;
+    Ok(match String::from_utf8(bytes) {
+        Ok(content) => content,
+        Err(e) => String::from_utf8_lossy(e.as_bytes()).into_owned(),
+    })
+}
+
+/// Encryption algorithms that only obfuscate embedded fonts; books using them are readable
+const FONT_OBFUSCATION: [&str; 2] = ["http://www.idpf.org/2008/embedding", "http://ns.adobe.com/pdf/enc#RC"];
+
+/// Whether the archive carries DRM: Adobe ADEPT (rights.xml), Apple FairPlay (sinf.xml),
+/// Readium LCP (license.lcpl), or encryption.xml entries beyond font obfuscation.
+/// Unreadable archives return false so `EpubDoc` reports the real error.
+fn is_drm_protected(epub_path: &Path) -> bool {
+    let Ok(mut archive) = epub::archive::EpubArchive::new(epub_path) else {
+        return false;
+    };
+
+    let has_entry = |name: &str| archive.files.iter().any(|f| f.eq_ignore_ascii_case(name));
+    if ["META-INF/rights.xml", "META-INF/sinf.xml", "META-INF/license.lcpl"]
+        .iter()
+        .any(|name| has_entry(name))
+    {
+        return true;
+    }
+
+    let Ok(encryption) = archive.get_entry_as_str("META-INF/encryption.xml") else {
+        return false;
+    };
+    let algorithms: Vec<&str> = encryption
+        .split("Algorithm=")
+        .skip(1)
+        .filter_map(|rest| {
+            let quote = rest.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+            rest[1..].split(quote).next()
+        })
+        .collect();
+
+    if algorithms.is_empty() {
+        encryption.contains("EncryptedData")
+    } else {
+        algorithms.iter().any(|alg| !FONT_OBFUSCATION.contains(alg))
+    }
+}
+
+/// What the HTML cleaner keeps besides the running text
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CleanOptions {
+    /// Drop tables, figures and captions with their content. Their fragments ("Fig",
+    /// "cont'd", column headers) make poor hard words, but a reader may want them.
+    pub strip_figures_and_tables: bool,
+}
+
+impl Default for CleanOptions {
+    /// The analysis default: tables and figures are stripped
+    fn default() -> Self {
+        Self { strip_figures_and_tables: true }
+    }
+}
 
-    let mut full_text = String::new();
-    let mut chapter_count = 0;
+/// Elements removed with their content when `CleanOptions::strip_figures_and_tables` is set
+const FIGURE_AND_TABLE_TAGS: [&str; 4] = ["table", "figure", "figcaption", "caption"];
 
-    // Build HTML cleaner - strip all tags, keep only text
+/// Build HTML cleaner - strip all tags, keep only text
+pub(crate) fn text_cleaner() -> Builder<'static> {
+    text_cleaner_with(CleanOptions::default())
+}
+
+/// `text_cleaner` with the given options. No attribute survives cleaning, so image
+/// alt text is never part of the text.
+pub(crate) fn text_cleaner_with(options: CleanOptions) -> Builder<'static> {
+    let mut removed = HashSet::from(["script", "style"]);
+    if options.strip_figures_and_tables {
+        removed.extend(FIGURE_AND_TABLE_TAGS);
+    }
     let mut cleaner = Builder::new();
     cleaner
         .tags(HashSet::new()) // No tags allowed - strips everything
-        .clean_content_tags(HashSet::from(["script", "style"]));
-
-    // Iterate through spine (reading order)
-    while doc.go_next() {
-        if let Some((content, _mime)) = doc.get_current_str() {
-            // Clean HTML to plain text
-            let clean = cleaner.clean(&content).to_string();
-
-            // Normalize whitespace
-            let normalized: String = clean
-                .split_whitespace()
-                .collect::<Vec<_>>()
-                .join(" ");
-
-            if !normalized.is_empty() {
-                if !full_text.is_empty() {
-                    full_text.push_str("\n\n");
+        .tag_attributes(HashMap::new()) // The defaults name "table", which may not be removed too
+        .clean_content_tags(removed);
+    cleaner
+}
+
+/// Clean HTML to plain text: block-level elements become paragraphs separated by
+/// "\n\n", and whitespace inside each paragraph is normalized to single spaces
+pub(crate) fn clean_html(cleaner: &Builder, html: &str) -> String {
+    clean_html_tagged(cleaner, html).0
+}
+
+/// `clean_html`, also returning the character ranges of verse paragraphs: those inside
+/// an element marked as verse (see `is_verse_tag`) and those made of several short
+/// `<br>`-separated lines. Adjacent verse paragraphs share one range.
+pub(crate) fn clean_html_tagged(cleaner: &Builder, html: &str) -> (String, Vec<Range<usize>>) {
+    let clean = cleaner.clean(&separate_blocks(html)).to_string();
+    // Undo the escaping ammonia serializes text with ("&amp;", "&nbsp;"); an entity the
+    // book itself escapes ("&amp;lt;") is text it shows, so it stays as written ("&lt;")
+    let clean = decode_entities(&clean);
+
+    let mut text = String::new();
+    let mut verse: Vec<Range<usize>> = Vec::new();
+    let mut chars = 0;
+    for piece in clean.split(PARAGRAPH_BREAK) {
+        let is_verse = piece.contains(VERSE_MARK) || is_line_broken_verse(piece);
+        // Rejoin hyphenated words while line breaks are still visible, one paragraph at a time
+        let piece: String = piece
+            .chars()
+            .filter(|&c| c != VERSE_MARK)
+            .map(|c| if c == LINE_BREAK { '\n' } else { c })
+            .collect();
+        let paragraph = normalize_plain_text(&piece);
+        if paragraph.is_empty() {
+            continue;
+        }
+        if !text.is_empty() {
+            text.push_str("\n\n");
+            chars += 2;
+        }
+        let range = chars..chars + paragraph.chars().count();
+        if is_verse {
+            match verse.last_mut() {
+                Some(last) if last.end + 2 == range.start => last.end = range.end,
+                _ => verse.push(range.clone()),
+            }
+        }
+        text.push_str(&paragraph);
+        chars = range.end;
+    }
+    (text, verse)
+}
+
+/// A paragraph needs this many `<br>`-separated lines to count as verse
+const MIN_VERSE_LINES: usize = 4;
+/// Longest line such a paragraph may have (prose broken with `<br>` runs the full width)
+const MAX_VERSE_LINE_CHARS: usize = 60;
+
+/// Whether a paragraph (before normalization) is a stanza set with line breaks
+fn is_line_broken_verse(paragraph: &str) -> bool {
+    is_verse_lines(paragraph.split(LINE_BREAK))
+}
+
+/// Whether the lines of a paragraph, as set, are a stanza: at least `MIN_VERSE_LINES`
+/// lines, none longer than `MAX_VERSE_LINE_CHARS`
+pub(crate) fn is_verse_lines<'a>(lines: impl Iterator<Item = &'a str>) -> bool {
+    let lines: Vec<&str> = lines.map(str::trim).filter(|l| !l.is_empty()).collect();
+    lines.len() >= MIN_VERSE_LINES && lines.iter().all(|line| line.chars().count() <= MAX_VERSE_LINE_CHARS)
+}
+
+/// Typographic variants folded into one character, so "don’t" is tokenized, looked up
+/// and stemmed exactly like "don't". Applied after NFKC, which already turns ligatures
+/// into letters and the non-breaking hyphen into U+2010.
+const PUNCTUATION_VARIANTS: &[(char, char)] = &[
+    ('\u{2019}', '\''), // right single quotation mark, the usual curly apostrophe
+    ('\u{2018}', '\''), // left single quotation mark, also used for elision ("‘tis")
+    ('\u{201B}', '\''), // single high-reversed-9 quotation mark
+    ('\u{02BC}', '\''), // modifier letter apostrophe
+    ('\u{2032}', '\''), // prime, mistaken for an apostrophe by some OCR
+    ('\u{2010}', '-'),  // hyphen
+    ('\u{2012}', '–'),  // figure dash
+    ('\u{2212}', '-'),  // minus sign
+    ('\u{2015}', '—'),  // horizontal bar
+    ('\u{2E3A}', '—'),  // two-em dash
+    ('\u{2E3B}', '—'),  // three-em dash
+];
+
+/// Apply NFKC (ligatures like "ﬁ" become "fi", so "ﬁnally" isn't a rare word of its
+/// own), fold apostrophes and dashes (see `PUNCTUATION_VARIANTS`), strip soft hyphens,
+/// rejoin line-break hyphenation checked against wordfreq, then normalize whitespace
+/// to single spaces
+pub(crate) fn normalize_plain_text(text: &str) -> String {
+    let text: String = text
+        .nfkc()
+        .map(|c| PUNCTUATION_VARIANTS.iter().find(|(from, _)| *from == c).map_or(c, |(_, to)| *to))
+        .collect();
+    let text = dehyphenate(&text, |word| nlp::wordfreq().word_frequency(word) > 0.0);
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Elements that start a new paragraph. Stripping their tags alone would glue
+/// the words on either side together ("believes</p><p>that's" -> "believesthat's").
+const BLOCK_TAGS: &[&str] = &[
+    "address", "article", "aside", "blockquote", "body", "caption", "dd", "div", "dl", "dt",
+    "figcaption", "figure", "footer", "h1", "h2", "h3", "h4", "h5", "h6", "header", "hr", "li",
+    "main", "nav", "ol", "p", "pre", "section", "table", "td", "th", "tr", "ul",
+];
+
+/// Placed at block boundaries by `separate_blocks`. Being text, it survives tag
+/// stripping; being whitespace, it never glues or splits a word.
+const PARAGRAPH_BREAK: char = '\u{2029}';
+/// Placed at `<br>` by `separate_blocks`, so source newlines aren't taken for lines of verse
+const LINE_BREAK: char = '\u{2028}';
+/// Follows the paragraph breaks inside verse elements (a private-use character,
+/// removed again by `clean_html_tagged`)
+const VERSE_MARK: char = '\u{E000}';
+
+/// Class names and `epub:type` values (after any vocabulary prefix, as in
+/// "z3998:verse") marking verse, which are also FB2's verse element names
+const VERSE_WORDS: [&str; 3] = ["poem", "verse", "stanza"];
+
+/// Whether an opening tag starts a verse element: an FB2 `<poem>` or `<stanza>`,
+/// or a block element with one of `VERSE_WORDS` as a whole class or `epub:type`
+/// token. Inline elements ("<span class=\"verse\">" for a verse number) don't count.
+fn is_verse_tag(tag: &str, name: &str) -> bool {
+    if VERSE_WORDS.contains(&name) {
+        return true;
+    }
+    if !BLOCK_TAGS.contains(&name) {
+        return false;
+    }
+    let is_verse_token = |token: &str| {
+        let token = token.rsplit(':').next().unwrap_or(token).to_ascii_lowercase();
+        VERSE_WORDS.contains(&token.as_str())
+    };
+    ["epub:type", "class"]
+        .iter()
+        .filter_map(|attr| attr_value(tag, attr))
+        .any(|value| value.split_whitespace().any(is_verse_token))
+}
+
+/// Insert a paragraph break before every opening or closing block-level tag, and a
+/// line break before `<br>`, which only breaks a line within its paragraph.
+/// Inline tags (`<b>`, `<span>`, ...) are left alone so "un<i>believ</i>able" stays one word.
+/// Paragraphs inside verse elements are marked with `VERSE_MARK`.
+fn separate_blocks(html: &str) -> String {
+    let mut out = String::with_capacity(html.len() + html.len() / 16);
+    separate_blocks_into(html, false, &mut out);
+    out
+}
+
+fn separate_blocks_into(html: &str, in_verse: bool, out: &mut String) {
+    let mut rest = html;
+
+    while let Some(lt) = rest.find('<') {
+        // A stray "<" with no tag after it is text; it and the rest are pushed below
+        let Some(gt) = rest[lt..].find('>') else {
+            break;
+        };
+        out.push_str(&rest[..lt]);
+        let tag = &rest[lt + 1..lt + gt];
+        let after = &rest[lt + gt + 1..];
+        if !tag.starts_with(['!', '?']) {
+            let name = tag_name(tag);
+            let is_open = !tag.starts_with('/') && !tag.ends_with('/');
+            if !in_verse && is_open && is_verse_tag(tag, &name) {
+                // The whole element is verse, and set apart from the text around it
+                let (inner, remainder) = split_element(after, &name);
+                out.push(PARAGRAPH_BREAK);
+                out.push(VERSE_MARK);
+                out.push_str(&rest[lt..lt + gt + 1]);
+                separate_blocks_into(inner, true, out);
+                out.push(PARAGRAPH_BREAK);
+                out.push_str(&format!("</{}>", name));
+                rest = remainder;
+                continue;
+            }
+            if name == "br" {
+                out.push(LINE_BREAK);
+            } else if BLOCK_TAGS.contains(&name.as_str()) {
+                out.push(PARAGRAPH_BREAK);
+                if in_verse {
+                    out.push(VERSE_MARK);
+                }
+            }
+        }
+        out.push_str(&rest[lt..lt + gt + 1]);
+        rest = after;
+    }
+    out.push_str(rest);
+}
+
+/// Strip soft hyphens (U+00AD) and rejoin words hyphenated across a line break.
+///
+/// "exam-\nple" becomes "example" when `is_word` accepts the joined form;
+/// otherwise the hyphen is kept and only the break removed ("well-\nknown" -> "well-known").
+fn dehyphenate(text: &str, is_word: impl Fn(&str) -> bool) -> String {
+    let chars: Vec<char> = text.chars().filter(|&c| c != '\u{AD}').collect();
+    let mut out = String::with_capacity(text.len());
+    // Letters of the word currently being written to `out`
+    let mut word = String::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+
+        if c == '-' && !word.is_empty() {
+            let mut j = i + 1;
+            let mut line_break = false;
+            while j < chars.len() && chars[j].is_whitespace() {
+                line_break |= chars[j] == '\n';
+                j += 1;
+            }
+            if line_break && j < chars.len() && chars[j].is_alphabetic() {
+                let rest: String = chars[j..].iter().take_while(|c| c.is_alphabetic()).collect();
+                if !is_word(&format!("{}{}", word, rest).to_lowercase()) {
+                    out.push('-');
+                    word.clear();
                 }
-                full_text.push_str(&normalized);
-                chapter_count += 1;
+                // Drop the line break and carry on with the second half
+                i = j;
+                continue;
             }
         }
+
+        if c.is_alphabetic() {
+            word.push(c);
+        } else {
+            word.clear();
+        }
+        out.push(c);
+        i += 1;
+    }
+
+    out
+}
+
+/// Book metadata from an EPUB's OPF package document
+#[derive(Debug, Default, Clone)]
+pub struct EpubMetadata {
+    /// `dc:title`, or the file name without its extension when missing
+    pub title: String,
+    /// `dc:creator` entries in document order
+    pub authors: Vec<String>,
+    /// `dc:date` (usually the publication date)
+    pub date: Option<String>,
+    /// `dc:language`, e.g. "en"
+    pub language: Option<String>,
+}
+
+impl EpubMetadata {
+    /// Metadata for a file whose OPF couldn't be read: just the file-name title
+    pub fn untitled(epub_path: &Path) -> Self {
+        Self {
+            title: file_title(epub_path),
+            ..Default::default()
+        }
     }
+}
 
-    Ok(ExtractedText {
-        full_text,
-        chapter_count,
+/// Read title, authors, date and language from an EPUB without extracting its text
+pub fn read_metadata(epub_path: &Path) -> Result<EpubMetadata, EpubError> {
+    let doc = open_doc(epub_path)?;
+
+    let value = |property: &str| {
+        doc.mdata(property)
+            .map(|item| item.value.trim().to_string())
+            .filter(|v| !v.is_empty())
+    };
+
+    Ok(EpubMetadata {
+        title: value("title").unwrap_or_else(|| file_title(epub_path)),
+        authors: doc
+            .metadata
+            .iter()
+            .filter(|item| item.property == "creator")
+            .map(|item| item.value.trim().to_string())
+            .filter(|v| !v.is_empty())
+            .collect(),
+        date: value("date"),
+        language: value("language"),
     })
 }
 
+/// An image file read out of an EPUB
+#[derive(Debug, Clone)]
+pub struct CoverImage {
+    pub bytes: Vec<u8>,
+    /// Media type from the manifest, e.g. "image/jpeg"
+    pub mime: String,
+}
+
+/// The book's cover: the manifest item with `properties="cover-image"` or the one named by
+/// `<meta name="cover">`, else the first image of the first spine document. None only
+/// when there is no image to be found.
+pub fn extract_cover(epub_path: &Path) -> Result<Option<CoverImage>, EpubError> {
+    let mut doc = open_doc(epub_path)?;
+
+    // `get_cover_id` only looks at the EPUB 3 property in EPUB 3 files, but many carry just the meta
+    let declared = [doc.get_cover_id(), doc.mdata("cover").map(|item| item.value.clone())];
+    for id in declared.into_iter().flatten() {
+        if let Some((bytes, mime)) = doc.get_resource(&id) {
+            return Ok(Some(CoverImage { bytes, mime }));
+        }
+    }
+
+    let Some(first) = doc.spine.first().map(|item| item.idref.clone()) else {
+        return Ok(None);
+    };
+    let base = doc
+        .resources
+        .get(&first)
+        .and_then(|r| r.path.parent().map(Path::to_path_buf))
+        .unwrap_or_default();
+    let Some((html, _mime)) = doc.get_resource_str(&first) else {
+        return Ok(None);
+    };
+    for src in image_sources(&html) {
+        let path = normalize_path(&base.join(src.split('#').next().unwrap_or_default()));
+        let Some(mime) = doc.get_resource_mime_by_path(&path).filter(|mime| mime.starts_with("image/")) else {
+            continue;
+        };
+        if let Some(bytes) = doc.get_resource_by_path(&path) {
+            return Ok(Some(CoverImage { bytes, mime }));
+        }
+    }
+    Ok(None)
+}
+
+/// Targets of `<img src>` and SVG `<image href>` / `<image xlink:href>` tags, in document order
+fn image_sources(html: &str) -> Vec<String> {
+    html.split('<')
+        .filter_map(|tag| {
+            let tag = tag.split('>').next().unwrap_or_default();
+            match tag_name(tag).as_str() {
+                "img" => attr_value(tag, "src"),
+                "image" => attr_value(tag, "xlink:href").or_else(|| attr_value(tag, "href")),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// File name without its extension, used as a title of last resort
+fn file_title(path: &Path) -> String {
+    path.file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// ISO 639-2 codes (both bibliographic and terminology forms) with a two-letter equivalent
+const THREE_LETTER_LANGUAGES: &[(&str, &str)] = &[
+    ("eng", "en"),
+    ("ger", "de"),
+    ("deu", "de"),
+    ("fre", "fr"),
+    ("fra", "fr"),
+    ("spa", "es"),
+    ("ita", "it"),
+    ("por", "pt"),
+    ("dut", "nl"),
+    ("nld", "nl"),
+    ("swe", "sv"),
+    ("dan", "da"),
+    ("nor", "no"),
+    ("fin", "fi"),
+    ("pol", "pl"),
+    ("rus", "ru"),
+    ("gre", "el"),
+    ("ell", "el"),
+    ("lat", "la"),
+    ("chi", "zh"),
+    ("zho", "zh"),
+    ("jpn", "ja"),
+    ("kor", "ko"),
+];
+
+/// Language names some books put in dc:language instead of a code, in English and
+/// in the language itself
+const LANGUAGE_NAMES: &[(&str, &str)] = &[
+    ("english", "en"),
+    ("german", "de"),
+    ("deutsch", "de"),
+    ("french", "fr"),
+    ("français", "fr"),
+    ("francais", "fr"),
+    ("spanish", "es"),
+    ("español", "es"),
+    ("espanol", "es"),
+    ("italian", "it"),
+    ("italiano", "it"),
+    ("portuguese", "pt"),
+    ("português", "pt"),
+    ("dutch", "nl"),
+    ("nederlands", "nl"),
+    ("swedish", "sv"),
+    ("svenska", "sv"),
+    ("danish", "da"),
+    ("dansk", "da"),
+    ("norwegian", "no"),
+    ("norsk", "no"),
+    ("finnish", "fi"),
+    ("suomi", "fi"),
+    ("polish", "pl"),
+    ("polski", "pl"),
+    ("russian", "ru"),
+    ("greek", "el"),
+    ("latin", "la"),
+    ("chinese", "zh"),
+    ("japanese", "ja"),
+    ("korean", "ko"),
+];
+
+/// Reduce a language tag ("en-US", "EN_gb", "eng", "English") to its base two-letter code.
+/// Returns None for empty, undetermined ("und", "mul", "zxx") or unrecognized values,
+/// so the language is guessed from the text instead.
+pub(crate) fn normalize_language(tag: &str) -> Option<String> {
+    let tag = tag.trim().to_lowercase();
+    if let Some((_, code)) = LANGUAGE_NAMES.iter().find(|(name, _)| *name == tag) {
+        return Some(code.to_string());
+    }
+    let base = tag.split(['-', '_']).next()?;
+    if base.len() == 2 && base.chars().all(|c| c.is_ascii_lowercase()) {
+        return Some(base.to_string());
+    }
+    THREE_LETTER_LANGUAGES
+        .iter()
+        .chain(LANGUAGE_NAMES)
+        .find(|(known, _)| *known == base)
+        .map(|(_, two)| two.to_string())
+}
+
+/// Read the table of contents of an EPUB
+pub fn get_toc(epub_path: &Path) -> Result<Vec<TocEntry>, EpubError> {
+    let mut doc = open_doc(epub_path)?;
+    Ok(read_toc(&mut doc))
+}
+
+/// Spine indexes of notes sections, as listed by the EPUB3 nav landmarks or the EPUB2 OPF guide
+fn notes_spine_items<R: Read + Seek>(doc: &mut EpubDoc<R>) -> HashSet<usize> {
+    let mut items = HashSet::new();
+
+    if let Some(nav_id) = doc.get_nav_id() {
+        let nav_path = doc.resources.get(&nav_id).map(|r| r.path.clone());
+        if let (Some(nav_path), Some((html, _mime))) = (nav_path, doc.get_resource_str(&nav_id)) {
+            let base = nav_path.parent().map(Path::to_path_buf).unwrap_or_default();
+            items.extend(notes_landmark_hrefs(&html).iter().filter_map(|href| resolve_href(doc, &base, href)));
+        }
+    }
+
+    let (root_file, root_base) = (doc.root_file.clone(), doc.root_base.clone());
+    if let Some(opf) = doc.get_resource_str_by_path(&root_file) {
+        items.extend(notes_landmark_hrefs(&opf).iter().filter_map(|href| resolve_href(doc, &root_base, href)));
+    }
+
+    items
+}
+
+/// Landmark types of whole notes sections
+const NOTES_SECTION_TYPES: [&str; 5] = ["notes", "footnotes", "endnotes", "rearnotes", "doc-endnotes"];
+
+/// Hrefs of landmarks pointing at notes sections: nav `<a epub:type="endnotes">` links
+/// and OPF guide `<reference type="notes">` entries
+fn notes_landmark_hrefs(html: &str) -> Vec<String> {
+    html.split('<')
+        .skip(1)
+        .filter_map(|rest| {
+            let tag = &rest[..rest.find('>')?];
+            if !matches!(tag_name(tag).as_str(), "a" | "reference") {
+                return None;
+            }
+            let types = attr_value(tag, "epub:type").or_else(|| attr_value(tag, "type"))?.to_ascii_lowercase();
+            let is_notes = types.split_whitespace().any(|t| NOTES_SECTION_TYPES.contains(&t));
+            is_notes.then(|| attr_value(tag, "href")).flatten()
+        })
+        .collect()
+}
+
+/// Prefer the EPUB3 nav document, falling back to the EPUB2 toc.ncx parsed by the epub crate
+fn read_toc<R: Read + Seek>(doc: &mut EpubDoc<R>) -> Vec<TocEntry> {
+    if let Some(nav_id) = doc.get_nav_id() {
+        let nav_path = doc.resources.get(&nav_id).map(|r| r.path.clone());
+        if let (Some(nav_path), Some((html, _mime))) = (nav_path, doc.get_resource_str(&nav_id)) {
+            let base = nav_path.parent().map(Path::to_path_buf).unwrap_or_default();
+            let entries: Vec<TocEntry> = parse_nav_toc(&html)
+                .into_iter()
+                .map(|(title, href, depth)| TocEntry {
+                    title,
+                    spine_index: resolve_href(doc, &base, &href),
+                    depth,
+                })
+                .collect();
+            if !entries.is_empty() {
+                return entries;
+            }
+        }
+    }
+
+    let mut entries = Vec::new();
+    flatten_navpoints(doc, &doc.toc, 0, &mut entries);
+    entries
+}
+
+fn flatten_navpoints<R: Read + Seek>(
+    doc: &EpubDoc<R>,
+    points: &[epub::doc::NavPoint],
+    depth: usize,
+    out: &mut Vec<TocEntry>,
+) {
+    for point in points {
+        let content = point.content.to_string_lossy();
+        let file = content.split('#').next().unwrap_or_default();
+        out.push(TocEntry {
+            title: point.label.trim().to_string(),
+            spine_index: doc.resource_uri_to_chapter(&normalize_path(Path::new(file))),
+            depth,
+        });
+        flatten_navpoints(doc, &point.children, depth + 1, out);
+    }
+}
+
+/// Map a nav href (relative to the nav document) to a spine index
+fn resolve_href<R: Read + Seek>(doc: &EpubDoc<R>, base: &Path, href: &str) -> Option<usize> {
+    let file = href.split('#').next().unwrap_or_default();
+    if file.is_empty() {
+        return None;
+    }
+    doc.resource_uri_to_chapter(&normalize_path(&base.join(file)))
+}
+
+/// Resolve `.` and `..` components so paths compare equal to manifest paths
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                out.pop();
+            }
+            other => out.push(other),
+        }
+    }
+    out
+}
+
+/// Extract (title, href, depth) triples from an EPUB3 nav document's `toc` nav
+fn parse_nav_toc(html: &str) -> Vec<(String, String, usize)> {
+    // Locate the toc nav (or the first nav if none is marked)
+    let start = html
+        .match_indices("<nav")
+        .map(|(i, _)| i)
+        .find(|&i| {
+            let tag_end = html[i..].find('>').map(|e| i + e).unwrap_or(html.len());
+            html[i..tag_end].contains("toc")
+        })
+        .or_else(|| html.find("<nav"));
+    let Some(start) = start else {
+        return Vec::new();
+    };
+    let end = html[start..].find("</nav>").map(|e| start + e).unwrap_or(html.len());
+    let nav = &html[start..end];
+
+    let mut entries = Vec::new();
+    let mut list_depth = 0usize;
+    let mut current: Option<(String, String)> = None;
+    let mut rest = nav;
+
+    while let Some(lt) = rest.find('<') {
+        if let Some((_, text)) = current.as_mut() {
+            text.push_str(&rest[..lt]);
+        }
+        let Some(gt) = rest[lt..].find('>') else {
+            break;
+        };
+        let tag = &rest[lt + 1..lt + gt];
+        rest = &rest[lt + gt + 1..];
+
+        let name = tag_name(tag);
+
+        match (tag.starts_with('/'), name.as_str()) {
+            (false, "ol") => list_depth += 1,
+            (true, "ol") => list_depth = list_depth.saturating_sub(1),
+            (false, "a") => {
+                if let Some(href) = attr_value(tag, "href") {
+                    current = Some((href, String::new()));
+                }
+            }
+            (true, "a") => {
+                if let Some((href, text)) = current.take() {
+                    let title = decode_entities(&text).split_whitespace().collect::<Vec<_>>().join(" ");
+                    if !title.is_empty() {
+                        entries.push((title, href, list_depth.saturating_sub(1)));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    entries
+}
+
+/// `epub:type` / ARIA `role` values marking footnote and endnote bodies
+const NOTE_TYPES: [&str; 10] = [
+    "footnote", "footnotes", "endnote", "endnotes", "rearnote", "rearnotes", "note",
+    "doc-footnote", "doc-endnote", "doc-endnotes",
+];
+/// `epub:type` / ARIA `role` values marking note reference markers
+const NOTEREF_TYPES: [&str; 2] = ["noteref", "doc-noteref"];
+
+/// Classes marking note bodies in books without `epub:type` (common in converted books)
+const NOTE_CLASSES: [&str; 6] = ["footnote", "footnotes", "endnote", "endnotes", "fn", "notes"];
+/// Classes marking note reference markers
+const NOTEREF_CLASSES: [&str; 5] = ["noteref", "footnote-ref", "footnote-reference", "fnref", "endnote-ref"];
+/// Elements that hold a whole note body when their class or id says so
+const NOTE_CONTAINERS: [&str; 7] = ["aside", "blockquote", "dd", "div", "li", "p", "section"];
+
+/// Whether an element id names a note: "fn3", "footnote-12", "endnote_4", "ftn7"
+fn is_note_id(id: &str) -> bool {
+    let id = id.to_ascii_lowercase();
+    ["footnote", "endnote", "ftn", "fn", "note"].iter().any(|prefix| {
+        id.strip_prefix(prefix)
+            .map(|rest| rest.trim_start_matches(['-', '_']))
+            .is_some_and(|number| !number.is_empty() && number.chars().all(|c| c.is_ascii_digit()))
+    })
+}
+
+/// Split chapter HTML into its main text and the HTML of each footnote/endnote body.
+///
+/// Notes are found by `epub:type`/`role`, or failing that by a footnote-like class
+/// or id on a block element. Note references (`epub:type="noteref"`, a noteref class,
+/// or a `<sup>` holding only a number or symbol) are dropped, so "word<sup>23</sup>"
+/// no longer reads as "word23".
+fn split_footnotes(html: &str) -> (String, Vec<String>) {
+    let mut body = String::with_capacity(html.len());
+    let mut notes = Vec::new();
+    let mut rest = html;
+
+    while let Some(lt) = rest.find('<') {
+        // A stray "<" with no tag after it is text; it and the rest are pushed below
+        let Some(gt) = rest[lt..].find('>') else {
+            break;
+        };
+        body.push_str(&rest[..lt]);
+        let tag = &rest[lt + 1..lt + gt];
+        let after = &rest[lt + gt + 1..];
+
+        let is_open = !tag.starts_with(['/', '!', '?']) && !tag.ends_with('/');
+        if is_open {
+            let name = tag_name(tag);
+            let types: Vec<String> = ["epub:type", "role"]
+                .iter()
+                .filter_map(|attr| attr_value(tag, attr))
+                .flat_map(|v| v.split_whitespace().map(str::to_ascii_lowercase).collect::<Vec<_>>())
+                .collect();
+            let classes: Vec<String> = attr_value(tag, "class")
+                .map(|v| v.split_whitespace().map(str::to_ascii_lowercase).collect())
+                .unwrap_or_default();
+            let is_container = NOTE_CONTAINERS.contains(&name.as_str());
+            let is_note = types.iter().any(|t| NOTE_TYPES.contains(&t.as_str()))
+                || is_container
+                    && (classes.iter().any(|c| NOTE_CLASSES.contains(&c.as_str()))
+                        || attr_value(tag, "id").is_some_and(|id| is_note_id(&id)));
+            let is_ref = types.iter().any(|t| NOTEREF_TYPES.contains(&t.as_str()))
+                || classes.iter().any(|c| NOTEREF_CLASSES.contains(&c.as_str()));
+
+            if is_note || is_ref || name == "sup" {
+                let (inner, remainder) = split_element(after, &name);
+                if is_note {
+                    // A notes section holding individual notes yields each of them
+                    let (_, nested) = split_footnotes(inner);
+                    if nested.is_empty() {
+                        notes.push(inner.to_string());
+                    } else {
+                        notes.extend(nested);
+                    }
+                    rest = remainder;
+                    continue;
+                }
+                if is_ref || is_note_marker(inner) {
+                    rest = remainder;
+                    continue;
+                }
+            }
+        }
+
+        body.push_str(&rest[lt..lt + gt + 1]);
+        rest = after;
+    }
+    body.push_str(rest);
+
+    (body, notes)
+}
+
+/// Content of an element up to its matching close tag, and the HTML after that tag
+fn split_element<'a>(html: &'a str, name: &str) -> (&'a str, &'a str) {
+    let mut depth = 1usize;
+    let mut pos = 0;
+
+    while let Some(lt) = html[pos..].find('<') {
+        let start = pos + lt;
+        let Some(gt) = html[start..].find('>') else {
+            break;
+        };
+        let tag = &html[start + 1..start + gt];
+        pos = start + gt + 1;
+
+        if tag_name(tag) == name {
+            if tag.starts_with('/') {
+                depth -= 1;
+                if depth == 0 {
+                    return (&html[..start], &html[pos..]);
+                }
+            } else if !tag.ends_with('/') {
+                depth += 1;
+            }
+        }
+    }
+
+    (html, "")
+}
+
+/// Whether superscript content is just a reference marker like "23", "*" or "[4]"
+fn is_note_marker(inner: &str) -> bool {
+    let mut text = String::new();
+    let mut in_tag = false;
+    for c in inner.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' => in_tag = false,
+            c if !in_tag && !c.is_whitespace() => text.push(c),
+            _ => {}
+        }
+    }
+    !text.is_empty()
+        && text.chars().count() <= 6
+        && text.chars().all(|c| c.is_ascii_digit() || "*†‡§¶[]()".contains(c))
+}
+
+/// Lowercased element name of a tag's inner text (`a href=..` -> "a", `/p` -> "p")
+fn tag_name(tag: &str) -> String {
+    tag.trim_start_matches('/')
+        .split(|c: char| c.is_whitespace() || c == '/')
+        .next()
+        .unwrap_or_default()
+        .to_ascii_lowercase()
+}
+
+fn attr_value(tag: &str, attr: &str) -> Option<String> {
+    let pattern = format!("{}=", attr);
+    let pos = tag
+        .match_indices(&pattern)
+        .map(|(i, _)| i)
+        .find(|&i| i == 0 || tag[..i].ends_with(char::is_whitespace))?;
+    let value = &tag[pos + pattern.len()..];
+    let quote = value.chars().next()?;
+    if quote == '"' || quote == '\'' {
+        value[1..].split(quote).next().map(str::to_string)
+    } else {
+        value.split_whitespace().next().map(str::to_string)
+    }
+}
+
+/// Longest reference name worth looking up ("CounterClockwiseContourIntegral" is 31)
+const MAX_ENTITY_LEN: usize = 32;
+
+/// Decode character references: named ones from the HTML5 table ("&mdash;", "&rsquo;")
+/// and numeric ones ("&#8217;", "&#x2019;"). Anything unrecognized is left as written.
+pub(crate) fn decode_entities(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp + 1..];
+        let decoded = rest
+            .find(';')
+            .filter(|&semi| semi <= MAX_ENTITY_LEN)
+            .and_then(|semi| Some((decode_entity(&rest[..semi])?, semi + 1)));
+        match decoded {
+            Some((chars, len)) => {
+                out.push_str(&chars);
+                rest = &rest[len..];
+            }
+            None => out.push('&'),
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// The characters a reference name (between "&" and ";") stands for
+fn decode_entity(name: &str) -> Option<String> {
+    let (first, second) = match name.strip_prefix('#') {
+        Some(number) => {
+            let code = match number.strip_prefix(['x', 'X']) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                None => number.parse().ok()?,
+            };
+            (code, 0)
+        }
+        None => *markup5ever::data::NAMED_ENTITIES.get(format!("{};", name).as_str())?,
+    };
+    if first == 0 {
+        return None;
+    }
+    [first, second].into_iter().filter(|&c| c != 0).map(char::from_u32).collect()
+}
+
+/// Title of the closest TOC entry at or before the given spine index
+fn toc_title_for(toc: &[TocEntry], spine_index: usize) -> Option<String> {
+    let mut best: Option<(usize, &TocEntry)> = None;
+    for entry in toc {
+        if let Some(idx) = entry.spine_index {
+            // Keep the first entry for a given file; later ones are usually sub-sections
+            if idx <= spine_index && best.map(|(b, _)| idx > b).unwrap_or(true) {
+                best = Some((idx, entry));
+            }
+        }
+    }
+    best.map(|(_, entry)| entry.title.clone())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::io::Write;
+
+    /// Write an EPUB with the given files (besides mimetype and container.xml) to `dir/name`
+    fn write_zip(dir: &Path, name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let path = dir.join(name);
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(&path).unwrap());
+        let stored = zip::write::SimpleFileOptions::default().compression_method(zip::CompressionMethod::Stored);
+        let container = [
+            ("mimetype", "application/epub+zip"),
+            (
+                "META-INF/container.xml",
+                r#"<?xml version="1.0"?>
+                <container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+                  <rootfiles><rootfile full-path="content.opf" media-type="application/oebps-package+xml"/></rootfiles>
+                </container>"#,
+            ),
+        ];
+        for (entry, content) in container.iter().chain(files) {
+            zip.start_file(*entry, stored).unwrap();
+            zip.write_all(content.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+        path
+    }
+
+    /// Write a one-chapter EPUB, plus any extra META-INF entries, to `dir/name`
+    fn write_epub(dir: &Path, name: &str, extra: &[(&str, &str)]) -> PathBuf {
+        let chapter = "<html><body><p>It was a bright cold day in April.</p></body></html>";
+        write_epub_with(dir, name, chapter, extra)
+    }
+
+    /// Like `write_epub`, with the given chapter markup
+    fn write_epub_with(dir: &Path, name: &str, chapter: &str, extra: &[(&str, &str)]) -> PathBuf {
+        let opf = r#"<?xml version="1.0"?>
+            <package xmlns="http://www.idpf.org/2007/opf" version="3.0" unique-identifier="id">
+              <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+                <dc:identifier id="id">fixture</dc:identifier><dc:title>Fixture</dc:title>
+              </metadata>
+              <manifest><item id="c1" href="c1.xhtml" media-type="application/xhtml+xml"/></manifest>
+              <spine><itemref idref="c1"/></spine>
+            </package>"#;
+        let mut files = vec![("content.opf", opf), ("c1.xhtml", chapter)];
+        files.extend_from_slice(extra);
+        write_zip(dir, name, &files)
+    }
+
+    fn encryption_xml(algorithm: &str, uri: &str) -> String {
+        format!(
+            r#"<encryption xmlns="urn:oasis:names:tc:opendocument:xmlns:container"
+                 xmlns:enc="http://www.w3.org/2001/04/xmlenc#">
+              <enc:EncryptedData>
+                <enc:EncryptionMethod Algorithm="{}"/>
+                <enc:CipherData><enc:CipherReference URI="{}"/></enc:CipherData>
+              </enc:EncryptedData>
+            </encryption>"#,
+            algorithm, uri
+        )
+    }
+
+    fn fixture(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(name)
+    }
 
     #[test]
-    fn test_html_cleaning() {
-        let mut cleaner = Builder::new();
-        cleaner
-            .tags(HashSet::new())
-            .clean_content_tags(HashSet::from(["script", "style"]));
+    fn test_read_metadata_from_fixture() {
+        let metadata = read_metadata(&fixture("minimal.epub")).unwrap();
+        assert_eq!(metadata.title, "The Minimal Fixture");
+        assert_eq!(metadata.authors, vec!["Ada Lovelace", "Charles Babbage"]);
+        assert_eq!(metadata.date.as_deref(), Some("1843-09-01"));
+        assert_eq!(metadata.language.as_deref(), Some("en-GB"));
+        let cover = extract_cover(&fixture("minimal.epub")).unwrap().unwrap();
+        assert!(cover.bytes.starts_with(b"\x89PNG"));
+        assert_eq!(cover.mime, "image/png");
+
+        let extracted = extract_text(&fixture("minimal.epub")).unwrap();
+        assert_eq!(extracted.language.as_deref(), Some("en"));
+        assert!(extracted.full_text.contains("Analytical Engine"));
+    }
+
+    #[test]
+    fn test_cover_falls_back_to_first_image() {
+        let opf = r#"<?xml version="1.0"?>
+            <package xmlns="http://www.idpf.org/2007/opf" version="3.0" unique-identifier="id">
+              <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+                <dc:identifier id="id">fixture</dc:identifier>
+              </metadata>
+              <manifest>
+                <item id="front" href="text/front.xhtml" media-type="application/xhtml+xml"/>
+                <item id="art" href="images/front.jpg" media-type="image/jpeg"/>
+              </manifest>
+              <spine><itemref idref="front"/></spine>
+            </package>"#;
+        let front = r#"<html><body><svg><image width="600" xlink:href="../images/front.jpg"/></svg></body></html>"#;
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_zip(
+            dir.path(),
+            "undeclared.epub",
+            &[("content.opf", opf), ("text/front.xhtml", front), ("images/front.jpg", "not really a jpeg")],
+        );
+
+        let cover = extract_cover(&path).unwrap().unwrap();
+        assert_eq!(cover.bytes, b"not really a jpeg");
+        assert_eq!(cover.mime, "image/jpeg");
+        assert_eq!(image_sources(r#"<p><img alt="x" src='a.png'/></p>"#), ["a.png"]);
+    }
+
+    #[test]
+    fn test_read_metadata_fallbacks() {
+        let opf = r#"<?xml version="1.0"?>
+            <package xmlns="http://www.idpf.org/2007/opf" version="3.0" unique-identifier="id">
+              <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+                <dc:identifier id="id">fixture</dc:identifier>
+              </metadata>
+              <manifest><item id="c1" href="c1.xhtml" media-type="application/xhtml+xml"/></manifest>
+              <spine><itemref idref="c1"/></spine>
+            </package>"#;
+        let dir = tempfile::tempdir().unwrap();
+        let chapter = "<html><body><p>Untitled.</p></body></html>";
+        let path = write_zip(dir.path(), "Wuthering Heights.epub", &[("content.opf", opf), ("c1.xhtml", chapter)]);
+
+        let metadata = read_metadata(&path).unwrap();
+        assert_eq!(metadata.title, "Wuthering Heights");
+        assert!(metadata.authors.is_empty());
+        assert_eq!(metadata.language, None);
+        assert!(extract_cover(&path).unwrap().is_none());
+
+        let missing = dir.path().join("Not There.epub");
+        assert!(read_metadata(&missing).is_err());
+        assert_eq!(EpubMetadata::untitled(&missing).title, "Not There");
+    }
+
+    #[test]
+    fn test_every_spine_item_is_read_and_unloadable_ones_reported() {
+        let opf = r#"<?xml version="1.0"?>
+            <package xmlns="http://www.idpf.org/2007/opf" version="3.0" unique-identifier="id">
+              <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+                <dc:identifier id="id">fixture</dc:identifier><dc:title>Fixture</dc:title>
+              </metadata>
+              <manifest>
+                <item id="c1" href="c1.xhtml" media-type="application/xhtml+xml"/>
+                <item id="blank" href="blank.xhtml" media-type="application/xhtml+xml"/>
+                <item id="lost" href="lost.xhtml" media-type="application/xhtml+xml"/>
+                <item id="c2" href="c2.xhtml" media-type="application/xhtml+xml"/>
+              </manifest>
+              <spine>
+                <itemref idref="c1"/><itemref idref="blank"/><itemref idref="lost"/><itemref idref="c2"/>
+              </spine>
+            </package>"#;
+        let dir = tempfile::tempdir().unwrap();
+        // lost.xhtml is in the manifest and spine but missing from the archive
+        let path = write_zip(
+            dir.path(),
+            "spine.epub",
+            &[
+                ("content.opf", opf),
+                ("c1.xhtml", "<html><body><p>First of all.</p></body></html>"),
+                ("blank.xhtml", "<html><body></body></html>"),
+                ("c2.xhtml", "<html><body><p>Last of all.</p></body></html>"),
+            ],
+        );
+
+        let extracted = extract_text(&path).unwrap();
+        assert_eq!(extracted.chapters[0].text, "First of all.");
+        // Spine of four, minus the empty item and the unloadable one
+        assert_eq!(extracted.chapter_count, 2);
+        assert_eq!(extracted.skipped_items, vec!["lost"]);
+        // A text-less page is normal and isn't reported
+        assert_eq!(extracted.warnings, vec!["Spine item 2 (lost) could not be loaded"]);
+
+        let fixture = extract_text(&fixture("minimal.epub")).unwrap();
+        assert_eq!(fixture.chapter_count, 1);
+        assert!(fixture.chapters[0].text.contains("The Analytical Engine weaves"));
+        assert!(fixture.skipped_items.is_empty());
+    }
+
+    #[test]
+    fn test_mostly_unreadable_books_are_rejected() {
+        let extracted = |spine_items: usize, skipped: usize| ExtractedText {
+            spine_items,
+            skipped_items: vec!["lost".to_string(); skipped],
+            ..ExtractedText::from_chapters(Vec::new())
+        };
+        assert!(extracted(10, 2).check_readable().is_ok());
+        assert!(extracted(0, 0).check_readable().is_ok());
+        assert!(matches!(
+            extracted(4, 1).check_readable(),
+            Err(EpubError::MostlyUnreadable { readable: 3, total: 4 })
+        ));
+    }
+
+    #[test]
+    fn test_malformed_containers_are_told_apart() {
+        let dir = tempfile::tempdir().unwrap();
+        let not_zip = dir.path().join("not_zip.epub");
+        std::fs::write(&not_zip, "PK but not really").unwrap();
+        assert!(matches!(extract_text(&not_zip), Err(EpubError::NotAZip(_))));
+
+        let no_container = dir.path().join("no_container.epub");
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(&no_container).unwrap());
+        zip.start_file("content.opf", zip::write::SimpleFileOptions::default()).unwrap();
+        zip.write_all(b"<package/>").unwrap();
+        zip.finish().unwrap();
+        assert!(matches!(extract_text(&no_container), Err(EpubError::MissingContainer)));
+
+        let no_opf = write_zip(dir.path(), "no_opf.epub", &[("c1.xhtml", "<p>Orphan.</p>")]);
+        assert!(matches!(read_metadata(&no_opf), Err(EpubError::MissingOpf(path)) if path == "content.opf"));
+    }
+
+    #[test]
+    fn test_spine_item_with_bad_utf8_is_read_lossily() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_epub_with(dir.path(), "utf8.epub", "<p>Placeholder.</p>", &[]);
+        // Copy the book with the chapter in Latin-1, as some converters write it
+        let latin1 = dir.path().join("latin1_raw.epub");
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(&latin1).unwrap());
+        let mut source = zip::ZipArchive::new(std::fs::File::open(&path).unwrap()).unwrap();
+        for i in 0..source.len() {
+            let mut entry = source.by_index(i).unwrap();
+            let mut content = Vec::new();
+            entry.read_to_end(&mut content).unwrap();
+            if entry.name() == "c1.xhtml" {
+                content = b"<p>Caf\xe9 au lait.</p>".to_vec();
+            }
+            zip.start_file(entry.name(), zip::write::SimpleFileOptions::default()).unwrap();
+            zip.write_all(&content).unwrap();
+        }
+        zip.finish().unwrap();
+
+        let extracted = extract_text(&latin1).unwrap();
+        assert!(extracted.skipped_items.is_empty());
+        assert_eq!(extracted.full_text, "Caf\u{FFFD} au lait.");
+        assert!(matches!(
+            read_spine_item(&mut EpubDoc::new(&latin1).unwrap(), "missing"),
+            Err(EpubError::SpineItemUnreadable { id }) if id == "missing"
+        ));
+    }
+
+    #[test]
+    fn test_outline_and_chapters_cover_a_large_book() {
+        // About 1.5MB of text over 300 chapters, the size that stalls a single IPC payload
+        let chapter_count = 300;
+        let paragraph = "<p>It is a truth universally acknowledged that a single man in possession of a good fortune must be in want of a wife.</p>";
+        let ids: Vec<String> = (0..chapter_count).map(|i| format!("c{}", i)).collect();
+        let manifest: String =
+            ids.iter().map(|id| format!(r#"<item id="{0}" href="{0}.xhtml" media-type="application/xhtml+xml"/>"#, id)).collect();
+        let spine: String = ids.iter().map(|id| format!(r#"<itemref idref="{}"/>"#, id)).collect();
+        let opf = format!(
+            r#"<?xml version="1.0"?>
+            <package xmlns="http://www.idpf.org/2007/opf" version="3.0" unique-identifier="id">
+              <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+                <dc:identifier id="id">large</dc:identifier><dc:title>Large</dc:title>
+              </metadata>
+              <manifest>{}</manifest><spine>{}</spine>
+            </package>"#,
+            manifest, spine
+        );
+        let pages: Vec<(String, String)> = ids
+            .iter()
+            .map(|id| (format!("{}.xhtml", id), format!("<html><body>{}</body></html>", paragraph.repeat(40))))
+            .collect();
+        let mut files = vec![("content.opf", opf.as_str())];
+        files.extend(pages.iter().map(|(name, html)| (name.as_str(), html.as_str())));
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_zip(dir.path(), "large.epub", &files);
+
+        let extracted = extract_text(&path).unwrap();
+        assert!(extracted.full_text.len() > 1_000_000);
+
+        let outline = extracted.outline();
+        assert_eq!(outline.len(), chapter_count);
+        assert_eq!(outline[299].index, 299);
+        assert_eq!(outline[299].title, "Chapter 300");
+        let words: usize = outline.iter().map(|chapter| chapter.word_count).sum();
+        assert_eq!(words, extracted.full_text.split_whitespace().count());
+
+        // Each chapter is a small slice of the whole text, matching its segment
+        let full: Vec<char> = extracted.full_text.chars().collect();
+        for (chapter, segment) in extracted.chapters.iter().zip(&extracted.segments) {
+            assert!(chapter.text.len() * 100 < extracted.full_text.len());
+            assert_eq!(chapter.text, full[segment.char_start..segment.char_end].iter().collect::<String>());
+        }
+    }
+
+    #[test]
+    fn test_empty_spine_item_is_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_epub_with(dir.path(), "empty.epub", "  \n", &[]);
+
+        let extracted = extract_text(&path).unwrap();
+        assert_eq!(extracted.chapter_count, 0);
+        assert!(extracted.skipped_items.is_empty());
+        assert_eq!(extracted.warnings, vec!["Spine item 0 (c1) is empty"]);
+    }
 
+    #[test]
+    fn test_extraction_reports_progress_and_can_be_cancelled() {
+        let path = fixture("minimal.epub");
+        let mut calls = Vec::new();
+        extract_text_with_progress(&path, &AtomicBool::new(false), |done, total| calls.push((done, total))).unwrap();
+        assert_eq!(calls, vec![(1, 1)]);
+
+        let cancelled = extract_text_with_progress(&path, &AtomicBool::new(true), |_, _| {});
+        assert!(matches!(cancelled, Err(EpubError::Cancelled)));
+    }
+
+    #[test]
+    fn test_normalize_language() {
+        assert_eq!(normalize_language("en-US").as_deref(), Some("en"));
+        assert_eq!(normalize_language(" EN_gb ").as_deref(), Some("en"));
+        assert_eq!(normalize_language("eng").as_deref(), Some("en"));
+        assert_eq!(normalize_language("ger").as_deref(), Some("de"));
+        assert_eq!(normalize_language("fr").as_deref(), Some("fr"));
+        assert_eq!(normalize_language("English").as_deref(), Some("en"));
+        assert_eq!(normalize_language("Deutsch").as_deref(), Some("de"));
+        assert_eq!(normalize_language("English_GB").as_deref(), Some("en"));
+        assert_eq!(normalize_language("und"), None);
+        assert_eq!(normalize_language("Klingon"), None);
+        assert_eq!(normalize_language(""), None);
+    }
+
+    #[test]
+    fn test_extracted_text_has_language() {
+        let opf = r#"<?xml version="1.0"?>
+            <package xmlns="http://www.idpf.org/2007/opf" version="3.0" unique-identifier="id">
+              <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+                <dc:identifier id="id">fixture</dc:identifier><dc:title>Fixture</dc:title>
+                <dc:language>fr-CA</dc:language>
+              </metadata>
+              <manifest><item id="c1" href="c1.xhtml" media-type="application/xhtml+xml"/></manifest>
+              <spine><itemref idref="c1"/></spine>
+            </package>"#;
+        let dir = tempfile::tempdir().unwrap();
+        let chapter = "<html><body><p>Il était une fois.</p></body></html>";
+        let path = write_zip(dir.path(), "fr.epub", &[("content.opf", opf), ("c1.xhtml", chapter)]);
+        assert_eq!(extract_text(&path).unwrap().language.as_deref(), Some("fr"));
+
+        // No dc:language at all
+        let path = write_epub_with(dir.path(), "plain.epub", chapter, &[]);
+        assert_eq!(extract_text(&path).unwrap().language, None);
+    }
+
+    #[test]
+    fn test_drm_protected_epub_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let encrypted = encryption_xml("http://www.w3.org/2001/04/xmlenc#aes128-cbc", "c1.xhtml");
+        let drm = write_epub(dir.path(), "drm.epub", &[("META-INF/encryption.xml", &encrypted)]);
+        assert!(matches!(extract_text(&drm), Err(EpubError::DrmProtected)));
+
+        let adept = write_epub(dir.path(), "adept.epub", &[("META-INF/rights.xml", "<adept:rights/>")]);
+        assert!(matches!(extract_text(&adept), Err(EpubError::DrmProtected)));
+    }
+
+    #[test]
+    fn test_font_obfuscation_is_not_drm() {
+        let dir = tempfile::tempdir().unwrap();
+        let obfuscated = encryption_xml("http://www.idpf.org/2008/embedding", "fonts/serif.otf");
+        let path = write_epub(dir.path(), "fonts.epub", &[("META-INF/encryption.xml", &obfuscated)]);
+
+        let extracted = extract_text(&path).unwrap();
+        assert_eq!(extracted.full_text, "It was a bright cold day in April.");
+    }
+
+    #[test]
+    fn test_extract_chapters_titles_from_nav() {
+        let opf = r#"<?xml version="1.0"?>
+            <package xmlns="http://www.idpf.org/2007/opf" version="3.0" unique-identifier="id">
+              <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+                <dc:identifier id="id">fixture</dc:identifier><dc:title>Fixture</dc:title>
+              </metadata>
+              <manifest>
+                <item id="nav" href="nav.xhtml" media-type="application/xhtml+xml" properties="nav"/>
+                <item id="intro" href="intro.xhtml" media-type="application/xhtml+xml"/>
+                <item id="c1" href="text/c1.xhtml" media-type="application/xhtml+xml"/>
+                <item id="blank" href="text/blank.xhtml" media-type="application/xhtml+xml"/>
+                <item id="c2" href="text/c2.xhtml" media-type="application/xhtml+xml"/>
+              </manifest>
+              <spine><itemref idref="intro"/><itemref idref="c1"/><itemref idref="blank"/><itemref idref="c2"/></spine>
+            </package>"#;
+        // Two entries point into c1; intro has no entry at all
+        let nav = r#"<html><body><nav epub:type="toc"><ol>
+              <li><a href="text/c1.xhtml">The Beginning</a></li>
+              <li><a href="text/c1.xhtml#part2">The Beginning, continued</a></li>
+              <li><a href="text/c2.xhtml">The End</a></li>
+            </ol></nav></body></html>"#;
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_zip(
+            dir.path(),
+            "nav.epub",
+            &[
+                ("content.opf", opf),
+                ("nav.xhtml", nav),
+                ("intro.xhtml", "<html><body><p>A short note first.</p></body></html>"),
+                ("text/c1.xhtml", "<html><body><p>Once upon a time.</p></body></html>"),
+                ("text/blank.xhtml", "<html><body><div></div></body></html>"),
+                ("text/c2.xhtml", "<html><body><p>Happily ever after.</p></body></html>"),
+            ],
+        );
+
+        let (chapters, report) = extract_chapters(&path, CleanOptions::default(), &AtomicBool::new(false), |_, _| {}).unwrap();
+        assert!(report.warnings.is_empty());
+        let summary: Vec<(usize, &str, &str)> =
+            chapters.iter().map(|c| (c.spine_index, c.title.as_str(), c.text.as_str())).collect();
+        assert_eq!(
+            summary,
+            vec![
+                // The first spine item is read too
+                (0, "Chapter 1", "A short note first."),
+                (1, "The Beginning", "Once upon a time."),
+                // The empty item is dropped
+                (3, "The End", "Happily ever after."),
+            ]
+        );
+
+        let extracted = extract_text(&path).unwrap();
+        assert_eq!(extracted.chapter_count, 3);
+        assert_eq!(extracted.full_text, "A short note first.\n\nOnce upon a time.\n\nHappily ever after.");
+    }
+
+    #[test]
+    fn test_html_cleaning() {
         let html = r#"<html><body><h1>Title</h1><p>Hello <b>world</b>!</p><script>evil()</script></body></html>"#;
-        let clean = cleaner.clean(html).to_string();
-        let normalized: String = clean.split_whitespace().collect::<Vec<_>>().join(" ");
+        assert_eq!(clean_html(&text_cleaner(), html), "Title\n\nHello world!");
+    }
+
+    #[test]
+    fn test_block_tags_separate_words() {
+        let cleaner = text_cleaner();
+        assert_eq!(clean_html(&cleaner, "<p>believes</p><p>that's</p>"), "believes\n\nthat's");
+        assert_eq!(clean_html(&cleaner, "<div>meets<br/>himself</div>"), "meets himself");
+        assert_eq!(clean_html(&cleaner, "<h2>Chapter</h2>One"), "Chapter\n\nOne");
+        assert_eq!(clean_html(&cleaner, "<ul><li>end</li><li>of</li></ul>"), "end\n\nof");
+        let keep_tables = text_cleaner_with(CleanOptions { strip_figures_and_tables: false });
+        assert_eq!(
+            clean_html(&keep_tables, "<table><tr><td>is</td><td>about</td></tr></table>"),
+            "is\n\nabout"
+        );
+        // Inline markup inside a word must not split it
+        assert_eq!(clean_html(&cleaner, "<p>un<i>believ</i>able</p>"), "unbelievable");
+        // A stray "<" in the text is kept once
+        assert_eq!(separate_blocks("<p>Mind that 1 < 2"), format!("{}<p>Mind that 1 < 2", PARAGRAPH_BREAK));
+    }
+
+    #[test]
+    fn test_decode_entities() {
+        assert_eq!(decode_entities("Part &amp; Parcel"), "Part & Parcel");
+        assert_eq!(decode_entities("wait&mdash;what"), "wait—what");
+        assert_eq!(decode_entities("don&#8217;t &#x2019;tis caf&#233;"), "don’t ’tis café");
+        // Not references: left alone
+        assert_eq!(decode_entities("AT&T &bogus; & &#xZZ; &"), "AT&T &bogus; & &#xZZ; &");
+    }
+
+    #[test]
+    fn test_cleaned_text_has_no_entities() {
+        let cleaner = text_cleaner();
+        let html = "<p>AT&amp;T&nbsp;said&mdash;don&#8217;t &lt;panic&gt;.</p><p>Write &amp;lt;p&amp;gt; for it</p>";
+        assert_eq!(clean_html(&cleaner, html), "AT&T said—don't <panic>.\n\nWrite &lt;p&gt; for it");
+        // Curly apostrophes tokenize like straight ones
+        assert_eq!(clean_html(&cleaner, "<p>Don’t</p>"), "Don't");
+    }
+
+    #[test]
+    fn test_paragraphs_survive_cleaning() {
+        let html = "<html><body><h1>Chapter One</h1>\n<p>The Engine\n   weaves <i>algebraical</i>\npatterns.</p>\
+            <blockquote><p>It has no pretensions.</p></blockquote><p>Whatever<br/>we order.</p></body></html>";
+        let text = clean_html(&text_cleaner(), html);
+        assert_eq!(text.split("\n\n").count(), 4);
+        assert_eq!(
+            text,
+            "Chapter One\n\nThe Engine weaves algebraical patterns.\n\nIt has no pretensions.\n\nWhatever we order."
+        );
+    }
+
+    #[test]
+    fn test_verse_paragraphs_are_tagged() {
+        let cleaner = text_cleaner();
+        let verse_text = |html: &str| {
+            let (text, verse) = clean_html_tagged(&cleaner, html);
+            let chars: Vec<char> = text.chars().collect();
+            verse.iter().map(|r| chars[r.clone()].iter().collect::<String>()).collect::<Vec<_>>()
+        };
+
+        // Marked up as a poem: every stanza, as one range
+        let poem = "<p>She said:</p><div class=\"poem\"><p class=\"stanza\">Thou still unravish'd bride</p>\
+            <p>Of quietness</p></div><p>And left.</p>";
+        assert_eq!(verse_text(poem), vec!["Thou still unravish'd bride\n\nOf quietness"]);
+        let epigraph = "<blockquote epub:type=\"z3998:verse\">Hail to thee</blockquote><p>Chapter text.</p>";
+        assert_eq!(verse_text(epigraph), vec!["Hail to thee"]);
+
+        // Unmarked, but set as short lines
+        let stanza = "<p>Tyger Tyger,<br/>burning bright,<br/>In the forests<br/>of the night;</p><p>Prose follows.</p>";
+        assert_eq!(verse_text(stanza), vec!["Tyger Tyger, burning bright, In the forests of the night;"]);
+
+        // Source line wrapping and a single break are not verse
+        let prose = "<p>It was a bright\ncold day\nin April, and\nthe clocks</p><p>Striking<br/>thirteen.</p>";
+        assert!(verse_text(prose).is_empty());
+        assert!(verse_text("<p class=\"universe\">A reverse.</p>").is_empty());
+
+        // Only whole class tokens on block elements
+        assert_eq!(verse_text("<div class=\"text Poem\">Hail to thee</div>"), vec!["Hail to thee"]);
+        assert!(verse_text("<p class=\"verse-number\">Chapter text.</p>").is_empty());
+        assert!(verse_text("<p class=\"poetry-line\">Chapter text.</p>").is_empty());
+        assert!(verse_text("<p>Chapter <span class=\"verse\">3</span> text.</p>").is_empty());
+    }
+
+    #[test]
+    fn test_extracted_epub_has_no_glued_words() {
+        use unicode_segmentation::UnicodeSegmentation;
+
+        // The book the nlp_filtering tests analyze, with nothing between its paragraphs,
+        // like the markup that used to produce "believesthat's"
+        let Ok(book) = std::fs::read_to_string(fixture("pride_and_prejudice.txt")) else {
+            eprintln!("Skipping: run `setup-test-fixtures` first");
+            return;
+        };
+        let book = book.replace("\r\n", "\n");
+        let paragraphs: Vec<String> = book
+            .split("\n\n")
+            .filter(|p| !p.trim().is_empty())
+            .map(|p| format!("<p>{}</p>", p.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")))
+            .collect();
+        let dir = tempfile::tempdir().unwrap();
+        let chapter = format!("<html><body>{}</body></html>", paragraphs.concat());
+        let path = write_epub_with(dir.path(), "glued.epub", &chapter, &[]);
+
+        // Cleaned one at a time, paragraphs have no neighbour to run into
+        let cleaner = text_cleaner();
+        let separate: Vec<String> = paragraphs.iter().map(|p| clean_html(&cleaner, p)).collect();
+        let text = extract_text(&path).unwrap().full_text;
+        assert_eq!(
+            text.unicode_words().collect::<Vec<_>>(),
+            separate.join("\n\n").unicode_words().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_dehyphenate_joins_line_break_hyphenation() {
+        let is_word = |w: &str| ["example", "consider", "consideration", "exceptional"].contains(&w);
+        assert_eq!(dehyphenate("an exam-\nple of it", is_word), "an example of it");
+        assert_eq!(dehyphenate("con-\n   sider-\nation", is_word), "consideration");
+        assert_eq!(dehyphenate("Excep-\r\ntional", is_word), "Exceptional");
+    }
+
+    #[test]
+    fn test_dehyphenate_keeps_real_hyphens() {
+        let is_word = |w: &str| ["well", "known", "wait", "then"].contains(&w);
+        assert_eq!(dehyphenate("a well-\nknown fact", is_word), "a well-known fact");
+        assert_eq!(dehyphenate("a well-known fact", is_word), "a well-known fact");
+        // Dashes between words and hyphens before spaces are left alone
+        assert_eq!(dehyphenate("wait -\nthen go", is_word), "wait -\nthen go");
+        assert_eq!(dehyphenate("pre- and post-war", is_word), "pre- and post-war");
+    }
+
+    #[test]
+    fn test_figures_and_tables_are_optionally_stripped() {
+        let html = r#"<p>Whales dive deep.</p>
+            <figure><img src="whale.png" alt="A sperm whale"/><figcaption>Fig. 3: Sperm whale</figcaption></figure>
+            <table><caption>Dive depths (cont'd)</caption><tr><th>Species</th><td>2,000 m</td></tr></table>
+            <p>Then they surface.</p>"#;
+
+        let stripped = clean_html(&text_cleaner(), html);
+        assert_eq!(stripped, "Whales dive deep.\n\nThen they surface.");
+
+        let full = clean_html(&text_cleaner_with(CleanOptions { strip_figures_and_tables: false }), html);
+        assert!(full.contains("Fig. 3: Sperm whale") && full.contains("Species"));
+        assert!(!full.contains("A sperm whale"));
+    }
+
+    #[test]
+    fn test_ligatures_and_typographic_variants_are_normalized() {
+        use unicode_segmentation::UnicodeSegmentation;
+
+        let cleaner = text_cleaner();
+        let plain = clean_html(&cleaner, "<p>It finally flew off, they'd say - well-known.</p>");
+        let fancy = clean_html(&cleaner, "<p>It \u{FB01}nally \u{FB02}ew\u{00A0}off, they\u{2019}d say \u{2212} well\u{2011}known.</p>");
+        assert_eq!(fancy, plain);
+        assert_eq!(fancy.unicode_words().collect::<Vec<_>>(), plain.unicode_words().collect::<Vec<_>>());
+
+        let freq = |word: &str| nlp::wordfreq().word_frequency(word);
+        assert_eq!(freq(&clean_html(&cleaner, "\u{FB01}nally")), freq("finally"));
+    }
+
+    #[test]
+    fn test_soft_hyphens_are_stripped() {
+        let cleaner = text_cleaner();
+        assert_eq!(clean_html(&cleaner, "<p>in\u{AD}com\u{AD}pre\u{AD}hen\u{AD}sible</p>"), "incomprehensible");
+    }
+
+    #[test]
+    fn test_split_footnotes() {
+        let html = r##"<p>It was a truth<a epub:type="noteref" href="#n1">1</a> universally
+            acknowledged<sup><a href="#n2">23</a></sup>, in the 19<sup>th</sup> century.</p>
+            <aside epub:type="footnote" id="n1"><p>A <em>famous</em> opening.</p></aside>
+            <section epub:type="endnotes"><h2>Notes</h2><ol>
+              <li epub:type="endnote" id="n2">Austen, 1813.</li>
+              <li epub:type="endnote" id="n3"><p>Second <span>note</span>.</p></li>
+            </ol></section>"##;
+
+        let (body, notes) = split_footnotes(html);
+        let cleaner = text_cleaner();
+        assert_eq!(
+            clean_html(&cleaner, &body),
+            "It was a truth universally acknowledged, in the 19th century."
+        );
+        let notes: Vec<String> = notes.iter().map(|n| clean_html(&cleaner, n)).collect();
+        assert_eq!(notes, vec!["A famous opening.", "Austen, 1813.", "Second note."]);
+
+        // A stray "<" in the text is kept once
+        let (body, notes) = split_footnotes("<p>Mind that 1 < 2");
+        assert_eq!(body, "<p>Mind that 1 < 2");
+        assert!(notes.is_empty());
+    }
+
+    #[test]
+    fn test_split_footnotes_by_class_and_id() {
+        let html = r##"<p>Quoth the raven<a class="footnote-ref" href="#fn1">[a]</a>.</p>
+            <p class="footnote" id="fn1">Ibid., op. cit.</p>
+            <div id="ftn2"><p>Poe, 1845.</p></div>
+            <p id="chapter-2">Nevermore.</p>"##;
+
+        let (body, notes) = split_footnotes(html);
+        let cleaner = text_cleaner();
+        assert_eq!(clean_html(&cleaner, &body), "Quoth the raven.\n\nNevermore.");
+        let notes: Vec<String> = notes.iter().map(|n| clean_html(&cleaner, n)).collect();
+        assert_eq!(notes, vec!["Ibid., op. cit.", "Poe, 1845."]);
+        assert!(!is_note_id("footnotes-intro") && is_note_id("endnote_4"));
+    }
+
+    #[test]
+    fn test_notes_landmark_hrefs() {
+        let nav = r#"<nav epub:type="landmarks"><ol>
+            <li><a epub:type="bodymatter" href="ch1.xhtml">Start</a></li>
+            <li><a epub:type="endnotes" href="notes.xhtml">Notes</a></li></ol></nav>"#;
+        assert_eq!(notes_landmark_hrefs(nav), vec!["notes.xhtml"]);
+
+        let opf = r#"<guide><reference type="toc" href="toc.xhtml"/><reference type="notes" title="Notes" href="n.xhtml#top"/></guide>"#;
+        assert_eq!(notes_landmark_hrefs(opf), vec!["n.xhtml#top"]);
+    }
+
+    #[test]
+    fn test_guide_notes_section_is_kept_out_of_the_text() {
+        let dir = tempfile::tempdir().unwrap();
+        let opf = r#"<?xml version="1.0"?>
+            <package xmlns="http://www.idpf.org/2007/opf" version="2.0" unique-identifier="id">
+              <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+                <dc:identifier id="id">fixture</dc:identifier><dc:title>Fixture</dc:title>
+              </metadata>
+              <manifest>
+                <item id="c1" href="c1.xhtml" media-type="application/xhtml+xml"/>
+                <item id="notes" href="notes.xhtml" media-type="application/xhtml+xml"/>
+              </manifest>
+              <spine><itemref idref="c1"/><itemref idref="notes"/></spine>
+              <guide><reference type="notes" title="Notes" href="notes.xhtml"/></guide>
+            </package>"#;
+        let path = write_zip(
+            dir.path(),
+            "notes.epub",
+            &[
+                ("content.opf", opf),
+                ("c1.xhtml", "<html><body><p>Once upon a midnight dreary.</p></body></html>"),
+                ("notes.xhtml", "<html><body><h2>Notes</h2><p>Ibid.</p><p>Op. cit.</p></body></html>"),
+            ],
+        );
+
+        let extracted = extract_text(&path).unwrap();
+        assert_eq!(extracted.full_text, "Once upon a midnight dreary.");
+        assert_eq!(extracted.removed_notes(), 1);
+        assert_eq!(extracted.footnotes, vec!["Notes\n\nIbid.\n\nOp. cit."]);
+    }
+
+    #[test]
+    fn test_join_chapters_with_footnotes() {
+        let chapter = |text: &str, notes: &[&str]| Chapter {
+            footnotes: notes.iter().map(|n| n.to_string()).collect(),
+            ..Chapter::fixture(0, "", text)
+        };
+        let chapters = vec![chapter("One.", &["Note a."]), chapter("", &["Note b."]), chapter("Two.", &[])];
+
+        assert_eq!(join_chapters(&chapters, false).0, "One.\n\nTwo.");
+        assert_eq!(join_chapters(&chapters, true).0, "One.\n\nNote a.\n\nNote b.\n\nTwo.");
+
+        let extracted = ExtractedText::from_chapters(chapters);
+        assert_eq!(extracted.full_text, "One.\n\nTwo.");
+        assert_eq!(extracted.footnotes, vec!["Note a.", "Note b."]);
+    }
+
+    #[test]
+    fn test_segments_map_offsets_to_spine_items() {
+        let chapter = |spine_index: usize, text: &str, notes: &[&str]| Chapter {
+            footnotes: notes.iter().map(|n| n.to_string()).collect(),
+            ..Chapter::fixture(spine_index, "", text)
+        };
+        let chapters = vec![chapter(1, "Café noir.", &["Note."]), chapter(4, "Naïve.", &[])];
+
+        let (text, segments) = join_chapters(&chapters, true);
+        assert_eq!(text, "Café noir.\n\nNote.\n\nNaïve.");
+        assert_eq!(
+            segments,
+            vec![
+                Segment { spine_index: 1, chapter_index: 0, char_start: 0, char_end: 17 },
+                Segment { spine_index: 4, chapter_index: 1, char_start: 19, char_end: 25 },
+            ]
+        );
+
+        // Character offsets, not bytes: "Naïve" starts at char 19 although "é" takes two bytes
+        assert_eq!(text.chars().skip(19).collect::<String>(), "Naïve.");
+        assert_eq!(locate(&segments, 19), Some(Location { spine_index: 4, char_offset: 0 }));
+        assert_eq!(locate(&segments, 5), Some(Location { spine_index: 1, char_offset: 5 }));
+        assert_eq!(locate(&segments, 18), None);
+        assert_eq!(locate(&segments, 25), None);
+    }
+
+    #[test]
+    fn test_front_sections_are_excluded() {
+        let mut extracted = ExtractedText::from_chapters(vec![
+            Chapter::fixture(0, "Dedication", "For my mother."),
+            Chapter::fixture(1, "A Note on the Translation", "Hermeneutic fidelity matters."),
+            Chapter::fixture(2, "Chapter One", "The river rose."),
+            Chapter::fixture(3, "Chapter Two", "It fell again."),
+            Chapter::fixture(4, "Chapter Three", "Nobody noticed."),
+            Chapter::fixture(5, "Introduction to the Sequel", "Late, so it stays."),
+        ]);
+
+        assert_eq!(extracted.exclude_front_sections(), 2);
+        assert_eq!(
+            extracted.excluded_sections,
+            vec![
+                ExcludedSection { index: 0, spine_index: 0, title: "Dedication".to_string(), word_count: 3 },
+                ExcludedSection {
+                    index: 1,
+                    spine_index: 1,
+                    title: "A Note on the Translation".to_string(),
+                    word_count: 3,
+                },
+            ]
+        );
+        assert_eq!(extracted.full_text, "The river rose.\n\nIt fell again.\n\nNobody noticed.\n\nLate, so it stays.");
+        // Chapter indices still point into the whole book
+        assert_eq!(extracted.chapters.len(), 6);
+        assert_eq!(extracted.segments[0].chapter_index, 2);
+    }
+
+    #[test]
+    fn test_front_section_titles() {
+        for title in [
+            "Preface",
+            "PREFACE.",
+            "Preface to the Second Edition",
+            "Author's Introduction",
+            "The Translator\u{2019}s Preface",
+            "Introduction: Reading Austen Today",
+            "Introduction by Tony Tanner",
+            "A Note on the Translation",
+            "Translator's Note",
+        ] {
+            assert!(is_front_section(title, 0, 10), "{}", title);
+        }
+        for title in ["The Introduction of Mr. Darcy", "Prefaces and Postscripts", "Chapter 1: The Preface", "Dedications"] {
+            assert!(!is_front_section(title, 0, 10), "{}", title);
+        }
+    }
+
+    #[test]
+    fn test_parse_nav_toc() {
+        let html = r#"<html><body>
+            <nav epub:type="landmarks"><ol><li><a href="cover.xhtml">Cover</a></li></ol></nav>
+            <nav epub:type="toc" id="toc"><h1>Contents</h1><ol>
+              <li><a href="text/ch1.xhtml">Chapter <em>One</em></a>
+                <ol><li><a href="text/ch1.xhtml#s2">Part &amp; Parcel</a></li></ol>
+              </li>
+              <li><a href='text/ch2.xhtml'>Chapter Two</a></li>
+            </ol></nav></body></html>"#;
+
+        let entries = parse_nav_toc(html);
+        assert_eq!(
+            entries,
+            vec![
+                ("Chapter One".to_string(), "text/ch1.xhtml".to_string(), 0),
+                ("Part & Parcel".to_string(), "text/ch1.xhtml#s2".to_string(), 1),
+                ("Chapter Two".to_string(), "text/ch2.xhtml".to_string(), 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_toc_title_for_uses_closest_preceding_entry() {
+        let toc = vec![
+            TocEntry { title: "One".to_string(), spine_index: Some(2), depth: 0 },
+            TocEntry { title: "One, part 2".to_string(), spine_index: Some(2), depth: 1 },
+            TocEntry { title: "Two".to_string(), spine_index: Some(5), depth: 0 },
+        ];
 
-        assert_eq!(normalized, "Title Hello world !");
+        assert_eq!(toc_title_for(&toc, 1), None);
+        assert_eq!(toc_title_for(&toc, 2).as_deref(), Some("One"));
+        assert_eq!(toc_title_for(&toc, 4).as_deref(), Some("One"));
+        assert_eq!(toc_title_for(&toc, 7).as_deref(), Some("Two"));
     }
 }
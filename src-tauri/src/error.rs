@@ -0,0 +1,123 @@
+//! Errors returned by the Tauri commands
+//!
+//! Each serializes as `{ "code": "no_epub", "message": "No EPUB file found for this book" }`,
+//! so the frontend can tell failures apart without matching on message text.
+
+use crate::calibre::CalibreError;
+use crate::covers::CoverError;
+use crate::epub::EpubError;
+use serde::ser::SerializeStruct;
+
+#[derive(Debug, thiserror::Error)]
+pub enum CommandError {
+    #[error("No library loaded")]
+    NoLibrary,
+    #[error("Unknown library id {0}")]
+    UnknownLibrary(u32),
+    #[error("Book {0} not found in folder library")]
+    BookNotFound(i64),
+    #[error("No EPUB file found for this book")]
+    NoEpub,
+    #[error("No readable book file found for this book")]
+    NoBookFile,
+    #[error("Analysis cancelled")]
+    Cancelled,
+    #[error("NLP resources are missing. Download them before analyzing.")]
+    ResourcesMissing,
+    #[error("This book is DRM-protected, so its text can't be read. Import a DRM-free copy into Calibre to analyze it.")]
+    DrmProtected,
+    #[error("This book's language is \"{0}\". Only English and German books can be analyzed.")]
+    UnsupportedLanguage(String),
+    #[error("{0}")]
+    Library(#[from] CalibreError),
+    #[error("{0}")]
+    Extraction(String),
+    /// The request can't be served as asked (wrong library kind, bad range, busy)
+    #[error("{0}")]
+    Invalid(String),
+    #[error("{0}")]
+    Io(String),
+    #[error("Failed to download resources: {0}")]
+    Download(String),
+    #[error("{0}")]
+    Internal(String),
+}
+
+impl CommandError {
+    /// Stable machine-readable name of the variant
+    pub fn code(&self) -> &'static str {
+        match self {
+            CommandError::NoLibrary => "no_library",
+            CommandError::UnknownLibrary(_) => "unknown_library",
+            CommandError::BookNotFound(_) => "book_not_found",
+            CommandError::NoEpub => "no_epub",
+            CommandError::NoBookFile => "no_book_file",
+            CommandError::Cancelled => "cancelled",
+            CommandError::ResourcesMissing => "resources_missing",
+            CommandError::DrmProtected => "drm_protected",
+            CommandError::UnsupportedLanguage(_) => "unsupported_language",
+            CommandError::Library(CalibreError::Busy) => "library_busy",
+            CommandError::Library(_) => "library",
+            CommandError::Extraction(_) => "extraction",
+            CommandError::Invalid(_) => "invalid",
+            CommandError::Io(_) => "io",
+            CommandError::Download(_) => "download",
+            CommandError::Internal(_) => "internal",
+        }
+    }
+}
+
+impl From<EpubError> for CommandError {
+    fn from(e: EpubError) -> Self {
+        match e {
+            EpubError::Cancelled => CommandError::Cancelled,
+            EpubError::DrmProtected => CommandError::DrmProtected,
+            other => CommandError::Extraction(other.to_string()),
+        }
+    }
+}
+
+impl From<CoverError> for CommandError {
+    fn from(e: CoverError) -> Self {
+        CommandError::Io(e.to_string())
+    }
+}
+
+impl From<tokio::task::JoinError> for CommandError {
+    fn from(e: tokio::task::JoinError) -> Self {
+        CommandError::Internal(format!("Task join error: {}", e))
+    }
+}
+
+impl serde::Serialize for CommandError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let mut error = serializer.serialize_struct("CommandError", 2)?;
+        error.serialize_field("code", self.code())?;
+        error.serialize_field("message", &self.to_string())?;
+        error.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serializes_code_and_message() {
+        let json = serde_json::to_value(CommandError::NoEpub).unwrap();
+        assert_eq!(json, serde_json::json!({"code": "no_epub", "message": "No EPUB file found for this book"}));
+
+        let json = serde_json::to_value(CommandError::from(CalibreError::Busy)).unwrap();
+        assert_eq!(json["code"], "library_busy");
+    }
+
+    #[test]
+    fn test_extraction_errors_keep_their_kind() {
+        assert!(matches!(CommandError::from(EpubError::Cancelled), CommandError::Cancelled));
+        assert!(matches!(CommandError::from(EpubError::DrmProtected), CommandError::DrmProtected));
+        assert_eq!(CommandError::from(EpubError::Open("bad zip".to_string())).code(), "extraction");
+    }
+}
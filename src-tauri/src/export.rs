@@ -0,0 +1,129 @@
+//! Library listing exports (CSV for spreadsheets, JSON for scripts)
+
+use crate::calibre::Book;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+/// One book of the exported listing. List fields are joined with "; " in CSV.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct LibraryRow {
+    pub id: i64,
+    pub title: String,
+    pub author: String,
+    pub series: Option<String>,
+    pub series_index: Option<f64>,
+    pub tags: Vec<String>,
+    pub formats: Vec<String>,
+    pub analyzed: bool,
+}
+
+impl LibraryRow {
+    pub fn new(book: &Book, analyzed: &HashSet<i64>) -> Self {
+        Self {
+            id: book.id,
+            title: book.title.clone(),
+            author: book.author.clone(),
+            series: book.series.clone(),
+            series_index: book.series_index,
+            tags: book.tags.clone(),
+            formats: book.formats.clone(),
+            analyzed: analyzed.contains(&book.id),
+        }
+    }
+}
+
+/// Separator for list fields in CSV cells
+const LIST_SEPARATOR: &str = "; ";
+
+const CSV_HEADER: [&str; 8] = ["id", "title", "author", "series", "series_index", "tags", "formats", "analyzed"];
+
+/// Render the listing in `format`
+pub fn render_library(rows: &[LibraryRow], format: ExportFormat) -> Result<String, String> {
+    match format {
+        ExportFormat::Json => serde_json::to_string_pretty(rows).map_err(|e| e.to_string()),
+        ExportFormat::Csv => library_csv(rows),
+    }
+}
+
+/// CSV with a header row. The writer quotes any cell containing commas, quotes or newlines.
+fn library_csv(rows: &[LibraryRow]) -> Result<String, String> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record(CSV_HEADER).map_err(|e| e.to_string())?;
+
+    for row in rows {
+        writer
+            .write_record([
+                row.id.to_string(),
+                row.title.clone(),
+                row.author.clone(),
+                row.series.clone().unwrap_or_default(),
+                row.series_index.map(|i| i.to_string()).unwrap_or_default(),
+                row.tags.join(LIST_SEPARATOR),
+                row.formats.join(LIST_SEPARATOR),
+                row.analyzed.to_string(),
+            ])
+            .map_err(|e| e.to_string())?;
+    }
+
+    let bytes = writer.into_inner().map_err(|e| e.to_string())?;
+    String::from_utf8(bytes).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(title: &str, series: Option<&str>, tags: &[&str]) -> LibraryRow {
+        LibraryRow {
+            id: 7,
+            title: title.to_string(),
+            author: "Brontë, Charlotte".to_string(),
+            series: series.map(String::from),
+            series_index: series.map(|_| 2.0),
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            formats: vec!["EPUB".to_string(), "PDF".to_string()],
+            analyzed: true,
+        }
+    }
+
+    #[test]
+    fn test_csv_round_trips_awkward_fields() {
+        let rows = vec![
+            row("Jane Eyre, an \"Autobiography\"", Some("Line one\nline two"), &["classic", "gothic, romance"]),
+            row("Villette", None, &[]),
+        ];
+        let csv = render_library(&rows, ExportFormat::Csv).unwrap();
+
+        let mut reader = csv::Reader::from_reader(csv.as_bytes());
+        assert_eq!(reader.headers().unwrap(), CSV_HEADER.as_slice());
+
+        let records: Vec<csv::StringRecord> = reader.records().map(Result::unwrap).collect();
+        assert_eq!(records.len(), 2);
+        assert_eq!(&records[0][1], "Jane Eyre, an \"Autobiography\"");
+        assert_eq!(&records[0][2], "Brontë, Charlotte");
+        assert_eq!(&records[0][3], "Line one\nline two");
+        assert_eq!(&records[0][4], "2");
+        assert_eq!(&records[0][5], "classic; gothic, romance");
+        assert_eq!(&records[0][6], "EPUB; PDF");
+        assert_eq!(&records[0][7], "true");
+        assert_eq!(&records[1][3], "");
+        assert_eq!(&records[1][4], "");
+    }
+
+    #[test]
+    fn test_json_export() {
+        let rows = vec![row("Shirley", Some("None"), &["classic"])];
+        let json = render_library(&rows, ExportFormat::Json).unwrap();
+
+        assert!(json.contains("\n  {"), "JSON should be pretty-printed");
+        let parsed: Vec<LibraryRow> = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, rows);
+    }
+}
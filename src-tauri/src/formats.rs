@@ -0,0 +1,487 @@
+//! Book file formats Lexis can extract text from
+//!
+//! EPUB is preferred; MOBI/AZW3, FB2, plain text (or Markdown) and, with the
+//! `pdf` feature, PDF are fallbacks for books that have no EPUB in the library.
+
+use crate::epub::{self, Chapter, CleanOptions, EpubError, EpubMetadata, ExtractedText};
+use crate::mobi;
+use crate::nlp;
+use serde::Serialize;
+use std::path::Path;
+use std::sync::atomic::AtomicBool;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum BookFormat {
+    Epub,
+    Mobi,
+    Azw3,
+    Fb2,
+    Txt,
+    #[serde(rename = "MD")]
+    Markdown,
+    Pdf,
+}
+
+impl BookFormat {
+    /// Formats in order of preference (best extraction quality first)
+    pub const PREFERENCE: [BookFormat; 7] = [
+        BookFormat::Epub,
+        BookFormat::Azw3,
+        BookFormat::Mobi,
+        BookFormat::Fb2,
+        BookFormat::Txt,
+        BookFormat::Markdown,
+        BookFormat::Pdf,
+    ];
+
+    /// Display name, matching Calibre's format names
+    pub fn label(&self) -> &'static str {
+        match self {
+            BookFormat::Epub => "EPUB",
+            BookFormat::Mobi => "MOBI",
+            BookFormat::Azw3 => "AZW3",
+            BookFormat::Fb2 => "FB2",
+            BookFormat::Txt => "TXT",
+            BookFormat::Markdown => "MD",
+            BookFormat::Pdf => "PDF",
+        }
+    }
+
+    pub fn from_extension(ext: &str) -> Option<Self> {
+        match ext.to_ascii_lowercase().as_str() {
+            "epub" => Some(BookFormat::Epub),
+            "mobi" | "prc" => Some(BookFormat::Mobi),
+            "azw3" | "azw" => Some(BookFormat::Azw3),
+            "fb2" => Some(BookFormat::Fb2),
+            "txt" => Some(BookFormat::Txt),
+            "md" | "markdown" => Some(BookFormat::Markdown),
+            // Without the feature PDFs can't be read, so they don't count as a format
+            "pdf" if cfg!(feature = "pdf") => Some(BookFormat::Pdf),
+            _ => None,
+        }
+    }
+
+    /// Best extractable format among Calibre format names (e.g. "EPUB", "PDF")
+    pub fn best<S: AsRef<str>>(formats: &[S]) -> Option<Self> {
+        formats
+            .iter()
+            .filter_map(|f| BookFormat::from_extension(f.as_ref()))
+            .min_by_key(|format| BookFormat::PREFERENCE.iter().position(|f| f == format))
+    }
+}
+
+/// Extract text from a book file of any supported format
+pub fn extract_text(path: &Path, format: BookFormat) -> Result<ExtractedText, EpubError> {
+    extract_text_with_options(path, format, CleanOptions::default())
+}
+
+/// `extract_text` with the given cleaning options, which apply to the HTML-based formats
+pub fn extract_text_with_options(
+    path: &Path,
+    format: BookFormat,
+    options: CleanOptions,
+) -> Result<ExtractedText, EpubError> {
+    match format {
+        BookFormat::Epub => epub::extract_text_with_options(path, options),
+        BookFormat::Mobi | BookFormat::Azw3 => mobi::extract_text(path, options),
+        BookFormat::Fb2 => extract_fb2(path, options),
+        BookFormat::Txt => extract_txt(path, false),
+        BookFormat::Markdown => extract_txt(path, true),
+        #[cfg(feature = "pdf")]
+        BookFormat::Pdf => crate::pdf::extract_text(path),
+        #[cfg(not(feature = "pdf"))]
+        BookFormat::Pdf => Err(EpubError::Unsupported("PDF support is not built in".to_string())),
+    }
+}
+
+/// `extract_text` with per-item progress and cancellation. Only EPUBs report progress
+/// as they go; the other formats are read in one step.
+pub fn extract_text_with_progress(
+    path: &Path,
+    format: BookFormat,
+    cancel: &AtomicBool,
+    on_progress: impl FnMut(usize, usize),
+) -> Result<ExtractedText, EpubError> {
+    match format {
+        BookFormat::Epub => epub::extract_text_with_progress(path, cancel, on_progress),
+        other => extract_text(path, other),
+    }
+}
+
+/// Set `ExtractedText::text_quality`, judging words by whether the wordfreq list
+/// of the book's `language` has them
+pub fn measure_text_quality(extracted: &mut ExtractedText, language: nlp::Language) {
+    let wordfreq = nlp::wordfreq_for(language);
+    extracted.text_quality = Some(text_quality(&extracted.full_text, |word| wordfreq.word_frequency(word) > 0.0));
+}
+
+/// FictionBook 2: XML with one `<section>` per chapter
+fn extract_fb2(path: &Path, options: CleanOptions) -> Result<ExtractedText, EpubError> {
+    let bytes = std::fs::read(path).map_err(|e| EpubError::Open(e.to_string()))?;
+    let xml = String::from_utf8_lossy(&bytes);
+
+    // Only the main body is text; binaries (images) and descriptions are skipped
+    let body_start = xml.find("<body").ok_or_else(|| EpubError::Open("FB2 file has no <body>".to_string()))?;
+    let body_end = xml[body_start..].find("</body>").map(|i| body_start + i).unwrap_or(xml.len());
+    let body = &xml[body_start..body_end];
+
+    let cleaner = epub::text_cleaner_with(options);
+    let mut chapters = Vec::new();
+
+    for (index, section) in body.split("<section").enumerate() {
+        let (text, verse) = epub::clean_html_tagged(&cleaner, section);
+        if text.is_empty() {
+            continue;
+        }
+        let title = section
+            .find("<title>")
+            .and_then(|start| {
+                let rest = &section[start..];
+                // A title can span several <p>; it's shown on one line
+                rest.find("</title>").map(|end| {
+                    let title = epub::clean_html(&cleaner, &rest[..end]);
+                    title.split_whitespace().collect::<Vec<_>>().join(" ")
+                })
+            })
+            .filter(|t| !t.is_empty())
+            .unwrap_or_else(|| format!("Chapter {}", chapters.len() + 1));
+        chapters.push(Chapter {
+            spine_index: index,
+            path: String::new(),
+            title,
+            text,
+            footnotes: Vec::new(),
+            verse,
+        });
+    }
+
+    Ok(ExtractedText::from_chapters(chapters))
+}
+
+/// Below this `text_quality` the extracted text is probably a broken encoding, DRM
+/// gibberish or a scanned PDF with a poor text layer, and analyses warn before
+/// running NER on it
+pub const POOR_TEXT_QUALITY: f32 = 0.85;
+/// Whitespace-separated tokens `text_quality` looks at, spread over the text
+const QUALITY_SAMPLE_TOKENS: usize = 5000;
+
+/// Share of words that `is_word` recognizes, over an even sample of up to
+/// `QUALITY_SAMPLE_TOKENS` whitespace-separated tokens. Surrounding punctuation is
+/// ignored, tokens without letters (numbers, dashes) don't count, and a hyphenated
+/// compound counts when each of its parts is a word. 0.0 when there are no words,
+/// since text without any can't be analyzed either.
+pub fn text_quality(text: &str, is_word: impl Fn(&str) -> bool) -> f32 {
+    let total = text.split_whitespace().count();
+    let step = total.div_ceil(QUALITY_SAMPLE_TOKENS).max(1);
+    let mut words = 0;
+    let mut good = 0;
+    for token in text.split_whitespace().step_by(step) {
+        let token = token.trim_matches(|c: char| !c.is_alphanumeric()).to_lowercase();
+        if !token.chars().any(char::is_alphabetic) {
+            continue;
+        }
+        words += 1;
+        if token.split('-').filter(|p| !p.is_empty()).all(&is_word) {
+            good += 1;
+        }
+    }
+    if words == 0 {
+        return 0.0;
+    }
+    good as f32 / words as f32
+}
+
+/// Decode a text file by its byte-order mark: UTF-16 (either endianness) or UTF-8.
+/// Without a BOM the bytes are read as UTF-8, replacing invalid sequences.
+pub fn decode_text(bytes: &[u8]) -> String {
+    let utf16 = |rest: &[u8], from_bytes: fn([u8; 2]) -> u16| {
+        let units: Vec<u16> = rest.chunks_exact(2).map(|pair| from_bytes([pair[0], pair[1]])).collect();
+        String::from_utf16_lossy(&units)
+    };
+    match bytes {
+        [0xEF, 0xBB, 0xBF, rest @ ..] => String::from_utf8_lossy(rest).into_owned(),
+        [0xFF, 0xFE, rest @ ..] => utf16(rest, u16::from_le_bytes),
+        [0xFE, 0xFF, rest @ ..] => utf16(rest, u16::from_be_bytes),
+        _ => String::from_utf8_lossy(bytes).into_owned(),
+    }
+}
+
+/// Plain text or Markdown, minus any Project Gutenberg header and footer.
+/// Blank lines separate paragraphs; "CHAPTER IV"-style headings (and, in
+/// Markdown, `#` headings) start chapters. Without headings the file is one chapter.
+fn extract_txt(path: &Path, markdown: bool) -> Result<ExtractedText, EpubError> {
+    let bytes = std::fs::read(path).map_err(|e| EpubError::Open(e.to_string()))?;
+    let decoded = decode_text(&bytes).replace("\r\n", "\n");
+    let body = nlp::strip_gutenberg_boilerplate(&decoded);
+
+    // (heading, paragraphs) per chapter; text before the first heading has no heading
+    let mut sections: Vec<(Option<String>, Vec<&str>)> = vec![(None, Vec::new())];
+    for block in paragraphs(body) {
+        if let Some(heading) = chapter_heading(block, markdown) {
+            sections.push((Some(heading), Vec::new()));
+        } else {
+            sections.last_mut().unwrap().1.push(block);
+        }
+    }
+
+    let mut chapters = Vec::new();
+    for (heading, blocks) in sections {
+        if heading.is_none() && blocks.is_empty() {
+            continue;
+        }
+        // Paragraphs are cleaned one at a time so hyphenation is only rejoined within
+        // one; plain text from print scans keeps its soft hyphens and line-break hyphens.
+        // Blocks of short lines are verse, and adjacent stanzas share one range.
+        let paragraphs = heading.iter().map(|heading| (heading.clone(), false)).chain(blocks.iter().map(|block| {
+            let block = if markdown { strip_link_targets(block) } else { block.to_string() };
+            (epub::normalize_plain_text(&block), epub::is_verse_lines(block.lines()))
+        }));
+        let mut text = String::new();
+        let mut verse: Vec<std::ops::Range<usize>> = Vec::new();
+        let mut chars = 0;
+        for (paragraph, is_verse) in paragraphs {
+            if !text.is_empty() {
+                text.push_str("\n\n");
+                chars += 2;
+            }
+            let range = chars..chars + paragraph.chars().count();
+            if is_verse {
+                match verse.last_mut() {
+                    Some(last) if last.end + 2 == range.start => last.end = range.end,
+                    _ => verse.push(range.clone()),
+                }
+            }
+            text.push_str(&paragraph);
+            chars = range.end;
+        }
+        chapters.push(Chapter {
+            spine_index: chapters.len(),
+            path: String::new(),
+            title: heading.unwrap_or_else(|| format!("Chapter {}", chapters.len() + 1)),
+            text,
+            footnotes: Vec::new(),
+            verse,
+        });
+    }
+
+    Ok(ExtractedText::from_chapters(chapters))
+}
+
+/// Blocks of text separated by blank lines
+fn paragraphs(text: &str) -> Vec<&str> {
+    let mut blocks = Vec::new();
+    let mut start: Option<usize> = None;
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        if line.trim().is_empty() {
+            if let Some(s) = start.take() {
+                blocks.push(text[s..offset].trim());
+            }
+        } else if start.is_none() {
+            start = Some(offset);
+        }
+        offset += line.len();
+    }
+    if let Some(s) = start {
+        blocks.push(text[s..].trim());
+    }
+    blocks
+}
+
+/// Markdown links and images keep their text and lose their URL:
+/// "[the Engine](notes.md)" -> "[the Engine]"
+fn strip_link_targets(block: &str) -> String {
+    let mut out = String::with_capacity(block.len());
+    let mut rest = block;
+    while let Some(i) = rest.find("](") {
+        let Some(close) = rest[i..].find(')') else { break };
+        out.push_str(&rest[..=i]);
+        rest = &rest[i + close + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Words that open a chapter heading when followed by a number ("CHAPTER XII.", "Book 2")
+const HEADING_WORDS: &[&str] = &["chapter", "book", "part", "kapitel", "teil"];
+
+/// The heading text if `block` is a chapter heading on its own line
+fn chapter_heading(block: &str, markdown: bool) -> Option<String> {
+    let line = block.trim();
+    if line.contains('\n') || line.chars().count() > 80 {
+        return None;
+    }
+    if markdown && line.starts_with('#') {
+        let heading = line.trim_start_matches('#').trim().trim_end_matches('#').trim();
+        return (!heading.is_empty()).then(|| heading.to_string());
+    }
+
+    let mut words = line.split_whitespace();
+    let first = words.next()?.to_lowercase();
+    let number = words.next()?.trim_end_matches(['.', ':']);
+    let is_number = !number.is_empty()
+        && (number.chars().all(|c| c.is_ascii_digit()) || number.chars().all(|c| "IVXLCDM".contains(c)));
+    (HEADING_WORDS.contains(&first.as_str()) && is_number).then(|| line.to_string())
+}
+
+/// Title, author and language from a Project Gutenberg header ("Title: Emma"),
+/// falling back to the file name when the file has no such header
+pub fn read_text_metadata(path: &Path) -> EpubMetadata {
+    let mut metadata = EpubMetadata::untitled(path);
+    let Ok(bytes) = std::fs::read(path) else {
+        return metadata;
+    };
+    let text = decode_text(&bytes);
+
+    // The header fields all come before the "*** START OF" marker
+    for line in text.lines().take(100).take_while(|line| !line.trim_start().starts_with("*** START OF")) {
+        let Some((field, value)) = line.split_once(':') else { continue };
+        let value = value.trim();
+        if value.is_empty() {
+            continue;
+        }
+        match field.trim() {
+            "Title" => metadata.title = value.to_string(),
+            "Author" => metadata.authors = value.split(" and ").map(|a| a.trim().to_string()).collect(),
+            // Gutenberg writes the language out by name ("Language: English")
+            "Language" => metadata.language = epub::normalize_language(value),
+            _ => {}
+        }
+    }
+    metadata
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_quality() {
+        let is_word = |word: &str| ["the", "engine", "weaves", "well", "known"].contains(&word);
+        assert_eq!(text_quality("The Engine, “weaves” — 1843 well-known.", is_word), 1.0);
+        // Mojibake and replacement characters
+        assert_eq!(text_quality("The Ã©ngine wâ€™eaves Ǝ\u{fffd}x", is_word), 0.25);
+        // OCR noise: half the words are junk; numbers and punctuation don't count
+        assert_eq!(text_quality("Tlie Engine wcaves the 1843 ;;", is_word), 0.5);
+        // Nothing to read at all
+        assert_eq!(text_quality("", is_word), 0.0);
+        assert_eq!(text_quality("1843 — 1844 ;;", is_word), 0.0);
+
+        // Long texts are sampled over their whole length, so junk at the end still counts
+        let text = format!("{}{}", "the ".repeat(9000), "qzxv ".repeat(1000));
+        assert_eq!(text_quality(&text, is_word), 0.9);
+    }
+
+    #[test]
+    fn test_text_quality_uses_the_books_language() {
+        let mut extracted = ExtractedText::from_chapters(vec![Chapter::fixture(0, "Kapitel 1", "der und die")]);
+        assert_eq!(extracted.text_quality, None);
+
+        measure_text_quality(&mut extracted, nlp::Language::German);
+        assert_eq!(extracted.text_quality, Some(1.0));
+    }
+
+    #[test]
+    fn test_txt_strips_soft_hyphens_and_keeps_compounds() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("scan.txt");
+        std::fs::write(&path, "An in\u{AD}com\u{AD}pre\u{AD}hen\u{AD}sible,\nwell-\nknown   tale.\n").unwrap();
+
+        let extracted = extract_text(&path, BookFormat::Txt).unwrap();
+        assert_eq!(extracted.full_text, "An incomprehensible, well-known tale.");
+    }
+
+    fn write_text(dir: &tempfile::TempDir, name: &str, contents: &str) -> std::path::PathBuf {
+        let path = dir.path().join(name);
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_txt_chapters_from_headings() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_text(
+            &dir,
+            "engine.txt",
+            "A preface.\r\n\r\nCHAPTER I.\r\n\r\nThe Engine weaves\r\npatterns.\r\n\r\nChapter 2\n\nIt does not originate.\n",
+        );
+
+        let extracted = extract_text(&path, BookFormat::Txt).unwrap();
+        let titles: Vec<&str> = extracted.chapters.iter().map(|c| c.title.as_str()).collect();
+        assert_eq!(titles, ["Chapter 1", "CHAPTER I.", "Chapter 2"]);
+        assert_eq!(extracted.chapters[1].text, "CHAPTER I.\n\nThe Engine weaves patterns.");
+        assert_eq!(extracted.chapters[2].spine_index, 2);
+    }
+
+    #[test]
+    fn test_txt_without_headings_is_one_chapter() {
+        let dir = tempfile::tempdir().unwrap();
+        // "Part of it" is prose, not a heading
+        let path = write_text(&dir, "essay.txt", "Part of it.\n\nThe rest of it.\n");
+
+        let extracted = extract_text(&path, BookFormat::Txt).unwrap();
+        assert_eq!(extracted.chapter_count, 1);
+        assert_eq!(extracted.full_text, "Part of it.\n\nThe rest of it.");
+    }
+
+    #[test]
+    fn test_txt_verse_ranges() {
+        let dir = tempfile::tempdir().unwrap();
+        let stanza = "Tyger Tyger, burning bright,\nIn the forests of the night;\nWhat immortal hand or eye,\nCould frame thy fearful symmetry?";
+        let path = write_text(&dir, "songs.txt", &format!("He recited:\n\n{}\n\n{}\n\nAnd stopped.\n", stanza, stanza));
+
+        let extracted = extract_text(&path, BookFormat::Txt).unwrap();
+        let chars: Vec<char> = extracted.full_text.chars().collect();
+        let verse: Vec<String> = extracted.verse.iter().map(|r| chars[r.clone()].iter().collect()).collect();
+        let line = stanza.replace('\n', " ");
+        assert_eq!(verse, vec![format!("{}\n\n{}", line, line)]);
+    }
+
+    #[test]
+    fn test_txt_strips_gutenberg_boilerplate() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_text(
+            &dir,
+            "pg.txt",
+            "Title: Sketch of the Analytical Engine\nAuthor: L. F. Menabrea and Ada Lovelace\nLanguage: English\n\n\
+             *** START OF THE PROJECT GUTENBERG EBOOK SKETCH ***\n\nThe Engine weaves.\n\n\
+             *** END OF THE PROJECT GUTENBERG EBOOK SKETCH ***\n\nLicense text.\n",
+        );
+
+        assert_eq!(extract_text(&path, BookFormat::Txt).unwrap().full_text, "The Engine weaves.");
+
+        let metadata = read_text_metadata(&path);
+        assert_eq!(metadata.title, "Sketch of the Analytical Engine");
+        assert_eq!(metadata.authors, ["L. F. Menabrea", "Ada Lovelace"]);
+        assert_eq!(metadata.language.as_deref(), Some("en"));
+        assert_eq!(read_text_metadata(&dir.path().join("untitled.md")).title, "untitled");
+    }
+
+    #[test]
+    fn test_markdown_headings_and_links() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_text(&dir, "notes.md", "# Notes\n\nSee [the Engine](engine.md).\n\n## Note A ##\n\nIt weaves.\n");
+
+        let extracted = extract_text(&path, BookFormat::Markdown).unwrap();
+        let titles: Vec<&str> = extracted.chapters.iter().map(|c| c.title.as_str()).collect();
+        assert_eq!(titles, ["Notes", "Note A"]);
+        assert_eq!(extracted.chapters[0].text, "Notes\n\nSee [the Engine].");
+        assert_eq!(BookFormat::from_extension("markdown"), Some(BookFormat::Markdown));
+    }
+
+    #[test]
+    fn test_decode_text_byte_order_marks() {
+        let text = "Café naïve";
+        let utf16le: Vec<u8> = [0xFF, 0xFE].into_iter().chain(text.encode_utf16().flat_map(u16::to_le_bytes)).collect();
+        let utf16be: Vec<u8> = [0xFE, 0xFF].into_iter().chain(text.encode_utf16().flat_map(u16::to_be_bytes)).collect();
+        let utf8_bom: Vec<u8> = [0xEF, 0xBB, 0xBF].into_iter().chain(text.bytes()).collect();
+
+        assert_eq!(decode_text(&utf16le), text);
+        assert_eq!(decode_text(&utf16be), text);
+        assert_eq!(decode_text(&utf8_bom), text);
+        assert_eq!(decode_text(text.as_bytes()), text);
+        // Invalid UTF-8 is replaced rather than rejected
+        assert_eq!(decode_text(b"Caf\xE9"), "Caf\u{FFFD}");
+    }
+}
@@ -1,81 +1,440 @@
+mod analysis_cache;
+mod book_text;
 mod calibre;
+mod covers;
 mod epub;
+mod error;
+mod export;
+mod formats;
+mod libraries;
+mod mobi;
 pub mod nlp;
+mod page_artifacts;
+#[cfg(feature = "pdf")]
+mod pdf;
 mod resources;
+mod vocabulary;
+mod watcher;
 
-use std::collections::HashMap;
+use error::CommandError;
+use std::collections::{HashMap, HashSet};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 use tauri::Emitter;
 use tokio::sync::mpsc;
 
 pub struct AppState {
-    pub library_path: Mutex<Option<String>>,
+    /// Libraries opened this session and the active one
+    pub libraries: Mutex<libraries::LibraryRegistry>,
     pub nlp: nlp::NlpPipeline,
     /// Active analysis jobs: book_id -> cancellation token
     pub active_jobs: Mutex<HashMap<i64, Arc<AtomicBool>>>,
+    /// Queued series analyses: member book_id -> the queue's cancellation token
+    pub series_jobs: Mutex<HashMap<i64, Arc<AtomicBool>>>,
+    /// Cancellation token of the running resource download, if any
+    pub download_job: Mutex<Option<Arc<AtomicBool>>>,
+    /// Words the user has marked as learned (persisted in the resource dir)
+    pub known_words: Mutex<HashSet<String>>,
+    /// The user's stopwords, added to the bundled list of the book's language
+    pub custom_stopwords: Mutex<HashSet<String>>,
+    /// Words the user marked as not a name or place, which NER never filters
+    pub entity_allowlist: Mutex<HashSet<String>>,
+    /// Which sentences analyses keep as example contexts (for this session)
+    pub context_policy: Mutex<nlp::ContextPolicy>,
+    /// Watcher for the library the frontend is showing, if any
+    pub library_watcher: Mutex<Option<watcher::LibraryWatcher>>,
+    /// The book last read chapter by chapter, so each `get_book_chapter` doesn't re-extract it
+    pub book_text_cache: book_text::BookTextCache,
 }
 
 impl Default for AppState {
     fn default() -> Self {
         Self {
-            library_path: Mutex::new(None),
+            libraries: Mutex::new(libraries::LibraryRegistry::default()),
             nlp: nlp::NlpPipeline::new(),
             active_jobs: Mutex::new(HashMap::new()),
+            series_jobs: Mutex::new(HashMap::new()),
+            download_job: Mutex::new(None),
+            known_words: Mutex::new(resources::load_known_words()),
+            custom_stopwords: Mutex::new(resources::load_custom_stopwords()),
+            entity_allowlist: Mutex::new(resources::load_entity_allowlist()),
+            context_policy: Mutex::new(nlp::ContextPolicy::default()),
+            library_watcher: Mutex::new(None),
+            book_text_cache: book_text::BookTextCache::default(),
         }
     }
 }
 
 #[tauri::command]
-fn scan_library(path: &str, state: tauri::State<AppState>) -> Result<Vec<calibre::Book>, calibre::CalibreError> {
-    let books = calibre::scan_library(path)?;
-    *state.library_path.lock().unwrap() = Some(path.to_string());
+fn scan_library(
+    path: &str,
+    author: Option<String>,
+    tag: Option<String>,
+    sort: Option<String>,
+    descending: Option<bool>,
+    state: tauri::State<AppState>,
+) -> Result<Vec<calibre::Book>, CommandError> {
+    let sort = sort.as_deref().map(calibre::SortKey::from_name).unwrap_or_default();
+    let books = calibre::scan_library(
+        path,
+        &calibre::BookFilter { author, tag, query: None },
+        sort,
+        descending.unwrap_or(false),
+    )?;
+    let mut libraries = state.libraries.lock().unwrap();
+    let id = libraries.add(path);
+    let _ = libraries.switch(id);
     Ok(books)
 }
 
+/// Largest page `scan_library_page` returns
+const MAX_PAGE_SIZE: usize = 500;
+
+/// Paged, searchable variant of `scan_library` for large libraries
+#[tauri::command]
+#[allow(clippy::too_many_arguments)] // Tauri maps each argument to a named JS parameter
+fn scan_library_page(
+    path: &str,
+    query: Option<String>,
+    author: Option<String>,
+    tag: Option<String>,
+    sort: Option<String>,
+    descending: Option<bool>,
+    offset: Option<usize>,
+    limit: Option<usize>,
+    state: tauri::State<AppState>,
+) -> Result<calibre::BookPage, CommandError> {
+    let sort = sort.as_deref().map(calibre::SortKey::from_name).unwrap_or_default();
+    let page = calibre::scan_library_page(
+        path,
+        &calibre::BookFilter { author, tag, query },
+        sort,
+        descending.unwrap_or(false),
+        offset.unwrap_or(0),
+        limit.unwrap_or(50).clamp(1, MAX_PAGE_SIZE),
+    )?;
+    let mut libraries = state.libraries.lock().unwrap();
+    let id = libraries.add(path);
+    let _ = libraries.switch(id);
+    Ok(page)
+}
+
+/// Use a plain directory of EPUBs as a library (no Calibre needed)
+#[tauri::command]
+fn scan_folder(path: &str, state: tauri::State<AppState>) -> Result<Vec<calibre::Book>, CommandError> {
+    let (books, files) = calibre::folder::scan_folder(std::path::Path::new(path), calibre::folder::MAX_DEPTH)?;
+    let mut libraries = state.libraries.lock().unwrap();
+    let id = libraries.add_folder(path, files);
+    let _ = libraries.switch(id);
+    Ok(books)
+}
+
+/// Path of the given library, or the active one
+fn library_path(state: &AppState, library_id: Option<u32>) -> Result<String, CommandError> {
+    state.libraries.lock().unwrap().path(library_id)
+}
+
+#[derive(Clone, serde::Serialize)]
+struct LibraryChanged {
+    path: String,
+    kinds: Vec<watcher::ChangeKind>,
+}
+
+/// Watch a library (the active one by default), emitting `library-changed` events.
+/// Replaces any previous watcher.
+#[tauri::command]
+fn watch_library(window: tauri::Window, library_id: Option<u32>, state: tauri::State<AppState>) -> Result<(), CommandError> {
+    let lib_path = library_path(&state, library_id)?;
+    if calibre::remote::is_remote(&lib_path) {
+        return Err(CommandError::Invalid("Content server libraries cannot be watched".to_string()));
+    }
+
+    let watching = state.library_watcher.lock().unwrap().as_ref().map(|w| w.root().to_path_buf());
+    if watching.is_some_and(|root| root == std::path::Path::new(&lib_path)) {
+        return Ok(());
+    }
+
+    let event_path = lib_path.clone();
+    let watcher = watcher::LibraryWatcher::start(lib_path.into(), move |kinds| {
+        let _ = window.emit("library-changed", LibraryChanged {
+            path: event_path.clone(),
+            kinds,
+        });
+    })
+    .map_err(|e| CommandError::Io(format!("Failed to watch library: {}", e)))?;
+    // Stopping the old watcher waits for its thread, so do it without holding the lock
+    let old = state.library_watcher.lock().unwrap().replace(watcher);
+    drop(old);
+    Ok(())
+}
+
+/// Stop watching; returns false if nothing was being watched
+#[tauri::command]
+fn unwatch_library(state: tauri::State<AppState>) -> bool {
+    let old = state.library_watcher.lock().unwrap().take();
+    old.is_some()
+}
+
+#[tauri::command]
+fn list_libraries(state: tauri::State<AppState>) -> Vec<libraries::LibraryInfo> {
+    state.libraries.lock().unwrap().list()
+}
+
+/// Register a library without scanning or activating it
+#[tauri::command]
+fn add_library(path: &str, state: tauri::State<AppState>) -> u32 {
+    state.libraries.lock().unwrap().add(path)
+}
+
+#[tauri::command]
+fn switch_library(library_id: u32, state: tauri::State<AppState>) -> Result<(), CommandError> {
+    state.libraries.lock().unwrap().switch(library_id)
+}
+
+#[tauri::command]
+fn search_books(query: &str, state: tauri::State<AppState>) -> Result<Vec<calibre::Book>, CommandError> {
+    let lib_path = library_path(&state, None)?;
+
+    calibre::search_books(&lib_path, query).map_err(CommandError::from)
+}
+
+/// Groups of books in the active library that look like duplicates
+#[tauri::command]
+fn find_duplicates(state: tauri::State<AppState>) -> Result<Vec<calibre::DuplicateGroup>, CommandError> {
+    let lib_path = library_path(&state, None)?;
+
+    calibre::find_duplicates(&lib_path).map_err(CommandError::from)
+}
+
+/// Authors of the active library with their book counts, for an author sidebar
 #[tauri::command]
-fn get_epub_path(book_id: i64, state: tauri::State<AppState>) -> Result<Option<String>, String> {
-    let lib_path = state.library_path.lock().unwrap();
-    let lib_path = lib_path.as_ref().ok_or("No library loaded")?;
+fn list_authors(state: tauri::State<AppState>) -> Result<Vec<calibre::AuthorSummary>, CommandError> {
+    let lib_path = library_path(&state, None)?;
+
+    calibre::list_authors(&lib_path).map_err(CommandError::from)
+}
 
-    calibre::get_epub_path(lib_path, book_id)
+#[tauri::command]
+fn get_books_by_author(
+    author_id: i64,
+    sort: Option<String>,
+    descending: Option<bool>,
+    state: tauri::State<AppState>,
+) -> Result<Vec<calibre::Book>, CommandError> {
+    let lib_path = library_path(&state, None)?;
+    let sort = sort.as_deref().map(calibre::SortKey::from_name).unwrap_or_default();
+
+    calibre::get_books_by_author(&lib_path, author_id, sort, descending.unwrap_or(false)).map_err(CommandError::from)
+}
+
+#[tauri::command]
+fn get_custom_columns(state: tauri::State<AppState>) -> Result<Vec<calibre::CustomColumn>, CommandError> {
+    let lib_path = library_path(&state, None)?;
+
+    calibre::get_custom_columns(&lib_path).map_err(CommandError::from)
+}
+
+/// Publisher, identifiers, rating and description of one book in the active library
+#[tauri::command]
+fn get_book_details(book_id: i64, state: tauri::State<AppState>) -> Result<calibre::BookDetails, CommandError> {
+    if folder_file(&state, None, book_id)?.is_some() {
+        return Err(CommandError::Invalid("Book details are only available for Calibre libraries".to_string()));
+    }
+    let lib_path = library_path(&state, None)?;
+
+    calibre::get_book_details(&lib_path, book_id).map_err(CommandError::from)
+}
+
+/// File of a book in a folder library (None for Calibre libraries)
+fn folder_file(state: &AppState, library_id: Option<u32>, book_id: i64) -> Result<Option<std::path::PathBuf>, CommandError> {
+    state.libraries.lock().unwrap().folder_file(library_id, book_id)
+}
+
+#[tauri::command]
+fn get_epub_path(book_id: i64, state: tauri::State<AppState>) -> Result<Option<String>, CommandError> {
+    if let Some(path) = folder_file(&state, None, book_id)? {
+        return Ok(Some(path.to_string_lossy().to_string()));
+    }
+    let lib_path = library_path(&state, None)?;
+
+    calibre::get_epub_path(&lib_path, book_id)
         .map(|p| p.map(|path| path.to_string_lossy().to_string()))
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
+}
+
+/// Cover thumbnail as a base64 data URL, resized to `max_dim` on the long edge
+#[tauri::command]
+async fn get_cover(book_id: i64, max_dim: u32, state: tauri::State<'_, AppState>) -> Result<Option<String>, CommandError> {
+    // Folder libraries have no cover files; read the image out of the EPUB instead
+    if let Some(path) = folder_file(&state, None, book_id)? {
+        if calibre::folder::file_format(&path) != Some(formats::BookFormat::Epub) {
+            return Ok(None);
+        }
+        return tokio::task::spawn_blocking(move || covers::epub_thumbnail_data_url(&path, max_dim))
+            .await
+            .map_err(CommandError::from)?
+            .map_err(CommandError::from);
+    }
+    let lib_path = library_path(&state, None)?;
+
+    let Some(cover_path) = calibre::get_cover_path(&lib_path, book_id).map_err(CommandError::from)? else {
+        return Ok(None);
+    };
+
+    tokio::task::spawn_blocking(move || covers::thumbnail_data_url(&cover_path, max_dim))
+        .await
+        .map_err(CommandError::from)?
+        .map(Some)
+        .map_err(CommandError::from)
 }
 
 #[derive(serde::Serialize)]
 struct BookText {
     text: String,
     chapter_count: usize,
+    chapter_titles: Vec<String>,
     word_count: usize,
+    /// Footnote and endnote bodies, kept out of `text`
+    footnotes: Vec<String>,
+    /// Number of sections that couldn't be read or were empty
+    extraction_warnings: usize,
+    /// Which spine item each range of `text` came from
+    segments: Vec<epub::Segment>,
 }
 
-#[tauri::command]
-fn get_book_text(book_id: i64, state: tauri::State<AppState>) -> Result<BookText, String> {
-    let lib_path = state.library_path.lock().unwrap();
-    let lib_path = lib_path.as_ref().ok_or("No library loaded")?;
+/// Resolve the EPUB file of a book in the active library
+fn book_epub_path(state: &AppState, book_id: i64) -> Result<std::path::PathBuf, CommandError> {
+    if let Some(path) = folder_file(state, None, book_id)? {
+        return Ok(path);
+    }
+    let lib_path = library_path(state, None)?;
 
-    let epub_path = calibre::get_epub_path(lib_path, book_id)
-        .map_err(|e| e.to_string())?
-        .ok_or("No EPUB file found for this book")?;
+    calibre::get_epub_path(&lib_path, book_id)
+        .map_err(CommandError::from)?
+        .ok_or(CommandError::NoEpub)
+}
 
-    let extracted = epub::extract_text(&epub_path).map_err(|e| e.to_string())?;
+/// Resolve the best readable file (any supported format) of a book in a library
+fn book_file(
+    state: &AppState,
+    library_id: Option<u32>,
+    book_id: i64,
+) -> Result<(std::path::PathBuf, formats::BookFormat), CommandError> {
+    if let Some(path) = folder_file(state, library_id, book_id)? {
+        let format = calibre::folder::file_format(&path).unwrap_or(formats::BookFormat::Epub);
+        return Ok((path, format));
+    }
+    let lib_path = library_path(state, library_id)?;
+
+    calibre::get_book_file(&lib_path, book_id)
+        .map_err(CommandError::from)?
+        .ok_or(CommandError::NoBookFile)
+}
+
+/// The whole book in one payload; readers should page with `get_book_outline` and
+/// `get_book_chapter` instead, which keep IPC messages small
+#[tauri::command]
+fn get_book_text(
+    book_id: i64,
+    library_id: Option<u32>,
+    include_figures: Option<bool>,
+    state: tauri::State<AppState>,
+) -> Result<BookText, CommandError> {
+    let (book_path, format) = book_file(&state, library_id, book_id)?;
+
+    // Analysis drops tables and figure captions; the reading view may want them intact
+    let options = epub::CleanOptions {
+        strip_figures_and_tables: !include_figures.unwrap_or(false),
+    };
+    let extracted = formats::extract_text_with_options(&book_path, format, options).map_err(CommandError::from)?;
 
     let word_count = extracted.full_text.split_whitespace().count();
 
     Ok(BookText {
         text: extracted.full_text,
         chapter_count: extracted.chapter_count,
+        chapter_titles: extracted.chapters.into_iter().map(|c| c.title).collect(),
         word_count,
+        footnotes: extracted.footnotes,
+        extraction_warnings: extracted.warnings.len(),
+        segments: extracted.segments,
     })
 }
 
+/// Extracted text of a book for the chapter-by-chapter commands, from `book_text_cache`
+/// when it is the book read last and hasn't changed since
+async fn chapter_source(
+    state: &AppState,
+    library_id: Option<u32>,
+    book_id: i64,
+    include_figures: Option<bool>,
+) -> Result<Arc<epub::ExtractedText>, CommandError> {
+    let (book_path, format) = book_file(state, library_id, book_id)?;
+    let options = epub::CleanOptions {
+        strip_figures_and_tables: !include_figures.unwrap_or(false),
+    };
+    let key = book_text::CacheKey::new(&book_path, options);
+    if let Some(extracted) = state.book_text_cache.get(&key) {
+        return Ok(extracted);
+    }
+
+    let extracted = tokio::task::spawn_blocking(move || formats::extract_text_with_options(&book_path, format, options))
+        .await
+        .map_err(CommandError::from)??;
+    let extracted = Arc::new(extracted);
+    state.book_text_cache.insert(key, Arc::clone(&extracted));
+    Ok(extracted)
+}
+
+/// Chapter titles and word counts, for loading a book one chapter at a time
+#[tauri::command]
+async fn get_book_outline(
+    book_id: i64,
+    library_id: Option<u32>,
+    include_figures: Option<bool>,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<epub::ChapterOutline>, CommandError> {
+    Ok(chapter_source(&state, library_id, book_id, include_figures).await?.outline())
+}
+
+/// One chapter of a book (see `get_book_outline`), instead of the whole text at once
+#[tauri::command]
+async fn get_book_chapter(
+    book_id: i64,
+    index: usize,
+    library_id: Option<u32>,
+    include_figures: Option<bool>,
+    state: tauri::State<'_, AppState>,
+) -> Result<book_text::BookChapter, CommandError> {
+    let extracted = chapter_source(&state, library_id, book_id, include_figures).await?;
+    book_text::BookChapter::from_extracted(&extracted, index).ok_or_else(|| {
+        CommandError::Invalid(format!("Invalid chapter {} (book has {} chapters)", index, extracted.chapters.len()))
+    })
+}
+
+#[tauri::command]
+fn get_toc(book_id: i64, state: tauri::State<AppState>) -> Result<Vec<epub::TocEntry>, CommandError> {
+    let epub_path = book_epub_path(&state, book_id)?;
+    epub::get_toc(&epub_path).map_err(CommandError::from)
+}
+
 #[derive(serde::Serialize)]
 struct AnalysisResult {
     book_id: i64,
     word_count: usize,
     hard_words: Vec<nlp::HardWord>,
     stats: nlp::AnalysisStats,
+    /// Number of sections that couldn't be read or were empty (see `BookText`)
+    extraction_warnings: usize,
+    /// Share of real words in the analyzed book (see `formats::text_quality`); below
+    /// `formats::POOR_TEXT_QUALITY` the results are suspect. None for pasted text.
+    text_quality: Option<f32>,
+    /// Footnote/endnote bodies set aside during extraction (analyzed only with `include_footnotes`)
+    removed_notes: usize,
+    /// Running-header and page-number lines removed (see `page_artifacts`)
+    removed_artifacts: usize,
+    /// Prefaces, dedications and the like left out (analyzed only with `include_front_sections`)
+    excluded_sections: Vec<epub::ExcludedSection>,
 }
 
 #[derive(serde::Serialize, Clone)]
@@ -87,78 +446,141 @@ struct AnalysisProgress {
     sample_words: Option<Vec<nlp::SampleWord>>,
 }
 
-#[tauri::command]
-async fn analyze_book(
+/// Payload of `analysis-warning`: something about the book that makes results suspect,
+/// sent while the analysis goes on
+#[derive(Clone, serde::Serialize)]
+struct AnalysisWarning {
     book_id: i64,
-    frequency_threshold: Option<f32>,
-    window: tauri::Window,
-    state: tauri::State<'_, AppState>,
-) -> Result<AnalysisResult, String> {
-    let threshold = frequency_threshold.unwrap_or(0.00005);
+    message: String,
+    /// `ExtractedText::text_quality` of the book
+    quality: f32,
+}
 
-    // Create cancellation token and register the job
-    let cancel_token = Arc::new(AtomicBool::new(false));
-    {
-        let mut jobs = state.active_jobs.lock().unwrap();
-        // Cancel any existing job for this book
-        if let Some(old_token) = jobs.get(&book_id) {
-            old_token.store(true, Ordering::SeqCst);
-        }
-        jobs.insert(book_id, Arc::clone(&cancel_token));
+/// Measure how much of the extracted text is real words of `language`, and warn the
+/// frontend when it looks like garbage, before the user waits for NER to run over it
+fn check_text_quality(
+    window: &tauri::Window,
+    book_id: i64,
+    extracted: &mut epub::ExtractedText,
+    language: nlp::Language,
+) -> f32 {
+    formats::measure_text_quality(extracted, language);
+    let quality = extracted.text_quality.unwrap_or(0.0);
+    if quality >= formats::POOR_TEXT_QUALITY {
+        return quality;
     }
+    eprintln!("Extracted text of book {} looks broken ({:.0}% recognizable)", book_id, quality * 100.0);
+    let _ = window.emit("analysis-warning", AnalysisWarning {
+        book_id,
+        message: format!(
+            "Only {:.0}% of this book's text is recognizable; it may be a scanned PDF, have a broken encoding or be DRM-protected, so results may be poor",
+            quality * 100.0
+        ),
+        quality,
+    });
+    quality
+}
 
-    let lib_path = {
-        let guard = state.library_path.lock().unwrap();
-        guard.clone().ok_or("No library loaded")?
-    };
-
-    let epub_path = calibre::get_epub_path(&lib_path, book_id)
-        .map_err(|e| e.to_string())?
-        .ok_or("No EPUB file found for this book")?;
+/// Payload of `analysis-partial-results`: a batch of hard words while analysis runs,
+/// then one final event (with no words) saying how the run ended
+#[derive(Clone, serde::Serialize)]
+struct PartialResults {
+    book_id: i64,
+    status: PartialStatus,
+    words: Vec<nlp::HardWord>,
+}
 
-    // Check cancellation before expensive operation
-    if cancel_token.load(Ordering::SeqCst) {
-        cleanup_job(&state, book_id);
-        return Err("Analysis cancelled".to_string());
-    }
+#[derive(Clone, Copy, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+enum PartialStatus {
+    /// More words may follow
+    Partial,
+    /// The command's return value holds the full sorted list
+    Complete,
+    /// Cancelled: discard the partial list
+    Cancelled,
+    /// Stopped early (e.g. missing resources): discard the partial list
+    Failed,
+}
 
-    let _ = window.emit("analysis-progress", AnalysisProgress {
-        book_id,
-        stage: "Extracting text".to_string(),
-        progress: 10,
-        detail: Some("Reading EPUB...".to_string()),
-        sample_words: None,
-    });
+/// Messages from the analysis thread to the event relay task
+enum RelayEvent {
+    Progress(nlp::AnalysisProgress),
+    Partial(Vec<nlp::HardWord>),
+}
 
-    let extracted = epub::extract_text(&epub_path).map_err(|e| e.to_string())?;
-    let word_count = extracted.full_text.split_whitespace().count();
+/// Create a cancellation token for `book_id`, cancelling any analysis already running for it
+fn register_job(state: &tauri::State<'_, AppState>, book_id: i64) -> Arc<AtomicBool> {
+    let cancel_token = Arc::new(AtomicBool::new(false));
+    let mut jobs = state.active_jobs.lock().unwrap();
+    if let Some(old_token) = jobs.get(&book_id) {
+        old_token.store(true, Ordering::SeqCst);
+    }
+    jobs.insert(book_id, Arc::clone(&cancel_token));
+    cancel_token
+}
 
+/// Run the NLP pipeline on `text` for the job registered under `book_id`, relaying
+/// progress and partial results to the window. The job is cleaned up when it ends.
+/// `segments` map `text` back to spine items and chapters (titled by `chapter_titles`)
+/// for `HardWord::first_occurrence` and `HardWord::first_chapter`; `verse` marks the
+/// passages `AnalysisOptions::skip_verse` leaves out.
+#[allow(clippy::too_many_arguments)]
+async fn run_analysis(
+    book_id: i64,
+    text: String,
+    segments: Vec<epub::Segment>,
+    verse: Vec<std::ops::Range<usize>>,
+    chapter_titles: Vec<String>,
+    threshold: f32,
+    options: nlp::AnalysisOptions,
+    language: nlp::Language,
+    cancel_token: Arc<AtomicBool>,
+    window: &tauri::Window,
+    state: &tauri::State<'_, AppState>,
+) -> Result<(Vec<nlp::HardWord>, nlp::AnalysisStats), CommandError> {
     // Check cancellation before NLP
     if cancel_token.load(Ordering::SeqCst) {
-        cleanup_job(&state, book_id);
-        return Err("Analysis cancelled".to_string());
+        cleanup_job(state, book_id);
+        return Err(CommandError::Cancelled);
+    }
+
+    // Fail before any partial results reach the UI rather than midway through
+    if !options.skip_ner && !nlp::NlpPipeline::resources_available(language) {
+        cleanup_job(state, book_id);
+        return Err(CommandError::ResourcesMissing);
     }
 
     // Run NLP analysis on a blocking thread with channel-based progress reporting
     // We use a channel to relay progress from the blocking thread to an async task
     // that can properly emit events through Tauri's event loop
-    let text = extracted.full_text;
     let cancel_clone = Arc::clone(&cancel_token);
 
-    // Channel for progress updates from blocking thread
-    let (progress_tx, mut progress_rx) = mpsc::unbounded_channel::<nlp::AnalysisProgress>();
+    // Channel for progress updates and partial results from the blocking thread
+    let (progress_tx, mut progress_rx) = mpsc::unbounded_channel::<RelayEvent>();
 
     // Spawn async task to relay progress events to the window
     let window_clone = window.clone();
     let progress_relay = tokio::spawn(async move {
-        while let Some(progress) = progress_rx.recv().await {
-            let _ = window_clone.emit("analysis-progress", AnalysisProgress {
-                book_id,
-                stage: progress.stage,
-                progress: progress.progress,
-                detail: progress.detail,
-                sample_words: progress.sample_words,
-            });
+        while let Some(event) = progress_rx.recv().await {
+            match event {
+                RelayEvent::Progress(progress) => {
+                    let _ = window_clone.emit("analysis-progress", AnalysisProgress {
+                        book_id,
+                        stage: progress.stage,
+                        progress: progress.progress,
+                        detail: progress.detail,
+                        sample_words: progress.sample_words,
+                    });
+                }
+                RelayEvent::Partial(words) => {
+                    let _ = window_clone.emit("analysis-partial-results", PartialResults {
+                        book_id,
+                        status: PartialStatus::Partial,
+                        words,
+                    });
+                }
+            }
             // Small yield to allow event loop to process
             tokio::task::yield_now().await;
         }
@@ -167,24 +589,64 @@ async fn analyze_book(
     // Give the relay task a chance to start
     tokio::task::yield_now().await;
 
+    let known_words = state.known_words.lock().unwrap().clone();
+    let custom_stopwords = state.custom_stopwords.lock().unwrap().clone();
+    let entity_allowlist = state.entity_allowlist.lock().unwrap().clone();
+    let context_policy = *state.context_policy.lock().unwrap();
+
     let nlp_result = tokio::task::spawn_blocking(move || {
-        let nlp = nlp::NlpPipeline::new();
-        let result = nlp.analyze_with_cancel(&text, threshold, &cancel_clone, |progress| {
-            let _ = progress_tx.send(progress);
-        });
+        let nlp = nlp::NlpPipeline::new()
+            .with_language(language)
+            .with_known_words(&known_words)
+            .with_stopwords(&custom_stopwords)
+            .with_entity_allowlist(&entity_allowlist)
+            .with_context_policy(context_policy)
+            .with_segments(segments)
+            .with_verse(verse)
+            .with_chapter_titles(chapter_titles);
+        let partial_tx = progress_tx.clone();
+        let result = nlp.analyze_with_cancel(
+            &text,
+            threshold,
+            &options,
+            &cancel_clone,
+            |progress| {
+                let _ = progress_tx.send(RelayEvent::Progress(progress));
+            },
+            |words| {
+                let _ = partial_tx.send(RelayEvent::Partial(words));
+            },
+        );
         drop(progress_tx);
+        drop(partial_tx);
         result
     })
     .await
-    .map_err(|e| format!("Task join error: {}", e))?;
+    .map_err(CommandError::from)?;
 
     // Wait for relay to finish processing remaining events (it will exit when sender is dropped)
     let _ = progress_relay.await;
 
     // Clean up job tracking
-    cleanup_job(&state, book_id);
+    cleanup_job(state, book_id);
 
-    let (hard_words, stats) = nlp_result.ok_or("Analysis cancelled")?;
+    // Tell the UI whether the partial list it built is final or should be discarded
+    let status = match &nlp_result {
+        Some(_) => PartialStatus::Complete,
+        None if cancel_token.load(Ordering::SeqCst) => PartialStatus::Cancelled,
+        None => PartialStatus::Failed,
+    };
+    let _ = window.emit("analysis-partial-results", PartialResults {
+        book_id,
+        status,
+        words: Vec::new(),
+    });
+
+    let (hard_words, stats) = match nlp_result {
+        Some(result) => result,
+        None if cancel_token.load(Ordering::SeqCst) => return Err(CommandError::Cancelled),
+        None => return Err(CommandError::Internal("Analysis failed".to_string())),
+    };
 
     let _ = window.emit("analysis-progress", AnalysisProgress {
         book_id,
@@ -194,11 +656,335 @@ async fn analyze_book(
         sample_words: None,
     });
 
+    Ok((hard_words, stats))
+}
+
+/// How to analyze a book; every field may be left out of the invoke payload
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct AnalyzeRequest {
+    frequency_threshold: f32,
+    skip_ner: bool,
+    /// First chapter to analyze; with `chapter_end` (exclusive) restricts the analysis to a range
+    chapter_start: Option<usize>,
+    chapter_end: Option<usize>,
+    include_footnotes: bool,
+    strip_page_artifacts: bool,
+    skip_verse: bool,
+    /// Language of the book's metadata, used when the text's own can't be detected
+    language: Option<String>,
+    word_order: nlp::WordOrder,
+    min_count: usize,
+    min_frequency: f32,
+    include_unlisted: bool,
+    collapse_derived: bool,
+    group_families: bool,
+    allowed_pos: Vec<nlp::pos::Pos>,
+    include_front_sections: bool,
+}
+
+impl Default for AnalyzeRequest {
+    fn default() -> Self {
+        Self {
+            frequency_threshold: nlp::DEFAULT_FREQUENCY_THRESHOLD,
+            skip_ner: false,
+            chapter_start: None,
+            chapter_end: None,
+            include_footnotes: false,
+            strip_page_artifacts: false,
+            skip_verse: false,
+            language: None,
+            word_order: nlp::WordOrder::default(),
+            min_count: 1,
+            min_frequency: 0.0,
+            include_unlisted: false,
+            collapse_derived: false,
+            group_families: false,
+            allowed_pos: Vec::new(),
+            include_front_sections: false,
+        }
+    }
+}
+
+impl AnalyzeRequest {
+    fn analysis_options(&self) -> nlp::AnalysisOptions {
+        nlp::AnalysisOptions {
+            skip_ner: self.skip_ner,
+            order: self.word_order,
+            min_count: self.min_count.max(1),
+            min_frequency: self.min_frequency.max(0.0),
+            include_unlisted: self.include_unlisted,
+            collapse_derived: self.collapse_derived,
+            group_families: self.group_families,
+            allowed_pos: self.allowed_pos.clone(),
+            skip_verse: self.skip_verse,
+        }
+    }
+
+    /// The options a saved analysis of this request is matched on
+    fn cache_options(&self) -> analysis_cache::CachedOptions {
+        analysis_cache::CachedOptions {
+            include_footnotes: self.include_footnotes,
+            strip_page_artifacts: self.strip_page_artifacts,
+            include_front_sections: self.include_front_sections,
+            language: self.language.clone(),
+            ..analysis_cache::CachedOptions::new(self.frequency_threshold, &self.analysis_options())
+        }
+    }
+}
+
+#[tauri::command]
+async fn analyze_book(
+    book_id: i64,
+    library_id: Option<u32>,
+    request: Option<AnalyzeRequest>,
+    window: tauri::Window,
+    state: tauri::State<'_, AppState>,
+) -> Result<AnalysisResult, CommandError> {
+    let request = request.unwrap_or_default();
+    let threshold = request.frequency_threshold;
+    let options = request.analysis_options();
+    let cache_options = request.cache_options();
+    let AnalyzeRequest {
+        chapter_start,
+        chapter_end,
+        include_footnotes,
+        strip_page_artifacts,
+        language,
+        include_front_sections,
+        ..
+    } = request;
+
+    // Resolve the book before registering the job, so a missing book leaves no job behind
+    let (book_path, format) = book_file(&state, library_id, book_id)?;
+    let lib_path = library_path(&state, library_id)?;
+
+    let cancel_token = register_job(&state, book_id);
+
+    // Check cancellation before expensive operation
+    if cancel_token.load(Ordering::SeqCst) {
+        cleanup_job(&state, book_id);
+        return Err(CommandError::Cancelled);
+    }
+
+    let _ = window.emit("analysis-progress", AnalysisProgress {
+        book_id,
+        stage: "Extracting text".to_string(),
+        progress: 10,
+        detail: Some(format!("Reading {}...", format.label())),
+        sample_words: None,
+    });
+
+    // Extract on a blocking thread, moving the bar from 10% to 20% as spine items are read
+    let extract_cancel = Arc::clone(&cancel_token);
+    let extract_window = window.clone();
+    let extraction = tokio::task::spawn_blocking(move || {
+        formats::extract_text_with_progress(&book_path, format, &extract_cancel, |done, total| {
+            let _ = extract_window.emit("analysis-progress", AnalysisProgress {
+                book_id,
+                stage: "Extracting text".to_string(),
+                progress: 10 + (done * 10 / total.max(1)) as u8,
+                detail: Some(format!("{}/{} sections", done, total)),
+                sample_words: None,
+            });
+        })
+    })
+    .await;
+    let extraction = match extraction {
+        Ok(extraction) => extraction,
+        Err(e) => {
+            cleanup_job(&state, book_id);
+            return Err(e.into());
+        }
+    };
+
+    // A few unreadable spine items only cost some words; most of the book missing is an error
+    let mut extracted = match extraction.and_then(|extracted| extracted.check_readable().map(|()| extracted)) {
+        Ok(extracted) => extracted,
+        Err(e) => {
+            cleanup_job(&state, book_id);
+            return Err(e.into());
+        }
+    };
+    if !extracted.skipped_items.is_empty() {
+        eprintln!(
+            "Analyzing book {} without {} of its {} spine items, which could not be read",
+            book_id,
+            extracted.skipped_items.len(),
+            extracted.spine_items
+        );
+    }
+
+    // Books converted from PDFs can repeat the title every page and keep page numbers
+    let removed_artifacts = if strip_page_artifacts {
+        page_artifacts::strip(&mut extracted, page_artifacts::DEFAULT_MIN_REPEATS)
+    } else {
+        0
+    };
+
+    // A translator's preface can outweigh the novel's own vocabulary, so front
+    // sections are left out of whole-book analyses unless asked for. A chapter
+    // range is analyzed as requested, front sections and all.
+    let whole_book = chapter_start.is_none() && chapter_end.is_none();
+    if whole_book && !include_front_sections {
+        extracted.exclude_front_sections();
+    }
+    let excluded_sections = std::mem::take(&mut extracted.excluded_sections);
+
+    // The book's own dc:language, or else its text, decides whether we can analyze it;
+    // library metadata is often blank or stale
+    let guess = nlp::detect::book_language(extracted.language.as_deref(), &extracted.full_text);
+    match &guess {
+        Some(guess) if !guess.is_confident() => eprintln!(
+            "Unsure of the language of book {}: guessed \"{}\" with confidence {:.2}",
+            book_id, guess.code, guess.confidence
+        ),
+        Some(guess) if nlp::Language::from_base_code(&guess.code).is_none() => {
+            cleanup_job(&state, book_id);
+            return Err(CommandError::UnsupportedLanguage(guess.code.clone()));
+        }
+        _ => {}
+    }
+    let language = guess
+        .filter(|guess| guess.is_confident())
+        .and_then(|guess| nlp::Language::from_base_code(&guess.code))
+        .or_else(|| language.as_deref().map(nlp::Language::from_code))
+        .unwrap_or_default();
+
+    let text_quality = Some(check_text_quality(&window, book_id, &mut extracted, language));
+    let extraction_warnings = extracted.warnings.len();
+    let removed_notes = extracted.removed_notes();
+
+    // Footnotes are left out unless asked for: their markers and bodies are mostly noise
+    // Optionally restrict analysis to a range of chapters (end is exclusive)
+    let chapter_titles: Vec<String> = extracted.chapters.iter().map(|c| c.title.clone()).collect();
+    let (text, segments, verse) = if !whole_book {
+        let start = chapter_start.unwrap_or(0);
+        let end = chapter_end.unwrap_or(extracted.chapters.len()).min(extracted.chapters.len());
+        if start >= end {
+            cleanup_job(&state, book_id);
+            return Err(CommandError::Invalid(format!(
+                "Invalid chapter range {}..{} (book has {} chapters)",
+                start,
+                end,
+                extracted.chapters.len()
+            )));
+        }
+        let (text, mut segments) = epub::join_chapters(&extracted.chapters[start..end], include_footnotes);
+        let verse = epub::verse_ranges(&extracted.chapters[start..end], &segments);
+        // Number chapters within the whole book, not the range
+        for segment in &mut segments {
+            segment.chapter_index += start;
+        }
+        (text, segments, verse)
+    } else if include_footnotes {
+        let (text, segments) = epub::join_chapters(&extracted.chapters, true);
+        let verse = epub::verse_ranges(&extracted.chapters, &segments);
+        (text, segments, verse)
+    } else {
+        (extracted.full_text, extracted.segments, extracted.verse)
+    };
+    let word_count = text.split_whitespace().count();
+
+    let (hard_words, stats) = run_analysis(book_id, text, segments, verse, chapter_titles, threshold, options, language, cancel_token, &window, &state).await?;
+
+    // Keep whole-book results for cross-book commands; failing to save doesn't fail the analysis
+    if whole_book {
+        let cached = analysis_cache::CachedAnalysis {
+            book_id,
+            word_count,
+            hard_words: hard_words.clone(),
+            readability: Some(stats.readability.clone()),
+            options: cache_options,
+        };
+        if let Err(e) = analysis_cache::save(&lib_path, &cached) {
+            eprintln!("Failed to cache analysis of book {}: {}", book_id, e);
+        }
+    }
+
     Ok(AnalysisResult {
         book_id,
         word_count,
         hard_words,
         stats,
+        extraction_warnings,
+        text_quality,
+        removed_notes,
+        removed_artifacts,
+        excluded_sections,
+    })
+}
+
+#[derive(serde::Serialize)]
+struct AggregateVocabulary {
+    words: Vec<vocabulary::AggregateWord>,
+    /// Requested books with no saved analysis
+    missing: Vec<i64>,
+}
+
+/// Hard words shared across the latest saved analyses of `book_ids` in a library
+/// (the active one by default), most worth learning first
+#[tauri::command]
+async fn aggregate_vocabulary(
+    book_ids: Vec<i64>,
+    library_id: Option<u32>,
+    state: tauri::State<'_, AppState>,
+) -> Result<AggregateVocabulary, CommandError> {
+    let lib_path = library_path(&state, library_id)?;
+    tokio::task::spawn_blocking(move || {
+        let mut analyses = Vec::new();
+        let mut missing = Vec::new();
+        for book_id in book_ids {
+            match analysis_cache::load(&lib_path, book_id) {
+                Some(analysis) => analyses.push(analysis),
+                None => missing.push(book_id),
+            }
+        }
+        AggregateVocabulary {
+            words: vocabulary::aggregate(&analyses),
+            missing,
+        }
+    })
+    .await
+    .map_err(CommandError::from)
+}
+
+/// Relative vocabulary difficulty of two books, analyzing whichever has no saved analysis
+#[tauri::command]
+async fn compare_books(
+    first_book_id: i64,
+    second_book_id: i64,
+    library_id: Option<u32>,
+    window: tauri::Window,
+    state: tauri::State<'_, AppState>,
+) -> Result<vocabulary::Comparison, CommandError> {
+    let first = saved_or_new_analysis(first_book_id, library_id, &window, &state).await?;
+    let second = saved_or_new_analysis(second_book_id, library_id, &window, &state).await?;
+    Ok(vocabulary::compare(&first, &second))
+}
+
+/// The saved whole-book analysis of `book_id` with default options, or a fresh one.
+/// Analyses saved before readability was cached are redone.
+async fn saved_or_new_analysis(
+    book_id: i64,
+    library_id: Option<u32>,
+    window: &tauri::Window,
+    state: &tauri::State<'_, AppState>,
+) -> Result<analysis_cache::CachedAnalysis, CommandError> {
+    let lib_path = library_path(state, library_id)?;
+    let options = AnalyzeRequest::default().cache_options();
+    if let Some(saved) =
+        analysis_cache::load_matching(&lib_path, book_id, &options).filter(|saved| saved.readability.is_some())
+    {
+        return Ok(saved);
+    }
+    let result = analyze_book(book_id, library_id, None, window.clone(), state.clone()).await?;
+    Ok(analysis_cache::CachedAnalysis {
+        book_id,
+        word_count: result.word_count,
+        hard_words: result.hard_words,
+        readability: Some(result.stats.readability),
+        options,
     })
 }
 
@@ -207,16 +993,304 @@ fn cleanup_job(state: &tauri::State<'_, AppState>, book_id: i64) {
     jobs.remove(&book_id);
 }
 
+#[derive(serde::Serialize, Clone)]
+struct SeriesProgress {
+    series: String,
+    /// 1-based position of the book being analyzed
+    index: usize,
+    total: usize,
+    book_id: i64,
+    title: String,
+}
+
+/// A series member that was not analyzed
+#[derive(serde::Serialize)]
+struct SeriesSkip {
+    book_id: i64,
+    title: String,
+    reason: String,
+}
+
+#[derive(serde::Serialize)]
+struct SeriesAnalysisResult {
+    series: String,
+    /// Analyses of the members that succeeded, in series order
+    results: Vec<AnalysisResult>,
+    skipped: Vec<SeriesSkip>,
+    /// The queue was cancelled before every member was analyzed
+    cancelled: bool,
+}
+
+/// Analyze a plain-text or Markdown file outside any library. The job is keyed by a synthetic
+/// (negative) id derived from the path, which progress events carry and
+/// `cancel_analysis` accepts. Of the request, the chapter range, footnote and page
+/// artifact options don't apply.
+#[tauri::command]
+async fn analyze_text_file(
+    path: String,
+    request: Option<AnalyzeRequest>,
+    window: tauri::Window,
+    state: tauri::State<'_, AppState>,
+) -> Result<AnalysisResult, CommandError> {
+    let path = std::path::PathBuf::from(path);
+    let book_id = calibre::folder::path_id(&path.canonicalize().unwrap_or_else(|_| path.clone()));
+    let request = request.unwrap_or_default();
+    let threshold = request.frequency_threshold;
+    let options = request.analysis_options();
+
+    let cancel_token = register_job(&state, book_id);
+
+    let _ = window.emit("analysis-progress", AnalysisProgress {
+        book_id,
+        stage: "Extracting text".to_string(),
+        progress: 10,
+        detail: Some("Reading text file...".to_string()),
+        sample_words: None,
+    });
+
+    let format = calibre::folder::file_format(&path)
+        .filter(|format| *format != formats::BookFormat::Epub)
+        .unwrap_or(formats::BookFormat::Txt);
+    let mut extracted = match formats::extract_text(&path, format) {
+        Ok(extracted) => extracted,
+        Err(e) => {
+            cleanup_job(&state, book_id);
+            return Err(e.into());
+        }
+    };
+    // A Gutenberg header may declare the language; otherwise guess it from the text
+    let declared = formats::read_text_metadata(&path).language;
+    let language = nlp::detect::book_language(declared.as_deref(), &extracted.full_text)
+        .filter(|guess| guess.is_confident())
+        .and_then(|guess| nlp::Language::from_base_code(&guess.code))
+        .unwrap_or_default();
+    if !request.include_front_sections {
+        extracted.exclude_front_sections();
+    }
+    let excluded_sections = std::mem::take(&mut extracted.excluded_sections);
+    let text_quality = Some(check_text_quality(&window, book_id, &mut extracted, language));
+    let extraction_warnings = extracted.warnings.len();
+    let removed_notes = extracted.removed_notes();
+    let chapter_titles = extracted.chapters.into_iter().map(|c| c.title).collect();
+    let text = extracted.full_text;
+    let segments = extracted.segments;
+    let word_count = text.split_whitespace().count();
+
+    let (hard_words, stats) = run_analysis(
+        book_id,
+        text,
+        segments,
+        extracted.verse,
+        chapter_titles,
+        threshold,
+        options,
+        language,
+        cancel_token,
+        &window,
+        &state,
+    )
+    .await?;
+
+    Ok(AnalysisResult {
+        book_id,
+        word_count,
+        hard_words,
+        stats,
+        extraction_warnings,
+        text_quality,
+        removed_notes,
+        removed_artifacts: 0,
+        excluded_sections,
+    })
+}
+
+/// Synthetic job id for `analyze_string`: negative like folder ids, and the same for the
+/// same text within a session. 48 bits keep it exact as a JavaScript number.
+fn text_job_id(text: &str) -> i64 {
+    use std::hash::{DefaultHasher, Hash, Hasher};
+    let mut hasher = DefaultHasher::new();
+    text.hash(&mut hasher);
+    -((hasher.finish() >> 16) as i64 + 1)
+}
+
+/// Analyze text passed in directly (pasted, or a selection in the reader) rather than read
+/// from a file. The job is keyed by `text_job_id`, which progress events carry and
+/// `cancel_analysis` accepts, and which comes back as the result's `book_id`.
+#[tauri::command]
+async fn analyze_string(
+    text: String,
+    threshold: Option<f32>,
+    skip_ner: Option<bool>,
+    window: tauri::Window,
+    state: tauri::State<'_, AppState>,
+) -> Result<AnalysisResult, CommandError> {
+    if text.trim().is_empty() {
+        return Err(CommandError::Invalid("No text to analyze".to_string()));
+    }
+    let book_id = text_job_id(&text);
+    let options = nlp::AnalysisOptions {
+        skip_ner: skip_ner.unwrap_or(false),
+        ..Default::default()
+    };
+    let cancel_token = register_job(&state, book_id);
+
+    let word_count = text.split_whitespace().count();
+    let language = nlp::detect_language(&text)
+        .filter(|guess| guess.is_confident())
+        .and_then(|guess| nlp::Language::from_base_code(&guess.code))
+        .unwrap_or_default();
+
+    let (hard_words, stats) = run_analysis(
+        book_id,
+        text,
+        Vec::new(),
+        Vec::new(),
+        Vec::new(),
+        threshold.unwrap_or(nlp::DEFAULT_FREQUENCY_THRESHOLD),
+        options,
+        language,
+        cancel_token,
+        &window,
+        &state,
+    )
+    .await?;
+
+    Ok(AnalysisResult {
+        book_id,
+        word_count,
+        hard_words,
+        stats,
+        extraction_warnings: 0,
+        text_quality: None,
+        removed_notes: 0,
+        removed_artifacts: 0,
+        excluded_sections: Vec::new(),
+    })
+}
+
+/// Analyze every book of a series in order, one at a time, through `analyze_book`.
+/// Emits `series-progress` before each book. Cancelling any member stops the queue;
+/// members that can't be read are reported in `skipped` instead of failing the batch.
+#[tauri::command]
+async fn analyze_series(
+    series_name: String,
+    library_id: Option<u32>,
+    frequency_threshold: Option<f32>,
+    skip_ner: Option<bool>,
+    include_footnotes: Option<bool>,
+    window: tauri::Window,
+    state: tauri::State<'_, AppState>,
+) -> Result<SeriesAnalysisResult, CommandError> {
+    // Pin the library now: every member is analyzed in it even if the user
+    // switches libraries while the queue runs
+    let library_id = Some(state.libraries.lock().unwrap().resolve(library_id)?);
+    if state.libraries.lock().unwrap().kind(library_id)? == libraries::LibraryKind::Folder {
+        return Err(CommandError::Invalid("Series are only available for Calibre libraries".to_string()));
+    }
+    let lib_path = library_path(&state, library_id)?;
+    let books = calibre::series_books(&lib_path, &series_name).map_err(CommandError::from)?;
+    if books.is_empty() {
+        return Err(CommandError::Invalid(format!("No books found in series \"{}\"", series_name)));
+    }
+
+    let queue_token = Arc::new(AtomicBool::new(false));
+    {
+        let mut series_jobs = state.series_jobs.lock().unwrap();
+        for book in &books {
+            series_jobs.insert(book.id, Arc::clone(&queue_token));
+        }
+    }
+
+    let total = books.len();
+    let mut results = Vec::new();
+    let mut skipped = Vec::new();
+
+    for (i, book) in books.iter().enumerate() {
+        if queue_token.load(Ordering::SeqCst) {
+            break;
+        }
+        if !book.has_readable_format {
+            skipped.push(SeriesSkip {
+                book_id: book.id,
+                title: book.title.clone(),
+                reason: "No readable format (EPUB, MOBI, AZW3, FB2 or TXT)".to_string(),
+            });
+            continue;
+        }
+
+        let _ = window.emit("series-progress", SeriesProgress {
+            series: series_name.clone(),
+            index: i + 1,
+            total,
+            book_id: book.id,
+            title: book.title.clone(),
+        });
+
+        let request = AnalyzeRequest {
+            frequency_threshold: frequency_threshold.unwrap_or(nlp::DEFAULT_FREQUENCY_THRESHOLD),
+            skip_ner: skip_ner.unwrap_or(false),
+            include_footnotes: include_footnotes.unwrap_or(false),
+            language: book.language.clone(),
+            ..AnalyzeRequest::default()
+        };
+        let result = analyze_book(book.id, library_id, Some(request), window.clone(), state.clone()).await;
+
+        match result {
+            Ok(result) => results.push(result),
+            // A cancelled member has already set the queue token (see cancel_analysis)
+            Err(_) if queue_token.load(Ordering::SeqCst) => break,
+            Err(reason) => skipped.push(SeriesSkip {
+                book_id: book.id,
+                title: book.title.clone(),
+                reason: reason.to_string(),
+            }),
+        }
+    }
+
+    {
+        let mut series_jobs = state.series_jobs.lock().unwrap();
+        for book in &books {
+            series_jobs.remove(&book.id);
+        }
+    }
+
+    Ok(SeriesAnalysisResult {
+        series: series_name,
+        results,
+        skipped,
+        cancelled: queue_token.load(Ordering::SeqCst),
+    })
+}
+
 #[tauri::command]
 fn cancel_analysis(book_id: i64, state: tauri::State<'_, AppState>) -> bool {
+    // Cancelling any member of a queued series stops the whole queue,
+    // including whichever member is running right now
+    let mut to_cancel = vec![book_id];
+    let mut cancelled = false;
+    {
+        let series_jobs = state.series_jobs.lock().unwrap();
+        if let Some(queue) = series_jobs.get(&book_id) {
+            queue.store(true, Ordering::SeqCst);
+            cancelled = true;
+            to_cancel.extend(
+                series_jobs
+                    .iter()
+                    .filter(|(_, token)| Arc::ptr_eq(token, queue))
+                    .map(|(id, _)| *id),
+            );
+        }
+    }
+
     let jobs = state.active_jobs.lock().unwrap();
-    if let Some(token) = jobs.get(&book_id) {
-        token.store(true, Ordering::SeqCst);
-        eprintln!("Cancelling analysis for book {}", book_id);
-        true
-    } else {
-        false
+    for id in to_cancel {
+        if let Some(token) = jobs.get(&id) {
+            token.store(true, Ordering::SeqCst);
+            eprintln!("Cancelling analysis for book {}", id);
+            cancelled = true;
+        }
     }
+    cancelled
 }
 
 #[tauri::command]
@@ -225,9 +1299,145 @@ fn get_active_jobs(state: tauri::State<'_, AppState>) -> Vec<i64> {
     jobs.keys().cloned().collect()
 }
 
+/// Write an export file chosen in the save dialog
+fn write_export(path: &str, content: &str) -> Result<(), CommandError> {
+    std::fs::write(path, content).map_err(|e| CommandError::Io(format!("Could not write {}: {}", path, e)))
+}
+
+#[tauri::command]
+fn export_json(path: String, content: String) -> Result<(), CommandError> {
+    write_export(&path, &content)
+}
+
+/// Export the listing of a library (the active one by default) as CSV or JSON.
+/// `analyzed_ids` are the books analyzed this session, marked in the `analyzed` column.
+#[tauri::command]
+fn export_library(
+    path: String,
+    format: export::ExportFormat,
+    analyzed_ids: Option<Vec<i64>>,
+    library_id: Option<u32>,
+    state: tauri::State<'_, AppState>,
+) -> Result<usize, CommandError> {
+    let kind = state.libraries.lock().unwrap().kind(library_id)?;
+    let lib_path = library_path(&state, library_id)?;
+    let books = match kind {
+        libraries::LibraryKind::Folder => {
+            calibre::folder::scan_folder(std::path::Path::new(&lib_path), calibre::folder::MAX_DEPTH)
+                .map_err(CommandError::from)?
+                .0
+        }
+        libraries::LibraryKind::Calibre => {
+            calibre::scan_library(&lib_path, &calibre::BookFilter::default(), calibre::SortKey::default(), false)
+                .map_err(CommandError::from)?
+        }
+    };
+
+    let analyzed: HashSet<i64> = analyzed_ids.unwrap_or_default().into_iter().collect();
+    let rows: Vec<export::LibraryRow> = books.iter().map(|book| export::LibraryRow::new(book, &analyzed)).collect();
+    write_export(&path, &export::render_library(&rows, format).map_err(CommandError::Internal)?)?;
+    Ok(rows.len())
+}
+
+/// Short definition of a word from the offline dictionary (None if it has no
+/// entry or the dictionary hasn't been downloaded)
+#[tauri::command]
+fn lookup_definition(word: String) -> Option<String> {
+    nlp::dictionary::lookup(&word)
+}
+
+#[tauri::command]
+fn get_known_words(state: tauri::State<'_, AppState>) -> Vec<String> {
+    let mut words: Vec<String> = state.known_words.lock().unwrap().iter().cloned().collect();
+    words.sort();
+    words
+}
+
+/// Replace the known-words list and persist it
+#[tauri::command]
+fn set_known_words(words: Vec<String>, state: tauri::State<'_, AppState>) -> Result<(), CommandError> {
+    let words: HashSet<String> = words
+        .into_iter()
+        .map(|w| w.trim().to_lowercase())
+        .filter(|w| !w.is_empty())
+        .collect();
+    resources::save_known_words(&words).map_err(CommandError::Io)?;
+    *state.known_words.lock().unwrap() = words;
+    Ok(())
+}
+
+/// Add words to the user's stopwords and persist them, returning the full custom list
+#[tauri::command]
+fn add_stopwords(words: Vec<String>, state: tauri::State<'_, AppState>) -> Result<Vec<String>, CommandError> {
+    let mut stopwords = state.custom_stopwords.lock().unwrap();
+    let mut merged = stopwords.clone();
+    merged.extend(words.into_iter().map(|w| w.trim().to_lowercase()).filter(|w| !w.is_empty()));
+    resources::save_custom_stopwords(&merged).map_err(CommandError::Io)?;
+    *stopwords = merged;
+    let mut sorted: Vec<String> = stopwords.iter().cloned().collect();
+    sorted.sort();
+    Ok(sorted)
+}
+
+/// Add words GLiNER wrongly filtered as names to the entity allowlist, so future
+/// analyses keep them, returning the full list
+#[tauri::command]
+fn add_entity_allowlist(words: Vec<String>, state: tauri::State<'_, AppState>) -> Result<Vec<String>, CommandError> {
+    update_entity_allowlist(&state, |allowlist| {
+        allowlist.extend(words.into_iter().map(|w| w.trim().to_lowercase()).filter(|w| !w.is_empty()));
+    })
+}
+
+/// Remove words from the entity allowlist, returning the full list
+#[tauri::command]
+fn remove_entity_allowlist(words: Vec<String>, state: tauri::State<'_, AppState>) -> Result<Vec<String>, CommandError> {
+    update_entity_allowlist(&state, |allowlist| {
+        for word in words {
+            allowlist.remove(&word.trim().to_lowercase());
+        }
+    })
+}
+
+/// Change the entity allowlist and persist it, returning it sorted
+fn update_entity_allowlist(
+    state: &AppState,
+    change: impl FnOnce(&mut HashSet<String>),
+) -> Result<Vec<String>, CommandError> {
+    let mut allowlist = state.entity_allowlist.lock().unwrap();
+    let mut updated = allowlist.clone();
+    change(&mut updated);
+    resources::save_entity_allowlist(&updated).map_err(CommandError::Io)?;
+    *allowlist = updated;
+    let mut sorted: Vec<String> = allowlist.iter().cloned().collect();
+    sorted.sort();
+    Ok(sorted)
+}
+
+/// Sentence lengths and number of example contexts analyses keep per word
+#[tauri::command]
+fn get_context_policy(state: tauri::State<'_, AppState>) -> nlp::ContextPolicy {
+    *state.context_policy.lock().unwrap()
+}
+
+/// Change the context policy for later analyses (this session), returning it as it will be used
+#[tauri::command]
+fn set_context_policy(policy: nlp::ContextPolicy, state: tauri::State<'_, AppState>) -> nlp::ContextPolicy {
+    let policy = policy.normalized();
+    *state.context_policy.lock().unwrap() = policy;
+    policy
+}
+
+/// Hosts resources are downloaded from (see `resources::MirrorConfig`)
+#[tauri::command]
+fn get_resource_mirror() -> resources::MirrorConfig {
+    resources::load_mirror_config()
+}
+
+/// Point downloads at other hosts, e.g. a HuggingFace mirror where huggingface.co is
+/// slow or blocked. Returns the configuration as it will be used.
 #[tauri::command]
-fn export_json(path: String, content: String) -> Result<(), String> {
-    std::fs::write(&path, content).map_err(|e| e.to_string())
+fn set_resource_mirror(config: resources::MirrorConfig) -> Result<resources::MirrorConfig, CommandError> {
+    resources::save_mirror_config(config).map_err(CommandError::Io)
 }
 
 #[tauri::command]
@@ -235,124 +1445,191 @@ fn get_resource_status() -> resources::ResourceStatus {
     resources::get_resource_status()
 }
 
+/// Space taken by models, dictionaries and caches, to show next to `clear_resources`
+#[tauri::command]
+fn get_disk_usage() -> resources::DiskUsage {
+    resources::disk_usage()
+}
+
+/// Delete downloaded models/dictionaries or cached data; returns the bytes freed
+#[tauri::command]
+fn clear_resources(resource: resources::ResourceKind, state: tauri::State<'_, AppState>) -> Result<u64, CommandError> {
+    // Don't pull a model out from under a running analysis
+    let jobs = state.active_jobs.lock().unwrap();
+    if !jobs.is_empty() {
+        return Err(CommandError::Invalid("Cannot clear resources while an analysis is running".to_string()));
+    }
+    resources::clear_resource(resource).map_err(CommandError::Io)
+}
+
 #[derive(serde::Serialize, Clone)]
 struct ResourceDownloadProgress {
     resource: String,
     file: String,
     downloaded: u64,
     total: u64,
+    /// 0-100, or 0 when the total size is unknown
+    percent: u8,
+    /// Byte offset a resumed download started from (0 when not resumed)
+    resumed_from: u64,
+    /// Current (or, while retrying, the failed) try, from 1; 0 when not downloading
+    attempt: u32,
+    /// Tries before the download fails
+    max_attempts: u32,
     status: String,
 }
 
-#[tauri::command]
-async fn download_resources(window: tauri::Window) -> Result<(), String> {
-    // Download GLiNER model in a blocking thread (it's a large download)
-    let window_clone = window.clone();
-
-    tokio::task::spawn_blocking(move || {
-        // Download GLiNER model
-        let result = resources::ensure_gliner_model(|status| {
-            let progress = match status {
-                resources::DownloadStatus::AlreadyExists => ResourceDownloadProgress {
-                    resource: "gliner".to_string(),
-                    file: "".to_string(),
-                    downloaded: 0,
-                    total: 0,
-                    status: "exists".to_string(),
-                },
-                resources::DownloadStatus::Downloading { file, progress, total } => ResourceDownloadProgress {
-                    resource: "gliner".to_string(),
-                    file,
-                    downloaded: progress,
-                    total,
-                    status: "downloading".to_string(),
-                },
-                resources::DownloadStatus::Completed => ResourceDownloadProgress {
-                    resource: "gliner".to_string(),
-                    file: "".to_string(),
-                    downloaded: 0,
-                    total: 0,
-                    status: "completed".to_string(),
-                },
-                resources::DownloadStatus::Failed(err) => ResourceDownloadProgress {
-                    resource: "gliner".to_string(),
-                    file: "".to_string(),
-                    downloaded: 0,
-                    total: 0,
-                    status: format!("failed: {}", err),
-                },
-            };
-            let _ = window_clone.emit("resource-download-progress", progress);
-        });
+impl ResourceDownloadProgress {
+    fn from_status(resource: &str, status: resources::DownloadStatus) -> Self {
+        let mut attempt = 0;
+        let (file, downloaded, total, resumed_from, status) = match status {
+            resources::DownloadStatus::AlreadyExists => (String::new(), 0, 0, 0, "exists".to_string()),
+            resources::DownloadStatus::Downloading { file, progress, total, resumed_from, attempt: current } => {
+                attempt = current;
+                (file, progress, total, resumed_from, "downloading".to_string())
+            }
+            resources::DownloadStatus::Retrying { file, attempt: failed, error } => {
+                attempt = failed;
+                (file, 0, 0, 0, format!("retrying: {}", error))
+            }
+            resources::DownloadStatus::Completed => (String::new(), 0, 0, 0, "completed".to_string()),
+            resources::DownloadStatus::Cancelled => (String::new(), 0, 0, 0, "cancelled".to_string()),
+            resources::DownloadStatus::Failed(err) => (String::new(), 0, 0, 0, format!("failed: {}", err)),
+        };
+        let percent = (downloaded * 100).checked_div(total).map_or(0, |p| p.min(100) as u8);
+        Self {
+            resource: resource.to_string(),
+            file,
+            downloaded,
+            total,
+            percent,
+            resumed_from,
+            attempt,
+            max_attempts: resources::DOWNLOAD_ATTEMPTS,
+            status,
+        }
+    }
+}
 
-        if let Err(e) = result {
-            eprintln!("Failed to download GLiNER model: {}", e);
-            return Err(e);
+/// Download any missing resources, emitting `resource-download-progress` events.
+/// Resources that already exist just report `exists`. `gliner_variant` picks the
+/// full (default) or the smaller quantized NER model.
+#[tauri::command]
+async fn download_resources(
+    gliner_variant: Option<resources::GlinerVariant>,
+    window: tauri::Window,
+    state: tauri::State<'_, AppState>,
+) -> Result<(), CommandError> {
+    let cancel_token = Arc::new(AtomicBool::new(false));
+    {
+        let mut download = state.download_job.lock().unwrap();
+        if download.is_some() {
+            return Err(CommandError::Invalid("A resource download is already running".to_string()));
         }
+        *download = Some(Arc::clone(&cancel_token));
+    }
 
-        // Download SymSpell dictionary (usually already exists)
-        let window_clone2 = window_clone.clone();
-        let result = resources::ensure_symspell_dict(|status| {
-            let progress = match status {
-                resources::DownloadStatus::AlreadyExists => ResourceDownloadProgress {
-                    resource: "symspell".to_string(),
-                    file: "".to_string(),
-                    downloaded: 0,
-                    total: 0,
-                    status: "exists".to_string(),
-                },
-                resources::DownloadStatus::Downloading { file, progress, total } => ResourceDownloadProgress {
-                    resource: "symspell".to_string(),
-                    file,
-                    downloaded: progress,
-                    total,
-                    status: "downloading".to_string(),
-                },
-                resources::DownloadStatus::Completed => ResourceDownloadProgress {
-                    resource: "symspell".to_string(),
-                    file: "".to_string(),
-                    downloaded: 0,
-                    total: 0,
-                    status: "completed".to_string(),
-                },
-                resources::DownloadStatus::Failed(err) => ResourceDownloadProgress {
-                    resource: "symspell".to_string(),
-                    file: "".to_string(),
-                    downloaded: 0,
-                    total: 0,
-                    status: format!("failed: {}", err),
-                },
-            };
-            let _ = window_clone2.emit("resource-download-progress", progress);
-        });
+    // Download in a blocking thread (the GLiNER model is a large download)
+    let result = tokio::task::spawn_blocking(move || {
+        resources::ensure_gliner_model(gliner_variant.unwrap_or_default(), &cancel_token, |status| {
+            let _ = window.emit("resource-download-progress", ResourceDownloadProgress::from_status("gliner", status));
+        })
+        .inspect_err(|e| eprintln!("Failed to download GLiNER model: {}", e))?;
+
+        // SymSpell dictionary (usually already exists)
+        resources::ensure_symspell_dict(resources::SYMSPELL_EN, &cancel_token, |status| {
+            let _ = window.emit("resource-download-progress", ResourceDownloadProgress::from_status("symspell", status));
+        })
+        .inspect_err(|e| eprintln!("Failed to download SymSpell dictionary: {}", e))?;
 
-        if let Err(e) = result {
-            eprintln!("Failed to download SymSpell dictionary: {}", e);
-            return Err(e);
+        // Offline dictionary for word definitions. It is optional: a failure is reported
+        // through its own progress events and words just have no definitions
+        match resources::ensure_dictionary(&cancel_token, |status| {
+            let _ = window.emit("resource-download-progress", ResourceDownloadProgress::from_status("dictionary", status));
+        }) {
+            Err(e) if e == resources::DOWNLOAD_CANCELLED => return Err(e),
+            Err(e) => eprintln!("Failed to download offline dictionary, continuing without definitions: {}", e),
+            Ok(_) => {}
         }
 
         Ok(())
     })
-    .await
-    .map_err(|e| format!("Task error: {}", e))?
+    .await;
+
+    *state.download_job.lock().unwrap() = None;
+    result?.map_err(CommandError::Download)
+}
+
+/// Stop a running resource download (partial files are resumed next time)
+#[tauri::command]
+fn cancel_download(state: tauri::State<'_, AppState>) -> bool {
+    match state.download_job.lock().unwrap().as_ref() {
+        Some(token) => {
+            token.store(true, Ordering::SeqCst);
+            true
+        }
+        None => false,
+    }
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    // Downloads interrupted long ago won't be resumed; don't let them hold disk space
+    let (removed, bytes) = resources::cleanup_partial_downloads(resources::PARTIAL_DOWNLOAD_MAX_AGE);
+    if removed > 0 {
+        eprintln!("Removed {} stale partial downloads ({} bytes reclaimed)", removed, bytes);
+    }
+
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_dialog::init())
         .manage(AppState::default())
         .invoke_handler(tauri::generate_handler![
             scan_library,
+            scan_library_page,
+            scan_folder,
+            list_libraries,
+            add_library,
+            switch_library,
+            search_books,
+            find_duplicates,
+            list_authors,
+            get_books_by_author,
+            watch_library,
+            unwatch_library,
+            get_custom_columns,
+            get_book_details,
             get_epub_path,
+            get_cover,
             get_book_text,
+            get_book_outline,
+            get_book_chapter,
+            get_toc,
             analyze_book,
+            analyze_series,
+            analyze_text_file,
+            analyze_string,
+            aggregate_vocabulary,
+            compare_books,
             export_json,
+            export_library,
+            lookup_definition,
             cancel_analysis,
             get_active_jobs,
+            get_known_words,
+            set_known_words,
+            add_stopwords,
+            add_entity_allowlist,
+            remove_entity_allowlist,
+            get_context_policy,
+            set_context_policy,
             get_resource_status,
-            download_resources
+            get_resource_mirror,
+            set_resource_mirror,
+            get_disk_usage,
+            clear_resources,
+            download_resources,
+            cancel_download
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
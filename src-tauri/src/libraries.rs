@@ -0,0 +1,213 @@
+//! Registry of the libraries opened in this session
+//!
+//! Each library path (directory or content server URL) gets a stable id, and one
+//! library is active at a time. Commands default to the active library.
+
+use crate::error::CommandError;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum LibraryKind {
+    /// Calibre library (local metadata.db or content server)
+    #[default]
+    Calibre,
+    /// Plain folder of EPUBs (see `calibre::folder`)
+    Folder,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct LibraryInfo {
+    pub id: u32,
+    pub path: String,
+    pub kind: LibraryKind,
+    pub active: bool,
+}
+
+#[derive(Debug)]
+struct Library {
+    path: String,
+    kind: LibraryKind,
+    /// Folder libraries have no database, so book files are remembered from the scan
+    files: HashMap<i64, PathBuf>,
+}
+
+#[derive(Debug, Default)]
+pub struct LibraryRegistry {
+    libraries: BTreeMap<u32, Library>,
+    active: Option<u32>,
+    next_id: u32,
+}
+
+impl LibraryRegistry {
+    /// Register a library path, returning its id (existing id if already registered)
+    pub fn add(&mut self, path: &str) -> u32 {
+        if let Some(id) = self.id_of(path) {
+            return id;
+        }
+        self.insert(path, LibraryKind::Calibre, HashMap::new())
+    }
+
+    /// Register (or rescan) a folder library with the files found in it
+    pub fn add_folder(&mut self, path: &str, files: HashMap<i64, PathBuf>) -> u32 {
+        if let Some(id) = self.id_of(path) {
+            let library = self.libraries.get_mut(&id).expect("id_of returned a registered id");
+            library.kind = LibraryKind::Folder;
+            library.files = files;
+            return id;
+        }
+        self.insert(path, LibraryKind::Folder, files)
+    }
+
+    fn insert(&mut self, path: &str, kind: LibraryKind, files: HashMap<i64, PathBuf>) -> u32 {
+        self.next_id += 1;
+        self.libraries.insert(
+            self.next_id,
+            Library {
+                path: path.to_string(),
+                kind,
+                files,
+            },
+        );
+        self.next_id
+    }
+
+    /// Make a library the active one
+    pub fn switch(&mut self, id: u32) -> Result<(), CommandError> {
+        if !self.libraries.contains_key(&id) {
+            return Err(CommandError::UnknownLibrary(id));
+        }
+        self.active = Some(id);
+        Ok(())
+    }
+
+    /// Id of the given library, or of the active one when `id` is None, so
+    /// long-running work keeps using it after the user switches libraries
+    pub fn resolve(&self, id: Option<u32>) -> Result<u32, CommandError> {
+        match id {
+            Some(id) => self.get(Some(id)).map(|_| id),
+            None => self.active.filter(|id| self.libraries.contains_key(id)).ok_or(CommandError::NoLibrary),
+        }
+    }
+
+    /// Path of the given library, or of the active one when `id` is None
+    pub fn path(&self, id: Option<u32>) -> Result<String, CommandError> {
+        self.get(id).map(|library| library.path.clone())
+    }
+
+    /// Kind of the given library, or of the active one when `id` is None
+    pub fn kind(&self, id: Option<u32>) -> Result<LibraryKind, CommandError> {
+        self.get(id).map(|library| library.kind)
+    }
+
+    /// File of a book in a folder library, or None for Calibre libraries
+    /// (which resolve books through their database instead)
+    pub fn folder_file(&self, id: Option<u32>, book_id: i64) -> Result<Option<PathBuf>, CommandError> {
+        let library = self.get(id)?;
+        match library.kind {
+            LibraryKind::Calibre => Ok(None),
+            LibraryKind::Folder => library
+                .files
+                .get(&book_id)
+                .cloned()
+                .map(Some)
+                .ok_or(CommandError::BookNotFound(book_id)),
+        }
+    }
+
+    pub fn list(&self) -> Vec<LibraryInfo> {
+        self.libraries
+            .iter()
+            .map(|(id, library)| LibraryInfo {
+                id: *id,
+                path: library.path.clone(),
+                kind: library.kind,
+                active: self.active == Some(*id),
+            })
+            .collect()
+    }
+
+    fn get(&self, id: Option<u32>) -> Result<&Library, CommandError> {
+        match id {
+            Some(id) => self.libraries.get(&id).ok_or(CommandError::UnknownLibrary(id)),
+            None => self
+                .active
+                .and_then(|id| self.libraries.get(&id))
+                .ok_or(CommandError::NoLibrary),
+        }
+    }
+
+    fn id_of(&self, path: &str) -> Option<u32> {
+        self.libraries.iter().find(|(_, l)| l.path == path).map(|(id, _)| *id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add_is_idempotent() {
+        let mut registry = LibraryRegistry::default();
+        let main = registry.add("/books/main");
+        let samples = registry.add("/books/samples");
+        assert_ne!(main, samples);
+        assert_eq!(registry.add("/books/main"), main);
+        assert_eq!(registry.list().len(), 2);
+    }
+
+    #[test]
+    fn test_active_library() {
+        let mut registry = LibraryRegistry::default();
+        assert!(matches!(registry.path(None), Err(CommandError::NoLibrary)));
+
+        let main = registry.add("/books/main");
+        let samples = registry.add("/books/samples");
+        // Adding does not activate
+        assert!(registry.path(None).is_err());
+
+        registry.switch(samples).unwrap();
+        assert_eq!(registry.path(None).unwrap(), "/books/samples");
+        assert_eq!(registry.path(Some(main)).unwrap(), "/books/main");
+        assert!(registry.list().iter().any(|l| l.id == samples && l.active));
+
+        assert!(matches!(registry.switch(99), Err(CommandError::UnknownLibrary(99))));
+        assert!(registry.path(Some(99)).is_err());
+    }
+
+    #[test]
+    fn test_resolve_pins_the_active_library() {
+        let mut registry = LibraryRegistry::default();
+        assert!(matches!(registry.resolve(None), Err(CommandError::NoLibrary)));
+
+        let main = registry.add("/books/main");
+        let samples = registry.add("/books/samples");
+        registry.switch(main).unwrap();
+        let pinned = registry.resolve(None).unwrap();
+        assert_eq!(pinned, main);
+
+        // Switching afterwards doesn't move work that resolved its library already
+        registry.switch(samples).unwrap();
+        assert_eq!(registry.path(Some(pinned)).unwrap(), "/books/main");
+        assert_eq!(registry.resolve(Some(main)).unwrap(), main);
+        assert!(matches!(registry.resolve(Some(99)), Err(CommandError::UnknownLibrary(99))));
+    }
+
+    #[test]
+    fn test_folder_library_files() {
+        let mut registry = LibraryRegistry::default();
+        let calibre = registry.add("/books/main");
+        let folder = registry.add_folder("/books/epubs", HashMap::from([(-7, PathBuf::from("/books/epubs/a.epub"))]));
+
+        assert_eq!(registry.folder_file(Some(calibre), 1).unwrap(), None);
+        assert_eq!(registry.folder_file(Some(folder), -7).unwrap(), Some(PathBuf::from("/books/epubs/a.epub")));
+        assert!(matches!(registry.folder_file(Some(folder), -8), Err(CommandError::BookNotFound(-8))));
+
+        // Rescanning replaces the remembered files but keeps the id
+        assert_eq!(registry.add_folder("/books/epubs", HashMap::new()), folder);
+        assert!(registry.folder_file(Some(folder), -7).is_err());
+        assert!(registry.list().iter().any(|l| l.id == folder && l.kind == LibraryKind::Folder));
+    }
+}
@@ -0,0 +1,255 @@
+//! Minimal MOBI / AZW3 reader
+//!
+//! Reads the PalmDB container, decompresses PalmDOC text records and splits the
+//! resulting HTML on page breaks. HUFF/CDIC compression and DRM are not supported.
+
+use crate::epub::{self, Chapter, CleanOptions, EpubError, ExtractedText};
+use std::path::Path;
+
+const COMPRESSION_NONE: u16 = 1;
+const COMPRESSION_PALMDOC: u16 = 2;
+const COMPRESSION_HUFFCDIC: u16 = 17480;
+
+/// Offset of the record list in the PalmDB header
+const PDB_HEADER_LEN: usize = 78;
+/// Uncompressed size of a full text record
+const TEXT_RECORD_SIZE: usize = 4096;
+
+pub fn extract_text(path: &Path, options: CleanOptions) -> Result<ExtractedText, EpubError> {
+    let data = std::fs::read(path).map_err(|e| EpubError::Open(e.to_string()))?;
+    let html = read_html(&data)?;
+
+    let cleaner = epub::text_cleaner_with(options);
+    let mut chapters = Vec::new();
+
+    // Mobipocket marks chapter boundaries with <mbp:pagebreak/>
+    for (index, part) in html.split("<mbp:pagebreak").enumerate() {
+        let (text, verse) = epub::clean_html_tagged(&cleaner, part);
+        if !text.is_empty() {
+            chapters.push(Chapter {
+                spine_index: index,
+                path: String::new(),
+                title: format!("Chapter {}", chapters.len() + 1),
+                text,
+                footnotes: Vec::new(),
+                verse,
+            });
+        }
+    }
+
+    Ok(ExtractedText::from_chapters(chapters))
+}
+
+/// Decode the text records of a MOBI/AZW3 file into its HTML markup
+fn read_html(data: &[u8]) -> Result<String, EpubError> {
+    let invalid = || EpubError::Open("Not a valid MOBI file".to_string());
+
+    if data.len() < PDB_HEADER_LEN || &data[60..68] != b"BOOKMOBI" && &data[60..68] != b"TEXtREAd" {
+        return Err(invalid());
+    }
+
+    let num_records = read_u16(data, 76).ok_or_else(invalid)? as usize;
+    let offsets: Vec<usize> = (0..num_records)
+        .map(|i| read_u32(data, PDB_HEADER_LEN + i * 8).map(|o| o as usize))
+        .collect::<Option<_>>()
+        .ok_or_else(invalid)?;
+
+    let record = |i: usize| -> Option<&[u8]> {
+        let start = *offsets.get(i)?;
+        let end = offsets.get(i + 1).copied().unwrap_or(data.len());
+        data.get(start..end)
+    };
+
+    let header = record(0).ok_or_else(invalid)?;
+    let compression = read_u16(header, 0).ok_or_else(invalid)?;
+    let text_length = read_u32(header, 4).ok_or_else(invalid)? as usize;
+    let text_records = read_u16(header, 8).ok_or_else(invalid)? as usize;
+    let encryption = read_u16(header, 12).ok_or_else(invalid)?;
+
+    if encryption != 0 {
+        return Err(EpubError::DrmProtected);
+    }
+
+    // Optional MOBI header following the PalmDOC header
+    let mut encoding = 1252;
+    let mut extra_flags = 0u16;
+    if header.get(16..20) == Some(b"MOBI") {
+        let mobi_header_len = read_u32(header, 20).unwrap_or(0) as usize;
+        encoding = read_u32(header, 28).unwrap_or(1252);
+        if mobi_header_len >= 0xE4 {
+            extra_flags = read_u16(header, 0xF2).unwrap_or(0);
+        }
+    }
+
+    // Both lengths come from the file, so reserve no more than the records present can hold
+    let mut text = Vec::with_capacity(text_length.min(text_records.min(num_records) * TEXT_RECORD_SIZE));
+    for i in 1..=text_records {
+        let Some(raw) = record(i) else { break };
+        let raw = &raw[..raw.len() - trailing_entries_size(raw, extra_flags).min(raw.len())];
+        match compression {
+            COMPRESSION_NONE => text.extend_from_slice(raw),
+            COMPRESSION_PALMDOC => palmdoc_decompress(raw, &mut text),
+            COMPRESSION_HUFFCDIC => {
+                return Err(EpubError::Unsupported("HUFF/CDIC compressed MOBI files are not supported".to_string()))
+            }
+            other => return Err(EpubError::Unsupported(format!("Unknown MOBI compression type {}", other))),
+        }
+    }
+    text.truncate(text_length);
+
+    Ok(if encoding == 65001 {
+        String::from_utf8_lossy(&text).into_owned()
+    } else {
+        decode_cp1252(&text)
+    })
+}
+
+/// PalmDOC LZ77 decompression, appending to `out`
+fn palmdoc_decompress(input: &[u8], out: &mut Vec<u8>) {
+    let mut i = 0;
+    while i < input.len() {
+        let c = input[i];
+        i += 1;
+        match c {
+            // Literal run of the next c bytes
+            0x01..=0x08 => {
+                let end = (i + c as usize).min(input.len());
+                out.extend_from_slice(&input[i..end]);
+                i = end;
+            }
+            // Back-reference: 11 bits distance, 3 bits length
+            0x80..=0xBF => {
+                let Some(&next) = input.get(i) else { break };
+                i += 1;
+                let pair = (((c as usize) << 8) | next as usize) & 0x3FFF;
+                let distance = pair >> 3;
+                let length = (pair & 0x07) + 3;
+                if distance == 0 || distance > out.len() {
+                    continue;
+                }
+                let start = out.len() - distance;
+                for k in 0..length {
+                    out.push(out[start + k]);
+                }
+            }
+            // Space followed by an ASCII character
+            0xC0..=0xFF => {
+                out.push(b' ');
+                out.push(c ^ 0x80);
+            }
+            _ => out.push(c),
+        }
+    }
+}
+
+/// Size of the trailing entries appended to each text record (see `extra_flags`)
+fn trailing_entries_size(record: &[u8], extra_flags: u16) -> usize {
+    let mut size = 0;
+    let mut flags = extra_flags >> 1;
+    while flags != 0 {
+        if flags & 1 != 0 {
+            size += trailing_entry_size(&record[..record.len().saturating_sub(size)]);
+        }
+        flags >>= 1;
+    }
+    // Multibyte character overlap
+    if extra_flags & 1 != 0 {
+        if let Some(&b) = record.len().checked_sub(size + 1).and_then(|i| record.get(i)) {
+            size += (b & 0x03) as usize + 1;
+        }
+    }
+    size
+}
+
+/// Backward-encoded variable width integer at the end of `data`
+fn trailing_entry_size(data: &[u8]) -> usize {
+    let mut result = 0usize;
+    let mut shift = 0;
+    for &b in data.iter().rev() {
+        result |= ((b & 0x7F) as usize) << shift;
+        shift += 7;
+        if b & 0x80 != 0 || shift >= 28 {
+            break;
+        }
+    }
+    result
+}
+
+fn decode_cp1252(bytes: &[u8]) -> String {
+    // Windows-1252 differs from Latin-1 only in the 0x80..0x9F range
+    const HIGH: [char; 32] = [
+        '€', '\u{81}', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\u{8D}', 'Ž', '\u{8F}',
+        '\u{90}', '‘', '’', '“', '”', '•', '–', '—', '˜', '™', 'š', '›', 'œ', '\u{9D}', 'ž', 'Ÿ',
+    ];
+    bytes
+        .iter()
+        .map(|&b| match b {
+            0x80..=0x9F => HIGH[(b - 0x80) as usize],
+            _ => b as char,
+        })
+        .collect()
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    data.get(offset..offset + 2).map(|b| u16::from_be_bytes([b[0], b[1]]))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    data.get(offset..offset + 4).map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A DRM-free KF8 file: one uncompressed UTF-8 text record holding two chapters
+    fn fixture() -> std::path::PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/minimal.azw3")
+    }
+
+    #[test]
+    fn test_extract_azw3_fixture() {
+        let extracted = extract_text(&fixture(), CleanOptions::default()).unwrap();
+        assert_eq!(extracted.chapter_count, 2);
+        // Block tags become paragraphs; inline tags don't split words
+        assert_eq!(extracted.chapters[0].text, "Chapter One\n\nThe Analytical Engine weaves algebraical\n\npatterns.");
+        assert!(extracted.chapters[1].text.contains("to originate anything.\n\nCafé naïve."));
+    }
+
+    #[test]
+    fn test_encrypted_file_is_drm_protected() {
+        let mut data = std::fs::read(fixture()).unwrap();
+        // Encryption type lives at offset 12 of record 0
+        let record0 = read_u32(&data, PDB_HEADER_LEN).unwrap() as usize;
+        data[record0 + 13] = 2;
+        assert!(matches!(read_html(&data), Err(EpubError::DrmProtected)));
+    }
+
+    #[test]
+    fn test_oversized_text_length() {
+        let mut data = std::fs::read(fixture()).unwrap();
+        let expected = read_html(&data).unwrap();
+        // Text length lives at offset 4 of record 0; a bogus 4 GiB must not be reserved up front
+        let record0 = read_u32(&data, PDB_HEADER_LEN).unwrap() as usize;
+        data[record0 + 4..record0 + 8].copy_from_slice(&u32::MAX.to_be_bytes());
+        assert_eq!(read_html(&data).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_palmdoc_decompress() {
+        // "abc" literal, back-reference (distance 3, length 3), then " d" as a space pair
+        let input = [b'a', b'b', b'c', 0x80, 0x18, b'd' | 0x80];
+        let mut out = Vec::new();
+        palmdoc_decompress(&input, &mut out);
+        assert_eq!(out, b"abcabc d");
+    }
+
+    #[test]
+    fn test_trailing_entries_size() {
+        // A trailing entry whose backward varint says 3 bytes
+        let record = [b'x', b'y', 0x00, b'z', 0x83];
+        assert_eq!(trailing_entries_size(&record, 0b10), 3);
+        // Multibyte overlap: low two bits of the last byte + the byte itself
+        assert_eq!(trailing_entries_size(&[b'x', b'y', 0x01], 0b1), 2);
+    }
+}
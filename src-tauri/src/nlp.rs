@@ -1,3 +1,12 @@
+pub mod detect;
+pub mod dictionary;
+pub mod family;
+pub mod pos;
+pub mod readability;
+
+pub use detect::{detect_language, LanguageGuess};
+
+use crate::epub::{self, Location, Segment};
 use crate::resources;
 use gliner::model::{GLiNER, input::text::TextInput, pipeline::span::SpanMode};
 use orp::params::RuntimeParameters;
@@ -5,22 +14,280 @@ use orp::params::RuntimeParameters;
 #[cfg(target_os = "macos")]
 use ort::execution_providers::CoreMLExecutionProvider;
 use rust_stemmers::{Algorithm, Stemmer};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::{Arc, OnceLock};
-use symspell::{AsciiStringStrategy, SymSpell};
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Mutex, OnceLock};
+use symspell::{SymSpell, UnicodeStringStrategy};
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
 use unicode_segmentation::UnicodeSegmentation;
 use wordfreq::WordFreq;
 use wordfreq_model::{load_wordfreq, ModelKind};
 
-#[derive(Debug, Serialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct HardWord {
     pub word: String,
     pub frequency_score: f64,
-    pub contexts: Vec<String>,
+    pub contexts: Vec<Context>,
     pub count: usize,
     pub variants: Vec<String>, // All forms found (gaiety, gaieties, etc.)
+    pub band: FrequencyBand,
+    /// Short gloss from the offline dictionary (None if not downloaded or no entry)
+    pub definition: Option<String>,
+    /// Index of the first sentence the word appears in (reading order)
+    pub first_seen: usize,
+    /// Guessed part of speech ("adjective", "adverb", ...), None for non-English text
+    pub pos: Option<String>,
+    /// Where the word first occurs in the book (see `NlpPipeline::with_segments`)
+    #[serde(default)]
+    pub first_occurrence: Option<Location>,
+    /// Index (into the book's chapter list) and title of the chapter the word first occurs in
+    #[serde(default)]
+    pub first_chapter: Option<usize>,
+    #[serde(default)]
+    pub first_chapter_title: Option<String>,
+}
+
+/// A sentence the word occurs in, with the occurrence's position
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct Context {
+    pub sentence: String,
+    /// Character (not byte) range of the matched form within `sentence`
+    pub match_start: usize,
+    pub match_end: usize,
+    /// Character offset of the sentence within the analyzed text
+    #[serde(default)]
+    pub sentence_offset: usize,
+}
+
+impl Context {
+    /// Build a context from a raw sentence and the byte range of the matched word,
+    /// normalizing runs of whitespace while keeping the range exact
+    fn new(sentence: &str, start: usize, end: usize, sentence_offset: usize) -> Self {
+        fn normalize(s: &str) -> String {
+            let mut out = String::with_capacity(s.len());
+            let mut in_space = false;
+            for c in s.chars() {
+                if c.is_whitespace() {
+                    if !in_space {
+                        out.push(' ');
+                    }
+                    in_space = true;
+                } else {
+                    out.push(c);
+                    in_space = false;
+                }
+            }
+            out
+        }
+
+        let before = normalize(&sentence[..start]);
+        let before = before.trim_start();
+        let word = &sentence[start..end];
+        let after = normalize(&sentence[end..]);
+
+        let match_start = before.chars().count();
+        Self {
+            sentence: format!("{}{}{}", before, word, after.trim_end()),
+            match_start,
+            match_end: match_start + word.chars().count(),
+            sentence_offset,
+        }
+    }
+}
+
+/// Which sentences are kept as a word's example contexts (see `NlpPipeline::with_context_policy`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct ContextPolicy {
+    /// Shortest sentence kept, in characters
+    pub min_len: usize,
+    /// Longest sentence kept, in characters
+    pub max_len: usize,
+    /// Contexts kept per word, best-scoring first
+    pub max_count: usize,
+}
+
+impl Default for ContextPolicy {
+    fn default() -> Self {
+        Self { min_len: 20, max_len: 500, max_count: 10 }
+    }
+}
+
+impl ContextPolicy {
+    /// Keep at least one context, and a length range that isn't empty
+    pub fn normalized(self) -> Self {
+        Self {
+            min_len: self.min_len,
+            max_len: self.max_len.max(self.min_len),
+            max_count: self.max_count.max(1),
+        }
+    }
+
+    fn fits(&self, sentence: &str) -> bool {
+        (self.min_len..=self.max_len).contains(&sentence.chars().count())
+    }
+}
+
+/// How useful a sentence is as an example for its matched word (higher is better).
+///
+/// Favours sentences of moderate length (40-200 chars) that end cleanly, do not
+/// open with the word itself, and use varied vocabulary around it.
+fn score_context(context: &Context) -> f32 {
+    let len = context.sentence.chars().count() as f32;
+    let length = if len < 40.0 {
+        len / 40.0
+    } else if len > 200.0 {
+        200.0 / len
+    } else {
+        1.0
+    };
+
+    let position = if context.match_start == 0 { 0.0 } else { 1.0 };
+
+    let words: Vec<String> = context.sentence.unicode_words().map(|w| w.to_lowercase()).collect();
+    let distinct = words.iter().collect::<HashSet<_>>().len();
+    let variety = if words.len() < 4 {
+        0.0
+    } else {
+        distinct as f32 / words.len() as f32
+    };
+
+    let complete = context
+        .sentence
+        .trim_end_matches(['"', '\'', '”', '’', ')'])
+        .ends_with(['.', '!', '?', '…']);
+
+    2.0 * length + position + variety + if complete { 0.5 } else { 0.0 }
+}
+
+/// The best-scoring contexts seen for one word, at most `ContextPolicy::max_count`.
+/// Only sentences that fit the policy's lengths are offered.
+struct ContextPool {
+    max_count: usize,
+    entries: Vec<(f32, Context)>,
+}
+
+/// Where a word occurs: (sentence index, match start, match end), the match in
+/// bytes of the sentence. Recorded during the first pass and turned into
+/// contexts only for words that pass the filters (see `NlpPipeline::pool_contexts`).
+type Occurrence = (usize, usize, usize);
+
+/// What the first pass of an analysis collects for one stem
+struct WordStats {
+    count: usize,
+    occurrences: Vec<Occurrence>,
+    /// Seen capitalized mid-sentence, so NER has to rule out a name
+    needs_ner: bool,
+    original_forms: HashSet<String>,
+    /// The sentences NER checks when `needs_ner` is set
+    ner_contexts: HashSet<String>,
+    /// (sentence index, character offset in the text) of the first occurrence
+    first_seen: (usize, usize),
+}
+
+impl WordStats {
+    fn new(first_seen: (usize, usize)) -> Self {
+        Self {
+            count: 0,
+            occurrences: Vec::new(),
+            needs_ner: false,
+            original_forms: HashSet::new(),
+            ner_contexts: HashSet::new(),
+            first_seen,
+        }
+    }
+}
+
+/// A stem that passed the frequency and user filters, waiting on NER
+struct Candidate {
+    stemmed: String,
+    count: usize,
+    contexts: ContextPool,
+    needs_ner: bool,
+    original_forms: HashSet<String>,
+    ner_contexts: HashSet<String>,
+    first_seen: (usize, usize),
+}
+
+impl ContextPool {
+    fn new(policy: ContextPolicy) -> Self {
+        Self { max_count: policy.max_count, entries: Vec::new() }
+    }
+
+    /// Keep the context if the pool has room or it beats the worst one kept so far
+    fn offer(&mut self, context: Context) {
+        if self.entries.iter().any(|(_, c)| c.sentence == context.sentence) {
+            return;
+        }
+        let score = score_context(&context);
+        if self.entries.len() < self.max_count {
+            self.entries.push((score, context));
+            return;
+        }
+        let worst = self
+            .entries
+            .iter_mut()
+            .min_by(|a, b| a.0.total_cmp(&b.0));
+        if let Some(worst) = worst {
+            if score > worst.0 {
+                *worst = (score, context);
+            }
+        }
+    }
+
+    /// Contexts ordered best first
+    fn into_ranked(mut self) -> Vec<Context> {
+        self.entries.sort_by(|a, b| b.0.total_cmp(&a.0));
+        self.entries.into_iter().map(|(_, c)| c).collect()
+    }
+}
+
+/// CEFR-style difficulty band derived from a word's frequency
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum FrequencyBand {
+    A1,
+    A2,
+    B1,
+    B2,
+    C1,
+    C2,
+    /// Not in the wordfreq dictionary at all
+    #[serde(rename = "unlisted")]
+    Unlisted,
+}
+
+/// Minimum frequency for each band, most common first. Anything rarer than the
+/// last entry (but still listed in wordfreq) is C2.
+///
+/// | band | frequency      | roughly          |
+/// |------|----------------|------------------|
+/// | A1   | >= 1e-4        | top ~1k words    |
+/// | A2   | >= 3e-5        | top ~3k words    |
+/// | B1   | >= 1e-5        | top ~6k words    |
+/// | B2   | >= 3e-6        | top ~12k words   |
+/// | C1   | >= 1e-6        | top ~25k words   |
+/// | C2   | > 0            | everything rarer |
+const BAND_THRESHOLDS: &[(f64, FrequencyBand)] = &[
+    (1e-4, FrequencyBand::A1),
+    (3e-5, FrequencyBand::A2),
+    (1e-5, FrequencyBand::B1),
+    (3e-6, FrequencyBand::B2),
+    (1e-6, FrequencyBand::C1),
+];
+
+/// Map a wordfreq frequency to its difficulty band
+pub fn frequency_band(frequency: f64) -> FrequencyBand {
+    if frequency <= 0.0 {
+        return FrequencyBand::Unlisted;
+    }
+    BAND_THRESHOLDS
+        .iter()
+        .find(|(min, _)| frequency >= *min)
+        .map(|(_, band)| *band)
+        .unwrap_or(FrequencyBand::C2)
 }
 
 #[derive(Debug, Serialize, Clone)]
@@ -37,26 +304,435 @@ pub struct SampleWord {
     pub is_entity: bool, // true = will be filtered, false = kept
 }
 
+/// A candidate left out as a name or place, listed so users can review the filtering
+#[derive(Debug, Serialize, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct FilteredEntity {
+    /// The form GLiNER recognized (lowercase, like `HardWord::word`)
+    pub word: String,
+    /// Label GLiNER gave it, one of `NER_LABELS`
+    pub label: String,
+}
+
 #[derive(Debug, Serialize, Clone)]
 pub struct AnalysisStats {
     pub total_candidates: usize,
-    pub filtered_by_ner: Vec<String>,
+    /// Candidates filtered as names and places, sorted by word, one entry per word
+    pub filtered_by_ner: Vec<FilteredEntity>,
     pub hard_words_count: usize,
+    /// Candidates dropped because the user marked them as known
+    pub filtered_known: usize,
+    /// Candidates dropped because their lemma is a stopword
+    pub filtered_stopwords: usize,
+    /// Candidates dropped for appearing fewer than `min_count` times in the book
+    pub filtered_min_count: usize,
+    /// The `min_count` the analysis ran with
+    pub min_count: usize,
+    /// Candidates dropped because their part of speech isn't in `allowed_pos`
+    pub filtered_pos: usize,
+    /// Words in verse sentences left out of the analysis (`skip_verse`)
+    pub excluded_verse_words: usize,
+    /// NER was skipped (fast mode), so names and places were not filtered
+    pub ner_skipped: bool,
+    pub readability: readability::Readability,
+}
+
+/// Language of the analyzed text. Selects the malformed-word rules and the
+/// SymSpell dictionary used to split run-together words.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Language {
+    #[default]
+    English,
+    German,
+}
+
+impl Language {
+    /// Language for an ISO 639 code as Calibre stores it ("eng", "deu"/"ger")
+    /// or a two-letter code; anything unsupported falls back to English
+    pub fn from_code(code: &str) -> Self {
+        match code.trim().to_lowercase().as_str() {
+            "deu" | "ger" | "de" => Language::German,
+            _ => Language::English,
+        }
+    }
+
+    /// Language for a normalized two-letter code, or None if it isn't supported
+    pub fn from_base_code(code: &str) -> Option<Self> {
+        match code {
+            "en" => Some(Language::English),
+            "de" => Some(Language::German),
+            _ => None,
+        }
+    }
+
+    /// Languages that write compounds as one word ("Geschwindigkeitsbegrenzung"),
+    /// where a long word splitting into two real words is usually legitimate
+    fn is_compounding(self) -> bool {
+        matches!(self, Language::German)
+    }
+
+    /// Fewest parts a segmentation needs before a word counts as run together
+    fn min_run_together_segments(self) -> usize {
+        if self.is_compounding() {
+            3
+        } else {
+            2
+        }
+    }
+
+    /// Common words that broken markup glues onto the previous word ("believethat")
+    fn glued_suffixes(self) -> &'static [&'static str] {
+        match self {
+            Language::English => &["that's", "that", "the", "this", "they"],
+            Language::German => &[],
+        }
+    }
+
+    fn symspell_dict(self) -> resources::SymspellDict {
+        match self {
+            Language::English => resources::SYMSPELL_EN,
+            Language::German => resources::SYMSPELL_DE,
+        }
+    }
+
+    /// Bundled stopword list (see `resources::parse_word_list`)
+    fn stopwords(self) -> &'static str {
+        match self {
+            Language::English => resources::STOPWORDS_EN,
+            Language::German => resources::STOPWORDS_DE,
+        }
+    }
+}
+
+/// Order of the returned hard words
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WordOrder {
+    /// Rarest first (lowest frequency score)
+    #[default]
+    Rarity,
+    /// By first occurrence in the book, for reading along
+    FirstSeen,
+    /// Grouped by the chapter of first occurrence, rarest first within each
+    Chapter,
+}
+
+/// Frequency threshold analyses use unless asked for another: words rarer than
+/// this count as hard
+pub const DEFAULT_FREQUENCY_THRESHOLD: f32 = 0.00005;
+
+/// Per-run analysis options
+#[derive(Debug, Clone)]
+pub struct AnalysisOptions {
+    /// Skip GLiNER entirely and return every frequency candidate (fast mode)
+    pub skip_ner: bool,
+    pub order: WordOrder,
+    /// Drop candidates seen fewer times than this in the book (1 keeps everything)
+    pub min_count: usize,
+    /// Drop listed words rarer than this (0.0 keeps everything up to the threshold)
+    pub min_frequency: f32,
+    /// Keep words wordfreq doesn't list at all (archaic or very obscure vocabulary).
+    /// Malformed words are still dropped.
+    pub include_unlisted: bool,
+    /// Group "-ly" adverbs with the adjective they derive from and show the adjective
+    pub collapse_derived: bool,
+    /// Merge derivational relatives ("felicity", "felicitous") into one word under
+    /// their family root (see `family`); English only, and lossier than stemming
+    pub group_families: bool,
+    /// Keep only words tagged with one of these parts of speech (empty keeps all)
+    pub allowed_pos: Vec<pos::Pos>,
+    /// Leave out sentences in verse (see `NlpPipeline::with_verse`)
+    pub skip_verse: bool,
+}
+
+impl Default for AnalysisOptions {
+    fn default() -> Self {
+        Self {
+            skip_ner: false,
+            order: WordOrder::default(),
+            min_count: 1,
+            min_frequency: 0.0,
+            include_unlisted: false,
+            collapse_derived: false,
+            group_families: false,
+            allowed_pos: Vec::new(),
+            skip_verse: false,
+        }
+    }
 }
 
+impl AnalysisOptions {
+    /// Whether a word with this frequency is rare enough for `threshold` and within the floor
+    fn keeps_frequency(&self, freq: f32, threshold: f32) -> bool {
+        if freq == 0.0 {
+            return self.include_unlisted;
+        }
+        freq <= threshold && freq >= self.min_frequency
+    }
+}
+
+/// Entities GLiNER found (lowercased texts and their words), with their labels
+type Entities = HashMap<String, String>;
+
+/// Entity labels GLiNER is asked to find
+const NER_LABELS: [&str; 5] = ["person", "location", "organization", "country", "city"];
+/// Sentences per GLiNER inference call when available memory is unknown
+const NER_BATCH_SIZE: usize = 64;
+/// Environment variable overriding the GLiNER batch size (for benchmarking)
+const NER_BATCH_SIZE_ENV: &str = "LEXIS_NER_BATCH_SIZE";
+/// Hard words per `on_partial` batch in `analyze_with_cancel`
+const PARTIAL_BATCH_SIZE: usize = 500;
+
+static WORDFREQ: OnceLock<WordFreq> = OnceLock::new();
+static WORDFREQ_DE: OnceLock<WordFreq> = OnceLock::new();
 static GLINER_MODEL: OnceLock<Option<GLiNER<SpanMode>>> = OnceLock::new();
-static SYMSPELL: OnceLock<Option<SymSpell<AsciiStringStrategy>>> = OnceLock::new();
+static SYMSPELL_EN: OnceLock<Option<SymSpell<UnicodeStringStrategy>>> = OnceLock::new();
+static SYMSPELL_DE: OnceLock<Option<SymSpell<UnicodeStringStrategy>>> = OnceLock::new();
+/// English stemmer shared by `lemma_key`, which runs once per word of every saved analysis
+static LEMMA_STEMMER: OnceLock<Stemmer> = OnceLock::new();
+
+/// Shared English wordfreq model, loaded once (also used by text extraction)
+pub(crate) fn wordfreq() -> &'static WordFreq {
+    WORDFREQ.get_or_init(|| load_wordfreq(ModelKind::LargeEn).expect("Failed to load wordfreq model"))
+}
+
+/// Shared wordfreq model of `language`, loaded once
+pub(crate) fn wordfreq_for(language: Language) -> &'static WordFreq {
+    match language {
+        Language::English => wordfreq(),
+        Language::German => WORDFREQ_DE
+            .get_or_init(|| load_wordfreq(ModelKind::LargeDe).expect("Failed to load German wordfreq model")),
+    }
+}
 
 pub struct NlpPipeline {
-    wordfreq: WordFreq,
+    wordfreq: &'static WordFreq,
     stemmer: Stemmer,
+    /// Words the user already knows (lowercased forms and their stems)
+    known_words: HashSet<String>,
+    /// Words the user marked as not an entity, never filtered by NER (forms and stems)
+    entity_allowlist: HashSet<String>,
+    /// Bundled stopwords of `language`, as exact lowercase forms
+    stopwords: HashSet<String>,
+    /// The user's own stopwords (words and their stems)
+    custom_stopwords: HashSet<String>,
+    /// GLiNER batches run concurrently (see `default_ner_workers`)
+    ner_workers: usize,
+    /// Sentences per GLiNER inference call (see `default_ner_batch_size`)
+    ner_batch_size: usize,
+    /// Which sentences are kept as contexts, and how many per word
+    context_policy: ContextPolicy,
+    /// Language of the text (see `Language`)
+    language: Language,
+    /// SymSpell segmentations done by `is_malformed_word` in the current analysis
+    segmentations: Mutex<SegmentationCache>,
+    /// Offline dictionary for `HardWord::definition`, if downloaded
+    dictionary: Option<Arc<dictionary::Dictionary>>,
+    /// Where the analyzed text's chapters came from, for `HardWord::first_occurrence`
+    segments: Vec<Segment>,
+    /// Titles by `Segment::chapter_index`, for `HardWord::first_chapter_title`
+    chapter_titles: Vec<String>,
+    /// Character ranges of the analyzed text that are verse, in order
+    verse: Vec<Range<usize>>,
+}
+
+/// Memoized `word_segmentation` results, keyed on the checked word. Only the
+/// run-together segmentation is kept (None when the word is not run together).
+#[derive(Debug, Default)]
+struct SegmentationCache {
+    results: HashMap<String, Option<String>>,
+    hits: usize,
+    /// Segmentations computed and served from cache by the last finished analysis
+    last_counts: (usize, usize),
+}
+
+/// ONNX Runtime sessions are thread-safe (`Session::run` takes `&self`), so NER
+/// workers share one model. Each run already uses several intra-op threads,
+/// hence only a few concurrent batches.
+fn default_ner_workers() -> usize {
+    std::thread::available_parallelism()
+        .map(|n| n.get() / 4)
+        .unwrap_or(1)
+        .clamp(1, 4)
+}
+
+/// Batch size from `LEXIS_NER_BATCH_SIZE` if set, otherwise sized to the machine
+fn default_ner_batch_size() -> usize {
+    if let Some(size) = std::env::var(NER_BATCH_SIZE_ENV).ok().and_then(|v| v.trim().parse::<usize>().ok()) {
+        return size.max(1);
+    }
+    let cores = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+    ner_batch_size_for(available_memory(), cores)
+}
+
+/// Every sentence in a batch is padded to the longest one, so a batch's tensors grow
+/// with its size; low-memory machines get small batches, big machines larger ones
+fn ner_batch_size_for(available_memory: Option<u64>, cores: usize) -> usize {
+    const GIB: u64 = 1 << 30;
+    match available_memory {
+        None => NER_BATCH_SIZE,
+        Some(bytes) if bytes < 4 * GIB => 16,
+        Some(bytes) if bytes < 8 * GIB => 32,
+        Some(bytes) if bytes >= 16 * GIB && cores >= 8 => 128,
+        Some(_) => NER_BATCH_SIZE,
+    }
+}
+
+/// Memory available to new allocations, where the OS tells us cheaply (Linux only)
+fn available_memory() -> Option<u64> {
+    let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+    let line = meminfo.lines().find(|l| l.starts_with("MemAvailable:"))?;
+    let kib: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kib * 1024)
 }
 
 impl NlpPipeline {
     pub fn new() -> Self {
-        let wordfreq = load_wordfreq(ModelKind::LargeEn).expect("Failed to load wordfreq model");
         let stemmer = Stemmer::create(Algorithm::English);
-        Self { wordfreq, stemmer }
+        Self {
+            wordfreq: wordfreq(),
+            known_words: HashSet::new(),
+            entity_allowlist: HashSet::new(),
+            stopwords: bundled_stopwords(Language::default()),
+            custom_stopwords: HashSet::new(),
+            ner_workers: default_ner_workers(),
+            ner_batch_size: default_ner_batch_size(),
+            context_policy: ContextPolicy::default(),
+            language: Language::default(),
+            segmentations: Mutex::new(SegmentationCache::default()),
+            dictionary: dictionary::shared(),
+            segments: Vec::new(),
+            chapter_titles: Vec::new(),
+            verse: Vec::new(),
+            stemmer,
+        }
+    }
+
+    /// Language of the text to analyze (also selects the word frequencies and bundled stopwords)
+    pub fn with_language(mut self, language: Language) -> Self {
+        self.language = language;
+        self.wordfreq = wordfreq_for(language);
+        self.stopwords = bundled_stopwords(language);
+        self
+    }
+
+    /// Sentence lengths and number of example sentences kept per word
+    pub fn with_context_policy(mut self, policy: ContextPolicy) -> Self {
+        self.context_policy = policy.normalized();
+        self
+    }
+
+    /// Map the analyzed text back to spine items (see `epub::join_chapters`),
+    /// so hard words record where they first occur
+    pub fn with_segments(mut self, segments: Vec<Segment>) -> Self {
+        self.segments = segments;
+        self
+    }
+
+    /// Chapter titles, indexed like `Segment::chapter_index`
+    pub fn with_chapter_titles(mut self, titles: Vec<String>) -> Self {
+        self.chapter_titles = titles;
+        self
+    }
+
+    /// Verse passages of the analyzed text (see `epub::ExtractedText::verse`),
+    /// skipped when `AnalysisOptions::skip_verse` is set
+    pub fn with_verse(mut self, verse: Vec<Range<usize>>) -> Self {
+        self.verse = verse;
+        self
+    }
+
+    /// Whether a character offset into the analyzed text falls in verse
+    fn in_verse(&self, char_offset: usize) -> bool {
+        self.verse
+            .get(self.verse.partition_point(|r| r.end <= char_offset))
+            .is_some_and(|r| r.start <= char_offset)
+    }
+
+    /// Number of GLiNER batches to run in parallel (1 = sequential)
+    pub fn with_ner_workers(mut self, workers: usize) -> Self {
+        self.ner_workers = workers.max(1);
+        self
+    }
+
+    /// Sentences per GLiNER inference call (at least 1)
+    pub fn with_ner_batch_size(mut self, size: usize) -> Self {
+        self.ner_batch_size = size.max(1);
+        self
+    }
+
+    /// Exclude these words (and anything sharing their stem) from results
+    pub fn with_known_words<'a>(mut self, words: impl IntoIterator<Item = &'a String>) -> Self {
+        self.known_words = normalize_known_words(&self.stemmer, words);
+        self
+    }
+
+    /// Never filter these words (or anything sharing their stem) as names, even
+    /// when GLiNER flags them
+    pub fn with_entity_allowlist<'a>(mut self, words: impl IntoIterator<Item = &'a String>) -> Self {
+        self.entity_allowlist = normalize_known_words(&self.stemmer, words);
+        self
+    }
+
+    /// Also exclude these stopwords (and anything sharing their stem), on top of
+    /// the language's bundled list
+    pub fn with_stopwords<'a>(mut self, words: impl IntoIterator<Item = &'a String>) -> Self {
+        self.custom_stopwords = normalize_known_words(&self.stemmer, words);
+        self
+    }
+
+    /// Check if a word form is a bundled stopword. These match exactly: their
+    /// stems would take real words with them ("hast" and "haste").
+    fn is_bundled_stopword(&self, form: &str) -> bool {
+        self.stopwords.contains(form)
+    }
+
+    /// Check if a candidate (by stem or any original form) is one of the user's stopwords
+    fn is_custom_stopword(&self, stemmed: &str, original_forms: &HashSet<String>) -> bool {
+        self.custom_stopwords.contains(stemmed) || original_forms.iter().any(|f| self.custom_stopwords.contains(f))
+    }
+
+    /// Number of bundled stopword forms seen that the frequency filter would have kept
+    fn count_stopwords(&self, forms: &HashSet<String>, frequency_threshold: f32, options: &AnalysisOptions) -> usize {
+        forms
+            .iter()
+            .filter(|form| options.keeps_frequency(self.wordfreq.word_frequency(form), frequency_threshold))
+            .count()
+    }
+
+    /// Check if a candidate (by stem or any original form) is in the known-words list
+    fn is_known(&self, stemmed: &str, original_forms: &HashSet<String>) -> bool {
+        !self.known_words.is_empty()
+            && (self.known_words.contains(stemmed) || original_forms.iter().any(|f| self.known_words.contains(f)))
+    }
+
+    /// The entity a candidate was recognized as, by stem or any original form,
+    /// unless it is on the user's entity allowlist
+    fn entity_match(
+        &self,
+        named_entities: &Entities,
+        stemmed: &str,
+        original_forms: &HashSet<String>,
+    ) -> Option<FilteredEntity> {
+        if self.entity_allowlist.contains(stemmed) || original_forms.iter().any(|f| self.entity_allowlist.contains(f)) {
+            return None;
+        }
+        let mut forms: Vec<&str> = original_forms.iter().map(|f| f.as_str()).collect();
+        forms.sort_by(|a, b| a.len().cmp(&b.len()).then_with(|| a.cmp(b)));
+        let (word, label) = std::iter::once(stemmed)
+            .chain(forms.iter().copied())
+            .find_map(|w| named_entities.get(w).map(|label| (w, label)))?;
+        // Show a real form rather than a stem that is not one
+        let word = if original_forms.contains(word) { word } else { forms.first().copied().unwrap_or(word) };
+        Some(FilteredEntity { word: word.to_string(), label: label.clone() })
+    }
+
+    /// The best contexts among a candidate's occurrences (see `Occurrence`)
+    fn pool_contexts(&self, occurrences: &[Occurrence], sentences: &[&str], offsets: &[usize]) -> ContextPool {
+        let mut pool = ContextPool::new(self.context_policy);
+        for &(index, start, end) in occurrences {
+            pool.offer(Context::new(sentences[index], start, end, offsets[index]));
+        }
+        pool
     }
 
     /// Stem a word (input must be lowercase)
@@ -64,6 +740,86 @@ impl NlpPipeline {
         self.stemmer.stem(word).to_string()
     }
 
+    /// NFC-normalized lowercase form of a word, and the key it is grouped under:
+    /// the stem with diacritics removed, so "Naïve", "naive" and "naïvely" collapse
+    fn word_key(&self, word: &str) -> (String, String) {
+        let form: String = word.nfc().collect::<String>().to_lowercase();
+        let key = self.stem(&fold_diacritics(&form));
+        (form, key)
+    }
+
+    /// `word_key`, but with a derived adverb grouped under its root when
+    /// `collapse_derived` is set, so "felicitously" joins "felicitous", and with
+    /// the key reduced to its family root when `group_families` is set
+    fn group_key(&self, word: &str, options: &AnalysisOptions) -> (String, String) {
+        let (form, key) = self.word_key(word);
+        let key = match self.derived_root(&form, options) {
+            Some(root) => self.word_key(&root).1,
+            None => key,
+        };
+        if options.group_families && self.language == Language::English {
+            let root = family::family_root(&form, &key);
+            return (form, root);
+        }
+        (form, key)
+    }
+
+    /// The adjective `form` derives from, if collapsing is on and it is an English "-ly" adverb
+    fn derived_root(&self, form: &str, options: &AnalysisOptions) -> Option<String> {
+        if !options.collapse_derived || self.language != Language::English || pos::tag(form) != pos::Pos::Adverb {
+            return None;
+        }
+        pos::adverb_root(form, |root| self.wordfreq.word_frequency(root) > 0.0)
+    }
+
+    /// Part of speech of a candidate, judged by its shortest form (the root when collapsing)
+    fn candidate_pos(&self, forms: &HashSet<String>, options: &AnalysisOptions) -> Option<pos::Pos> {
+        if self.language != Language::English {
+            return None;
+        }
+        let form = forms.iter().min_by(|a, b| a.len().cmp(&b.len()).then(a.cmp(b)))?;
+        let root = self.derived_root(form, options);
+        Some(pos::tag(root.as_deref().unwrap_or(form)))
+    }
+
+    /// SymSpell segmentation of `check_word` if it splits into enough real words
+    /// to count as run together (see `is_run_together`)
+    fn run_together_segmentation(&self, check_word: &str) -> Option<String> {
+        let symspell = get_symspell(self.language)?;
+        let segmentation = symspell.word_segmentation(check_word, 2);
+        let segments: Vec<&str> = segmentation.segmented_string.split_whitespace().collect();
+
+        // Segments must be real words of the text's language
+        let is_word = |s: &str| self.wordfreq.word_frequency(s) > 0.0;
+        is_run_together(&segments, self.language.min_run_together_segments(), is_word)
+            .then(|| segmentation.segmented_string.clone())
+    }
+
+    /// Look up `check_word` in the segmentation cache, running `segment` on a miss
+    fn cached_segmentation(&self, check_word: &str, segment: impl FnOnce() -> Option<String>) -> Option<String> {
+        let mut cache = self.segmentations.lock().unwrap();
+        if let Some(result) = cache.results.get(check_word) {
+            let result = result.clone();
+            cache.hits += 1;
+            return result;
+        }
+        let result = segment();
+        cache.results.insert(check_word.to_string(), result.clone());
+        result
+    }
+
+    /// Log and drop the segmentation cache once candidate filtering is done,
+    /// so it never outlives one analysis
+    fn finish_segmentations(&self) {
+        let mut cache = self.segmentations.lock().unwrap();
+        *cache = SegmentationCache {
+            last_counts: (cache.results.len(), cache.hits),
+            ..SegmentationCache::default()
+        };
+        let (computed, cached) = cache.last_counts;
+        eprintln!("SymSpell segmentations: {} computed, {} served from cache", computed, cached);
+    }
+
     /// Check if a word looks like concatenated words (e.g., "believethat's")
     /// Returns true if the word should be filtered out as malformed
     ///
@@ -96,37 +852,18 @@ impl NlpPipeline {
         }
 
         // Only for words NOT in dictionary: try symspell segmentation
-        if let Some(symspell) = get_symspell() {
-            if check_word.len() >= 8 {
-                let segmentation = symspell.word_segmentation(check_word, 2);
-                let segments: Vec<&str> = segmentation.segmented_string.split_whitespace().collect();
-
-                // If segmentation found multiple words, check if it makes sense
-                if segments.len() >= 2 {
-                    // All segments must be at least 3 chars and be real words
-                    let all_valid = segments.iter().all(|s| {
-                        s.len() >= 3 && self.wordfreq.word_frequency(s) > 0.0
-                    });
-
-                    if all_valid {
-                        eprintln!("Filtering malformed word '{}' -> '{}'", word, segmentation.segmented_string);
-                        return true;
-                    }
-                }
+        if check_word.len() >= 8 {
+            if let Some(segmented) = self.cached_segmentation(check_word, || self.run_together_segmentation(check_word)) {
+                eprintln!("Filtering malformed word '{}' -> '{}'", word, segmented);
+                return true;
             }
         }
 
         // Fallback: heuristic for obvious concatenations with common words
-        let common_suffixes = ["that's", "that", "the", "this", "they"];
-
-        for suffix in &common_suffixes {
-            if word.ends_with(suffix) && word.len() > suffix.len() + 4 {
-                let prefix = &word[..word.len() - suffix.len()];
-                if prefix.len() >= 4 && self.wordfreq.word_frequency(prefix) > 0.0 {
-                    eprintln!("Filtering malformed word '{}' (heuristic: '{}' + '{}')", word, prefix, suffix);
-                    return true;
-                }
-            }
+        let is_word = |s: &str| self.wordfreq.word_frequency(s) > 0.0;
+        if let Some((prefix, suffix)) = glued_suffix(word, self.language.glued_suffixes(), is_word) {
+            eprintln!("Filtering malformed word '{}' (heuristic: '{}' + '{}')", word, prefix, suffix);
+            return true;
         }
 
         false
@@ -136,17 +873,22 @@ impl NlpPipeline {
         resources::is_gliner_available()
     }
 
+    /// Whether the GLiNER model and `language`'s SymSpell dictionary, which a
+    /// non-fast analysis needs, are downloaded
+    pub fn resources_available(language: Language) -> bool {
+        Self::is_gliner_available() && resources::is_symspell_dict_available(language.symspell_dict())
+    }
+
     fn get_gliner(&self) -> Option<&GLiNER<SpanMode>> {
         GLINER_MODEL.get_or_init(|| {
             let model_dir = resources::get_gliner_dir();
             let tokenizer_path = model_dir.join("tokenizer.json");
-            let model_path = model_dir.join("model.onnx");
-
-            if !tokenizer_path.exists() || !model_path.exists() {
+            // Whichever variant (full or quantized) is downloaded
+            let Some(model_path) = resources::get_gliner_model_path() else {
                 eprintln!("GLiNER model not found at {:?}", model_dir);
                 eprintln!("Run resource download to fetch the model automatically");
                 return None;
-            }
+            };
 
             // Configure runtime with CoreML on macOS for better performance
             #[cfg(target_os = "macos")]
@@ -186,19 +928,16 @@ impl NlpPipeline {
         &self,
         sentences: &[&str],
         mut on_progress: F,
-    ) -> HashSet<String>
+    ) -> Entities
     where
         F: FnMut(usize, usize, usize, &[String]), // (sentences_processed, total_sentences, entities_found, recent_entities)
     {
-        let mut entities = HashSet::new();
-        let mut recent_entities: Vec<String> = Vec::new();
-
         let Some(gliner) = self.get_gliner() else {
-            return entities;
+            return Entities::new();
         };
 
         if sentences.is_empty() {
-            return entities;
+            return Entities::new();
         }
 
         // Filter and prepare chunks
@@ -209,96 +948,121 @@ impl NlpPipeline {
             .collect();
 
         if chunks.is_empty() {
-            return entities;
+            return Entities::new();
         }
 
-        let total_sentences = chunks.len();
-        eprintln!("Running GLiNER on {} sentences...", total_sentences);
+        eprintln!("Running GLiNER on {} sentences...", chunks.len());
 
-        // Process in smaller batches for better CoreML utilization
-        let batch_size = 64;
-        let mut processed = 0;
+        let never_cancel = AtomicBool::new(false);
+        let entities = self.infer_entities(gliner, &chunks, &never_cancel, |processed, total, found, new_entities| {
+            // Report progress after processing each batch with recent entities
+            on_progress(processed, total, found.len(), new_entities);
+        });
 
-        let mut total_infer_ms: u128 = 0;
-        for (batch_idx, batch) in chunks.chunks(batch_size).enumerate() {
-            let input = match TextInput::from_str(
-                batch,
-                &["person", "location", "organization", "country", "city"],
-            ) {
-                Ok(input) => input,
-                Err(e) => {
-                    eprintln!("Failed to create GLiNER input: {}", e);
-                    processed += batch.len();
-                    continue;
-                }
-            };
+        eprintln!("GLiNER found {} unique entities", entities.len());
+        entities
+    }
+
+    /// Run GLiNER over `chunks` in batches, on up to `ner_workers` threads sharing the model.
+    ///
+    /// `on_batch(processed, total, entities, new_entities)` runs on the calling thread as
+    /// each batch finishes (in completion order). Workers stop taking batches once
+    /// `cancel` is set.
+    fn infer_entities<F>(
+        &self,
+        gliner: &GLiNER<SpanMode>,
+        chunks: &[&str],
+        cancel: &AtomicBool,
+        mut on_batch: F,
+    ) -> Entities
+    where
+        F: FnMut(usize, usize, &Entities, &[String]),
+    {
+        let batches: Vec<&[&str]> = chunks.chunks(self.ner_batch_size).collect();
+        let workers = self.ner_workers.min(batches.len()).max(1);
+        eprintln!(
+            "GLiNER: {} sentences in {} batches of up to {} on {} workers",
+            chunks.len(),
+            batches.len(),
+            self.ner_batch_size,
+            workers
+        );
+        let next_batch = AtomicUsize::new(0);
+        let (tx, rx) = mpsc::channel::<(usize, Vec<(String, String)>, u128)>();
 
-            // Clear recent for this batch
-            recent_entities.clear();
-
-            let infer_start = std::time::Instant::now();
-            match gliner.inference(input) {
-                Ok(output) => {
-                    for spans in output.spans.iter() {
-                        for span in spans.iter() {
-                            let entity_text = span.text().to_lowercase();
-                            if entities.insert(entity_text.clone()) {
-                                // New entity found
-                                recent_entities.push(entity_text.clone());
-                            }
-                            // Also add individual words from multi-word entities
-                            for word in entity_text.split_whitespace() {
-                                if entities.insert(word.to_string()) {
-                                    recent_entities.push(word.to_string());
-                                }
-                            }
+        let mut entities = Entities::new();
+        let mut processed = 0;
+        let mut total_infer_ms: u128 = 0;
+        let wall_start = std::time::Instant::now();
+
+        std::thread::scope(|scope| {
+            for _ in 0..workers {
+                let tx = tx.clone();
+                let (batches, next_batch) = (&batches, &next_batch);
+                scope.spawn(move || {
+                    while !cancel.load(Ordering::SeqCst) {
+                        let Some(batch) = batches.get(next_batch.fetch_add(1, Ordering::SeqCst)) else {
+                            break;
+                        };
+                        let infer_start = std::time::Instant::now();
+                        let found = infer_batch(gliner, batch);
+                        if tx.send((batch.len(), found, infer_start.elapsed().as_millis())).is_err() {
+                            break;
                         }
                     }
-                }
-                Err(e) => {
-                    eprintln!("GLiNER inference error: {}", e);
-                }
+                });
             }
-            let infer_elapsed = infer_start.elapsed();
-            total_infer_ms += infer_elapsed.as_millis();
-            if batch_idx == 0 {
-                eprintln!(
-                    "GLiNER first batch inference: {} ms for {} sentences (batch size {})",
-                    infer_elapsed.as_millis(),
-                    batch.len(),
-                    batch_size
-                );
+            drop(tx);
+
+            for (batch_len, found, infer_ms) in rx {
+                processed += batch_len;
+                total_infer_ms += infer_ms;
+                // An entity keeps the label it was first found with
+                let mut new_entities = Vec::new();
+                for (entity, label) in found {
+                    if let std::collections::hash_map::Entry::Vacant(slot) = entities.entry(entity) {
+                        new_entities.push(slot.key().clone());
+                        slot.insert(label);
+                    }
+                }
+                on_batch(processed, chunks.len(), &entities, &new_entities);
             }
+        });
 
-            processed += batch.len();
-            // Report progress after processing each batch with recent entities
-            on_progress(processed, total_sentences, entities.len(), &recent_entities);
-        }
-
-        if total_sentences > 0 {
-            let avg_ms = total_infer_ms as f64 / total_sentences as f64;
+        if processed > 0 {
             eprintln!(
-                "GLiNER total inference time: {} ms for {} sentences (avg {:.2} ms/sentence)",
+                "GLiNER inference: {} ms wall clock, {} ms summed over {} worker(s), {} sentences (avg {:.2} ms/sentence)",
+                wall_start.elapsed().as_millis(),
                 total_infer_ms,
-                total_sentences,
-                avg_ms
+                workers,
+                processed,
+                total_infer_ms as f64 / processed as f64
             );
         }
 
-        eprintln!("GLiNER found {} unique entities", entities.len());
         entities
     }
 
-    pub fn analyze<F>(&self, text: &str, frequency_threshold: f32, mut on_progress: F) -> (Vec<HardWord>, AnalysisStats)
+    pub fn analyze<F>(&self, text: &str, frequency_threshold: f32, on_progress: F) -> (Vec<HardWord>, AnalysisStats)
+    where
+        F: FnMut(AnalysisProgress),
+    {
+        self.analyze_with_options(text, frequency_threshold, &AnalysisOptions::default(), on_progress)
+    }
+
+    pub fn analyze_with_options<F>(
+        &self,
+        text: &str,
+        frequency_threshold: f32,
+        options: &AnalysisOptions,
+        mut on_progress: F,
+    ) -> (Vec<HardWord>, AnalysisStats)
     where
         F: FnMut(AnalysisProgress),
     {
         // Split into sentences for context
-        let sentences: Vec<&str> = text
-            .split(|c| c == '.' || c == '!' || c == '?')
-            .map(|s| s.trim())
-            .filter(|s| !s.is_empty())
-            .collect();
+        let sentences = segment_sentences(strip_gutenberg_boilerplate(text));
+        let offsets = sentence_offsets(text, &sentences);
 
         on_progress(AnalysisProgress {
             stage: "Analyzing text".to_string(),
@@ -311,14 +1075,19 @@ impl NlpPipeline {
 
         // FIRST PASS: Collect word counts and identify hard word CANDIDATES using wordfreq
         // This is fast and filters out most words before we even touch GLiNER
-        // Key is stemmed form, value is (count, contexts, is_proper_noun_candidate, original_forms)
-        let mut word_data: HashMap<String, (usize, Vec<String>, bool, HashSet<String>, HashSet<String>)> = HashMap::new();
-
-        for sentence in &sentences {
-            let words: Vec<&str> = sentence.unicode_words().collect();
-
-            for word in &words {
-                let lower = word.to_lowercase();
+        // Key is stemmed form
+        let mut word_data: HashMap<String, WordStats> = HashMap::new();
+
+        let mut excluded_verse_words = 0usize;
+        let mut stopword_forms = HashSet::new();
+        for (sentence_index, sentence) in sentences.iter().enumerate() {
+            if options.skip_verse && self.in_verse(offsets[sentence_index]) {
+                excluded_verse_words += sentence.unicode_words().count();
+                continue;
+            }
+            let fits_context = self.context_policy.fits(sentence);
+            for (start, word) in sentence.unicode_word_indices() {
+                let (lower, stemmed) = self.group_key(word, options);
 
                 // Skip short words
                 if lower.len() < 3 {
@@ -330,37 +1099,50 @@ impl NlpPipeline {
                     continue;
                 }
 
-                // Stem the word for grouping (running, runs, run -> run)
-                let stemmed = self.stem(&lower);
+                // Skip stopwords (archaic forms, interjections, numerals) before
+                // they are grouped with real words sharing their stem
+                if self.is_bundled_stopword(&lower) {
+                    stopword_forms.insert(lower);
+                    continue;
+                }
+
+                // `stemmed` groups inflections and spellings (running, runs, run -> run;
+                // café, cafe -> cafe)
 
                 // Check if likely proper noun (will need NER verification)
                 let is_proper = is_likely_proper_noun(word, sentence);
 
-                let entry = word_data.entry(stemmed).or_insert((0, Vec::new(), false, HashSet::new(), HashSet::new()));
-                entry.0 += 1;
+                let entry = word_data
+                    .entry(stemmed)
+                    .or_insert_with(|| {
+                        WordStats::new((sentence_index, offsets[sentence_index] + sentence[..start].chars().count()))
+                    });
+                entry.count += 1;
                 if is_proper {
-                    entry.2 = true; // Mark as needing NER check
+                    entry.needs_ner = true; // Mark as needing NER check
                 }
-                entry.3.insert(lower); // Track original forms
+                entry.original_forms.insert(lower); // Track original forms
 
-                // Store context sentence (no limit - UI will handle display)
-                if sentence.len() > 20 && sentence.len() < 500 {
-                    let context = format!("{}.", sentence);
-                    if !entry.1.contains(&context) {
-                        entry.1.push(context.clone());
-                    }
-                    if is_proper {
-                        entry.4.insert(context);
-                    }
+                // Example sentences are ranked once the word is known to be a candidate
+                if fits_context {
+                    entry.occurrences.push((sentence_index, start, start + word.len()));
+                }
+                if is_proper {
+                    entry.ner_contexts.insert(sentence.to_string());
                 }
             }
         }
 
         // Filter to get hard word candidates based on frequency
         // Use stemmed form for frequency lookup, but try original forms too
-        let candidates: Vec<(String, usize, Vec<String>, bool, HashSet<String>, HashSet<String>)> = word_data
+        let mut filtered_known = 0usize;
+        let mut filtered_stopwords = self.count_stopwords(&stopword_forms, frequency_threshold, options);
+        let mut filtered_min_count = 0usize;
+        let mut filtered_pos = 0usize;
+        let candidates: Vec<Candidate> = word_data
             .into_iter()
-            .filter_map(|(stemmed, (count, contexts, needs_ner, original_forms, ner_contexts))| {
+            .filter_map(|(stemmed, stats)| {
+                let WordStats { count, occurrences, needs_ner, original_forms, ner_contexts, first_seen } = stats;
                 // Filter out malformed words (EPUB parsing errors like "believethat's")
                 for form in &original_forms {
                     if self.is_malformed_word(form) {
@@ -380,39 +1162,74 @@ impl NlpPipeline {
                     }
                 }
 
-                // Filter out very common words and words not in dictionary
-                if freq > frequency_threshold || freq == 0.0 {
+                // Filter out very common words, and words outside the floor or not in dictionary unless asked for
+                if !options.keeps_frequency(freq, frequency_threshold) {
                     return None;
                 }
 
-                Some((stemmed, count, contexts, needs_ner, original_forms, ner_contexts))
-            })
-            .collect();
+                // Filter out the user's stopwords
+                if self.is_custom_stopword(&stemmed, &original_forms) {
+                    filtered_stopwords += 1;
+                    return None;
+                }
 
-        eprintln!("Found {} hard word candidates after wordfreq filtering", candidates.len());
+                // Filter out words the user has already learned
+                if self.is_known(&stemmed, &original_forms) {
+                    filtered_known += 1;
+                    return None;
+                }
 
-        // SECOND PASS: Only run GLiNER on sentences containing candidates that need NER verification
-        // This is MUCH faster than running on the entire book
-        let sentences_needing_ner: Vec<&str> = candidates
-            .iter()
-            .filter(|(_, _, _, needs_ner, _, _)| *needs_ner)
-            .flat_map(|(_, _, _, _, _, ner_contexts)| {
-                ner_contexts.iter().map(|c| c.trim_end_matches('.'))
+                // Filter out words too rare in this book (likely typos or OCR artifacts)
+                if count < options.min_count {
+                    filtered_min_count += 1;
+                    return None;
+                }
+
+                // Filter out parts of speech the user didn't ask for
+                if !options.allowed_pos.is_empty()
+                    && !self.candidate_pos(&original_forms, options).is_some_and(|p| options.allowed_pos.contains(&p))
+                {
+                    filtered_pos += 1;
+                    return None;
+                }
+
+                let contexts = self.pool_contexts(&occurrences, &sentences, &offsets);
+                Some(Candidate { stemmed, count, contexts, needs_ner, original_forms, ner_contexts, first_seen })
             })
+            .collect();
+        self.finish_segmentations();
+
+        eprintln!("Found {} hard word candidates after wordfreq filtering", candidates.len());
+
+        // SECOND PASS: Only run GLiNER on sentences containing candidates that need NER verification
+        // This is MUCH faster than running on the entire book
+        let sentences_needing_ner: Vec<&str> = candidates
+            .iter()
+            .filter(|candidate| candidate.needs_ner)
+            .flat_map(|candidate| candidate.ner_contexts.iter().map(|c| c.trim_end_matches('.')))
             .collect::<HashSet<_>>()
             .into_iter()
             .collect();
 
         let total_candidates = candidates.len();
-        let named_entities = if !sentences_needing_ner.is_empty() {
+        let named_entities = if options.skip_ner {
+            eprintln!("Skipping NER (fast mode)");
+            on_progress(AnalysisProgress {
+                stage: "Filtering names & places".to_string(),
+                progress: 80,
+                detail: Some("Skipped (fast mode)".to_string()),
+                sample_words: None,
+            });
+            Entities::new()
+        } else if !sentences_needing_ner.is_empty() {
             let total_ner_sentences = sentences_needing_ner.len();
             eprintln!("Running NER on {} sentences containing proper noun candidates...", total_ner_sentences);
 
             // Get sample rare words (sorted by frequency, rarest first) to show in progress
             let rare_word_samples: Vec<String> = {
                 let mut sorted_candidates: Vec<_> = candidates.iter()
-                    .map(|(_, _, _, _, forms, _)| {
-                        let form = forms.iter().next().cloned().unwrap_or_default();
+                    .map(|candidate| {
+                        let form = candidate.original_forms.iter().next().cloned().unwrap_or_default();
                         let freq = self.wordfreq.word_frequency(&form);
                         (form, freq)
                     })
@@ -473,88 +1290,32 @@ impl NlpPipeline {
                 detail: Some("No NER needed".to_string()),
                 sample_words: None,
             });
-            HashSet::new()
+            Entities::new()
         };
 
         eprintln!("Found {} named entities to filter", named_entities.len());
 
         // Track filtered words
-        let mut filtered_by_ner: Vec<String> = Vec::new();
+        let mut filtered_by_ner: Vec<FilteredEntity> = Vec::new();
 
         // Final filtering and scoring
         let mut scored_words: Vec<HardWord> = candidates
             .into_iter()
-            .filter_map(|(stemmed, count, contexts, needs_ner, original_forms, _)| {
+            .filter_map(|candidate| {
                 // If it was flagged as needing NER and any form is a named entity, skip it
-                if needs_ner {
-                    if named_entities.contains(&stemmed) {
-                        filtered_by_ner.push(stemmed.clone());
+                if candidate.needs_ner {
+                    if let Some(entity) = self.entity_match(&named_entities, &candidate.stemmed, &candidate.original_forms) {
+                        filtered_by_ner.push(entity);
                         return None;
                     }
-                    for original in &original_forms {
-                        if named_entities.contains(original) {
-                            filtered_by_ner.push(original.clone());
-                            return None;
-                        }
-                    }
                 }
 
-                // Pick the best original form for display:
-                // 1. Prefer forms that exist in wordfreq dictionary
-                // 2. Among those, prefer the shortest (likely base form)
-                // 3. Fall back to shortest original form
-                let mut best_form: Option<(String, f32)> = None;
-                for form in &original_forms {
-                    let freq = self.wordfreq.word_frequency(form);
-                    if freq > 0.0 {
-                        if best_form.is_none() || form.len() < best_form.as_ref().unwrap().0.len() {
-                            best_form = Some((form.clone(), freq));
-                        }
-                    }
-                }
-                let (display_word, freq) = best_form.unwrap_or_else(|| {
-                    // No form in dictionary, pick shortest
-                    let shortest = original_forms.iter()
-                        .min_by_key(|s| s.len())
-                        .cloned()
-                        .unwrap_or(stemmed.clone());
-                    let freq = self.wordfreq.word_frequency(&stemmed);
-                    (shortest, freq)
-                });
-
-                // Clean up contexts: remove &nbsp; and highlight the word
-                let clean_contexts: Vec<String> = contexts.iter()
-                    .map(|ctx| {
-                        ctx.replace("&nbsp;", " ")
-                           .replace('\u{00A0}', " ") // non-breaking space
-                           .split_whitespace()
-                           .collect::<Vec<_>>()
-                           .join(" ")
-                    })
-                    .collect();
-
-                // Collect variants (other forms found)
-                let mut variants: Vec<String> = original_forms.into_iter()
-                    .filter(|f| f != &display_word)
-                    .collect();
-                variants.sort();
-
-                Some(HardWord {
-                    word: display_word,
-                    frequency_score: freq as f64,
-                    contexts: clean_contexts,
-                    count,
-                    variants,
-                })
+                Some(self.hard_word(candidate, options))
             })
             .collect();
+        sort_filtered(&mut filtered_by_ner);
 
-        // Sort by frequency (ascending = rarest first)
-        scored_words.sort_by(|a, b| {
-            a.frequency_score
-                .partial_cmp(&b.frequency_score)
-                .unwrap_or(std::cmp::Ordering::Equal)
-        });
+        sort_words(&mut scored_words, options.order);
 
         on_progress(AnalysisProgress {
             stage: "Complete".to_string(),
@@ -569,22 +1330,76 @@ impl NlpPipeline {
             total_candidates,
             filtered_by_ner,
             hard_words_count: scored_words.len(),
+            filtered_known,
+            filtered_stopwords,
+            filtered_min_count,
+            min_count: options.min_count,
+            filtered_pos,
+            excluded_verse_words,
+            ner_skipped: options.skip_ner,
+            readability: readability::score(&sentences),
         };
 
         (scored_words, stats)
     }
 
+    /// Build the result entry for a candidate: display the form chosen by
+    /// `display_form`, listing the others as variants
+    fn hard_word(&self, candidate: Candidate, options: &AnalysisOptions) -> HardWord {
+        let Candidate { stemmed, count, contexts, original_forms, first_seen: (first_seen, first_offset), .. } = candidate;
+        let (display_word, freq) = match display_form(&original_forms, |form| self.wordfreq.word_frequency(form)) {
+            Some((form, freq)) if freq > 0.0 => (form, freq),
+            Some((form, _)) => (form, self.wordfreq.word_frequency(&stemmed)),
+            None => (stemmed.clone(), self.wordfreq.word_frequency(&stemmed)),
+        };
+        // Show a derived adverb as its adjective, which stays listed if only the adverb occurs
+        let (display_word, freq) = match self.derived_root(&display_word, options) {
+            Some(root) => {
+                let root_freq = self.wordfreq.word_frequency(&root);
+                (root, root_freq)
+            }
+            None => (display_word, freq),
+        };
+        let pos = (self.language == Language::English).then(|| pos::tag(&display_word).label().to_string());
+
+        let mut variants: Vec<String> = original_forms.into_iter().filter(|f| f != &display_word).collect();
+        variants.sort();
+        let definition = self.dictionary.as_ref().and_then(|d| d.lookup(&display_word));
+        let first_chapter = epub::segment_at(&self.segments, first_offset).map(|segment| segment.chapter_index);
+
+        HardWord {
+            definition,
+            word: display_word,
+            frequency_score: freq as f64,
+            contexts: contexts.into_ranked(),
+            count,
+            variants,
+            band: frequency_band(freq as f64),
+            first_seen,
+            pos,
+            first_occurrence: epub::locate(&self.segments, first_offset),
+            first_chapter,
+            first_chapter_title: first_chapter.and_then(|index| self.chapter_titles.get(index).cloned()),
+        }
+    }
+
     /// Analyze text with cancellation support
-    /// Returns None if cancelled, Some((words, stats)) otherwise
-    pub fn analyze_with_cancel<F>(
+    /// Returns None if cancelled or, before anything else runs, if NER resources are missing;
+    /// Some((words, stats)) otherwise.
+    /// `on_partial` receives batches of hard words as soon as they are certain to be
+    /// in the result: non-proper-noun words before NER runs, the rest after it.
+    pub fn analyze_with_cancel<F, P>(
         &self,
         text: &str,
         frequency_threshold: f32,
+        options: &AnalysisOptions,
         cancel_token: &Arc<AtomicBool>,
         mut on_progress: F,
+        mut on_partial: P,
     ) -> Option<(Vec<HardWord>, AnalysisStats)>
     where
         F: FnMut(AnalysisProgress),
+        P: FnMut(Vec<HardWord>),
     {
         // Check cancellation at key points
         macro_rules! check_cancel {
@@ -596,11 +1411,16 @@ impl NlpPipeline {
             };
         }
 
-        let sentences: Vec<&str> = text
-            .split(|c| c == '.' || c == '!' || c == '?')
-            .map(|s| s.trim())
-            .filter(|s| !s.is_empty())
-            .collect();
+        // HARD FAIL: Resources must be available before analysis, so no partial results
+        // stream out first (fast mode is meant for users without downloaded resources,
+        // so it falls back to heuristics)
+        if !options.skip_ner && !Self::resources_available(self.language) {
+            eprintln!("ERROR: GLiNER model or SymSpell dictionary missing. Download resources first.");
+            return None;
+        }
+
+        let sentences = segment_sentences(strip_gutenberg_boilerplate(text));
+        let offsets = sentence_offsets(text, &sentences);
 
         check_cancel!();
 
@@ -613,37 +1433,45 @@ impl NlpPipeline {
 
         eprintln!("Processing {} sentences...", sentences.len());
 
-        let mut word_data: HashMap<String, (usize, Vec<String>, bool, HashSet<String>, HashSet<String>)> = HashMap::new();
+        let mut word_data: HashMap<String, WordStats> = HashMap::new();
 
+        let mut excluded_verse_words = 0usize;
+        let mut stopword_forms = HashSet::new();
         for (i, sentence) in sentences.iter().enumerate() {
             // Check cancellation every 100 sentences
             if i % 100 == 0 {
                 check_cancel!();
             }
+            if options.skip_verse && self.in_verse(offsets[i]) {
+                excluded_verse_words += sentence.unicode_words().count();
+                continue;
+            }
+            let fits_context = self.context_policy.fits(sentence);
 
-            let words: Vec<&str> = sentence.unicode_words().collect();
-            for word in &words {
+            for (start, word) in sentence.unicode_word_indices() {
                 if word.len() < 3 || word.chars().any(|c| c.is_numeric()) {
                     continue;
                 }
-                let lower = word.to_lowercase();
-                let stemmed = self.stem(&lower);
+                let (lower, stemmed) = self.group_key(word, options);
+                if self.is_bundled_stopword(&lower) {
+                    stopword_forms.insert(lower);
+                    continue;
+                }
                 let is_proper = is_likely_proper_noun(word, sentence);
 
                 let entry = word_data.entry(stemmed.clone()).or_insert_with(|| {
-                    (0, Vec::new(), false, HashSet::new(), HashSet::new())
+                    WordStats::new((i, offsets[i] + sentence[..start].chars().count()))
                 });
-                entry.0 += 1;
+                entry.count += 1;
                 if is_proper {
-                    entry.2 = true;
+                    entry.needs_ner = true;
                 }
-                entry.3.insert(lower);
-                let context = sentence.to_string();
-                if entry.1.len() < 10 {
-                    entry.1.push(context.clone());
+                entry.original_forms.insert(lower);
+                if fits_context {
+                    entry.occurrences.push((i, start, start + word.len()));
                 }
                 if is_proper {
-                    entry.4.insert(context);
+                    entry.ner_contexts.insert(sentence.to_string());
                 }
             }
         }
@@ -651,9 +1479,14 @@ impl NlpPipeline {
         check_cancel!();
 
         // Filter candidates using wordfreq
-        let candidates: Vec<(String, usize, Vec<String>, bool, HashSet<String>, HashSet<String>)> = word_data
+        let mut filtered_known = 0usize;
+        let mut filtered_stopwords = self.count_stopwords(&stopword_forms, frequency_threshold, options);
+        let mut filtered_min_count = 0usize;
+        let mut filtered_pos = 0usize;
+        let candidates: Vec<Candidate> = word_data
             .into_iter()
-            .filter_map(|(stemmed, (count, contexts, needs_ner, original_forms, ner_contexts))| {
+            .filter_map(|(stemmed, stats)| {
+                let WordStats { count, occurrences, needs_ner, original_forms, ner_contexts, first_seen } = stats;
                 for form in &original_forms {
                     if self.is_malformed_word(form) {
                         return None;
@@ -670,13 +1503,37 @@ impl NlpPipeline {
                     }
                 }
 
-                if freq > frequency_threshold || freq == 0.0 {
+                if !options.keeps_frequency(freq, frequency_threshold) {
                     return None;
                 }
 
-                Some((stemmed, count, contexts, needs_ner, original_forms, ner_contexts))
+                if self.is_custom_stopword(&stemmed, &original_forms) {
+                    filtered_stopwords += 1;
+                    return None;
+                }
+
+                if self.is_known(&stemmed, &original_forms) {
+                    filtered_known += 1;
+                    return None;
+                }
+
+                if count < options.min_count {
+                    filtered_min_count += 1;
+                    return None;
+                }
+
+                if !options.allowed_pos.is_empty()
+                    && !self.candidate_pos(&original_forms, options).is_some_and(|p| options.allowed_pos.contains(&p))
+                {
+                    filtered_pos += 1;
+                    return None;
+                }
+
+                let contexts = self.pool_contexts(&occurrences, &sentences, &offsets);
+                Some(Candidate { stemmed, count, contexts, needs_ner, original_forms, ner_contexts, first_seen })
             })
             .collect();
+        self.finish_segmentations();
 
         check_cancel!();
 
@@ -689,38 +1546,41 @@ impl NlpPipeline {
             sample_words: None,
         });
 
+        // Words that never need NER are final already: stream them before the slow NER pass
+        let (proper_noun_candidates, ready): (Vec<_>, Vec<_>) =
+            candidates.into_iter().partition(|candidate| candidate.needs_ner);
+        let mut scored_words: Vec<HardWord> = ready
+            .into_iter()
+            .map(|candidate| self.hard_word(candidate, options))
+            .collect();
+        for batch in scored_words.chunks(PARTIAL_BATCH_SIZE) {
+            on_partial(batch.to_vec());
+        }
+
         // NER filtering with progress updates
-        let proper_noun_candidates: Vec<&(String, usize, Vec<String>, bool, HashSet<String>, HashSet<String>)> =
-            candidates.iter().filter(|(_, _, _, needs_ner, _, _)| *needs_ner).collect();
 
         // Collect all candidate words that need NER checking (for display)
         let candidate_words: Vec<String> = proper_noun_candidates
             .iter()
-            .flat_map(|(_, _, _, _, forms, _)| forms.iter().cloned())
+            .flat_map(|candidate| candidate.original_forms.iter().cloned())
             .collect::<HashSet<_>>()
             .into_iter()
             .collect();
 
         check_cancel!();
 
-        // HARD FAIL: Resources must be available before analysis
-        // Check SymSpell (required for malformed word detection)
-        if !resources::is_symspell_available() {
-            eprintln!("ERROR: SymSpell dictionary required but not available. Download resources first.");
-            return None;
-        }
-
-        // If there are proper noun candidates, we MUST have GLiNER available
-        // Fail hard if model is missing - don't silently skip NER
-        if !proper_noun_candidates.is_empty() && !Self::is_gliner_available() {
-            eprintln!("ERROR: GLiNER model required but not available. Download resources first.");
-            return None;
-        }
-
-        let named_entities = if !proper_noun_candidates.is_empty() {
+        let named_entities = if options.skip_ner {
+            on_progress(AnalysisProgress {
+                stage: "Filtering names & places".to_string(),
+                progress: 80,
+                detail: Some("Skipped (fast mode)".to_string()),
+                sample_words: None,
+            });
+            Entities::new()
+        } else if !proper_noun_candidates.is_empty() {
             let sentences_to_check: Vec<&str> = proper_noun_candidates
                 .iter()
-                .flat_map(|(_, _, _, _, _, ner_contexts)| ner_contexts.iter().map(|s| s.as_str()))
+                .flat_map(|candidate| candidate.ner_contexts.iter().map(|s| s.as_str()))
                 .collect::<HashSet<_>>()
                 .into_iter()
                 .collect();
@@ -743,7 +1603,7 @@ impl NlpPipeline {
                 sample_words: Some(all_candidates.clone()),
             });
 
-            let mut entities = HashSet::new();
+            let mut entities = Entities::new();
             if let Some(gliner) = self.get_gliner() {
                 // Emit progress to confirm model is loaded
                 on_progress(AnalysisProgress {
@@ -758,87 +1618,26 @@ impl NlpPipeline {
                     .filter(|s| !s.is_empty() && s.len() < 512)
                     .collect();
 
-                let total_chunks = chunks.len();
-                let batch_size = 64;
-                let mut processed = 0;
-                let mut total_infer_ms: u128 = 0;
-
-                for (batch_idx, batch) in chunks.chunks(batch_size).enumerate() {
-                    check_cancel!();
-
-                    let pre_progress = 45 + (processed * 35 / total_chunks.max(1)) as u8;
-                    on_progress(AnalysisProgress {
-                        stage: "Filtering names & places".to_string(),
-                        progress: pre_progress.min(79),
-                        detail: Some(format!("Processing batch {}/{}...", batch_idx + 1, (total_chunks + batch_size - 1) / batch_size)),
-                        sample_words: None,
-                    });
-
-                    let input = match TextInput::from_str(
-                        batch,
-                        &["person", "location", "organization", "country", "city"],
-                    ) {
-                        Ok(input) => input,
-                        Err(_) => {
-                            processed += batch.len();
-                            continue;
-                        }
-                    };
-
-                    let infer_start = std::time::Instant::now();
-                    if let Ok(output) = gliner.inference(input) {
-                        for spans in output.spans.iter() {
-                            for span in spans.iter() {
-                                let entity_text = span.text().to_lowercase();
-                                entities.insert(entity_text.clone());
-                                for word in entity_text.split_whitespace() {
-                                    entities.insert(word.to_string());
-                                }
-                            }
-                        }
-                    }
-                    let infer_elapsed = infer_start.elapsed();
-                    total_infer_ms += infer_elapsed.as_millis();
-                    if batch_idx == 0 {
-                        eprintln!(
-                            "GLiNER first batch inference: {} ms for {} sentences (batch size {})",
-                            infer_elapsed.as_millis(),
-                            batch.len(),
-                            batch_size
-                        );
-                    }
-
-                    processed += batch.len();
-
+                entities = self.infer_entities(gliner, &chunks, cancel_token, |processed, total, found, _| {
                     // Update progress (45% to 80% during NER inference)
-                    let ner_progress = 45 + (processed * 35 / total_chunks.max(1)) as u8;
+                    let ner_progress = 45 + (processed * 35 / total.max(1)) as u8;
 
                     // Show current classification state of ALL candidate words
                     let word_states: Vec<SampleWord> = candidate_words
                         .iter()
                         .map(|w| SampleWord {
                             word: w.clone(),
-                            is_entity: entities.contains(w),
+                            is_entity: found.contains_key(w),
                         })
                         .collect();
 
                     on_progress(AnalysisProgress {
                         stage: "Filtering names & places".to_string(),
                         progress: ner_progress.min(80),
-                        detail: Some(format!("{}/{} sentences, {} names found", processed, total_chunks, entities.len())),
+                        detail: Some(format!("{}/{} sentences, {} names found", processed, total, found.len())),
                         sample_words: Some(word_states),
                     });
-                }
-
-                if total_chunks > 0 {
-                    let avg_ms = total_infer_ms as f64 / total_chunks as f64;
-                    eprintln!(
-                        "GLiNER total inference time: {} ms for {} sentences (avg {:.2} ms/sentence)",
-                        total_infer_ms,
-                        total_chunks,
-                        avg_ms
-                    );
-                }
+                });
             }
             entities
         } else {
@@ -849,75 +1648,30 @@ impl NlpPipeline {
                 detail: Some("No proper noun candidates".to_string()),
                 sample_words: None,
             });
-            HashSet::new()
+            Entities::new()
         };
 
         check_cancel!();
 
-        let mut filtered_by_ner: Vec<String> = Vec::new();
+        let mut filtered_by_ner: Vec<FilteredEntity> = Vec::new();
 
-        let mut scored_words: Vec<HardWord> = candidates
+        let survivors: Vec<HardWord> = proper_noun_candidates
             .into_iter()
-            .filter_map(|(stemmed, count, contexts, needs_ner, original_forms, _)| {
-                if needs_ner {
-                    if named_entities.contains(&stemmed) {
-                        filtered_by_ner.push(stemmed.clone());
-                        return None;
-                    }
-                    for original in &original_forms {
-                        if named_entities.contains(original) {
-                            filtered_by_ner.push(original.clone());
-                            return None;
-                        }
-                    }
-                }
-
-                let mut best_form: Option<(String, f32)> = None;
-                for form in &original_forms {
-                    let freq = self.wordfreq.word_frequency(form);
-                    if freq > 0.0 {
-                        if best_form.is_none() || form.len() < best_form.as_ref().unwrap().0.len() {
-                            best_form = Some((form.clone(), freq));
-                        }
-                    }
+            .filter_map(|candidate| {
+                if let Some(entity) = self.entity_match(&named_entities, &candidate.stemmed, &candidate.original_forms) {
+                    filtered_by_ner.push(entity);
+                    return None;
                 }
-                let (display_word, freq) = best_form.unwrap_or_else(|| {
-                    let shortest = original_forms.iter()
-                        .min_by_key(|s| s.len())
-                        .cloned()
-                        .unwrap_or(stemmed.clone());
-                    let freq = self.wordfreq.word_frequency(&stemmed);
-                    (shortest, freq)
-                });
-
-                let clean_contexts: Vec<String> = contexts.iter()
-                    .map(|ctx| {
-                        ctx.replace("&nbsp;", " ")
-                           .replace('\u{00A0}', " ")
-                           .split_whitespace()
-                           .collect::<Vec<_>>()
-                           .join(" ")
-                    })
-                    .collect();
-
-                let mut variants: Vec<String> = original_forms.into_iter()
-                    .filter(|f| f != &display_word)
-                    .collect();
-                variants.sort();
-
-                Some(HardWord {
-                    word: display_word,
-                    frequency_score: freq as f64,
-                    contexts: clean_contexts,
-                    count,
-                    variants,
-                })
+                Some(self.hard_word(candidate, options))
             })
             .collect();
+        sort_filtered(&mut filtered_by_ner);
+        for batch in survivors.chunks(PARTIAL_BATCH_SIZE) {
+            on_partial(batch.to_vec());
+        }
+        scored_words.extend(survivors);
 
-        scored_words.sort_by(|a, b| {
-            a.frequency_score.partial_cmp(&b.frequency_score).unwrap_or(std::cmp::Ordering::Equal)
-        });
+        sort_words(&mut scored_words, options.order);
 
         on_progress(AnalysisProgress {
             stage: "Complete".to_string(),
@@ -930,16 +1684,59 @@ impl NlpPipeline {
             total_candidates,
             filtered_by_ner,
             hard_words_count: scored_words.len(),
+            filtered_known,
+            filtered_stopwords,
+            filtered_min_count,
+            min_count: options.min_count,
+            filtered_pos,
+            excluded_verse_words,
+            ner_skipped: options.skip_ner,
+            readability: readability::score(&sentences),
         };
 
         Some((scored_words, stats))
     }
 }
 
-fn get_symspell() -> Option<&'static SymSpell<AsciiStringStrategy>> {
-    SYMSPELL.get_or_init(|| {
+/// Run one GLiNER batch, returning entity texts (lowercased) and their individual
+/// words, each with the entity's label
+fn infer_batch(gliner: &GLiNER<SpanMode>, batch: &[&str]) -> Vec<(String, String)> {
+    let input = match TextInput::from_str(batch, &NER_LABELS) {
+        Ok(input) => input,
+        Err(e) => {
+            eprintln!("Failed to create GLiNER input: {}", e);
+            return Vec::new();
+        }
+    };
+
+    let mut found = Vec::new();
+    match gliner.inference(input) {
+        Ok(output) => {
+            for spans in output.spans.iter() {
+                for span in spans.iter() {
+                    let entity_text = span.text().to_lowercase();
+                    let label = span.class().to_string();
+                    // Also add individual words from multi-word entities
+                    let words: Vec<(String, String)> =
+                        entity_text.split_whitespace().map(|w| (w.to_string(), label.clone())).collect();
+                    found.push((entity_text, label));
+                    found.extend(words);
+                }
+            }
+        }
+        Err(e) => eprintln!("GLiNER inference error: {}", e),
+    }
+    found
+}
+
+fn get_symspell(language: Language) -> Option<&'static SymSpell<UnicodeStringStrategy>> {
+    let cell = match language {
+        Language::English => &SYMSPELL_EN,
+        Language::German => &SYMSPELL_DE,
+    };
+    cell.get_or_init(|| {
         // Use the resource system to ensure dictionary is available
-        let dict_path = match resources::ensure_symspell_dict(|_status| {
+        let dict_path = match resources::ensure_symspell_dict(language.symspell_dict(), &AtomicBool::new(false), |_status| {
             // Silent download for symspell (it's small)
         }) {
             Ok(path) => path,
@@ -949,23 +1746,300 @@ fn get_symspell() -> Option<&'static SymSpell<AsciiStringStrategy>> {
             }
         };
 
-        let mut symspell: SymSpell<AsciiStringStrategy> = SymSpell::default();
+        load_symspell(&dict_path)
+    }).as_ref()
+}
 
-        let loaded = symspell.load_dictionary(
-            dict_path.to_str().unwrap_or(""),
-            0,
-            1,
-            " ",
-        );
+/// Load a SymSpell frequency dictionary ("word count" per line). Words are kept
+/// as they are, so umlauts and ß survive (the ASCII strategy transliterates them).
+fn load_symspell(dict_path: &std::path::Path) -> Option<SymSpell<UnicodeStringStrategy>> {
+    let mut symspell: SymSpell<UnicodeStringStrategy> = SymSpell::default();
+
+    let loaded = symspell.load_dictionary(
+        dict_path.to_str().unwrap_or(""),
+        0,
+        1,
+        " ",
+    );
+
+    if !loaded {
+        eprintln!("Failed to load SymSpell dictionary from {:?}", dict_path);
+        return None;
+    }
 
-        if !loaded {
-            eprintln!("Failed to load SymSpell dictionary from {:?}", dict_path);
+    eprintln!("SymSpell dictionary loaded successfully");
+    Some(symspell)
+}
+
+/// Abbreviations whose trailing period does not end a sentence (lowercase, without the final dot)
+const ABBREVIATIONS: &[&str] = &[
+    "mr", "mrs", "ms", "dr", "jr", "sr", "prof", "rev", "hon", "gen", "col", "capt", "lt",
+    "sgt", "maj", "gov", "sen", "rep", "messrs", "mme", "mlle", "vs", "e.g", "i.e", "cf", "viz",
+    "vol", "ch", "chap", "pp", "fig", "approx", "dept", "inc", "ltd", "mt", "ave",
+];
+
+/// Abbreviations that are also ordinary words, so only taken as such before a number ("No. 5")
+const NUMBER_ABBREVIATIONS: &[&str] = &["no"];
+
+/// Abbreviations that are also ordinary words, so only taken as such before a name ("St. Paul")
+const NAME_ABBREVIATIONS: &[&str] = &["st"];
+
+/// Trim the Project Gutenberg license header and footer, keeping only the text
+/// between the "*** START OF ... PROJECT GUTENBERG EBOOK ... ***" and
+/// "*** END OF ... ***" marker lines. Text without the markers is returned unchanged.
+pub fn strip_gutenberg_boilerplate(text: &str) -> &str {
+    // ASCII uppercasing keeps byte offsets identical to `text`
+    let upper = text.to_ascii_uppercase();
+
+    // A marker ends at its closing "***", or at the line break if that is missing.
+    // Extracted text may already have its newlines collapsed into spaces.
+    let find_marker = |marker: &str, from: usize| {
+        let mut search = from;
+        while let Some(i) = upper[search..].find(marker) {
+            let start = search + i;
+            let after = start + marker.len();
+            let line_end = upper[after..].find('\n').map(|e| after + e).unwrap_or(upper.len());
+            let end = upper[after..line_end].find("***").map(|e| after + e + 3).unwrap_or(line_end);
+            if upper[start..end].contains("PROJECT GUTENBERG") {
+                return Some((start, end));
+            }
+            search = end;
+        }
+        None
+    };
+
+    let start = find_marker("*** START OF", 0);
+    let end = find_marker("*** END OF", start.map(|(_, e)| e).unwrap_or(0));
+    if start.is_none() && end.is_none() {
+        return text;
+    }
+
+    let body_start = start.map(|(_, e)| e).unwrap_or(0);
+    let body_end = end.map(|(s, _)| s).unwrap_or(text.len());
+    text[body_start..body_end].trim()
+}
+
+/// Split text into sentences, keeping terminal punctuation.
+///
+/// Unlike a naive split on `.`/`!`/`?`, periods after common abbreviations ("Mr.", "e.g."),
+/// initials ("J. K.", "U.S.A."), and inside numbers ("3.14") do not end a sentence, and
+/// an ellipsis only ends one when the next word is capitalized. A paragraph break
+/// ("\n\n") always ends a sentence, so a heading never runs into the text after it.
+pub fn segment_sentences(text: &str) -> Vec<&str> {
+    text.split("\n\n").flat_map(segment_paragraph).collect()
+}
+
+/// Character offset within `text` of each of `sentences`, which must be slices of
+/// `text` in order (as `segment_sentences` returns them). Counted in one pass.
+fn sentence_offsets(text: &str, sentences: &[&str]) -> Vec<usize> {
+    let mut offsets = Vec::with_capacity(sentences.len());
+    let (mut byte, mut chars) = (0, 0);
+    for sentence in sentences {
+        let start = sentence.as_ptr() as usize - text.as_ptr() as usize;
+        chars += text[byte..start].chars().count();
+        byte = start;
+        offsets.push(chars);
+    }
+    offsets
+}
+
+fn segment_paragraph(text: &str) -> Vec<&str> {
+    let chars: Vec<(usize, char)> = text.char_indices().collect();
+    let mut sentences = Vec::new();
+    let mut start = 0;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let (_, c) = chars[i];
+        if !matches!(c, '.' | '!' | '?' | '…') {
+            i += 1;
+            continue;
+        }
+
+        // Decimal point: digit on both sides
+        if c == '.'
+            && i > 0
+            && chars[i - 1].1.is_ascii_digit()
+            && chars.get(i + 1).map(|(_, n)| n.is_ascii_digit()).unwrap_or(false)
+        {
+            i += 1;
+            continue;
+        }
+
+        // Token before the punctuation (back to the previous whitespace)
+        let token_start = chars[..i]
+            .iter()
+            .rposition(|(_, ch)| ch.is_whitespace())
+            .map(|p| p + 1)
+            .unwrap_or(0);
+        let token: String = chars[token_start..i]
+            .iter()
+            .map(|(_, ch)| *ch)
+            .filter(|ch| ch.is_alphanumeric() || *ch == '.')
+            .collect();
+
+        // Consume the whole terminator run, including closing quotes/brackets
+        let is_ellipsis = c == '…' || chars.get(i + 1).map(|(_, n)| *n == '.').unwrap_or(false);
+        let mut end = i + 1;
+        while end < chars.len() && matches!(chars[end].1, '.' | '!' | '?' | '…') {
+            end += 1;
+        }
+        while end < chars.len() && matches!(chars[end].1, '"' | '\'' | '”' | '’' | ')' | ']') {
+            end += 1;
+        }
+
+        // A terminator must be followed by whitespace (or end of text)
+        if end < chars.len() && !chars[end].1.is_whitespace() {
+            i = end;
+            continue;
+        }
+
+        let next_word_char = chars[end..]
+            .iter()
+            .map(|(_, ch)| *ch)
+            .find(|ch| !ch.is_whitespace() && !matches!(ch, '"' | '\'' | '“' | '‘' | '(' | '['));
+
+        if c == '.' && !is_ellipsis {
+            let lower = token.to_lowercase();
+            let is_abbreviation = ABBREVIATIONS.contains(&lower.as_str())
+                || NUMBER_ABBREVIATIONS.contains(&lower.as_str())
+                    && next_word_char.is_some_and(|ch| ch.is_ascii_digit())
+                || NAME_ABBREVIATIONS.contains(&lower.as_str()) && next_word_char.is_some_and(char::is_uppercase);
+            // Initials: "J." or dotted acronyms like "U.S.A."
+            let is_initial = !token.is_empty()
+                && token
+                    .split('.')
+                    .all(|part| part.chars().count() == 1 && part.chars().all(|ch| ch.is_uppercase()));
+            if is_abbreviation || is_initial {
+                i = end;
+                continue;
+            }
+        }
+
+        // Ellipses and other terminators followed by a lowercase word continue the sentence
+        if next_word_char.map(|ch| ch.is_lowercase()).unwrap_or(false) {
+            i = end;
+            continue;
+        }
+
+        let end_byte = chars.get(end).map(|(b, _)| *b).unwrap_or(text.len());
+        let sentence = text[start..end_byte].trim();
+        if !sentence.is_empty() {
+            sentences.push(sentence);
+        }
+        start = end_byte;
+        i = end;
+    }
+
+    let rest = text[start..].trim();
+    if !rest.is_empty() {
+        sentences.push(rest);
+    }
+
+    sentences
+}
+
+/// The key an analysis groups a word's forms under (see `NlpPipeline::word_key`),
+/// so hard words from different analyses can be matched: "gaiety" and "Gaieties" share one
+pub fn lemma_key(word: &str) -> String {
+    let form: String = word.nfc().collect::<String>().to_lowercase();
+    let stemmer = LEMMA_STEMMER.get_or_init(|| Stemmer::create(Algorithm::English));
+    stemmer.stem(&fold_diacritics(&form)).to_string()
+}
+
+/// The bundled stopwords of `language`, NFC-normalized and lowercase like word forms
+fn bundled_stopwords(language: Language) -> HashSet<String> {
+    resources::parse_word_list(language.stopwords()).map(|w| w.nfc().collect()).collect()
+}
+
+/// Lowercase known words and add their grouping keys, so "gaiety" also excludes
+/// "gaieties" and "naïve" also excludes "naive"
+fn normalize_known_words<'a>(stemmer: &Stemmer, words: impl IntoIterator<Item = &'a String>) -> HashSet<String> {
+    let mut known = HashSet::new();
+    for word in words {
+        let lower = word.trim().nfc().collect::<String>().to_lowercase();
+        if lower.is_empty() {
+            continue;
+        }
+        known.insert(stemmer.stem(&fold_diacritics(&lower)).to_string());
+        known.insert(lower);
+    }
+    known
+}
+
+/// Sort hard words in the requested order (ties broken by rarity)
+fn sort_words(words: &mut [HardWord], order: WordOrder) {
+    let by_rarity = |a: &HardWord, b: &HardWord| a.frequency_score.total_cmp(&b.frequency_score);
+    match order {
+        WordOrder::Rarity => words.sort_by(by_rarity),
+        WordOrder::FirstSeen => words.sort_by(|a, b| a.first_seen.cmp(&b.first_seen).then_with(|| by_rarity(a, b))),
+        // Words without a known chapter go last
+        WordOrder::Chapter => words.sort_by(|a, b| {
+            let chapter = |w: &HardWord| w.first_chapter.unwrap_or(usize::MAX);
+            chapter(a).cmp(&chapter(b)).then_with(|| by_rarity(a, b))
+        }),
+    }
+}
+
+/// Sort entities filtered by NER by word, keeping one entry per word
+fn sort_filtered(filtered: &mut Vec<FilteredEntity>) {
+    filtered.sort();
+    filtered.dedup_by(|a, b| a.word == b.word);
+}
+
+/// Whether the segmentation of an unknown word looks like words run together:
+/// at least `min_segments` parts, each a real word of 3+ letters
+fn is_run_together(segments: &[&str], min_segments: usize, is_word: impl Fn(&str) -> bool) -> bool {
+    segments.len() >= min_segments && segments.iter().all(|s| s.len() >= 3 && is_word(s))
+}
+
+/// Split off a common word glued onto the end of a real word ("believethat"),
+/// returning (prefix, suffix)
+fn glued_suffix<'a, 'w>(
+    word: &'w str,
+    suffixes: &[&'a str],
+    is_word: impl Fn(&str) -> bool,
+) -> Option<(&'w str, &'a str)> {
+    suffixes.iter().find_map(|suffix| {
+        if !word.ends_with(suffix) || word.len() <= suffix.len() + 4 {
             return None;
         }
+        let prefix = &word[..word.len() - suffix.len()];
+        (prefix.len() >= 4 && is_word(prefix)).then_some((prefix, *suffix))
+    })
+}
 
-        eprintln!("SymSpell dictionary loaded successfully");
-        Some(symspell)
-    }).as_ref()
+/// Remove combining marks after canonical decomposition ("naïve" -> "naive")
+fn fold_diacritics(word: &str) -> String {
+    word.nfd().filter(|c| !is_combining_mark(*c)).collect()
+}
+
+/// Pick the form to display for a group of spellings, with its frequency.
+/// Forms found in wordfreq win; among those the shortest (ignoring diacritics) wins,
+/// then the more frequent, so the accented spelling is shown when it is the one
+/// wordfreq knows. Without any known form, the shortest wins and ties go to the
+/// accented spelling.
+fn display_form(forms: &HashSet<String>, frequency: impl Fn(&str) -> f32) -> Option<(String, f32)> {
+    let ranked = |form: &String| {
+        let folded = fold_diacritics(form);
+        let accented = folded != *form;
+        (folded.chars().count(), accented, frequency(form))
+    };
+
+    forms
+        .iter()
+        .map(|form| (form, ranked(form)))
+        .min_by(|(a, (a_len, a_accented, a_freq)), (b, (b_len, b_accented, b_freq))| {
+            let a_known = *a_freq > 0.0;
+            let b_known = *b_freq > 0.0;
+            b_known
+                .cmp(&a_known)
+                .then(a_len.cmp(b_len))
+                .then(b_freq.total_cmp(a_freq))
+                .then(b_accented.cmp(a_accented))
+                .then(a.cmp(b))
+        })
+        .map(|(form, (_, _, freq))| (form.clone(), freq))
 }
 
 fn is_likely_proper_noun(word: &str, sentence: &str) -> bool {
@@ -993,3 +2067,711 @@ impl Default for NlpPipeline {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Options for the short texts analyzed here: no NER model, and words missing
+    /// from the frequency list kept
+    fn quick_options() -> AnalysisOptions {
+        AnalysisOptions { skip_ner: true, include_unlisted: true, ..Default::default() }
+    }
+
+    /// Analyze `text` without cancelling or watching progress
+    fn analyze_text(
+        pipeline: &NlpPipeline,
+        text: &str,
+        threshold: f32,
+        options: &AnalysisOptions,
+    ) -> (Vec<HardWord>, AnalysisStats) {
+        pipeline
+            .analyze_with_cancel(text, threshold, options, &Arc::new(AtomicBool::new(false)), |_| {}, |_| {})
+            .unwrap()
+    }
+
+    #[test]
+    fn test_frequency_band_boundaries() {
+        assert_eq!(frequency_band(5e-3), FrequencyBand::A1);
+        assert_eq!(frequency_band(1e-4), FrequencyBand::A1);
+        assert_eq!(frequency_band(9.9e-5), FrequencyBand::A2);
+        assert_eq!(frequency_band(3e-5), FrequencyBand::A2);
+        assert_eq!(frequency_band(1e-5), FrequencyBand::B1);
+        assert_eq!(frequency_band(3e-6), FrequencyBand::B2);
+        assert_eq!(frequency_band(1e-6), FrequencyBand::C1);
+        assert_eq!(frequency_band(9.9e-7), FrequencyBand::C2);
+        assert_eq!(frequency_band(1e-9), FrequencyBand::C2);
+    }
+
+    #[test]
+    fn test_frequency_band_unlisted() {
+        assert_eq!(frequency_band(0.0), FrequencyBand::Unlisted);
+        assert_eq!(serde_json::to_string(&FrequencyBand::Unlisted).unwrap(), "\"unlisted\"");
+        assert_eq!(serde_json::to_string(&FrequencyBand::B2).unwrap(), "\"B2\"");
+    }
+
+    #[test]
+    fn test_normalize_known_words() {
+        let stemmer = Stemmer::create(Algorithm::English);
+        let words = vec!["Gaiety".to_string(), "  ".to_string(), "obfuscate".to_string()];
+        let known = normalize_known_words(&stemmer, &words);
+
+        assert!(known.contains("gaiety"));
+        assert!(known.contains(&stemmer.stem("gaieties").to_string()));
+        assert!(known.contains("obfuscate"));
+        assert!(!known.contains(""));
+    }
+
+    #[test]
+    fn test_filtered_entities_respect_entity_allowlist() {
+        let pipeline = NlpPipeline::new().with_entity_allowlist(&["Grace".to_string()]);
+        let entities: Entities = [("grace", "person"), ("paris", "city"), ("pari", "city")]
+            .into_iter()
+            .map(|(w, l)| (w.to_string(), l.to_string()))
+            .collect();
+        let forms = |fs: &[&str]| fs.iter().map(|f| f.to_string()).collect::<HashSet<_>>();
+
+        assert_eq!(pipeline.entity_match(&entities, "grace", &forms(&["grace", "graces"])), None);
+        // A stem match is reported under the word's real form
+        assert_eq!(
+            pipeline.entity_match(&entities, "pari", &forms(&["paris"])),
+            Some(FilteredEntity { word: "paris".to_string(), label: "city".to_string() })
+        );
+        assert_eq!(pipeline.entity_match(&entities, "obfusc", &forms(&["obfuscate"])), None);
+
+        let entity = |w: &str, l: &str| FilteredEntity { word: w.to_string(), label: l.to_string() };
+        let mut filtered = vec![entity("paris", "city"), entity("adam", "person"), entity("paris", "location")];
+        sort_filtered(&mut filtered);
+        assert_eq!(filtered, vec![entity("adam", "person"), entity("paris", "city")]);
+    }
+
+    #[test]
+    fn test_segment_sentences_basic() {
+        assert_eq!(
+            segment_sentences("It rained. Was it cold? Yes!  "),
+            vec!["It rained.", "Was it cold?", "Yes!"]
+        );
+    }
+
+    #[test]
+    fn test_segment_sentences_titles() {
+        assert_eq!(
+            segment_sentences("Mr. Darcy bowed to Mrs. Bennet. Dr. Jones laughed."),
+            vec!["Mr. Darcy bowed to Mrs. Bennet.", "Dr. Jones laughed."]
+        );
+    }
+
+    #[test]
+    fn test_segment_sentences_latin_abbreviations() {
+        assert_eq!(
+            segment_sentences("Bring fruit, e.g. apples or pears. Also i.e. nothing else. Done."),
+            vec!["Bring fruit, e.g. apples or pears.", "Also i.e. nothing else.", "Done."]
+        );
+    }
+
+    #[test]
+    fn test_segment_sentences_ambiguous_abbreviations() {
+        // "no" and "st" end sentences unless a number or a name follows
+        assert_eq!(
+            segment_sentences("She said no. Then she left. See No. 5 at St. Paul's. It was no. 3 in line."),
+            vec!["She said no.", "Then she left.", "See No. 5 at St. Paul's.", "It was no. 3 in line."]
+        );
+    }
+
+    #[test]
+    fn test_segment_sentences_decimals_and_initials() {
+        assert_eq!(
+            segment_sentences("Pi is 3.14 or so. J. R. R. Tolkien lived in the U.K. for years."),
+            vec!["Pi is 3.14 or so.", "J. R. R. Tolkien lived in the U.K. for years."]
+        );
+    }
+
+    #[test]
+    fn test_segment_sentences_ellipses() {
+        assert_eq!(
+            segment_sentences("Well... perhaps not. He paused... Then he left… Quietly."),
+            vec!["Well... perhaps not.", "He paused...", "Then he left…", "Quietly."]
+        );
+    }
+
+    #[test]
+    fn test_segment_sentences_closing_quotes() {
+        assert_eq!(
+            segment_sentences("\"Stop!\" she cried. \"Why?\" he asked."),
+            vec!["\"Stop!\" she cried.", "\"Why?\" he asked."]
+        );
+    }
+
+    #[test]
+    fn test_segment_sentences_paragraph_breaks() {
+        // A heading without punctuation, and a paragraph ending in an abbreviation
+        assert_eq!(
+            segment_sentences("Chapter One\n\nThey met at 5 p.m.\n\nIt rained. Then it stopped"),
+            vec!["Chapter One", "They met at 5 p.m.", "It rained.", "Then it stopped"]
+        );
+    }
+
+    /// Header and footer excerpts from Project Gutenberg's eBook #1342
+    const GUTENBERG_HEADER: &str = "The Project Gutenberg eBook of Pride and Prejudice
+
+This ebook is for the use of anyone anywhere in the United States and
+most other parts of the world at no cost and with almost no restrictions
+whatsoever. You may copy it, give it away or re-use it under the terms
+of the Project Gutenberg License included with this ebook or online
+at www.gutenberg.org.
+
+Title: Pride and Prejudice
+
+Author: Jane Austen
+
+*** START OF THE PROJECT GUTENBERG EBOOK PRIDE AND PREJUDICE ***
+";
+    const GUTENBERG_FOOTER: &str = "
+*** END OF THE PROJECT GUTENBERG EBOOK PRIDE AND PREJUDICE ***
+
+Updated editions will replace the previous one—the old editions will
+be renamed.
+";
+    const BODY: &str = "It is a truth universally acknowledged, that a single man in possession\nof a good fortune, must be in want of a wife.";
+
+    #[test]
+    fn test_context_offsets() {
+        let sentence = "She  was\u{00A0}quite  obsequious\u{00A0}today.";
+        let start = sentence.find("obsequious").unwrap();
+        let ctx = Context::new(sentence, start, start + "obsequious".len(), 0);
+        assert_eq!(ctx.sentence, "She was quite obsequious today.");
+        let matched: String = ctx.sentence.chars().skip(ctx.match_start).take(ctx.match_end - ctx.match_start).collect();
+        assert_eq!(matched, "obsequious");
+
+        // Offsets count characters, not bytes
+        let sentence = "  Café owners were sanguine.";
+        let start = sentence.find("sanguine").unwrap();
+        let ctx = Context::new(sentence, start, start + "sanguine".len(), 0);
+        assert_eq!(ctx.sentence, "Café owners were sanguine.");
+        assert_eq!((ctx.match_start, ctx.match_end), (17, 25));
+    }
+
+    fn context(sentence: &str, word: &str) -> Context {
+        let start = sentence.find(word).unwrap();
+        Context::new(sentence, start, start + word.len(), 0)
+    }
+
+    #[test]
+    fn test_score_context_prefers_useful_sentences() {
+        let good = context("The clerk's manner was so obsequious that even the duke grew uneasy.", "obsequious");
+        let short = context("Obsequious, too.", "Obsequious");
+        let at_start = context("Obsequious clerks filled every corner of the crowded counting house.", "Obsequious");
+        let truncated = context("and the clerk, obsequious as ever, bowed and bowed and", "obsequious");
+        let repetitive = context("Bowed and bowed and bowed and bowed, so obsequious, bowed and bowed.", "obsequious");
+
+        let score = score_context(&good);
+        assert!(score > score_context(&short));
+        assert!(score > score_context(&at_start));
+        assert!(score > score_context(&truncated));
+        assert!(score > score_context(&repetitive));
+    }
+
+    #[test]
+    fn test_context_pool_keeps_best() {
+        let mut pool = ContextPool::new(ContextPolicy { max_count: 2, ..Default::default() });
+        pool.offer(context("Sanguine, he said.", "Sanguine"));
+        pool.offer(context("Despite the storm, the captain remained sanguine about reaching port by dawn.", "sanguine"));
+        pool.offer(context("She was, as always, quietly sanguine about the outcome of the trial.", "sanguine"));
+        // Duplicate sentences are ignored
+        pool.offer(context("She was, as always, quietly sanguine about the outcome of the trial.", "sanguine"));
+
+        let ranked = pool.into_ranked();
+        assert_eq!(ranked.len(), 2);
+        assert!(ranked.iter().all(|c| c.sentence != "Sanguine, he said."));
+        assert!(score_context(&ranked[0]) >= score_context(&ranked[1]));
+
+        // A poor context is still kept when it is the only one
+        let mut pool = ContextPool::new(ContextPolicy { max_count: 3, ..Default::default() });
+        pool.offer(context("Sanguine!", "Sanguine"));
+        assert_eq!(pool.into_ranked().len(), 1);
+    }
+
+    #[test]
+    fn test_context_policy_bounds_sentence_length() {
+        let long = format!("The captain remained sanguine{}.", ", and the sea was calm".repeat(25));
+        let text = format!("Sanguine, he said. {} She was, as always, quietly sanguine about the trial.", long);
+        let pipeline = NlpPipeline::new().with_context_policy(ContextPolicy::default());
+        let options = AnalysisOptions { skip_ner: true, ..Default::default() };
+        let (words, _) = analyze_text(&pipeline, &text, 1.0, &options);
+        let sanguine = words.iter().find(|w| w.word == "sanguine").unwrap();
+        let sentences: Vec<&str> = sanguine.contexts.iter().map(|c| c.sentence.as_str()).collect();
+        assert_eq!(sentences, vec!["She was, as always, quietly sanguine about the trial."]);
+
+        // A word seen only in sentences outside the bounds has no context
+        let (words, _) = pipeline.analyze("Sanguine, he said.", 1.0, |_| {});
+        assert!(words.iter().find(|w| w.word == "sanguine").unwrap().contexts.is_empty());
+
+        let odd = ContextPolicy { min_len: 50, max_len: 10, max_count: 0 }.normalized();
+        assert_eq!((odd.max_len, odd.max_count), (50, 1));
+    }
+
+    #[test]
+    fn test_partial_batches_match_final_result() {
+        let pipeline = NlpPipeline::new();
+        let text = "The perspicacious clerk wrote sesquipedalian memoranda. \
+                    His obstreperous colleague ignored the memorandum entirely.";
+        let options = AnalysisOptions { skip_ner: true, ..Default::default() };
+        let mut streamed: Vec<String> = Vec::new();
+
+        let (words, _) = pipeline
+            .analyze_with_cancel(text, 1.0, &options, &Arc::new(AtomicBool::new(false)), |_| {}, |batch| {
+                streamed.extend(batch.into_iter().map(|w| w.word))
+            })
+            .unwrap();
+
+        let mut finished: Vec<String> = words.into_iter().map(|w| w.word).collect();
+        streamed.sort();
+        finished.sort();
+        assert_eq!(streamed, finished);
+    }
+
+    #[test]
+    fn test_missing_resources_fail_before_partial_results() {
+        let pipeline = NlpPipeline::new();
+        if NlpPipeline::resources_available(pipeline.language) {
+            eprintln!("Skipping: NER resources are downloaded");
+            return;
+        }
+        let text = "The perspicacious clerk wrote sesquipedalian memoranda.";
+        let mut streamed = 0;
+
+        let result = pipeline.analyze_with_cancel(
+            text,
+            1.0,
+            &AnalysisOptions::default(),
+            &Arc::new(AtomicBool::new(false)),
+            |_| {},
+            |batch| streamed += batch.len(),
+        );
+        assert!(result.is_none());
+        assert_eq!(streamed, 0);
+    }
+
+    #[test]
+    fn test_ner_batch_size_follows_memory() {
+        const GIB: u64 = 1 << 30;
+        assert_eq!(ner_batch_size_for(None, 8), NER_BATCH_SIZE);
+        assert_eq!(ner_batch_size_for(Some(2 * GIB), 8), 16);
+        assert_eq!(ner_batch_size_for(Some(6 * GIB), 8), 32);
+        assert_eq!(ner_batch_size_for(Some(12 * GIB), 8), NER_BATCH_SIZE);
+        assert_eq!(ner_batch_size_for(Some(32 * GIB), 16), 128);
+        // Plenty of memory but few cores to feed big batches
+        assert_eq!(ner_batch_size_for(Some(32 * GIB), 4), NER_BATCH_SIZE);
+    }
+
+    #[test]
+    fn test_frequency_floor_and_unlisted_words() {
+        let default = AnalysisOptions::default();
+        assert!(default.keeps_frequency(1e-7, 5e-5));
+        assert!(!default.keeps_frequency(1e-4, 5e-5));
+        assert!(!default.keeps_frequency(0.0, 5e-5));
+
+        let options = AnalysisOptions {
+            min_frequency: 1e-6,
+            include_unlisted: true,
+            ..Default::default()
+        };
+        assert!(options.keeps_frequency(1e-6, 5e-5));
+        assert!(options.keeps_frequency(2e-5, 5e-5));
+        assert!(!options.keeps_frequency(1e-7, 5e-5));
+        // Unlisted words bypass the floor when they are asked for
+        assert!(options.keeps_frequency(0.0, 5e-5));
+    }
+
+    #[test]
+    fn test_sort_words_by_first_seen() {
+        let word = |w: &str, frequency_score: f64, first_seen: usize| HardWord {
+            word: w.to_string(),
+            frequency_score,
+            contexts: Vec::new(),
+            count: 1,
+            variants: Vec::new(),
+            band: frequency_band(frequency_score),
+            definition: None,
+            first_seen,
+            pos: None,
+            first_occurrence: None,
+            first_chapter: None,
+            first_chapter_title: None,
+        };
+        let mut words = vec![word("sanguine", 1e-6, 40), word("obsequious", 2e-7, 12), word("amiable", 9e-7, 12)];
+
+        sort_words(&mut words, WordOrder::Rarity);
+        let order: Vec<&str> = words.iter().map(|w| w.word.as_str()).collect();
+        assert_eq!(order, ["obsequious", "amiable", "sanguine"]);
+
+        words.reverse();
+        sort_words(&mut words, WordOrder::FirstSeen);
+        let order: Vec<&str> = words.iter().map(|w| w.word.as_str()).collect();
+        assert_eq!(order, ["obsequious", "amiable", "sanguine"]);
+        assert_eq!(words[2].first_seen, 40);
+
+        // Chapters in order, rarest first within each, unknown chapters last
+        for (word, chapter) in words.iter_mut().zip([Some(3), None, Some(1)]) {
+            word.first_chapter = chapter;
+        }
+        words.push(HardWord { word: "lugubrious".to_string(), first_chapter: Some(3), ..word("", 5e-8, 50) });
+        sort_words(&mut words, WordOrder::Chapter);
+        let order: Vec<&str> = words.iter().map(|w| w.word.as_str()).collect();
+        assert_eq!(order, ["sanguine", "lugubrious", "obsequious", "amiable"]);
+    }
+
+    #[test]
+    fn test_min_count_drops_words_seen_once() {
+        let pipeline = NlpPipeline::new();
+        let text = "The perspicacious clerk wrote sesquipedalian memoranda. \
+                    His perspicacious colleague was obstreperous.";
+        let run = |min_count| {
+            let options = AnalysisOptions { skip_ner: true, min_count, ..Default::default() };
+            analyze_text(&pipeline, text, 1.0, &options)
+        };
+
+        let (all, all_stats) = run(1);
+        let (repeated, stats) = run(2);
+        assert_eq!(all_stats.filtered_min_count, 0);
+        assert!(repeated.iter().all(|w| w.count >= 2));
+        assert_eq!(repeated.len() + stats.filtered_min_count, all.len());
+    }
+
+    #[test]
+    fn test_collapse_derived_merges_adverbs_into_their_adjective() {
+        // "wearily" and "weary" stem apart ("wearili", "weari"), so only collapsing joins them
+        let wordfreq = WordFreq::new([("the", 1000.0), ("weary", 1.0), ("wearily", 0.5)]);
+        let pipeline = NlpPipeline { wordfreq: Box::leak(Box::new(wordfreq)), ..NlpPipeline::new() };
+        let text = "The weary traveller sat down. She smiled wearily. A weary sigh followed.";
+        let run = |collapse_derived| {
+            let options = AnalysisOptions { collapse_derived, ..quick_options() };
+            let (words, _) = analyze_text(&pipeline, text, 1.0, &options);
+            words.into_iter().filter(|w| w.word.starts_with("wear")).collect::<Vec<_>>()
+        };
+
+        assert_eq!(run(false).len(), 2);
+        let collapsed = run(true);
+        assert_eq!(collapsed.len(), 1);
+        assert_eq!(collapsed[0].word, "weary");
+        assert_eq!(collapsed[0].count, 3);
+        assert_eq!(collapsed[0].variants, ["wearily"]);
+    }
+
+    #[test]
+    fn test_group_families_merges_derivational_relatives() {
+        let pipeline = NlpPipeline::new();
+        let text = "Her audacity was famous. The audacious plan worked. \
+                    She spoke audaciously beneath the stairs.";
+        let run = |group_families| {
+            let options = AnalysisOptions { group_families, ..quick_options() };
+            let (words, _) = analyze_text(&pipeline, text, 1.0, &options);
+            words
+        };
+
+        let family = |words: &[HardWord]| {
+            words
+                .iter()
+                .filter(|w| w.word.starts_with("audac") || w.variants.iter().any(|v| v.starts_with("audac")))
+                .cloned()
+                .collect::<Vec<_>>()
+        };
+        assert!(family(&run(false)).len() > 1);
+
+        let words = run(true);
+        let grouped = family(&words);
+        assert_eq!(grouped.len(), 1);
+        let word = &grouped[0];
+        assert_eq!(word.count, 3);
+        let mut members = word.variants.clone();
+        members.push(word.word.clone());
+        members.sort();
+        assert_eq!(members, ["audacious", "audaciously", "audacity"]);
+        // A lookalike keeps its own entry
+        assert!(words.iter().any(|w| w.word == "beneath" && w.variants.is_empty()));
+    }
+
+    #[test]
+    fn test_first_seen_is_earliest_sentence() {
+        let pipeline = NlpPipeline::new();
+        let text = "Nothing rare here. The perspicacious clerk smiled. Another perspicacious remark.";
+        let options = AnalysisOptions { order: WordOrder::FirstSeen, ..quick_options() };
+        let (words, _) = analyze_text(&pipeline, text, 1.0, &options);
+
+        // Every word in the text is a candidate at threshold 1.0, in reading order
+        assert!(words.windows(2).all(|w| w[0].first_seen <= w[1].first_seen));
+        let word = words.iter().find(|w| w.word == "perspicacious").expect("perspicacious is a candidate");
+        assert_eq!(word.first_seen, 1);
+    }
+
+    #[test]
+    fn test_first_occurrence_maps_to_spine_item() {
+        let chapters = [
+            epub::Chapter::fixture(2, "", "Nothing rare here."),
+            epub::Chapter::fixture(5, "", "Café talk.\n\nThe perspicacious clerk smiled."),
+        ];
+        let (text, segments) = epub::join_chapters(&chapters, false);
+
+        let pipeline = NlpPipeline::new()
+            .with_segments(segments)
+            .with_chapter_titles(vec!["Prologue".to_string(), "The Clerk".to_string()]);
+        let options = quick_options();
+        let (words, _) = analyze_text(&pipeline, &text, 1.0, &options);
+
+        let word = words.iter().find(|w| w.word == "perspicacious").unwrap();
+        // "Café talk.\n\n" is 12 characters, "The " 4 more
+        assert_eq!(word.first_occurrence, Some(Location { spine_index: 5, char_offset: 16 }));
+        assert_eq!(word.contexts[0].sentence_offset, "Nothing rare here.\n\nCafé talk.\n\n".chars().count());
+        assert_eq!((word.first_chapter, word.first_chapter_title.as_deref()), (Some(1), Some("The Clerk")));
+    }
+
+    #[test]
+    fn test_skip_verse() {
+        let text = "The perspicacious clerk smiled.\n\nThe lugubrious moon doth wane.";
+        let verse = text.find("The lugubrious").unwrap()..text.len();
+        let pipeline = NlpPipeline::new().with_verse(vec![verse]);
+        let run = |skip_verse: bool| {
+            let options = AnalysisOptions { skip_verse, ..quick_options() };
+            let (words, stats) = analyze_text(&pipeline, text, 1.0, &options);
+            (words.into_iter().map(|w| w.word).collect::<Vec<_>>(), stats)
+        };
+
+        let (words, stats) = run(false);
+        assert!(words.contains(&"lugubrious".to_string()));
+        assert_eq!(stats.excluded_verse_words, 0);
+
+        let (words, stats) = run(true);
+        assert!(words.contains(&"perspicacious".to_string()));
+        assert!(!words.contains(&"lugubrious".to_string()));
+        assert_eq!(stats.excluded_verse_words, 5);
+    }
+
+    #[test]
+    fn test_stopwords_filtered_after_frequency() {
+        let pipeline = NlpPipeline::new().with_stopwords(&["quixotic".to_string()]);
+        let options = quick_options();
+        let text = "Thou hast a perspicacious mind. Hmm, the quixotic clerk smiled quixotically.";
+        let (words, stats) = analyze_text(&pipeline, text, 1.0, &options);
+
+        let found: Vec<&str> = words.iter().map(|w| w.word.as_str()).collect();
+        assert!(found.contains(&"perspicacious"));
+        for stopword in ["thou", "hast", "hmm", "quixotic", "quixotically"] {
+            assert!(!found.contains(&stopword), "{} should be a stopword", stopword);
+        }
+        // thou, hast, hmm and quixotic(ally), which share a stem
+        assert_eq!(stats.filtered_stopwords, 4);
+    }
+
+    #[test]
+    fn test_bundled_stopwords_match_exact_forms() {
+        let pipeline = NlpPipeline::new();
+        for stopword in ["hast", "er", "thou"] {
+            assert!(pipeline.is_bundled_stopword(stopword), "{}", stopword);
+        }
+        // Real words sharing a stopword's stem
+        for word in ["haste", "hasted", "erred", "thousand"] {
+            assert!(!pipeline.is_bundled_stopword(word), "{}", word);
+        }
+
+        let german = NlpPipeline::new().with_language(Language::German);
+        assert!(german.is_bundled_stopword("äh") && german.is_bundled_stopword("selbigen"));
+        assert!(!german.is_bundled_stopword("hast") && !german.is_bundled_stopword("achtung"));
+    }
+
+    #[test]
+    fn test_german_uses_german_frequencies_and_keeps_umlauts() {
+        let german = NlpPipeline::new().with_language(Language::German);
+        assert!(german.wordfreq.word_frequency("wehmut") > 0.0);
+        assert_eq!(NlpPipeline::new().wordfreq.word_frequency("wehmut"), 0.0);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("de.txt");
+        std::fs::write(&path, "große 5000\nhaus 8000\nübermut 300\n").unwrap();
+        let symspell = load_symspell(&path).unwrap();
+        assert_eq!(symspell.word_segmentation("großehaus", 2).segmented_string, "große haus");
+        assert_eq!(symspell.word_segmentation("übermut", 2).segmented_string, "übermut");
+    }
+
+    #[test]
+    fn test_hard_word_prefers_shortest_form() {
+        let pipeline = NlpPipeline::new();
+        let forms: HashSet<String> = ["memoranda", "memorandum", "memorandums"].iter().map(|s| s.to_string()).collect();
+        let candidate = Candidate {
+            stemmed: "memorandum".to_string(),
+            count: 3,
+            contexts: ContextPool::new(ContextPolicy::default()),
+            needs_ner: false,
+            original_forms: forms,
+            ner_contexts: HashSet::new(),
+            first_seen: (0, 0),
+        };
+        let word = pipeline.hard_word(candidate, &AnalysisOptions::default());
+
+        assert_eq!(word.word, "memoranda");
+        assert_eq!(word.variants, vec!["memorandum", "memorandums"]);
+        assert_eq!(word.count, 3);
+    }
+
+    #[test]
+    fn test_segmentation_cache() {
+        let pipeline = NlpPipeline::new();
+        let calls = std::cell::Cell::new(0);
+        let segment = || {
+            calls.set(calls.get() + 1);
+            Some("believe that".to_string())
+        };
+
+        // Repeated words (e.g. "believethat" and "believethat's") segment once
+        assert_eq!(pipeline.cached_segmentation("believethat", segment), Some("believe that".to_string()));
+        assert_eq!(pipeline.cached_segmentation("believethat", segment), Some("believe that".to_string()));
+        assert_eq!(pipeline.cached_segmentation("marmalades", || None), None);
+        assert_eq!(pipeline.cached_segmentation("marmalades", segment), None);
+        assert_eq!(calls.get(), 1);
+        assert_eq!(pipeline.segmentations.lock().unwrap().hits, 2);
+
+        // Cleared after each analysis's filtering pass
+        pipeline.finish_segmentations();
+        assert!(pipeline.segmentations.lock().unwrap().results.is_empty());
+        pipeline.cached_segmentation("believethat", segment);
+        assert_eq!(calls.get(), 2);
+    }
+
+    #[test]
+    fn test_segmentations_per_analysis() {
+        // EPUB joins repeat across a book, with and without a trailing "'s"
+        let sentence = "I believethat's true, and I believethat still; the housekeeperwas late and thehousekeeper knew it.";
+        let text = vec![sentence; 20].join(" ");
+        let pipeline = NlpPipeline::new();
+        let options = AnalysisOptions {
+            skip_ner: true,
+            ..AnalysisOptions::default()
+        };
+        analyze_text(&pipeline, &text, 0.00005, &options);
+
+        // Without the cache every malformed check of a long unknown form segments:
+        // one per form, so 4 here. With it, each checked word segments once.
+        let (computed, cached) = pipeline.segmentations.lock().unwrap().last_counts;
+        assert_eq!(computed + cached, 4);
+        assert_eq!(computed, 3);
+    }
+
+    #[test]
+    fn test_language_from_code() {
+        assert_eq!(Language::from_code("deu"), Language::German);
+        assert_eq!(Language::from_code("ger"), Language::German);
+        assert_eq!(Language::from_code("DE"), Language::German);
+        assert_eq!(Language::from_code("eng"), Language::English);
+        assert_eq!(Language::from_code("fra"), Language::English);
+    }
+
+    #[test]
+    fn test_run_together_english() {
+        let words = ["believe", "that", "neighbor", "hood"];
+        let is_word = |s: &str| words.contains(&s);
+        let min = Language::English.min_run_together_segments();
+
+        assert!(is_run_together(&["believe", "that"], min, is_word));
+        // Too-short parts are segmentation noise, not glued words
+        assert!(!is_run_together(&["neighbor", "ho", "od"], min, is_word));
+        assert_eq!(
+            glued_suffix("believethat", Language::English.glued_suffixes(), is_word),
+            Some(("believe", "that"))
+        );
+    }
+
+    #[test]
+    fn test_long_german_compounds_are_valid() {
+        let words = ["geschwindigkeits", "begrenzung", "lebens", "versicherung", "kinder", "garten", "the"];
+        let is_word = |s: &str| words.contains(&s);
+        let min = Language::German.min_run_together_segments();
+
+        // Two-part compounds are ordinary German words
+        assert!(!is_run_together(&["geschwindigkeits", "begrenzung"], min, is_word));
+        assert!(!is_run_together(&["lebens", "versicherung"], min, is_word));
+        // The English rule would have dropped them
+        assert!(is_run_together(&["lebens", "versicherung"], Language::English.min_run_together_segments(), is_word));
+        // Three or more parts are still treated as run together
+        assert!(is_run_together(&["kinder", "garten", "lebens"], min, is_word));
+
+        // English glue words are not applied to German text
+        assert_eq!(glued_suffix("kindergartenthe", Language::German.glued_suffixes(), |_| true), None);
+    }
+
+    fn forms(words: &[&str]) -> HashSet<String> {
+        words.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_word_key_ignores_case_and_diacritics() {
+        let pipeline = NlpPipeline::new();
+        let (form, key) = pipeline.word_key("Naïve");
+        assert_eq!(form, "naïve");
+        assert_eq!(key, pipeline.word_key("naive").1);
+        assert_eq!(pipeline.word_key("CAFÉ").1, pipeline.word_key("cafe").1);
+
+        // Decomposed input ("e" + combining acute) is stored in NFC
+        let (form, key) = pipeline.word_key("cafe\u{301}");
+        assert_eq!(form, "café");
+        assert_eq!(key, pipeline.word_key("Café").1);
+    }
+
+    #[test]
+    fn test_display_form_prefers_known_spelling() {
+        // Only the accented spelling is in wordfreq
+        let freq = |w: &str| if w == "naïve" { 1e-5 } else { 0.0 };
+        assert_eq!(display_form(&forms(&["naive", "naïve"]), freq), Some(("naïve".to_string(), 1e-5)));
+
+        // Only the plain spelling is in wordfreq
+        let freq = |w: &str| if w == "cafe" { 2e-5 } else { 0.0 };
+        assert_eq!(display_form(&forms(&["café", "cafe"]), freq), Some(("cafe".to_string(), 2e-5)));
+
+        // Both known: the more frequent spelling is canonical
+        let freq = |w: &str| match w {
+            "façade" => 3e-6,
+            "facade" => 1e-6,
+            _ => 0.0,
+        };
+        assert_eq!(display_form(&forms(&["facade", "façade"]), freq).unwrap().0, "façade");
+
+        // Neither known: the accented spelling wins the tie
+        assert_eq!(display_form(&forms(&["naive", "naïve"]), |_| 0.0).unwrap().0, "naïve");
+        assert_eq!(display_form(&HashSet::new(), |_| 0.0), None);
+    }
+
+    #[test]
+    fn test_normalize_known_words_folds_diacritics() {
+        let stemmer = Stemmer::create(Algorithm::English);
+        let known = normalize_known_words(&stemmer, &["Naïve".to_string()]);
+        assert!(known.contains("naïve"));
+        assert!(known.contains(&stemmer.stem("naive").to_string()));
+    }
+
+    #[test]
+    fn test_strip_gutenberg_boilerplate() {
+        let text = format!("{GUTENBERG_HEADER}\n{BODY}\n{GUTENBERG_FOOTER}");
+        assert_eq!(strip_gutenberg_boilerplate(&text), BODY);
+
+        // Older releases say "THIS" instead of "THE"
+        let older = text.replace("START OF THE", "START OF THIS");
+        assert_eq!(strip_gutenberg_boilerplate(&older), BODY);
+    }
+
+    #[test]
+    fn test_strip_gutenberg_boilerplate_collapsed_whitespace() {
+        // Extracted text has line breaks collapsed into single spaces
+        let text = format!("{GUTENBERG_HEADER}{BODY}{GUTENBERG_FOOTER}")
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ");
+        let body = BODY.split_whitespace().collect::<Vec<_>>().join(" ");
+        assert_eq!(strip_gutenberg_boilerplate(&text), body);
+    }
+
+    #[test]
+    fn test_strip_gutenberg_boilerplate_without_markers() {
+        assert_eq!(strip_gutenberg_boilerplate(BODY), BODY);
+        // A stray "***" scene break is not a marker
+        let scene_break = "He left.\n\n*** START OF something else ***\n\nShe stayed.";
+        assert_eq!(strip_gutenberg_boilerplate(scene_break), scene_break);
+    }
+}
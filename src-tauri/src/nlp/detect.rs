@@ -0,0 +1,146 @@
+//! Guess a book's language from its metadata or, failing that, its text
+//!
+//! The text heuristic counts each language's most common function words
+//! ("the", "und", "les", ...) in the opening words of the book. Function words
+//! make up a large share of any prose, so a few thousand words are plenty.
+
+use serde::Serialize;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// How many words from the start of the text are examined
+const SAMPLE_WORDS: usize = 5000;
+
+/// Fewest stopword hits needed before the text says anything about its language
+const MIN_HITS: usize = 20;
+
+/// Below this confidence a guess shouldn't be acted on
+pub const CONFIDENT: f32 = 0.5;
+
+/// Frequent function words per language (two-letter code)
+const STOPWORDS: &[(&str, &[&str])] = &[
+    ("en", &[
+        "the", "and", "of", "to", "a", "in", "is", "that", "it", "was", "he", "for", "with", "his",
+        "as", "had", "you", "not", "be", "her", "she", "on", "at", "by", "which", "have", "this", "but",
+    ]),
+    ("de", &[
+        "der", "die", "und", "das", "ist", "nicht", "zu", "den", "mit", "sich", "ich", "er", "es", "ein",
+        "eine", "auf", "dem", "sie", "war", "von", "auch", "wie", "aber", "noch", "im", "wenn", "nach",
+    ]),
+    ("fr", &[
+        "le", "la", "les", "de", "et", "des", "un", "une", "est", "que", "qui", "dans", "pas", "il", "elle",
+        "du", "au", "pour", "ne", "sur", "se", "avec", "mais", "je", "me", "ma", "mes", "son", "sa", "ce",
+        "était", "avait", "nous",
+    ]),
+    ("es", &[
+        "el", "la", "los", "las", "y", "que", "de", "en", "un", "una", "es", "no", "se", "por", "con",
+        "para", "del", "lo", "su", "como", "pero", "más", "le", "al", "era", "muy", "yo", "ella",
+    ]),
+    ("it", &[
+        "il", "la", "di", "che", "e", "non", "un", "una", "per", "con", "del", "della", "gli", "si",
+        "le", "è", "ma", "lo", "sono", "come", "nel", "alla", "anche", "era", "io", "lui", "questo",
+    ]),
+    ("nl", &[
+        "de", "het", "een", "en", "van", "is", "niet", "dat", "ik", "op", "te", "zijn", "met", "voor",
+        "hij", "maar", "aan", "er", "ook", "als", "nog", "wat", "zij", "bij", "dan", "was", "om",
+    ]),
+];
+
+/// A language guess as a two-letter code, with how far it can be trusted
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LanguageGuess {
+    pub code: String,
+    /// 1.0 for declared metadata; for text, how clearly the best language beat
+    /// the runner-up (0.0 = a tie)
+    pub confidence: f32,
+}
+
+impl LanguageGuess {
+    pub fn is_confident(&self) -> bool {
+        self.confidence >= CONFIDENT
+    }
+}
+
+/// Guess the language of `text` from its function words. None when the text
+/// is too short or in none of the known languages.
+pub fn detect_language(text: &str) -> Option<LanguageGuess> {
+    let mut hits = vec![0usize; STOPWORDS.len()];
+    for word in text.unicode_words().take(SAMPLE_WORDS) {
+        let word = word.to_lowercase();
+        for (count, (_, words)) in hits.iter_mut().zip(STOPWORDS) {
+            if words.contains(&word.as_str()) {
+                *count += 1;
+            }
+        }
+    }
+
+    let mut ranked: Vec<(usize, &'static str)> = hits.into_iter().zip(STOPWORDS.iter().map(|(code, _)| *code)).collect();
+    ranked.sort_by_key(|(hits, _)| std::cmp::Reverse(*hits));
+    let (best, code) = ranked[0];
+    let runner_up = ranked[1].0;
+    if best < MIN_HITS {
+        return None;
+    }
+
+    Some(LanguageGuess {
+        code: code.to_string(),
+        confidence: 1.0 - runner_up as f32 / best as f32,
+    })
+}
+
+/// The book's declared language (a normalized code, see `epub::normalize_language`,
+/// which drops generic tags like "und") when it has one, else a guess from its text
+pub fn book_language(declared: Option<&str>, text: &str) -> Option<LanguageGuess> {
+    match declared {
+        Some(code) => Some(LanguageGuess {
+            code: code.to_string(),
+            confidence: 1.0,
+        }),
+        None => detect_language(text),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ENGLISH: &str = "It was the best of times, it was the worst of times, it was the age of wisdom, \
+        it was the age of foolishness, it was the epoch of belief, it was the epoch of incredulity, it was \
+        the season of Light, it was the season of Darkness, it was the spring of hope, it was the winter of \
+        despair, we had everything before us, we had nothing before us.";
+
+    const SPANISH: &str = "En un lugar de la Mancha, de cuyo nombre no quiero acordarme, no ha mucho tiempo \
+        que vivía un hidalgo de los de lanza en astillero, adarga antigua, rocín flaco y galgo corredor. Una \
+        olla de algo más vaca que carnero, salpicón las más noches, duelos y quebrantos los sábados, lentejas \
+        los viernes, algún palomino de añadidura los domingos, consumían las tres partes de su hacienda.";
+
+    const FRENCH: &str = "Longtemps, je me suis couché de bonne heure. Parfois, à peine ma bougie éteinte, \
+        mes yeux se fermaient si vite que je n'avais pas le temps de me dire: Je m'endors. Et, une demi-heure \
+        après, la pensée qu'il était temps de chercher le sommeil m'éveillait; je voulais poser le volume que \
+        je croyais avoir encore dans les mains et souffler ma lumière; je n'avais pas cessé en dormant de \
+        faire des réflexions sur ce que je venais de lire, mais ces réflexions avaient pris un tour un peu \
+        particulier; il me semblait que j'étais moi-même ce dont parlait l'ouvrage.";
+
+    #[test]
+    fn test_detects_english_spanish_and_french() {
+        for (text, code) in [(ENGLISH, "en"), (SPANISH, "es"), (FRENCH, "fr")] {
+            let guess = detect_language(text).unwrap();
+            assert_eq!(guess.code, code);
+            assert!(guess.is_confident(), "{} guessed with confidence {}", code, guess.confidence);
+        }
+    }
+
+    #[test]
+    fn test_short_text_is_undetermined() {
+        assert_eq!(detect_language("The Analytical Engine"), None);
+        assert_eq!(detect_language(""), None);
+    }
+
+    #[test]
+    fn test_declared_language_wins() {
+        let declared = book_language(Some("de"), ENGLISH).unwrap();
+        assert_eq!((declared.code.as_str(), declared.confidence), ("de", 1.0));
+        // Languages the heuristic doesn't know are still trusted when declared
+        assert_eq!(book_language(Some("ja"), ENGLISH).unwrap().code, "ja");
+        assert_eq!(book_language(None, FRENCH).unwrap().code, "fr");
+    }
+}
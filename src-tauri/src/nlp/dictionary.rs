@@ -0,0 +1,163 @@
+//! Offline word definitions from the dictionary downloaded by the resources system
+//!
+//! The dictionary is a JSON object of headword -> definition. Lookups try the
+//! word itself, then simple lemma guesses ("gaieties" -> "gaiety"), and return
+//! a short gloss: the first sense, cut to `MAX_GLOSS_CHARS`.
+
+use crate::resources;
+use std::collections::HashMap;
+use std::io::Read;
+use std::sync::{Arc, Mutex};
+
+/// Longest gloss returned by `Dictionary::lookup`
+const MAX_GLOSS_CHARS: usize = 200;
+
+/// Loaded on first use once the file has been downloaded
+static SHARED: Mutex<Option<Arc<Dictionary>>> = Mutex::new(None);
+
+pub struct Dictionary {
+    /// Lowercased headword -> full definition
+    entries: HashMap<String, String>,
+}
+
+impl Dictionary {
+    /// Parse the JSON dictionary. Headwords are matched case-insensitively.
+    pub fn from_reader(reader: impl Read) -> Result<Self, String> {
+        let raw: HashMap<String, String> = serde_json::from_reader(reader).map_err(|e| e.to_string())?;
+        let mut entries = HashMap::with_capacity(raw.len());
+        for (word, definition) in raw {
+            entries.entry(word.to_lowercase()).or_insert(definition);
+        }
+        Ok(Self { entries })
+    }
+
+    /// Short gloss for `word` or its lemma, or None if the dictionary has neither
+    pub fn lookup(&self, word: &str) -> Option<String> {
+        lemma_candidates(&word.trim().to_lowercase())
+            .iter()
+            .find_map(|candidate| self.entries.get(candidate))
+            .map(|definition| short_gloss(definition))
+            .filter(|gloss| !gloss.is_empty())
+    }
+}
+
+/// The downloaded dictionary, or None until it has been downloaded
+pub fn shared() -> Option<Arc<Dictionary>> {
+    let mut shared = SHARED.lock().unwrap();
+    if shared.is_none() && resources::is_dictionary_available() {
+        let path = resources::get_dictionary_path();
+        match std::fs::File::open(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|file| Dictionary::from_reader(std::io::BufReader::new(file)))
+        {
+            Ok(dictionary) => *shared = Some(Arc::new(dictionary)),
+            Err(e) => eprintln!("Failed to load dictionary from {:?}: {}", path, e),
+        }
+    }
+    shared.clone()
+}
+
+/// Definition of `word` from the shared dictionary
+pub fn lookup(word: &str) -> Option<String> {
+    shared()?.lookup(word)
+}
+
+/// The word followed by guesses at its lemma from common English inflections
+fn lemma_candidates(word: &str) -> Vec<String> {
+    let mut candidates = vec![word.to_string()];
+    let mut push = |stem: &str, ending: &str| {
+        if stem.chars().count() >= 2 {
+            candidates.push(format!("{}{}", stem, ending));
+        }
+    };
+
+    if let Some(stem) = word.strip_suffix("ies").or_else(|| word.strip_suffix("ied")) {
+        push(stem, "y");
+    }
+    if let Some(stem) = word.strip_suffix("es") {
+        push(stem, "");
+    }
+    if let Some(stem) = word.strip_suffix('s').filter(|s| !s.ends_with('s')) {
+        push(stem, "");
+    }
+    for suffix in ["ed", "ing"] {
+        if let Some(stem) = word.strip_suffix(suffix) {
+            push(stem, "");
+            push(stem, "e");
+            // Doubled final consonant: "stopped" -> "stop"
+            let mut chars = stem.chars().rev();
+            if let (Some(last), Some(before)) = (chars.next(), chars.next()) {
+                if last == before && !"aeiou".contains(last) {
+                    push(&stem[..stem.len() - last.len_utf8()], "");
+                }
+            }
+        }
+    }
+    if let Some(stem) = word.strip_suffix("ly") {
+        push(stem, "");
+    }
+    candidates
+}
+
+/// First sense of a definition, cut at a word boundary to `MAX_GLOSS_CHARS`
+fn short_gloss(definition: &str) -> String {
+    let definition = definition.trim();
+    let definition = definition.strip_prefix("1.").unwrap_or(definition).trim_start();
+    let first_sense = definition.split(" 2.").next().unwrap_or(definition).trim();
+
+    if first_sense.chars().count() <= MAX_GLOSS_CHARS {
+        return first_sense.to_string();
+    }
+    let cut: String = first_sense.chars().take(MAX_GLOSS_CHARS).collect();
+    let cut = cut.rsplit_once(' ').map(|(head, _)| head).unwrap_or(&cut);
+    format!("{}…", cut.trim_end_matches([',', ';', ':']))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dictionary() -> Dictionary {
+        let json = r#"{
+            "GAIETY": "The state of being gay; merriment.",
+            "Obfuscate": "To darken; to obscure.",
+            "STOP": "1. To close, as an aperture. 2. To arrest the progress of.",
+            "ABSTRUSE": "Remote from apprehension; difficult to be comprehended."
+        }"#;
+        Dictionary::from_reader(json.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn test_lookup_is_case_insensitive() {
+        let dictionary = dictionary();
+        assert_eq!(dictionary.lookup("Gaiety").as_deref(), Some("The state of being gay; merriment."));
+        assert_eq!(dictionary.lookup("obfuscate").as_deref(), Some("To darken; to obscure."));
+    }
+
+    #[test]
+    fn test_lookup_uses_lemma() {
+        let dictionary = dictionary();
+        assert_eq!(dictionary.lookup("gaieties").as_deref(), Some("The state of being gay; merriment."));
+        assert_eq!(dictionary.lookup("obfuscated").as_deref(), Some("To darken; to obscure."));
+        assert_eq!(dictionary.lookup("obfuscating").as_deref(), Some("To darken; to obscure."));
+        assert_eq!(dictionary.lookup("stopped").as_deref(), Some("To close, as an aperture."));
+        assert_eq!(
+            dictionary.lookup("abstrusely").as_deref(),
+            Some("Remote from apprehension; difficult to be comprehended.")
+        );
+    }
+
+    #[test]
+    fn test_missing_entry_is_none() {
+        assert_eq!(dictionary().lookup("quixotic"), None);
+        assert_eq!(dictionary().lookup(""), None);
+    }
+
+    #[test]
+    fn test_short_gloss_truncates_at_word_boundary() {
+        let long = "word ".repeat(60);
+        let gloss = short_gloss(&long);
+        assert!(gloss.ends_with("word…"));
+        assert!(gloss.chars().count() <= MAX_GLOSS_CHARS + 1);
+    }
+}
@@ -0,0 +1,88 @@
+//! Derivational word families for English hard words
+//!
+//! Stemming groups inflections ("benevolences" with "benevolence") and some
+//! derivations, but leaves relatives like "felicity"/"felicitous" or
+//! "audacity"/"audacious" apart. A few suffix rules applied to Snowball stems
+//! bring those together. Grouping this way is lossier than stemming, so it is
+//! only used when `AnalysisOptions::group_families` is set.
+
+/// Stem endings rewritten toward the family root, tried in order; the first match
+/// applies and the rules run again on the result. A rule only applies to words with
+/// one of its endings, the derivations it undoes, so "implicit" keeps its "cit"
+/// and "stimuli" its "i".
+const FAMILY_RULES: &[(&str, &str, &[&str])] = &[
+    // "ostentati" (ostentatious) -> "ostent" (ostentation)
+    ("ati", "", &["atious", "atiously"]),
+    // "ubiquit" (ubiquitous) -> "ubiqu" (ubiquity)
+    ("uit", "u", &["uitous", "uitously", "uity"]),
+    // "obstinac" (obstinacy) -> "obstin" (obstinate)
+    ("inac", "in", &["inacy"]),
+    ("imac", "im", &["imacy"]),
+    // "felicit" (felicitous) -> "felic" (felicity)
+    ("cit", "c", &["citous", "citously"]),
+    // "generous" -> "generos" (generosity)
+    ("ous", "os", &["ous", "ously"]),
+    // "audaci" (audacious) -> "audac" (audacity)
+    ("i", "", &["ious", "iously", "y"]),
+];
+
+/// Shortest root a rule may leave, so short stems like "studi" (study) don't
+/// collapse into unrelated words ("stud")
+const MIN_ROOT_LEN: usize = 5;
+
+/// The family root of `word` from its Snowball stem (see `FAMILY_RULES`); stems
+/// no rule applies to are their own root
+pub fn family_root(word: &str, stem: &str) -> String {
+    let mut root = stem.to_string();
+    while let Some(next) = FAMILY_RULES.iter().find_map(|(suffix, replacement, endings)| {
+        if !endings.iter().any(|ending| word.ends_with(ending)) {
+            return None;
+        }
+        let base = root.strip_suffix(suffix)?;
+        let next = format!("{}{}", base, replacement);
+        (next.chars().count() >= MIN_ROOT_LEN).then_some(next)
+    }) {
+        root = next;
+    }
+    root
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_stemmers::{Algorithm, Stemmer};
+
+    fn root(word: &str) -> String {
+        family_root(word, &Stemmer::create(Algorithm::English).stem(word))
+    }
+
+    #[test]
+    fn test_families_share_a_root() {
+        let families: &[&[&str]] = &[
+            &["benevolent", "benevolence", "benevolently"],
+            &["felicity", "felicitous", "felicitously"],
+            &["audacity", "audacious", "audaciously"],
+            &["generosity", "generous", "generously"],
+            &["ubiquity", "ubiquitous"],
+            &["gratuity", "gratuitous"],
+            &["obstinacy", "obstinate"],
+            &["ostentation", "ostentatious"],
+            &["melancholy", "melancholic"],
+        ];
+        for family in families {
+            let roots: Vec<String> = family.iter().map(|w| root(w)).collect();
+            assert!(roots.iter().all(|r| *r == roots[0]), "{:?} -> {:?}", family, roots);
+        }
+    }
+
+    #[test]
+    fn test_lookalikes_stay_apart() {
+        assert_ne!(root("beneath"), root("benevolent"));
+        assert_ne!(root("general"), root("generous"));
+        assert_ne!(root("study"), root("stud"));
+        assert_ne!(root("limit"), root("lime"));
+        assert_ne!(root("implicit"), root("implicate"));
+        assert_ne!(root("explicit"), root("explicate"));
+        assert_ne!(root("stimuli"), root("stimulate"));
+    }
+}
@@ -0,0 +1,119 @@
+//! Suffix-based part-of-speech guesses for English hard words
+//!
+//! Hard words are rare and usually derived ("felicitously", "obfuscation"),
+//! so their suffix says more about their part of speech than a tagger trained
+//! on common text would. Words with no telling suffix are taken to be nouns.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Pos {
+    Noun,
+    Verb,
+    Adjective,
+    Adverb,
+}
+
+impl Pos {
+    pub fn label(self) -> &'static str {
+        match self {
+            Pos::Noun => "noun",
+            Pos::Verb => "verb",
+            Pos::Adjective => "adjective",
+            Pos::Adverb => "adverb",
+        }
+    }
+}
+
+/// Adjectives that end in "-ly" and would otherwise be taken for adverbs
+const LY_ADJECTIVES: &[&str] = &[
+    "beastly", "burly", "comely", "costly", "courtly", "curly", "early", "elderly", "friendly", "ghastly",
+    "ghostly", "heavenly", "holy", "homely", "jolly", "kindly", "leisurely", "likely", "lively", "lonely",
+    "lovely", "lowly", "manly", "masterly", "miserly", "motherly", "orderly", "portly", "scholarly", "sickly",
+    "silly", "slovenly", "stately", "surly", "timely", "ugly", "ungainly", "unruly", "wily", "woolly", "worldly",
+];
+
+const ADJECTIVE_SUFFIXES: &[&str] = &["ous", "ful", "ive", "able", "ible", "ical", "ic", "ish", "less", "esque"];
+const VERB_SUFFIXES: &[&str] = &["ize", "ify", "ing", "ed"];
+
+/// Best guess at the part of speech of a lowercase English word
+pub fn tag(word: &str) -> Pos {
+    let long_enough = |suffix: &str| word.len() >= suffix.len() + 3;
+
+    if LY_ADJECTIVES.contains(&word) {
+        return Pos::Adjective;
+    }
+    if word.ends_with("ly") && long_enough("ly") {
+        return Pos::Adverb;
+    }
+    if ADJECTIVE_SUFFIXES.iter().any(|s| word.ends_with(s) && long_enough(s)) {
+        return Pos::Adjective;
+    }
+    if VERB_SUFFIXES.iter().any(|s| word.ends_with(s) && long_enough(s)) {
+        return Pos::Verb;
+    }
+    Pos::Noun
+}
+
+/// The adjective an "-ly" adverb derives from, if `is_word` accepts one of the
+/// guesses: "felicitously" -> "felicitous", "happily" -> "happy",
+/// "probably" -> "probable", "fully" -> "full", "basically" -> "basic"
+pub fn adverb_root(word: &str, is_word: impl Fn(&str) -> bool) -> Option<String> {
+    let stem = word.strip_suffix("ly")?;
+    let mut guesses = vec![stem.to_string()];
+    if let Some(base) = stem.strip_suffix('i') {
+        guesses.push(format!("{}y", base));
+    }
+    if stem.ends_with("ab") || stem.ends_with("ib") {
+        guesses.push(format!("{}le", stem));
+    }
+    if stem.ends_with('l') {
+        guesses.push(format!("{}l", stem));
+    }
+    if let Some(base) = stem.strip_suffix("al").filter(|b| b.ends_with("ic")) {
+        guesses.push(base.to_string());
+    }
+
+    guesses.into_iter().find(|guess| guess.chars().count() >= 3 && is_word(guess))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_word(word: &str) -> bool {
+        ["felicitous", "happy", "probable", "full", "basic", "whimsical", "terrible"].contains(&word)
+    }
+
+    #[test]
+    fn test_tag_by_suffix() {
+        assert_eq!(tag("felicitously"), Pos::Adverb);
+        assert_eq!(tag("felicitous"), Pos::Adjective);
+        assert_eq!(tag("obfuscating"), Pos::Verb);
+        assert_eq!(tag("mollify"), Pos::Verb);
+        assert_eq!(tag("obfuscation"), Pos::Noun);
+        // "-ly" adjectives and short words aren't adverbs
+        assert_eq!(tag("ungainly"), Pos::Adjective);
+        assert_eq!(tag("fly"), Pos::Noun);
+        assert_eq!(tag("king"), Pos::Noun);
+    }
+
+    #[test]
+    fn test_ly_adverbs_collapse_to_their_root() {
+        assert_eq!(adverb_root("felicitously", is_word).as_deref(), Some("felicitous"));
+        assert_eq!(adverb_root("happily", is_word).as_deref(), Some("happy"));
+        assert_eq!(adverb_root("probably", is_word).as_deref(), Some("probable"));
+        assert_eq!(adverb_root("terribly", is_word).as_deref(), Some("terrible"));
+        assert_eq!(adverb_root("fully", is_word).as_deref(), Some("full"));
+        assert_eq!(adverb_root("basically", is_word).as_deref(), Some("basic"));
+        // The "-al" adjective wins when it exists
+        assert_eq!(adverb_root("whimsically", is_word).as_deref(), Some("whimsical"));
+    }
+
+    #[test]
+    fn test_adverb_root_needs_a_real_word() {
+        assert_eq!(adverb_root("ungainly", is_word), None);
+        assert_eq!(adverb_root("felicitous", is_word), None);
+    }
+}
@@ -0,0 +1,107 @@
+//! Readability scores computed from already-segmented sentences
+
+use serde::{Deserialize, Serialize};
+use unicode_segmentation::UnicodeSegmentation;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct Readability {
+    /// Flesch Reading Ease (higher = easier, ~60-70 is plain English)
+    pub flesch_reading_ease: f64,
+    /// Flesch-Kincaid grade level (US school grade)
+    pub flesch_kincaid_grade: f64,
+    /// Average number of words per sentence
+    pub avg_sentence_length: f64,
+}
+
+/// Compute readability scores over the given sentences
+pub fn score(sentences: &[&str]) -> Readability {
+    let mut word_count = 0usize;
+    let mut syllable_count = 0usize;
+
+    for sentence in sentences {
+        for word in sentence.unicode_words() {
+            word_count += 1;
+            syllable_count += count_syllables(word);
+        }
+    }
+
+    if sentences.is_empty() || word_count == 0 {
+        return Readability::default();
+    }
+
+    let words_per_sentence = word_count as f64 / sentences.len() as f64;
+    let syllables_per_word = syllable_count as f64 / word_count as f64;
+
+    Readability {
+        flesch_reading_ease: 206.835 - 1.015 * words_per_sentence - 84.6 * syllables_per_word,
+        flesch_kincaid_grade: 0.39 * words_per_sentence + 11.8 * syllables_per_word - 15.59,
+        avg_sentence_length: words_per_sentence,
+    }
+}
+
+/// Estimate syllables by counting vowel groups, ignoring a silent trailing "e"
+pub fn count_syllables(word: &str) -> usize {
+    let word: Vec<char> = word
+        .to_lowercase()
+        .chars()
+        .filter(|c| c.is_alphabetic())
+        .collect();
+    if word.is_empty() {
+        return 0;
+    }
+
+    let is_vowel = |c: char| matches!(c, 'a' | 'e' | 'i' | 'o' | 'u' | 'y');
+
+    let mut count = 0;
+    let mut prev_vowel = false;
+    for &c in &word {
+        let vowel = is_vowel(c);
+        if vowel && !prev_vowel {
+            count += 1;
+        }
+        prev_vowel = vowel;
+    }
+
+    // Silent "e" ("make", "table" keeps its "le" syllable)
+    let n = word.len();
+    let silent_e = n > 2 && word[n - 1] == 'e' && !is_vowel(word[n - 2]);
+    let consonant_le = n > 2 && word[n - 2] == 'l' && !is_vowel(word[n - 3]);
+    if silent_e && !consonant_le {
+        count -= 1;
+    }
+
+    count.max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_count_syllables() {
+        assert_eq!(count_syllables("cat"), 1);
+        assert_eq!(count_syllables("make"), 1);
+        assert_eq!(count_syllables("table"), 2);
+        assert_eq!(count_syllables("ephemeral"), 4);
+        assert_eq!(count_syllables("beautiful"), 3);
+        assert_eq!(count_syllables("the"), 1);
+        assert_eq!(count_syllables("123"), 0);
+    }
+
+    #[test]
+    fn test_simple_text_is_easier_than_complex_text() {
+        let simple = score(&["The cat sat.", "The dog ran."]);
+        let complex = score(&["The obsequious functionary acquiesced to the supercilious magistrate's importunate demands."]);
+
+        assert!((simple.avg_sentence_length - 3.0).abs() < f64::EPSILON);
+        assert!(simple.flesch_reading_ease > complex.flesch_reading_ease);
+        assert!(simple.flesch_kincaid_grade < complex.flesch_kincaid_grade);
+    }
+
+    #[test]
+    fn test_empty_input() {
+        let r = score(&[]);
+        assert_eq!(r.flesch_reading_ease, 0.0);
+        assert_eq!(r.avg_sentence_length, 0.0);
+    }
+}
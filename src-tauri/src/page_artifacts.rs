@@ -0,0 +1,178 @@
+//! Running headers and page numbers left in books converted from PDFs
+//!
+//! Such conversions repeat the book title or author every page and keep page
+//! numbers as lines of their own, which inflates counts and makes odd contexts.
+//! Both are removed line by line from each chapter before the chapters are
+//! joined again, so segments stay exact.
+
+use crate::epub::{self, ExtractedText};
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// A short line must repeat more than this many times to count as a running header
+pub const DEFAULT_MIN_REPEATS: usize = 5;
+
+/// Running headers are a title, an author or a chapter name, never a paragraph
+const MAX_HEADER_WORDS: usize = 8;
+
+/// Words left lowercase in title case ("The Call of the Wild")
+const MINOR_WORDS: &[&str] = &["a", "an", "and", "at", "by", "for", "in", "of", "on", "or", "the", "to"];
+
+/// Characters around page numbers and headers ("- 12 -", "Moby Dick | 12")
+const SEPARATORS: &[char] = &['-', '–', '—', '|', '·', '•', '[', ']', '(', ')'];
+
+/// Remove running headers (short title-cased lines repeated more than `min_repeats`
+/// times across the book) and page-number lines from every chapter, rebuilding
+/// the joined text. Returns how many lines were removed.
+pub fn strip(extracted: &mut ExtractedText, min_repeats: usize) -> usize {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for chapter in &extracted.chapters {
+        for key in chapter.text.lines().filter_map(header_key) {
+            *counts.entry(key).or_default() += 1;
+        }
+    }
+    let is_artifact = |line: &str| {
+        is_page_number(line) || header_key(line).is_some_and(|key| counts[&key] > min_repeats)
+    };
+
+    let mut removed = 0;
+    for chapter in &mut extracted.chapters {
+        let mut text = String::new();
+        let mut verse: Vec<Range<usize>> = Vec::new();
+        let mut old_offset = 0;
+        let mut chars = 0;
+        for paragraph in chapter.text.split("\n\n") {
+            let was_verse = chapter.verse.iter().any(|r| r.contains(&old_offset));
+            old_offset += paragraph.chars().count() + 2;
+
+            let lines: Vec<&str> = paragraph.lines().filter(|line| !is_artifact(line)).collect();
+            removed += paragraph.lines().count() - lines.len();
+            if lines.is_empty() {
+                continue;
+            }
+            if !text.is_empty() {
+                text.push_str("\n\n");
+                chars += 2;
+            }
+            let kept = lines.join("\n");
+            let start = chars;
+            chars += kept.chars().count();
+            text.push_str(&kept);
+            // Verse ranges cover whole paragraphs, so they are rebuilt one paragraph at a time
+            if was_verse {
+                let end = chars;
+                match verse.last_mut() {
+                    Some(last) if last.end + 2 == start => last.end = end,
+                    _ => verse.push(start..end),
+                }
+            }
+        }
+        chapter.text = text;
+        chapter.verse = verse;
+    }
+
+    if removed > 0 {
+        let (full_text, segments) = epub::join_chapters(&extracted.chapters, false);
+        extracted.full_text = full_text;
+        extracted.verse = epub::verse_ranges(&extracted.chapters, &segments);
+        extracted.segments = segments;
+    }
+    removed
+}
+
+/// A lone page number: "12", "- 12 -", "Page 12", "p. 12", "12 of 300"
+fn is_page_number(line: &str) -> bool {
+    let line = line.trim().trim_matches(|c: char| SEPARATORS.contains(&c) || c.is_whitespace());
+    let tokens: Vec<String> = line.split_whitespace().map(|t| t.to_lowercase()).collect();
+    let tokens: Vec<&str> = tokens.iter().map(String::as_str).collect();
+    let is_number = |t: &str| !t.is_empty() && t.chars().all(|c| c.is_ascii_digit());
+    match tokens.as_slice() {
+        [n] => is_number(n),
+        ["page" | "p." | "p", n] => is_number(n),
+        ["page", n, "of", m] | [n, "of", m] => is_number(n) && is_number(m),
+        _ => false,
+    }
+}
+
+/// The line without a page number at either end, if it could be a running header:
+/// a few title-cased words that don't end a sentence (so repeated dialogue like
+/// "Yes, sir." is kept)
+fn header_key(line: &str) -> Option<String> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let is_edge_noise = |w: &&str| w.chars().all(|c| c.is_ascii_digit() || SEPARATORS.contains(&c));
+    let start = words.iter().position(|w| !is_edge_noise(w))?;
+    let end = words.iter().rposition(|w| !is_edge_noise(w))? + 1;
+    let words = &words[start..end];
+
+    if words.len() > MAX_HEADER_WORDS || words.last()?.ends_with(['.', '!', '?', '"', '”', ',', ';', ':']) {
+        return None;
+    }
+    let title_cased = words.iter().enumerate().all(|(i, word)| match word.chars().find(|c| c.is_alphabetic()) {
+        Some(c) => c.is_uppercase() || (i > 0 && MINOR_WORDS.contains(&word.to_lowercase().as_str())),
+        None => true,
+    });
+    let has_letters = words.iter().any(|w| w.chars().any(char::is_alphabetic));
+    (title_cased && has_letters).then(|| words.join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::epub::Chapter;
+
+    fn book(texts: &[&str]) -> ExtractedText {
+        let chapters = texts
+            .iter()
+            .enumerate()
+            .map(|(i, text)| Chapter {
+                path: format!("ch{}.xhtml", i),
+                ..Chapter::fixture(i, &format!("Chapter {}", i + 1), text)
+            })
+            .collect();
+        ExtractedText::from_chapters(chapters)
+    }
+
+    #[test]
+    fn test_strips_repeated_headers_and_page_numbers() {
+        let page = |n: usize| format!("THE WHALING VOYAGE\nCall me Ishmael, page {} said.\n\n{}", n, n);
+        let pages: Vec<String> = (1..=4).map(page).collect();
+        let mut extracted = book(&[&pages[..2].join("\n\n"), &pages[2..].join("\n\n")]);
+
+        let removed = strip(&mut extracted, 3);
+
+        assert_eq!(removed, 8);
+        assert!(!extracted.full_text.contains("WHALING"));
+        assert_eq!(extracted.chapters[0].text, "Call me Ishmael, page 1 said.\n\nCall me Ishmael, page 2 said.");
+        assert_eq!(extracted.segments[1].char_start, extracted.chapters[0].text.chars().count() + 2);
+    }
+
+    #[test]
+    fn test_keeps_rare_lines_and_repeated_dialogue() {
+        let text = ["Herman Melville 12", "Yes, Sir.", "Herman Melville", "Yes, Sir.", "13 Herman Melville", "Yes, Sir."];
+        let mut extracted = book(&[&text.join("\n")]);
+
+        // The header appears three times (page numbers aside), so it needs a threshold below three
+        assert_eq!(strip(&mut extracted, 3), 0);
+        assert_eq!(strip(&mut extracted, 2), 3);
+        assert_eq!(extracted.full_text, "Yes, Sir.\nYes, Sir.\nYes, Sir.");
+    }
+
+    #[test]
+    fn test_is_page_number() {
+        for line in ["12", " - 12 - ", "Page 12", "p. 7", "[3]", "12 of 300"] {
+            assert!(is_page_number(line), "{:?}", line);
+        }
+        for line in ["Page one", "12 Angry Men", "Chapter 12", "12 ships"] {
+            assert!(!is_page_number(line), "{:?}", line);
+        }
+    }
+
+    #[test]
+    fn test_header_key() {
+        assert_eq!(header_key("12 | The Call of the Wild").as_deref(), Some("The Call of the Wild"));
+        assert_eq!(header_key("THE CALL OF THE WILD - 7").as_deref(), Some("THE CALL OF THE WILD"));
+        assert_eq!(header_key("the call of the wild"), None);
+        assert_eq!(header_key("Stop."), None);
+        assert_eq!(header_key("42"), None);
+    }
+}
@@ -0,0 +1,78 @@
+//! Best-effort PDF text extraction (behind the `pdf` feature)
+//!
+//! PDFs have no reliable chapter structure, so every page becomes a "chapter".
+//! Scanned PDFs often carry no text layer, or a garbled one, which analyses
+//! catch with `formats::text_quality`.
+
+use crate::epub::{self, Chapter, EpubError, ExtractedText};
+use std::path::Path;
+
+pub fn extract_text(path: &Path) -> Result<ExtractedText, EpubError> {
+    // The parser panics on some malformed files rather than returning an error
+    let pages = std::panic::catch_unwind(|| pdf_extract::extract_text_by_pages(path))
+        .map_err(|_| EpubError::Open("The PDF could not be parsed".to_string()))?
+        .map_err(|e| EpubError::Open(e.to_string()))?;
+
+    let chapters: Vec<Chapter> = pages
+        .iter()
+        .enumerate()
+        .filter_map(|(index, page)| {
+            // Rejoins words hyphenated across line breaks, which PDFs keep
+            let text = epub::normalize_plain_text(page);
+            (!text.is_empty()).then(|| Chapter {
+                spine_index: index,
+                path: String::new(),
+                title: format!("Page {}", index + 1),
+                text,
+                footnotes: Vec::new(),
+                verse: Vec::new(),
+            })
+        })
+        .collect();
+
+    Ok(ExtractedText::from_chapters(chapters))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{formats, nlp};
+
+    /// A PDF with `pages` pages that have no text layer, like a scan without OCR
+    fn blank_pdf(pages: usize) -> Vec<u8> {
+        let kids: Vec<String> = (0..pages).map(|i| format!("{} 0 R", i + 3)).collect();
+        let mut objects = vec![
+            "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+            format!("<< /Type /Pages /Kids [{}] /Count {} >>", kids.join(" "), pages),
+        ];
+        for _ in 0..pages {
+            objects.push("<< /Type /Page /Parent 2 0 R /MediaBox [0 0 612 792] >>".to_string());
+        }
+
+        let mut pdf = b"%PDF-1.4\n".to_vec();
+        let mut offsets = Vec::new();
+        for (i, object) in objects.iter().enumerate() {
+            offsets.push(pdf.len());
+            pdf.extend(format!("{} 0 obj\n{}\nendobj\n", i + 1, object).bytes());
+        }
+        let xref = pdf.len();
+        pdf.extend(format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).bytes());
+        for offset in offsets {
+            pdf.extend(format!("{:010} 00000 n \n", offset).bytes());
+        }
+        pdf.extend(format!("trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n", objects.len() + 1, xref).bytes());
+        pdf
+    }
+
+    #[test]
+    fn test_pages_without_text_score_zero() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("scan.pdf");
+        std::fs::write(&path, blank_pdf(2)).unwrap();
+
+        let mut extracted = extract_text(&path).unwrap();
+        assert!(extracted.chapters.is_empty());
+        formats::measure_text_quality(&mut extracted, nlp::Language::English);
+        assert_eq!(extracted.text_quality, Some(0.0));
+    }
+}
@@ -3,21 +3,216 @@
 //! Handles auto-downloading and caching of NLP models and dictionaries.
 //! All resources are stored in the XDG data directory.
 
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
 use std::fs;
 use std::io::{Read, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
 
-/// Base URL for HuggingFace model downloads
+/// Default base URL for HuggingFace model downloads (see `MirrorConfig`)
 const HUGGINGFACE_BASE: &str = "https://huggingface.co";
 
-/// GLiNER model repository on HuggingFace
+/// Default GLiNER model repository on HuggingFace
 const GLINER_REPO: &str = "onnx-community/gliner_large-v2.1";
 
-/// SymSpell dictionary URL
-const SYMSPELL_DICT_URL: &str = "https://raw.githubusercontent.com/wolfgarbe/SymSpell/master/SymSpell/frequency_dictionary_en_82_765.txt";
+/// Default base URL for files hosted on GitHub (the SymSpell and offline dictionaries)
+const GITHUB_RAW_BASE: &str = "https://raw.githubusercontent.com";
 
-/// Progress callback for resource downloads
-pub type ProgressCallback = Box<dyn Fn(&str, u64, u64) + Send>;
+/// SymSpell dictionary paths under `GITHUB_RAW_BASE`
+const SYMSPELL_DICT_PATH: &str = "wolfgarbe/SymSpell/master/SymSpell/frequency_dictionary_en_82_765.txt";
+const SYMSPELL_DE_DICT_PATH: &str = "wolfgarbe/SymSpell/master/SymSpell.FrequencyDictionary/de-100k.txt";
+
+/// Offline dictionary (Webster's Unabridged 1913, public domain) as a JSON
+/// object of headword -> definition, under `GITHUB_RAW_BASE`
+const DICTIONARY_PATH: &str = "adambom/dictionary/master/dictionary.json";
+const DICTIONARY_FILE: &str = "dictionary.json";
+
+/// Expected SHA-256 hashes of the downloaded files.
+///
+/// Every download is checked against these, and so is every installed copy before
+/// it is used. `None` means no upstream hash is pinned yet: such a file is accepted
+/// unverified, as before checksums were added, until its hash is filled in here.
+const GLINER_MODEL_SHA256: Option<&str> = None;
+const GLINER_QUANTIZED_MODEL_SHA256: Option<&str> = None;
+const GLINER_TOKENIZER_SHA256: Option<&str> = None;
+const SYMSPELL_DICT_SHA256: Option<&str> = None;
+const SYMSPELL_DE_DICT_SHA256: Option<&str> = None;
+const DICTIONARY_SHA256: Option<&str> = None;
+
+/// Which GLiNER model file to download and run. Both find the same entity types;
+/// NER only filters names and places out of the candidates, so the quantized
+/// model's small loss in accuracy rarely changes the results.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GlinerVariant {
+    /// Full-precision weights (~650MB): the most accurate
+    #[default]
+    Full,
+    /// int8-quantized weights, about a quarter of the size and faster on CPU,
+    /// at the cost of missing a few more names
+    Quantized,
+}
+
+impl GlinerVariant {
+    /// Model file name, both in the repository's `onnx/` directory and locally
+    fn file(self) -> &'static str {
+        match self {
+            GlinerVariant::Full => "model.onnx",
+            GlinerVariant::Quantized => "model_quantized.onnx",
+        }
+    }
+
+    fn sha256(self) -> Option<&'static str> {
+        match self {
+            GlinerVariant::Full => GLINER_MODEL_SHA256,
+            GlinerVariant::Quantized => GLINER_QUANTIZED_MODEL_SHA256,
+        }
+    }
+
+    /// Approximate size of the model file, for the disk space check
+    fn download_size(self) -> u64 {
+        match self {
+            GlinerVariant::Full => 650 * MB,
+            GlinerVariant::Quantized => 180 * MB,
+        }
+    }
+}
+
+const MB: u64 = 1024 * 1024;
+
+/// Free space kept on top of a download's size: the tokenizer and some room
+/// for everything else on the volume
+const DISK_SPACE_MARGIN: u64 = 100 * MB;
+
+/// A SymSpell frequency dictionary, stored in the SymSpell directory
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SymspellDict {
+    pub file: &'static str,
+    /// Path under the GitHub raw-file mirrors
+    path: &'static str,
+    sha256: Option<&'static str>,
+}
+
+/// English dictionary (the one `download_resources` fetches)
+pub const SYMSPELL_EN: SymspellDict = SymspellDict {
+    file: "frequency_dictionary_en_82_765.txt",
+    path: SYMSPELL_DICT_PATH,
+    sha256: SYMSPELL_DICT_SHA256,
+};
+
+/// German dictionary, fetched the first time a German book is analyzed
+pub const SYMSPELL_DE: SymspellDict = SymspellDict {
+    file: "de-100k.txt",
+    path: SYMSPELL_DE_DICT_PATH,
+    sha256: SYMSPELL_DE_DICT_SHA256,
+};
+
+/// Where resources are downloaded from, saved as `mirrors.json` in the resource dir.
+/// Each list is tried in order, moving to the next mirror when one fails.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct MirrorConfig {
+    /// HuggingFace-compatible hosts for the GLiNER model (e.g. "https://hf-mirror.com")
+    pub huggingface: Vec<String>,
+    /// GLiNER repository on those hosts
+    pub gliner_repo: String,
+    /// Hosts serving GitHub raw files, for the SymSpell and offline dictionaries
+    pub github_raw: Vec<String>,
+}
+
+impl Default for MirrorConfig {
+    fn default() -> Self {
+        Self {
+            huggingface: vec![HUGGINGFACE_BASE.to_string()],
+            gliner_repo: GLINER_REPO.to_string(),
+            github_raw: vec![GITHUB_RAW_BASE.to_string()],
+        }
+    }
+}
+
+impl MirrorConfig {
+    /// Drop blank entries and trailing slashes; empty lists and repos fall back to the defaults
+    pub fn normalized(self) -> Self {
+        let defaults = Self::default();
+        let clean = |hosts: Vec<String>, default: Vec<String>| {
+            let hosts: Vec<String> = hosts
+                .iter()
+                .map(|h| h.trim().trim_end_matches('/').to_string())
+                .filter(|h| !h.is_empty())
+                .collect();
+            if hosts.is_empty() {
+                default
+            } else {
+                hosts
+            }
+        };
+        let repo = self.gliner_repo.trim().trim_matches('/').to_string();
+        Self {
+            huggingface: clean(self.huggingface, defaults.huggingface),
+            gliner_repo: if repo.is_empty() { defaults.gliner_repo } else { repo },
+            github_raw: clean(self.github_raw, defaults.github_raw),
+        }
+    }
+
+    /// URLs of a file in the GLiNER repository, one per HuggingFace mirror
+    fn gliner_urls(&self, file: &str) -> Vec<String> {
+        self.huggingface
+            .iter()
+            .map(|host| format!("{}/{}/resolve/main/{}", host, self.gliner_repo, file))
+            .collect()
+    }
+
+    /// URLs of a GitHub raw file, one per mirror
+    fn github_raw_urls(&self, path: &str) -> Vec<String> {
+        self.github_raw.iter().map(|host| format!("{}/{}", host, path)).collect()
+    }
+}
+
+/// Path of the mirror configuration
+pub fn get_mirror_config_path() -> PathBuf {
+    get_resource_dir().join("mirrors.json")
+}
+
+/// The saved mirror configuration, or the defaults if none is saved (or it is unreadable)
+pub fn load_mirror_config() -> MirrorConfig {
+    fs::read_to_string(get_mirror_config_path())
+        .ok()
+        .and_then(|json| serde_json::from_str::<MirrorConfig>(&json).ok())
+        .unwrap_or_default()
+        .normalized()
+}
+
+/// Persist the mirror configuration, returning it as it will be used
+pub fn save_mirror_config(config: MirrorConfig) -> Result<MirrorConfig, String> {
+    let config = config.normalized();
+    let path = get_mirror_config_path();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create resource directory: {}", e))?;
+    }
+    let json = serde_json::to_string_pretty(&config).map_err(|e| e.to_string())?;
+    fs::write(&path, json).map_err(|e| format!("Failed to save mirror configuration: {}", e))?;
+    Ok(config)
+}
+
+/// Tries per file before a download fails; later tries resume the partial file
+pub const DOWNLOAD_ATTEMPTS: u32 = 3;
+/// Wait before the second try, doubled for each one after
+const RETRY_BASE_DELAY: Duration = Duration::from_secs(1);
+/// Give up on a server that doesn't accept the connection in this long
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(15);
+/// Give up on a connection that sends nothing for this long (per read, not the whole file)
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Bundled stopword lists (see `stopwords/`), one word per line with `#` comments
+pub const STOPWORDS_EN: &str = include_str!("../stopwords/en.txt");
+pub const STOPWORDS_DE: &str = include_str!("../stopwords/de.txt");
+
+/// Files already verified this session (path, size, mtime), so status checks
+/// don't re-hash the 650MB model every time
+static VERIFIED: Mutex<Vec<(PathBuf, u64, SystemTime)>> = Mutex::new(Vec::new());
 
 /// Get the base resource directory (XDG data directory)
 pub fn get_resource_dir() -> PathBuf {
@@ -37,37 +232,250 @@ pub fn get_symspell_dir() -> PathBuf {
     get_resource_dir().join("symspell")
 }
 
-/// Check if GLiNER model is available
+/// Get the offline dictionary directory
+pub fn get_dictionary_dir() -> PathBuf {
+    get_resource_dir().join("dictionary")
+}
+
+/// Path of the offline dictionary file (whether or not it is downloaded)
+pub fn get_dictionary_path() -> PathBuf {
+    get_dictionary_dir().join(DICTIONARY_FILE)
+}
+
+/// Get the cover thumbnail cache directory (alongside the resource dir)
+pub fn get_cover_cache_dir() -> PathBuf {
+    get_resource_dir()
+        .parent()
+        .map(|p| p.join("covers"))
+        .unwrap_or_else(|| PathBuf::from("covers"))
+}
+
+/// Get the directory holding saved analyses, one JSON file per book
+pub fn get_analysis_cache_dir() -> PathBuf {
+    get_resource_dir().join("analyses")
+}
+
+/// Get the path of the user's known-words list (one word per line)
+pub fn get_known_words_path() -> PathBuf {
+    get_resource_dir().join("known_words.txt")
+}
+
+/// Load the persisted known-words list (empty if none saved yet)
+pub fn load_known_words() -> HashSet<String> {
+    load_word_list(&get_known_words_path())
+}
+
+/// Persist the known-words list
+pub fn save_known_words(words: &HashSet<String>) -> Result<(), String> {
+    save_word_list(&get_known_words_path(), words).map_err(|e| format!("Failed to save known words: {}", e))
+}
+
+/// Get the path of the entity allowlist: words the user marked as not a name or place
+pub fn get_entity_allowlist_path() -> PathBuf {
+    get_resource_dir().join("entity_allowlist.txt")
+}
+
+/// Load the words NER never filters (empty if none saved yet)
+pub fn load_entity_allowlist() -> HashSet<String> {
+    load_word_list(&get_entity_allowlist_path())
+}
+
+/// Persist the words NER never filters
+pub fn save_entity_allowlist(words: &HashSet<String>) -> Result<(), String> {
+    save_word_list(&get_entity_allowlist_path(), words).map_err(|e| format!("Failed to save entity allowlist: {}", e))
+}
+
+/// Get the path of the user's own stopwords, added to the bundled list
+pub fn get_custom_stopwords_path() -> PathBuf {
+    get_resource_dir().join("stopwords.txt")
+}
+
+/// Load the user's custom stopwords (empty if none saved yet)
+pub fn load_custom_stopwords() -> HashSet<String> {
+    load_word_list(&get_custom_stopwords_path())
+}
+
+/// Persist the user's custom stopwords
+pub fn save_custom_stopwords(words: &HashSet<String>) -> Result<(), String> {
+    save_word_list(&get_custom_stopwords_path(), words).map_err(|e| format!("Failed to save stopwords: {}", e))
+}
+
+/// Words of a one-per-line list, lowercased, skipping blank lines and `#` comments
+pub fn parse_word_list(content: &str) -> impl Iterator<Item = String> + '_ {
+    content
+        .lines()
+        .map(|l| l.trim())
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .map(|l| l.to_lowercase())
+}
+
+/// Words saved one per line by `save_word_list`, lowercased, skipping blank lines
+fn load_word_list(path: &Path) -> HashSet<String> {
+    fs::read_to_string(path)
+        .map(|content| {
+            content
+                .lines()
+                .map(|l| l.trim().to_lowercase())
+                .filter(|l| !l.is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn save_word_list(path: &Path, words: &HashSet<String>) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut sorted: Vec<&String> = words.iter().collect();
+    sorted.sort();
+    let content: String = sorted.iter().map(|w| format!("{}\n", w)).collect();
+    fs::write(path, content)
+}
+
+/// Check if a GLiNER model (either variant) is available (and intact)
 pub fn is_gliner_available() -> bool {
-    let dir = get_gliner_dir();
-    dir.join("model.onnx").exists() && dir.join("tokenizer.json").exists()
+    installed_gliner_variant().is_some()
 }
 
-/// Check if SymSpell dictionary is available
+/// Check if the given GLiNER variant is available (and intact)
+pub fn is_gliner_variant_available(variant: GlinerVariant) -> bool {
+    is_gliner_variant_in(&get_gliner_dir(), variant, gliner_sha256)
+}
+
+/// The GLiNER variant that will be loaded: the full model if it is downloaded, else the quantized one
+pub fn installed_gliner_variant() -> Option<GlinerVariant> {
+    installed_gliner_variant_in(&get_gliner_dir(), gliner_sha256)
+}
+
+/// Pinned hash of a file in the GLiNER directory
+fn gliner_sha256(file: &str) -> Option<&'static str> {
+    [GlinerVariant::Full, GlinerVariant::Quantized]
+        .into_iter()
+        .find(|variant| variant.file() == file)
+        .map_or(GLINER_TOKENIZER_SHA256, GlinerVariant::sha256)
+}
+
+/// Whether `variant` and the tokenizer are in `dir`, checked against the `pinned` hashes
+fn is_gliner_variant_in(dir: &Path, variant: GlinerVariant, pinned: fn(&str) -> Option<&'static str>) -> bool {
+    verify_file(&dir.join(variant.file()), pinned(variant.file()))
+        && verify_file(&dir.join("tokenizer.json"), pinned("tokenizer.json"))
+}
+
+fn installed_gliner_variant_in(dir: &Path, pinned: fn(&str) -> Option<&'static str>) -> Option<GlinerVariant> {
+    [GlinerVariant::Full, GlinerVariant::Quantized]
+        .into_iter()
+        .find(|variant| is_gliner_variant_in(dir, *variant, pinned))
+}
+
+/// Path of the installed GLiNER model file (see `installed_gliner_variant`)
+pub fn get_gliner_model_path() -> Option<PathBuf> {
+    installed_gliner_variant().map(|variant| get_gliner_dir().join(variant.file()))
+}
+
+/// Check if the English SymSpell dictionary is available (and intact)
 pub fn is_symspell_available() -> bool {
-    get_symspell_dir().join("frequency_dictionary_en_82_765.txt").exists()
+    is_symspell_dict_available(SYMSPELL_EN)
+}
+
+/// Check if a SymSpell dictionary is available (and intact)
+pub fn is_symspell_dict_available(dict: SymspellDict) -> bool {
+    verify_file(&get_symspell_dir().join(dict.file), dict.sha256)
+}
+
+/// Check if the offline dictionary is available (and intact)
+pub fn is_dictionary_available() -> bool {
+    verify_file(&get_dictionary_path(), DICTIONARY_SHA256)
+}
+
+/// Check that a resource file exists and matches its pinned hash. A corrupt file is
+/// deleted so the next `ensure_*` call downloads it again; a file without a pinned
+/// hash is accepted as it is.
+fn verify_file(path: &Path, expected: Option<&str>) -> bool {
+    let Ok(metadata) = fs::metadata(path) else {
+        return false;
+    };
+    let Some(reference) = expected else {
+        return true;
+    };
+    let stamp = (metadata.len(), metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH));
+
+    let mut verified = VERIFIED.lock().unwrap();
+    if verified.iter().any(|(p, len, mtime)| p == path && (*len, *mtime) == stamp) {
+        return true;
+    }
+
+    match sha256_file(path) {
+        Ok(actual) if actual.eq_ignore_ascii_case(reference) => {
+            verified.push((path.to_path_buf(), stamp.0, stamp.1));
+            true
+        }
+        Ok(actual) => {
+            eprintln!(
+                "Checksum mismatch for {:?} (expected {}, got {}), removing it so it is downloaded again",
+                path, reference, actual
+            );
+            let _ = fs::remove_file(path);
+            false
+        }
+        Err(e) => {
+            eprintln!("Failed to hash {:?}: {}", path, e);
+            false
+        }
+    }
+}
+
+/// Hex-encoded SHA-256 of a file
+fn sha256_file(path: &Path) -> std::io::Result<String> {
+    let mut file = fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = [0u8; 64 * 1024];
+    loop {
+        let n = file.read(&mut buffer)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buffer[..n]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
 }
 
 /// Resource download status
 #[derive(Debug, Clone)]
 pub enum DownloadStatus {
     AlreadyExists,
-    Downloading { file: String, progress: u64, total: u64 },
+    /// `progress` and `total` are absolute byte counts; `resumed_from` is the
+    /// offset a resumed download started at (0 for a fresh download); `attempt`
+    /// counts from 1 (see `DOWNLOAD_ATTEMPTS`)
+    Downloading { file: String, progress: u64, total: u64, resumed_from: u64, attempt: u32 },
+    /// Attempt `attempt` failed with `error`; the next one starts after a backoff
+    Retrying { file: String, attempt: u32, error: String },
     Completed,
+    Cancelled,
     Failed(String),
 }
 
-/// Ensure GLiNER model is available, downloading if necessary
+/// Error message used when a download is stopped via its cancel flag
+pub const DOWNLOAD_CANCELLED: &str = "Download cancelled";
+
+/// Ensure the given GLiNER variant is available, downloading if necessary
 /// Returns the model directory path
-pub fn ensure_gliner_model<F>(on_progress: F) -> Result<PathBuf, String>
+/// Setting `cancel` stops the download; the partial file is kept and resumed next time.
+pub fn ensure_gliner_model<F>(variant: GlinerVariant, cancel: &AtomicBool, on_progress: F) -> Result<PathBuf, String>
+where
+    F: Fn(DownloadStatus) + Send,
+{
+    download_gliner_model(variant, cancel, &on_progress).inspect_err(|e| on_progress(failure_status(e)))
+}
+
+fn download_gliner_model<F>(variant: GlinerVariant, cancel: &AtomicBool, on_progress: &F) -> Result<PathBuf, String>
 where
     F: Fn(DownloadStatus) + Send,
 {
     let model_dir = get_gliner_dir();
-    let model_path = model_dir.join("model.onnx");
+    let model_path = model_dir.join(variant.file());
     let tokenizer_path = model_dir.join("tokenizer.json");
 
-    if model_path.exists() && tokenizer_path.exists() {
+    if is_gliner_variant_available(variant) {
         on_progress(DownloadStatus::AlreadyExists);
         return Ok(model_dir);
     }
@@ -76,31 +484,22 @@ where
     fs::create_dir_all(&model_dir)
         .map_err(|e| format!("Failed to create model directory: {}", e))?;
 
+    let mirrors = load_mirror_config();
+
     // Download tokenizer.json first (smaller file)
-    if !tokenizer_path.exists() {
-        let url = format!("{}/{}/resolve/main/tokenizer.json", HUGGINGFACE_BASE, GLINER_REPO);
-        eprintln!("Downloading GLiNER tokenizer from {}...", url);
-        download_file(&url, &tokenizer_path, |progress, total| {
-            on_progress(DownloadStatus::Downloading {
-                file: "tokenizer.json".to_string(),
-                progress,
-                total,
-            });
-        })?;
+    if !verify_file(&tokenizer_path, GLINER_TOKENIZER_SHA256) {
+        let urls = mirrors.gliner_urls("tokenizer.json");
+        eprintln!("Downloading GLiNER tokenizer from {}...", urls[0]);
+        download_file(&urls, &tokenizer_path, "tokenizer.json", GLINER_TOKENIZER_SHA256, cancel, on_progress)?;
     }
 
-    // Download model.onnx (large file ~650MB)
-    if !model_path.exists() {
-        let url = format!("{}/{}/resolve/main/onnx/model.onnx", HUGGINGFACE_BASE, GLINER_REPO);
-        eprintln!("Downloading GLiNER model from {}...", url);
-        eprintln!("This is a large file (~650MB), please wait...");
-        download_file(&url, &model_path, |progress, total| {
-            on_progress(DownloadStatus::Downloading {
-                file: "model.onnx".to_string(),
-                progress,
-                total,
-            });
-        })?;
+    // Download the model (large file, ~650MB at full precision)
+    if !verify_file(&model_path, variant.sha256()) {
+        ensure_disk_space(&model_dir, &model_path, variant.download_size())?;
+        let urls = mirrors.gliner_urls(&format!("onnx/{}", variant.file()));
+        eprintln!("Downloading GLiNER model from {}...", urls[0]);
+        eprintln!("This is a large file, please wait...");
+        download_file(&urls, &model_path, variant.file(), variant.sha256(), cancel, on_progress)?;
     }
 
     on_progress(DownloadStatus::Completed);
@@ -108,16 +507,89 @@ where
     Ok(model_dir)
 }
 
-/// Ensure SymSpell dictionary is available, downloading if necessary
+/// Fail early if the volume holding `dir` can't fit the rest of `dest`'s download.
+/// A partial `.download` file counts towards the size, but is removed when even
+/// the remainder doesn't fit, so it doesn't hold on to space the user must free.
+fn ensure_disk_space(dir: &Path, dest: &Path, size: u64) -> Result<(), String> {
+    let temp_path = dest.with_extension("download");
+    let partial_len = fs::metadata(&temp_path).map(|m| m.len()).unwrap_or(0);
+    let needed = required_space(size, partial_len);
+    let result = check_disk_space(needed, available_space(dir));
+    if result.is_err() && partial_len > 0 {
+        let _ = fs::remove_file(&temp_path);
+    }
+    result
+}
+
+/// Bytes still needed for a `size`-byte download with `partial_len` bytes already on disk
+fn required_space(size: u64, partial_len: u64) -> u64 {
+    size.saturating_sub(partial_len) + DISK_SPACE_MARGIN
+}
+
+/// `available` is None when the free space can't be queried, which skips the check
+fn check_disk_space(needed: u64, available: Option<u64>) -> Result<(), String> {
+    match available {
+        Some(available) if available < needed => Err(format!(
+            "Insufficient disk space: the download needs {} MB but only {} MB are free",
+            needed.div_ceil(MB),
+            available / MB
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Free bytes on the volume holding `path`, available to this user
+#[cfg(unix)]
+fn available_space(path: &Path) -> Option<u64> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = std::ffi::CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stat = std::mem::MaybeUninit::<libc::statvfs>::uninit();
+    // SAFETY: `path` is NUL-terminated and `stat` is only read after statvfs fills it in
+    let stat = unsafe {
+        if libc::statvfs(path.as_ptr(), stat.as_mut_ptr()) != 0 {
+            return None;
+        }
+        stat.assume_init()
+    };
+    Some(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+/// Free bytes on the volume holding `path`, available to this user
+#[cfg(windows)]
+fn available_space(path: &Path) -> Option<u64> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows_sys::Win32::Storage::FileSystem::GetDiskFreeSpaceExW;
+
+    let wide: Vec<u16> = path.as_os_str().encode_wide().chain(std::iter::once(0)).collect();
+    let mut available = 0u64;
+    // SAFETY: `wide` is NUL-terminated and the totals we don't need may be null
+    let ok = unsafe { GetDiskFreeSpaceExW(wide.as_ptr(), &mut available, std::ptr::null_mut(), std::ptr::null_mut()) };
+    (ok != 0).then_some(available)
+}
+
+#[cfg(not(any(unix, windows)))]
+fn available_space(_path: &Path) -> Option<u64> {
+    None
+}
+
+/// Ensure a SymSpell dictionary is available, downloading if necessary
 /// Returns the dictionary file path
-pub fn ensure_symspell_dict<F>(on_progress: F) -> Result<PathBuf, String>
+pub fn ensure_symspell_dict<F>(dict: SymspellDict, cancel: &AtomicBool, on_progress: F) -> Result<PathBuf, String>
+where
+    F: Fn(DownloadStatus) + Send,
+{
+    download_symspell_dict(dict, cancel, &on_progress).inspect_err(|e| on_progress(failure_status(e)))
+}
+
+fn download_symspell_dict<F>(dict: SymspellDict, cancel: &AtomicBool, on_progress: &F) -> Result<PathBuf, String>
 where
     F: Fn(DownloadStatus) + Send,
 {
     let dict_dir = get_symspell_dir();
-    let dict_path = dict_dir.join("frequency_dictionary_en_82_765.txt");
+    let dict_path = dict_dir.join(dict.file);
 
-    if dict_path.exists() {
+    if is_symspell_dict_available(dict) {
         on_progress(DownloadStatus::AlreadyExists);
         return Ok(dict_path);
     }
@@ -127,88 +599,742 @@ where
         .map_err(|e| format!("Failed to create dictionary directory: {}", e))?;
 
     eprintln!("Downloading SymSpell dictionary...");
-    download_file(SYMSPELL_DICT_URL, &dict_path, |progress, total| {
-        on_progress(DownloadStatus::Downloading {
-            file: "frequency_dictionary_en_82_765.txt".to_string(),
-            progress,
-            total,
-        });
-    })?;
+    let urls = load_mirror_config().github_raw_urls(dict.path);
+    download_file(&urls, &dict_path, dict.file, dict.sha256, cancel, on_progress)?;
 
     on_progress(DownloadStatus::Completed);
     eprintln!("SymSpell dictionary downloaded successfully to {:?}", dict_path);
     Ok(dict_path)
 }
 
-/// Download a file with progress tracking
-fn download_file<F>(url: &str, dest: &PathBuf, on_progress: F) -> Result<(), String>
+/// Ensure the offline dictionary is available, downloading if necessary
+/// Returns the dictionary file path
+pub fn ensure_dictionary<F>(cancel: &AtomicBool, on_progress: F) -> Result<PathBuf, String>
 where
-    F: Fn(u64, u64),
+    F: Fn(DownloadStatus) + Send,
 {
-    let response = ureq::get(url)
-        .call()
-        .map_err(|e| format!("Failed to download {}: {}", url, e))?;
+    download_dictionary(cancel, &on_progress).inspect_err(|e| on_progress(failure_status(e)))
+}
 
-    let total_size = response
+fn download_dictionary<F>(cancel: &AtomicBool, on_progress: &F) -> Result<PathBuf, String>
+where
+    F: Fn(DownloadStatus) + Send,
+{
+    let dict_path = get_dictionary_path();
+
+    if is_dictionary_available() {
+        on_progress(DownloadStatus::AlreadyExists);
+        return Ok(dict_path);
+    }
+
+    fs::create_dir_all(get_dictionary_dir())
+        .map_err(|e| format!("Failed to create dictionary directory: {}", e))?;
+
+    eprintln!("Downloading offline dictionary...");
+    let urls = load_mirror_config().github_raw_urls(DICTIONARY_PATH);
+    download_file(&urls, &dict_path, DICTIONARY_FILE, DICTIONARY_SHA256, cancel, on_progress)?;
+
+    on_progress(DownloadStatus::Completed);
+    eprintln!("Offline dictionary downloaded successfully to {:?}", dict_path);
+    Ok(dict_path)
+}
+
+fn failure_status(error: &str) -> DownloadStatus {
+    if error == DOWNLOAD_CANCELLED {
+        DownloadStatus::Cancelled
+    } else {
+        DownloadStatus::Failed(error.to_string())
+    }
+}
+
+/// Why a download attempt failed, which decides whether `download_file` tries again
+enum AttemptError {
+    /// Network trouble (timeouts, dropped connections, 5xx): worth another attempt
+    Transient(String),
+    /// Cancelled, or a failure another attempt won't fix (404, a full disk)
+    Fatal(String),
+}
+
+/// HTTP client with connect and read timeouts, so a stalled connection fails
+/// (and is retried) instead of hanging forever. Goes through the proxy named by
+/// `ALL_PROXY`, `HTTPS_PROXY` or `HTTP_PROXY` (or their lowercase forms), if set.
+pub(crate) fn http_agent() -> ureq::Agent {
+    ureq::AgentBuilder::new()
+        .try_proxy_from_env(true)
+        .timeout_connect(CONNECT_TIMEOUT)
+        .timeout_read(READ_TIMEOUT)
+        .build()
+}
+
+/// Backoff before the attempt after `attempt` (1s, 2s, 4s, ...)
+fn retry_delay(attempt: u32) -> Duration {
+    RETRY_BASE_DELAY * 2u32.pow(attempt.saturating_sub(1))
+}
+
+/// Statuses that may succeed on a later try: server errors, timeouts and rate limits
+fn is_transient_status(code: u16) -> bool {
+    code >= 500 || code == 408 || code == 429
+}
+
+fn request_error(url: &str, error: ureq::Error) -> AttemptError {
+    let message = format!("Failed to download {}: {}", url, error);
+    match error {
+        ureq::Error::Status(code, _) if !is_transient_status(code) => AttemptError::Fatal(message),
+        _ => AttemptError::Transient(message),
+    }
+}
+
+/// Sleep for `delay`, waking early (with an error) if the download is cancelled
+fn wait_unless_cancelled(delay: Duration, cancel: &AtomicBool) -> Result<(), String> {
+    let step = Duration::from_millis(100);
+    let mut waited = Duration::ZERO;
+    while waited < delay {
+        if cancel.load(Ordering::SeqCst) {
+            return Err(DOWNLOAD_CANCELLED.to_string());
+        }
+        std::thread::sleep(step);
+        waited += step;
+    }
+    Ok(())
+}
+
+/// Download a file from the first of `urls` that works (mirrors of the same file),
+/// with progress tracking reported as `file`
+///
+/// Failed attempts are retried up to `DOWNLOAD_ATTEMPTS` times with exponential
+/// backoff. Each attempt writes to a `.download` file next to `dest`, so a retry
+/// (or a later download) resumes from its end with an HTTP Range request; servers
+/// that don't honour the range get a fresh download instead. A URL that fails
+/// for good has its partial file deleted, so the next mirror doesn't resume it.
+///
+/// The finished file is hashed before the rename, and discarded if it doesn't
+/// match `expected` (when a hash is pinned).
+fn download_file<F>(
+    urls: &[String],
+    dest: &Path,
+    file: &str,
+    expected: Option<&str>,
+    cancel: &AtomicBool,
+    on_progress: &F,
+) -> Result<(), String>
+where
+    F: Fn(DownloadStatus),
+{
+    let agent = http_agent();
+    let mut last_error = format!("No download URL for {}", file);
+    for url in urls {
+        match download_with_retries(&agent, url, dest, file, expected, cancel, on_progress) {
+            Ok(()) => return Ok(()),
+            Err(e) if e == DOWNLOAD_CANCELLED => return Err(e),
+            Err(e) => {
+                eprintln!("Download from {} failed: {}", url, e);
+                last_error = e;
+                // Mirrors may serve different builds of a file, so the next one starts over
+                let _ = fs::remove_file(dest.with_extension("download"));
+            }
+        }
+    }
+    Err(last_error)
+}
+
+/// Download from one URL, retrying with backoff (see `download_file`)
+fn download_with_retries<F>(
+    agent: &ureq::Agent,
+    url: &str,
+    dest: &Path,
+    file: &str,
+    expected: Option<&str>,
+    cancel: &AtomicBool,
+    on_progress: &F,
+) -> Result<(), String>
+where
+    F: Fn(DownloadStatus),
+{
+    let mut attempt = 1;
+    loop {
+        let result = download_attempt(agent, url, dest, expected, cancel, |progress, total, resumed_from| {
+            on_progress(DownloadStatus::Downloading {
+                file: file.to_string(),
+                progress,
+                total,
+                resumed_from,
+                attempt,
+            });
+        });
+        match result {
+            Ok(()) => return Ok(()),
+            Err(AttemptError::Transient(error)) if attempt < DOWNLOAD_ATTEMPTS => {
+                let delay = retry_delay(attempt);
+                eprintln!(
+                    "Attempt {}/{} failed: {}; retrying in {}s",
+                    attempt,
+                    DOWNLOAD_ATTEMPTS,
+                    error,
+                    delay.as_secs()
+                );
+                on_progress(DownloadStatus::Retrying {
+                    file: file.to_string(),
+                    attempt,
+                    error,
+                });
+                wait_unless_cancelled(delay, cancel)?;
+                attempt += 1;
+            }
+            Err(AttemptError::Transient(error)) => {
+                return Err(format!("{} (gave up after {} attempts)", error, DOWNLOAD_ATTEMPTS))
+            }
+            Err(AttemptError::Fatal(error)) => return Err(error),
+        }
+    }
+}
+
+/// One try of `download_file`, resuming any partial file
+fn download_attempt<F>(
+    agent: &ureq::Agent,
+    url: &str,
+    dest: &Path,
+    expected: Option<&str>,
+    cancel: &AtomicBool,
+    on_progress: F,
+) -> Result<(), AttemptError>
+where
+    F: Fn(u64, u64, u64), // (downloaded, total, resumed_from)
+{
+    let temp_path = dest.with_extension("download");
+    let partial_len = fs::metadata(&temp_path).map(|m| m.len()).unwrap_or(0);
+
+    let mut request = agent.get(url);
+    if partial_len > 0 {
+        request = request.set("Range", &format!("bytes={}-", partial_len));
+    }
+
+    let response = match request.call() {
+        Ok(response) => response,
+        // Partial file is already complete (or stale): start over
+        Err(ureq::Error::Status(416, _)) => {
+            eprintln!("Server rejected resume range for {}, restarting download", url);
+            agent.get(url).call().map_err(|e| request_error(url, e))?
+        }
+        Err(e) => return Err(request_error(url, e)),
+    };
+
+    let content_length = response
         .header("content-length")
         .and_then(|s| s.parse::<u64>().ok())
         .unwrap_or(0);
 
+    // Only a 206 means the server honoured the range; a 200 is the whole file
+    let resumed_from = if partial_len > 0 && response.status() == 206 {
+        eprintln!("Resuming download of {} from byte {}", url, partial_len);
+        partial_len
+    } else {
+        0
+    };
+
+    let total_size = if resumed_from > 0 {
+        response
+            .header("content-range")
+            .and_then(content_range_total)
+            .unwrap_or(if content_length > 0 { resumed_from + content_length } else { 0 })
+    } else {
+        content_length
+    };
+
     let mut reader = response.into_reader();
 
-    // Use a temporary file to avoid partial downloads
-    let temp_path = dest.with_extension("download");
-    let mut file = fs::File::create(&temp_path)
-        .map_err(|e| format!("Failed to create file: {}", e))?;
+    let mut file = if resumed_from > 0 {
+        fs::OpenOptions::new().append(true).open(&temp_path)
+    } else {
+        fs::File::create(&temp_path)
+    }
+    .map_err(|e| AttemptError::Fatal(format!("Failed to create file: {}", e)))?;
 
-    let mut downloaded: u64 = 0;
+    let mut downloaded: u64 = resumed_from;
     let mut buffer = [0u8; 8192];
     let mut last_progress_update = std::time::Instant::now();
 
     loop {
-        let bytes_read = reader.read(&mut buffer)
-            .map_err(|e| format!("Failed to read response: {}", e))?;
+        if cancel.load(Ordering::SeqCst) {
+            // Keep the partial file so the next attempt resumes it
+            on_progress(downloaded, total_size, resumed_from);
+            return Err(AttemptError::Fatal(DOWNLOAD_CANCELLED.to_string()));
+        }
+
+        // A dropped or stalled connection leaves the partial file for the next attempt
+        let bytes_read = reader
+            .read(&mut buffer)
+            .map_err(|e| AttemptError::Transient(format!("Failed to read response: {}", e)))?;
 
         if bytes_read == 0 {
             break;
         }
 
         file.write_all(&buffer[..bytes_read])
-            .map_err(|e| format!("Failed to write file: {}", e))?;
+            .map_err(|e| AttemptError::Fatal(format!("Failed to write file: {}", e)))?;
 
         downloaded += bytes_read as u64;
 
         // Update progress at most every 100ms to avoid flooding
         if last_progress_update.elapsed().as_millis() >= 100 {
-            on_progress(downloaded, total_size);
+            on_progress(downloaded, total_size, resumed_from);
             last_progress_update = std::time::Instant::now();
         }
     }
 
     // Final progress update
-    on_progress(downloaded, total_size);
+    on_progress(downloaded, total_size, resumed_from);
+    drop(file);
+
+    if let Some(expected) = expected {
+        let actual =
+            sha256_file(&temp_path).map_err(|e| AttemptError::Fatal(format!("Failed to hash download: {}", e)))?;
+        if !actual.eq_ignore_ascii_case(expected) {
+            // Don't keep a corrupt partial around to be "resumed"; the next attempt starts over
+            let _ = fs::remove_file(&temp_path);
+            return Err(AttemptError::Transient(format!(
+                "Checksum mismatch for {}: expected {}, got {}. The download was corrupted; please try again.",
+                url, expected, actual
+            )));
+        }
+    }
 
     // Rename temp file to final destination
     fs::rename(&temp_path, dest)
-        .map_err(|e| format!("Failed to finalize download: {}", e))?;
+        .map_err(|e| AttemptError::Fatal(format!("Failed to finalize download: {}", e)))?;
 
     Ok(())
 }
 
+/// Total size from a `Content-Range: bytes start-end/total` header
+fn content_range_total(header: &str) -> Option<u64> {
+    header.rsplit_once('/')?.1.trim().parse().ok()
+}
+
+/// Stored data selector for `clear_resource`, matching the fields of `DiskUsage`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResourceKind {
+    Gliner,
+    Symspell,
+    Dictionary,
+    /// Saved analyses (they are recreated by analyzing the books again)
+    Analyses,
+    /// Cover thumbnails
+    Covers,
+    /// Every downloaded resource; saved analyses and covers are kept
+    All,
+}
+
+/// Delete a downloaded resource (or all of them), returning the number of bytes freed
+pub fn clear_resource(kind: ResourceKind) -> Result<u64, String> {
+    let dirs = match kind {
+        ResourceKind::Gliner => vec![get_gliner_dir()],
+        ResourceKind::Symspell => vec![get_symspell_dir()],
+        ResourceKind::Dictionary => vec![get_dictionary_dir()],
+        ResourceKind::Analyses => vec![get_analysis_cache_dir()],
+        ResourceKind::Covers => vec![get_cover_cache_dir()],
+        ResourceKind::All => vec![get_gliner_dir(), get_symspell_dir(), get_dictionary_dir()],
+    };
+
+    let mut freed = 0;
+    for dir in &dirs {
+        freed += remove_dir(dir).map_err(|e| format!("Failed to delete {:?}: {}", dir, e))?;
+    }
+
+    VERIFIED.lock().unwrap().retain(|(path, _, _)| !dirs.iter().any(|d| path.starts_with(d)));
+    eprintln!("Cleared {:?} resources ({} bytes freed)", kind, freed);
+    Ok(freed)
+}
+
+/// Bytes taken by each kind of stored data (see `ResourceKind` to clear one)
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize)]
+pub struct DiskUsage {
+    pub gliner: u64,
+    pub symspell: u64,
+    pub dictionary: u64,
+    pub analyses: u64,
+    pub covers: u64,
+    pub total: u64,
+}
+
+/// How much space models, dictionaries and caches take; missing directories count as 0
+pub fn disk_usage() -> DiskUsage {
+    let mut usage = DiskUsage {
+        gliner: stored_size(&get_gliner_dir()),
+        symspell: stored_size(&get_symspell_dir()),
+        dictionary: stored_size(&get_dictionary_dir()),
+        analyses: stored_size(&get_analysis_cache_dir()),
+        covers: stored_size(&get_cover_cache_dir()),
+        total: 0,
+    };
+    usage.total = usage.gliner + usage.symspell + usage.dictionary + usage.analyses + usage.covers;
+    usage
+}
+
+/// Size of a directory tree, or 0 if it is missing or unreadable
+fn stored_size(dir: &Path) -> u64 {
+    dir_size(dir).unwrap_or(0)
+}
+
+/// Partial downloads untouched for this long are deleted at startup rather than resumed
+pub const PARTIAL_DOWNLOAD_MAX_AGE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Delete `.download` files left in the resource dirs by interrupted downloads that
+/// haven't been touched for `max_age`; newer ones are kept so the next download
+/// resumes them. Returns the number of files removed and the bytes reclaimed.
+pub fn cleanup_partial_downloads(max_age: Duration) -> (usize, u64) {
+    let dirs = [get_gliner_dir(), get_symspell_dir(), get_dictionary_dir()];
+    cleanup_partial_downloads_in(&dirs, max_age, SystemTime::now())
+}
+
+fn cleanup_partial_downloads_in(dirs: &[PathBuf], max_age: Duration, now: SystemTime) -> (usize, u64) {
+    let mut removed = 0;
+    let mut bytes = 0;
+    for entry in dirs.iter().filter_map(|dir| fs::read_dir(dir).ok()).flatten().flatten() {
+        let path = entry.path();
+        if path.extension().is_none_or(|ext| ext != "download") {
+            continue;
+        }
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        let age = metadata.modified().ok().and_then(|modified| now.duration_since(modified).ok());
+        if metadata.is_file() && age.is_some_and(|age| age >= max_age) && fs::remove_file(&path).is_ok() {
+            removed += 1;
+            bytes += metadata.len();
+        }
+    }
+    (removed, bytes)
+}
+
+/// Remove a directory tree, returning the total size of the files in it (0 if missing)
+fn remove_dir(dir: &Path) -> std::io::Result<u64> {
+    if !dir.exists() {
+        return Ok(0);
+    }
+    let size = dir_size(dir)?;
+    fs::remove_dir_all(dir)?;
+    Ok(size)
+}
+
+fn dir_size(dir: &Path) -> std::io::Result<u64> {
+    let mut size = 0;
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        size += if metadata.is_dir() { dir_size(&entry.path())? } else { metadata.len() };
+    }
+    Ok(size)
+}
+
 /// Get status of all resources
 pub fn get_resource_status() -> ResourceStatus {
+    let gliner_variant = installed_gliner_variant();
     ResourceStatus {
-        gliner_available: is_gliner_available(),
+        gliner_available: gliner_variant.is_some(),
+        gliner_variant,
         gliner_path: get_gliner_dir(),
         symspell_available: is_symspell_available(),
-        symspell_path: get_symspell_dir().join("frequency_dictionary_en_82_765.txt"),
+        symspell_path: get_symspell_dir().join(SYMSPELL_EN.file),
+        dictionary_available: is_dictionary_available(),
+        dictionary_path: get_dictionary_path(),
     }
 }
 
 #[derive(Debug, Clone, serde::Serialize)]
 pub struct ResourceStatus {
     pub gliner_available: bool,
+    /// The model variant analyses load, when one is downloaded (see `GlinerVariant`)
+    pub gliner_variant: Option<GlinerVariant>,
     pub gliner_path: PathBuf,
     pub symspell_available: bool,
     pub symspell_path: PathBuf,
+    /// The offline dictionary is optional: without it words have no definitions
+    pub dictionary_available: bool,
+    pub dictionary_path: PathBuf,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sha256_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("abc.txt");
+        fs::write(&path, "abc").unwrap();
+        assert_eq!(sha256_file(&path).unwrap(), ABC_SHA256);
+    }
+
+    /// SHA-256 of "abc"
+    const ABC_SHA256: &str = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+
+    #[test]
+    fn test_verify_file_against_pinned_hash() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("model.onnx");
+
+        // Missing file
+        assert!(!verify_file(&path, Some(ABC_SHA256)));
+
+        // No pinned hash: accepted unverified
+        fs::write(&path, "abc").unwrap();
+        assert!(verify_file(&path, None));
+
+        // Matching hash
+        assert!(verify_file(&path, Some(ABC_SHA256)));
+
+        // Mismatching hash: file removed so it gets re-downloaded
+        let bad = dir.path().join("bad.onnx");
+        fs::write(&bad, "truncated").unwrap();
+        assert!(!verify_file(&bad, Some(ABC_SHA256)));
+        assert!(!bad.exists());
+    }
+
+    #[test]
+    fn test_checksum_mismatch_fails_download() {
+        let (base, server) = serve(vec![
+            b"HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\nabd",
+            b"HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\nabd",
+            b"HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\nabd",
+        ]);
+
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("dict.txt");
+        let urls = [format!("{}/dict.txt", base)];
+        let error =
+            download_file(&urls, &dest, "dict.txt", Some(ABC_SHA256), &AtomicBool::new(false), &|_| {}).unwrap_err();
+
+        assert!(error.contains("Checksum mismatch"), "{}", error);
+        assert!(!dest.exists());
+        assert!(!dest.with_extension("download").exists());
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_remove_dir_reports_freed_bytes() {
+        let dir = tempfile::tempdir().unwrap();
+        let gliner = dir.path().join("gliner");
+        fs::create_dir_all(gliner.join("onnx")).unwrap();
+        fs::write(gliner.join("tokenizer.json"), vec![0u8; 100]).unwrap();
+        fs::write(gliner.join("onnx").join("model.onnx"), vec![0u8; 250]).unwrap();
+
+        assert_eq!(remove_dir(&gliner).unwrap(), 350);
+        assert!(!gliner.exists());
+        // Already gone
+        assert_eq!(remove_dir(&gliner).unwrap(), 0);
+    }
+
+    /// Hashes of the stand-in GLiNER files the tests write
+    fn test_gliner_sha256(file: &str) -> Option<&'static str> {
+        match file {
+            // "fp32"
+            "model.onnx" => Some("d1f50bdb51e6ec880d97294012b7b94d37a371bc61e6bfc58288d2d58e73c39e"),
+            // "int8"
+            "model_quantized.onnx" => Some("cb1525bced78da2c03c42fe15bf15663b584566ef6244ff91d892caa011fec1e"),
+            // "{}"
+            _ => Some("44136fa355b3678a1146ad16f7e8649e94fb4fc21fe77e8310c060f61caaff8a"),
+        }
+    }
+
+    #[test]
+    fn test_installed_gliner_variant_prefers_full() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join(GlinerVariant::Quantized.file()), "int8").unwrap();
+        // No tokenizer yet
+        assert_eq!(installed_gliner_variant_in(dir.path(), test_gliner_sha256), None);
+
+        fs::write(dir.path().join("tokenizer.json"), "{}").unwrap();
+        assert_eq!(installed_gliner_variant_in(dir.path(), test_gliner_sha256), Some(GlinerVariant::Quantized));
+
+        fs::write(dir.path().join(GlinerVariant::Full.file()), "fp32").unwrap();
+        assert_eq!(installed_gliner_variant_in(dir.path(), test_gliner_sha256), Some(GlinerVariant::Full));
+
+        // Nothing pinned: the files are taken as they are
+        assert_eq!(installed_gliner_variant_in(dir.path(), |_| None), Some(GlinerVariant::Full));
+    }
+
+    #[test]
+    fn test_stored_size_counts_nested_files() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("onnx")).unwrap();
+        fs::write(dir.path().join("tokenizer.json"), "{}").unwrap();
+        fs::write(dir.path().join("onnx").join("model.onnx"), "weights").unwrap();
+
+        assert_eq!(stored_size(dir.path()), 9);
+        assert_eq!(stored_size(&dir.path().join("missing")), 0);
+    }
+
+    #[test]
+    fn test_cleanup_partial_downloads() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("model.download"), "partial").unwrap();
+        fs::write(dir.path().join("model.onnx"), "model").unwrap();
+        let dirs = [dir.path().to_path_buf(), dir.path().join("missing")];
+        let max_age = Duration::from_secs(60);
+
+        // Too recent: kept for resuming
+        assert_eq!(cleanup_partial_downloads_in(&dirs, max_age, SystemTime::now()), (0, 0));
+        assert!(dir.path().join("model.download").exists());
+
+        let later = SystemTime::now() + Duration::from_secs(120);
+        assert_eq!(cleanup_partial_downloads_in(&dirs, max_age, later), (1, 7));
+        assert!(!dir.path().join("model.download").exists());
+        assert!(dir.path().join("model.onnx").exists());
+    }
+
+    #[test]
+    fn test_disk_space_check() {
+        assert_eq!(required_space(650 * MB, 600 * MB), 50 * MB + DISK_SPACE_MARGIN);
+        assert_eq!(required_space(MB, 2 * MB), DISK_SPACE_MARGIN);
+        assert!(check_disk_space(200 * MB, Some(200 * MB)).is_ok());
+        assert!(check_disk_space(200 * MB, None).is_ok());
+        let error = check_disk_space(200 * MB, Some(150 * MB)).unwrap_err();
+        assert!(error.starts_with("Insufficient disk space"), "{}", error);
+        assert!(error.contains("200 MB") && error.contains("150 MB"), "{}", error);
+
+        let dir = tempfile::tempdir().unwrap();
+        assert!(available_space(dir.path()).is_some_and(|free| free > 0));
+        let dest = dir.path().join("model.onnx");
+        fs::write(dest.with_extension("download"), "partial").unwrap();
+        assert!(ensure_disk_space(dir.path(), &dest, 1).is_ok());
+        assert!(dest.with_extension("download").exists());
+        // No volume has this much room: the stale partial file is cleaned up
+        assert!(ensure_disk_space(dir.path(), &dest, u64::MAX / 2).is_err());
+        assert!(!dest.with_extension("download").exists());
+    }
+
+    #[test]
+    fn test_retry_policy() {
+        assert_eq!(retry_delay(1), Duration::from_secs(1));
+        assert_eq!(retry_delay(2), Duration::from_secs(2));
+        assert!(is_transient_status(503) && is_transient_status(429) && is_transient_status(408));
+        assert!(!is_transient_status(404) && !is_transient_status(403));
+    }
+
+    /// Path and Range header of a request `serve` answered
+    type Request = (String, Option<String>);
+
+    /// Answer one connection per response in turn, returning each request's path and Range header
+    fn serve(responses: Vec<&'static [u8]>) -> (String, std::thread::JoinHandle<Vec<Request>>) {
+        use std::io::BufRead;
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            let mut requests = Vec::new();
+            for response in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut reader = std::io::BufReader::new(stream.try_clone().unwrap());
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let path = line.split_whitespace().nth(1).unwrap_or_default().to_string();
+                let mut range = None;
+                line.clear();
+                while reader.read_line(&mut line).unwrap() > 0 && line != "\r\n" {
+                    if let Some(value) = line.to_lowercase().strip_prefix("range: ") {
+                        range = Some(value.trim().to_string());
+                    }
+                    line.clear();
+                }
+                requests.push((path, range));
+                stream.write_all(response).unwrap();
+            }
+            requests
+        });
+        (base, server)
+    }
+
+    #[test]
+    fn test_retry_resumes_dropped_download() {
+        let (base, server) = serve(vec![
+            // Promise ten bytes, send five and hang up
+            b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\nhello",
+            b"HTTP/1.1 206 Partial Content\r\nContent-Length: 5\r\nContent-Range: bytes 5-9/10\r\n\r\nworld",
+        ]);
+
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("dict.txt");
+        let statuses = Mutex::new(Vec::new());
+        let urls = [format!("{}/dict.txt", base)];
+        let expected = format!("{:x}", Sha256::digest(b"helloworld"));
+        download_file(&urls, &dest, "dict.txt", Some(&expected), &AtomicBool::new(false), &|status| {
+            statuses.lock().unwrap().push(status)
+        })
+        .unwrap();
+
+        assert_eq!(fs::read_to_string(&dest).unwrap(), "helloworld");
+        let ranges: Vec<Option<String>> = server.join().unwrap().into_iter().map(|(_, range)| range).collect();
+        assert_eq!(ranges, vec![None, Some("bytes=5-".to_string())]);
+        let statuses = statuses.into_inner().unwrap();
+        assert!(statuses.iter().any(|s| matches!(s, DownloadStatus::Retrying { attempt: 1, .. })));
+        assert!(matches!(
+            statuses.last(),
+            Some(DownloadStatus::Downloading { progress: 10, total: 10, resumed_from: 5, attempt: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn test_falls_back_to_next_mirror() {
+        let (base, server) = serve(vec![
+            b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n",
+            b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\n{}",
+        ]);
+        let mirrors = MirrorConfig {
+            github_raw: vec![format!("{}/blocked/", base), format!("{}/mirror", base)],
+            ..MirrorConfig::default()
+        }
+        .normalized();
+
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join(DICTIONARY_FILE);
+        let urls = mirrors.github_raw_urls(DICTIONARY_PATH);
+        let expected = format!("{:x}", Sha256::digest(b"{}"));
+        download_file(&urls, &dest, DICTIONARY_FILE, Some(&expected), &AtomicBool::new(false), &|_| {}).unwrap();
+
+        assert_eq!(fs::read_to_string(&dest).unwrap(), "{}");
+        let paths: Vec<String> = server.join().unwrap().into_iter().map(|(path, _)| path).collect();
+        assert_eq!(paths, vec![format!("/blocked/{}", DICTIONARY_PATH), format!("/mirror/{}", DICTIONARY_PATH)]);
+    }
+
+    #[test]
+    fn test_next_mirror_does_not_resume_partial() {
+        let (base, server) = serve(vec![
+            // The first mirror drops the connection, then fails for good
+            b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\nhello",
+            b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n",
+            b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\nHELLOWORLD",
+        ]);
+
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("dict.txt");
+        let urls = [format!("{}/first/dict.txt", base), format!("{}/second/dict.txt", base)];
+        let expected = format!("{:x}", Sha256::digest(b"HELLOWORLD"));
+        download_file(&urls, &dest, "dict.txt", Some(&expected), &AtomicBool::new(false), &|_| {}).unwrap();
+
+        assert_eq!(fs::read_to_string(&dest).unwrap(), "HELLOWORLD");
+        let ranges: Vec<Option<String>> = server.join().unwrap().into_iter().map(|(_, range)| range).collect();
+        assert_eq!(ranges, vec![None, Some("bytes=5-".to_string()), None]);
+    }
+
+    #[test]
+    fn test_mirror_config_defaults() {
+        let config: MirrorConfig = serde_json::from_str(r#"{"huggingface": ["https://hf-mirror.com/", " "]}"#).unwrap();
+        let config = config.normalized();
+        assert_eq!(
+            config.gliner_urls("tokenizer.json"),
+            vec![format!("https://hf-mirror.com/{}/resolve/main/tokenizer.json", GLINER_REPO)]
+        );
+        assert_eq!(config.github_raw, vec![GITHUB_RAW_BASE.to_string()]);
+
+        let cleared = MirrorConfig { huggingface: Vec::new(), gliner_repo: String::new(), github_raw: Vec::new() };
+        assert_eq!(cleared.normalized(), MirrorConfig::default());
+    }
+
+    #[test]
+    fn test_content_range_total() {
+        assert_eq!(content_range_total("bytes 1000-1999/2000"), Some(2000));
+        assert_eq!(content_range_total("bytes 1000-1999/*"), None);
+        assert_eq!(content_range_total("garbage"), None);
+    }
 }
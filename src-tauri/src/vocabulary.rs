@@ -0,0 +1,219 @@
+//! Vocabulary across several analyzed books
+//!
+//! `aggregate` merges words by lowercase form. The ranking favours words that are both
+//! rare and shared by many books: score = rarity × book_count, where rarity is
+//! -log10 of the word's frequency (about 5 for "uncommon", 7+ for very rare).
+//! `compare` sets two books side by side, matching words by lemma key.
+
+use crate::analysis_cache::CachedAnalysis;
+use crate::nlp::readability::Readability;
+use crate::nlp::{self, FrequencyBand, HardWord};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+/// Frequency assumed for words wordfreq doesn't list, when scoring rarity
+const UNLISTED_FREQUENCY: f64 = 1e-9;
+
+#[derive(Debug, Serialize, Clone)]
+pub struct AggregateWord {
+    pub word: String,
+    pub frequency_score: f64,
+    pub band: FrequencyBand,
+    pub definition: Option<String>,
+    /// Occurrences summed over all books
+    pub count: usize,
+    pub book_count: usize,
+    /// Books the word appears in, in the order they were given
+    pub book_ids: Vec<i64>,
+    /// rarity × book_count, the ranking key
+    pub score: f64,
+}
+
+/// -log10 of the frequency, so rarer words score higher
+fn rarity(frequency: f64) -> f64 {
+    let frequency = if frequency > 0.0 { frequency } else { UNLISTED_FREQUENCY };
+    -frequency.log10()
+}
+
+/// Merge the hard words of `analyses`, ranked by score (then total count, then word)
+pub fn aggregate(analyses: &[CachedAnalysis]) -> Vec<AggregateWord> {
+    let mut merged: HashMap<String, AggregateWord> = HashMap::new();
+
+    for analysis in analyses {
+        for hard_word in &analysis.hard_words {
+            let entry = merged.entry(hard_word.word.to_lowercase()).or_insert_with(|| AggregateWord {
+                word: hard_word.word.clone(),
+                frequency_score: hard_word.frequency_score,
+                band: hard_word.band,
+                definition: None,
+                count: 0,
+                book_count: 0,
+                book_ids: Vec::new(),
+                score: 0.0,
+            });
+            entry.count += hard_word.count;
+            if entry.definition.is_none() {
+                entry.definition = hard_word.definition.clone();
+            }
+            if !entry.book_ids.contains(&analysis.book_id) {
+                entry.book_ids.push(analysis.book_id);
+            }
+        }
+    }
+
+    let mut words: Vec<AggregateWord> = merged
+        .into_values()
+        .map(|mut word| {
+            word.book_count = word.book_ids.len();
+            word.score = rarity(word.frequency_score) * word.book_count as f64;
+            word
+        })
+        .collect();
+    words.sort_by(|a, b| {
+        b.score
+            .total_cmp(&a.score)
+            .then(b.count.cmp(&a.count))
+            .then_with(|| a.word.cmp(&b.word))
+    });
+    words
+}
+
+/// One side of a `Comparison`
+#[derive(Debug, Serialize, Clone)]
+pub struct BookDifficulty {
+    pub book_id: i64,
+    pub word_count: usize,
+    pub hard_word_count: usize,
+    /// Median frequency of the hard words; lower means rarer vocabulary
+    pub median_frequency: f64,
+    pub readability: Option<Readability>,
+    /// Hard words the other book doesn't have, rarest first
+    pub unique_words: Vec<String>,
+}
+
+/// Relative difficulty of two books
+#[derive(Debug, Serialize, Clone)]
+pub struct Comparison {
+    pub first: BookDifficulty,
+    pub second: BookDifficulty,
+    /// Hard words of both books (as the first book spells them), rarest first
+    pub shared_words: Vec<String>,
+}
+
+/// Compare the hard words and readability of two analyses
+pub fn compare(first: &CachedAnalysis, second: &CachedAnalysis) -> Comparison {
+    let first_keys: HashSet<String> = first.hard_words.iter().map(|w| nlp::lemma_key(&w.word)).collect();
+    let second_keys: HashSet<String> = second.hard_words.iter().map(|w| nlp::lemma_key(&w.word)).collect();
+
+    Comparison {
+        first: difficulty(first, |key| !second_keys.contains(key)),
+        second: difficulty(second, |key| !first_keys.contains(key)),
+        shared_words: rarest_first(&first.hard_words, |key| second_keys.contains(key)),
+    }
+}
+
+fn difficulty(analysis: &CachedAnalysis, is_unique: impl Fn(&str) -> bool) -> BookDifficulty {
+    let mut frequencies: Vec<f64> = analysis.hard_words.iter().map(|w| w.frequency_score).collect();
+    frequencies.sort_by(f64::total_cmp);
+    let median_frequency = match frequencies.len() {
+        0 => 0.0,
+        n if n % 2 == 1 => frequencies[n / 2],
+        n => (frequencies[n / 2 - 1] + frequencies[n / 2]) / 2.0,
+    };
+
+    BookDifficulty {
+        book_id: analysis.book_id,
+        word_count: analysis.word_count,
+        hard_word_count: analysis.hard_words.len(),
+        median_frequency,
+        readability: analysis.readability.clone(),
+        unique_words: rarest_first(&analysis.hard_words, is_unique),
+    }
+}
+
+/// The words whose lemma key passes `keep`, rarest first
+fn rarest_first(words: &[HardWord], keep: impl Fn(&str) -> bool) -> Vec<String> {
+    let mut kept: Vec<&HardWord> = words.iter().filter(|w| keep(&nlp::lemma_key(&w.word))).collect();
+    kept.sort_by(|a, b| a.frequency_score.total_cmp(&b.frequency_score).then_with(|| a.word.cmp(&b.word)));
+    kept.into_iter().map(|w| w.word.clone()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nlp::{frequency_band, HardWord};
+
+    fn hard_word(word: &str, frequency: f64, count: usize) -> HardWord {
+        HardWord {
+            word: word.to_string(),
+            frequency_score: frequency,
+            contexts: Vec::new(),
+            count,
+            variants: Vec::new(),
+            band: frequency_band(frequency),
+            definition: None,
+            first_seen: 0,
+            pos: None,
+            first_occurrence: None,
+            first_chapter: None,
+            first_chapter_title: None,
+        }
+    }
+
+    fn analysis(book_id: i64, words: Vec<HardWord>) -> CachedAnalysis {
+        CachedAnalysis {
+            book_id,
+            word_count: 1000,
+            hard_words: words,
+            readability: None,
+            options: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_aggregate_sums_counts_and_books() {
+        let words = aggregate(&[
+            analysis(1, vec![hard_word("ennui", 1e-6, 2), hard_word("sesquipedalian", 1e-8, 1)]),
+            analysis(2, vec![hard_word("Ennui", 1e-6, 3)]),
+            analysis(3, vec![hard_word("ennui", 1e-6, 1)]),
+        ]);
+
+        let ennui = words.iter().find(|w| w.word == "ennui").unwrap();
+        assert_eq!(ennui.count, 6);
+        assert_eq!(ennui.book_count, 3);
+        assert_eq!(ennui.book_ids, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_breadth_outranks_rarity_alone() {
+        // Rarer, but in one book (8 × 1) vs. less rare across three (6 × 3)
+        let words = aggregate(&[
+            analysis(1, vec![hard_word("sesquipedalian", 1e-8, 1), hard_word("ennui", 1e-6, 1)]),
+            analysis(2, vec![hard_word("ennui", 1e-6, 1)]),
+            analysis(3, vec![hard_word("ennui", 1e-6, 1)]),
+        ]);
+        let order: Vec<&str> = words.iter().map(|w| w.word.as_str()).collect();
+        assert_eq!(order, ["ennui", "sesquipedalian"]);
+        assert!((words[0].score - 18.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_unlisted_words_count_as_very_rare() {
+        let words = aggregate(&[analysis(1, vec![hard_word("ennui", 1e-6, 1), hard_word("quoth", 0.0, 1)])]);
+        assert_eq!(words[0].word, "quoth");
+    }
+
+    #[test]
+    fn test_compare_matches_on_lemma() {
+        let first = analysis(1, vec![hard_word("gaieties", 1e-6, 2), hard_word("ennui", 2e-6, 1), hard_word("quoth", 0.0, 1)]);
+        let second = analysis(2, vec![hard_word("Gaiety", 1e-6, 1), hard_word("lugubrious", 3e-7, 4)]);
+
+        let comparison = compare(&first, &second);
+        assert_eq!(comparison.shared_words, ["gaieties"]);
+        assert_eq!(comparison.first.unique_words, ["quoth", "ennui"]);
+        assert_eq!(comparison.second.unique_words, ["lugubrious"]);
+        assert_eq!(comparison.first.hard_word_count, 3);
+        assert_eq!(comparison.first.median_frequency, 1e-6);
+        assert_eq!(comparison.second.median_frequency, (3e-7 + 1e-6) / 2.0);
+    }
+}
@@ -0,0 +1,219 @@
+//! Watch a Calibre library for changes made while Lexis is open
+//!
+//! Uses the OS file watcher (through `notify`) on the whole library tree, so
+//! books added in nested folders are seen too. It never opens metadata.db, and
+//! Calibre's write-to-temp then rename pattern shows up as events on the
+//! database path. Calibre writes in bursts, and the debouncer flushes each
+//! closed file on its own, so a burst is gathered until the library has been
+//! quiet for a while and then reported once as the kinds of change it contained.
+
+use notify_debouncer_full::notify::{self, EventKind, RecommendedWatcher, RecursiveMode};
+use notify_debouncer_full::{new_debouncer, DebounceEventResult, Debouncer, RecommendedCache};
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+/// How long the library must stay unchanged before a change is reported
+const DEBOUNCE: Duration = Duration::from_millis(1500);
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    /// metadata.db (or its WAL) was written: books added, edited or removed
+    Metadata,
+    /// Book folders or files under the library root changed
+    Files,
+    /// metadata.db no longer exists (library moved or deleted)
+    Removed,
+}
+
+/// Kinds of change a burst of events on paths under `root` amounts to
+fn changes<'a>(root: &Path, events: impl IntoIterator<Item = (&'a EventKind, &'a PathBuf)>) -> BTreeSet<ChangeKind> {
+    let mut kinds = BTreeSet::new();
+    for (kind, path) in events {
+        if matches!(kind, EventKind::Access(_)) {
+            continue;
+        }
+        // metadata.db, its WAL and journal, and Calibre's temp copies of it
+        let is_database = path.parent() == Some(root)
+            && path.file_name().is_some_and(|name| name.to_string_lossy().starts_with("metadata.db"));
+        if is_database {
+            kinds.insert(ChangeKind::Metadata);
+        } else {
+            kinds.insert(ChangeKind::Files);
+        }
+    }
+    if kinds.contains(&ChangeKind::Metadata) && !root.join("metadata.db").exists() {
+        kinds.remove(&ChangeKind::Metadata);
+        kinds.insert(ChangeKind::Removed);
+    }
+    kinds
+}
+
+/// A running watcher; dropping it stops watching and waits for its threads
+pub struct LibraryWatcher {
+    root: PathBuf,
+    debouncer: Option<Debouncer<RecommendedWatcher, RecommendedCache>>,
+    reporter: Option<JoinHandle<()>>,
+}
+
+impl LibraryWatcher {
+    /// Watch `root` recursively, calling `on_change` with the kinds of change seen in each burst
+    pub fn start<F>(root: PathBuf, on_change: F) -> notify::Result<Self>
+    where
+        F: Fn(Vec<ChangeKind>) + Send + 'static,
+    {
+        Self::start_with(root, DEBOUNCE, on_change)
+    }
+
+    fn start_with<F>(root: PathBuf, debounce: Duration, on_change: F) -> notify::Result<Self>
+    where
+        F: Fn(Vec<ChangeKind>) + Send + 'static,
+    {
+        // Events carry absolute paths; compare them against the canonical root
+        let event_root = root.canonicalize().unwrap_or_else(|_| root.clone());
+        let (tx, rx) = mpsc::channel::<BTreeSet<ChangeKind>>();
+        let mut debouncer = new_debouncer(debounce / 4, None, move |result: DebounceEventResult| match result {
+            Ok(events) => {
+                let kinds = changes(&event_root, events.iter().flat_map(|e| e.paths.iter().map(move |p| (&e.kind, p))));
+                if !kinds.is_empty() {
+                    let _ = tx.send(kinds);
+                }
+            }
+            Err(errors) => eprintln!("Library watcher errors: {:?}", errors),
+        })?;
+        debouncer.watch(&root, RecursiveMode::Recursive)?;
+
+        // Gather a burst until nothing has changed for `debounce`; ends when the
+        // debouncer (and with it the sender) is dropped
+        let reporter = std::thread::spawn(move || {
+            while let Ok(mut kinds) = rx.recv() {
+                loop {
+                    match rx.recv_timeout(debounce) {
+                        Ok(more) => kinds.extend(more),
+                        Err(RecvTimeoutError::Timeout) => break,
+                        Err(RecvTimeoutError::Disconnected) => return,
+                    }
+                }
+                // A database removed and then recreated within the burst is just written
+                if kinds.contains(&ChangeKind::Removed) && kinds.contains(&ChangeKind::Metadata) {
+                    kinds.remove(&ChangeKind::Removed);
+                }
+                on_change(kinds.into_iter().collect());
+            }
+        });
+
+        Ok(Self {
+            root,
+            debouncer: Some(debouncer),
+            reporter: Some(reporter),
+        })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+}
+
+impl Drop for LibraryWatcher {
+    fn drop(&mut self) {
+        if let Some(debouncer) = self.debouncer.take() {
+            debouncer.stop();
+        }
+        if let Some(reporter) = self.reporter.take() {
+            let _ = reporter.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    const QUIET: Duration = Duration::from_millis(100);
+
+    fn watch(root: &Path) -> (LibraryWatcher, mpsc::Receiver<Vec<ChangeKind>>) {
+        let (tx, rx) = mpsc::channel();
+        let watcher = LibraryWatcher::start_with(root.to_path_buf(), QUIET, move |kinds| {
+            let _ = tx.send(kinds);
+        })
+        .unwrap();
+        (watcher, rx)
+    }
+
+    /// Everything reported until the watcher has been quiet for a while
+    fn collect(rx: &mpsc::Receiver<Vec<ChangeKind>>) -> BTreeSet<ChangeKind> {
+        let mut kinds: BTreeSet<ChangeKind> = rx.recv_timeout(Duration::from_secs(5)).unwrap().into_iter().collect();
+        while let Ok(more) = rx.recv_timeout(QUIET * 5) {
+            kinds.extend(more);
+        }
+        kinds
+    }
+
+    #[test]
+    fn test_burst_of_writes_is_reported_once() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("metadata.db"), b"v1").unwrap();
+        let (_watcher, rx) = watch(dir.path());
+
+        for i in 0..5 {
+            std::fs::write(dir.path().join("metadata.db"), format!("version {}", i)).unwrap();
+            std::thread::sleep(QUIET / 5);
+        }
+
+        let kinds = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(kinds, vec![ChangeKind::Metadata]);
+        assert!(rx.recv_timeout(QUIET * 5).is_err(), "burst should be reported once");
+    }
+
+    #[test]
+    fn test_atomic_rename_and_new_book_folder() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("metadata.db"), b"v1").unwrap();
+        let (_watcher, rx) = watch(dir.path());
+
+        // Calibre-style: write a temp file, rename it over the database, add a book folder
+        let tmp = dir.path().join("metadata.db.tmp");
+        std::fs::write(&tmp, b"version two").unwrap();
+        std::fs::rename(&tmp, dir.path().join("metadata.db")).unwrap();
+        std::fs::create_dir_all(dir.path().join("George Eliot").join("Middlemarch (1)")).unwrap();
+
+        assert_eq!(collect(&rx), BTreeSet::from([ChangeKind::Metadata, ChangeKind::Files]));
+    }
+
+    #[test]
+    fn test_book_added_in_nested_folder() {
+        let dir = tempfile::tempdir().unwrap();
+        let shelf = dir.path().join("Fiction").join("Victorian");
+        std::fs::create_dir_all(&shelf).unwrap();
+        let (_watcher, rx) = watch(dir.path());
+
+        std::fs::write(shelf.join("Middlemarch.epub"), b"PK").unwrap();
+        assert_eq!(collect(&rx), BTreeSet::from([ChangeKind::Files]));
+    }
+
+    #[test]
+    fn test_removed_database() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("metadata.db"), b"v1").unwrap();
+        let (_watcher, rx) = watch(dir.path());
+
+        std::fs::remove_file(dir.path().join("metadata.db")).unwrap();
+        assert_eq!(collect(&rx), BTreeSet::from([ChangeKind::Removed]));
+    }
+
+    #[test]
+    fn test_drop_stops_watching() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("metadata.db"), b"v1").unwrap();
+        let (watcher, rx) = watch(dir.path());
+        drop(watcher);
+
+        std::fs::write(dir.path().join("metadata.db"), b"changed after drop").unwrap();
+        assert!(rx.recv_timeout(QUIET * 5).is_err());
+    }
+}
@@ -0,0 +1,44 @@
+//! Timing comparison of sequential and parallel GLiNER batches on a whole novel
+//!
+//! Needs the GLiNER model and the Pride and Prejudice fixture (see nlp_filtering.rs),
+//! and is ignored by default since it runs NER over the book twice. Run with:
+//! cargo test --release --test ner_timing -- --ignored --nocapture
+
+use desktop_lib::nlp::NlpPipeline;
+use std::collections::BTreeSet;
+use std::path::Path;
+use std::time::Instant;
+
+const PRIDE_PREJUDICE_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/pride_and_prejudice.txt");
+
+#[test]
+#[ignore]
+fn test_parallel_ner_timing() {
+    if !NlpPipeline::is_gliner_available() {
+        eprintln!("Skipping NER timing: GLiNER model not available");
+        return;
+    }
+    let Ok(text) = std::fs::read_to_string(Path::new(PRIDE_PREJUDICE_PATH)) else {
+        eprintln!("Skipping NER timing: run `setup-test-fixtures` first");
+        return;
+    };
+
+    // Load the model once so neither run pays for it
+    NlpPipeline::new().analyze("Elizabeth walked to Meryton.", 0.00005, |_| {});
+
+    let run = |pipeline: NlpPipeline| {
+        let start = Instant::now();
+        let (_, stats) = pipeline.analyze(&text, 0.00005, |_| {});
+        (start.elapsed(), stats.filtered_by_ner.into_iter().collect::<BTreeSet<_>>())
+    };
+    let (sequential, sequential_entities) = run(NlpPipeline::new().with_ner_workers(1));
+    let (parallel, parallel_entities) = run(NlpPipeline::new());
+
+    eprintln!(
+        "NER over Pride and Prejudice: sequential {:.1}s, parallel {:.1}s ({:.2}x)",
+        sequential.as_secs_f64(),
+        parallel.as_secs_f64(),
+        sequential.as_secs_f64() / parallel.as_secs_f64()
+    );
+    assert_eq!(sequential_entities, parallel_entities);
+}
@@ -5,13 +5,16 @@
 //! 2. Rare/hard words (low frequency) are kept IN
 //! 3. Malformed EPUB concatenations are filtered OUT
 //! 4. Named entities (proper nouns) are filtered OUT
+//! 5. Project Gutenberg license boilerplate is stripped before analysis
 //!
 //! Run with: cargo test --test nlp_filtering
 //!
 //! Setup: Run `setup-test-fixtures` devenv script first to download test books.
 
-use desktop_lib::nlp::NlpPipeline;
+use desktop_lib::nlp::{strip_gutenberg_boilerplate, AnalysisOptions, NlpPipeline};
 use std::collections::HashSet;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
 use std::fs;
 use std::path::Path;
 
@@ -235,6 +238,39 @@ fn test_proper_nouns_filtered_by_ner() {
     );
 }
 
+#[test]
+fn test_allowlisted_words_survive_ner() {
+    if !NlpPipeline::is_gliner_available() {
+        eprintln!("Skipping NER test: GLiNER model not available");
+        return;
+    }
+
+    // Keep unlisted words so names reach the NER stage
+    let options = AnalysisOptions { include_unlisted: true, ..Default::default() };
+    let text = r#"
+        Elizabeth walked from Longbourn to Pemberley in the rain.
+        At Pemberley the housekeeper showed her the gallery.
+        The obsequious Mr. Collins wrote from Hunsford.
+    "#;
+    let analyze = |pipeline: NlpPipeline| {
+        let (hard_words, stats) = pipeline
+            .analyze_with_cancel(text, 0.00005, &options, &Arc::new(AtomicBool::new(false)), |_| {}, |_| {})
+            .expect("analysis was not cancelled");
+        let found: HashSet<String> = hard_words.into_iter().map(|w| w.word).collect();
+        (found, stats)
+    };
+
+    let (_, stats) = analyze(NlpPipeline::new());
+    if !stats.filtered_by_ner.iter().any(|e| e.word == "pemberley") {
+        eprintln!("Skipping allowlist check: GLiNER did not flag 'pemberley'");
+        return;
+    }
+
+    let (found, stats) = analyze(NlpPipeline::new().with_entity_allowlist(&["Pemberley".to_string()]));
+    assert!(found.contains("pemberley"), "Allowlisted 'pemberley' should survive NER");
+    assert!(stats.filtered_by_ner.iter().all(|e| e.word != "pemberley"));
+}
+
 #[test]
 fn test_frequency_threshold_affects_results() {
     if !ensure_fixtures_exist() {
@@ -272,9 +308,9 @@ fn test_contexts_are_captured() {
     let (hard_words, _stats) = pipeline.analyze(&text, 0.00005, |_progress| {});
 
     // Count how many words have context
-    // Note: The NLP pipeline only stores context for sentences between 20-500 chars,
-    // so some words may legitimately have no context if they only appear in
-    // very short or very long sentences.
+    // Note: Contexts are only kept for sentences within the `ContextPolicy` lengths
+    // (20-500 chars by default), so some words may legitimately have no context if
+    // they only appear in very short or very long sentences.
     let words_with_context = hard_words.iter().filter(|w| !w.contexts.is_empty()).count();
     let words_without_context: Vec<_> = hard_words.iter()
         .filter(|w| w.contexts.is_empty())
@@ -311,12 +347,32 @@ fn test_contexts_are_captured() {
     for word in hard_words.iter().filter(|w| !w.contexts.is_empty()) {
         for ctx in &word.contexts {
             total_contexts += 1;
-            if ctx.len() <= 10 {
-                short_contexts.push((word.word.as_str(), ctx.as_str()));
+            if ctx.sentence.len() <= 10 {
+                short_contexts.push((word.word.as_str(), ctx.sentence.as_str()));
             }
         }
     }
 
+    // The match range points at one of the word's forms
+    for word in hard_words.iter().take(200) {
+        for ctx in &word.contexts {
+            let matched: String = ctx
+                .sentence
+                .chars()
+                .skip(ctx.match_start)
+                .take(ctx.match_end - ctx.match_start)
+                .collect::<String>()
+                .to_lowercase();
+            assert!(
+                matched == word.word || word.variants.contains(&matched),
+                "Context match {:?} is not a form of {:?} in {:?}",
+                matched,
+                word.word,
+                ctx.sentence
+            );
+        }
+    }
+
     // Allow up to 1% short contexts (book artifacts like TOC, chapter markers)
     let short_ratio = short_contexts.len() as f64 / total_contexts.max(1) as f64;
     assert!(
@@ -383,3 +439,30 @@ fn test_valid_dictionary_words_not_filtered_as_malformed() {
          Valid dictionary words may have been incorrectly filtered as malformed."
     );
 }
+
+#[test]
+fn test_gutenberg_boilerplate_not_in_contexts() {
+    if !ensure_fixtures_exist() {
+        eprintln!("Skipping test: fixtures not found");
+        return;
+    }
+
+    let text = get_test_text().expect("Failed to read test text");
+    assert!(text.contains("*** START OF THE PROJECT GUTENBERG EBOOK"), "Fixture should have the Gutenberg header");
+
+    let body = strip_gutenberg_boilerplate(&text);
+    assert!(body.len() < text.len());
+    assert!(body.contains("It is a truth universally acknowledged"));
+    assert!(!body.contains("Project Gutenberg License"));
+
+    let pipeline = NlpPipeline::new();
+    let (hard_words, _stats) = pipeline.analyze(&text, 0.00005, |_progress| {});
+
+    let leaked: Vec<_> = hard_words
+        .iter()
+        .flat_map(|w| w.contexts.iter())
+        .filter(|ctx| ctx.sentence.to_lowercase().contains("gutenberg"))
+        .take(5)
+        .collect();
+    assert!(leaked.is_empty(), "License text leaked into contexts: {:?}", leaked);
+}